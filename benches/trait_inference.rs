@@ -0,0 +1,93 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use reflect::*;
+
+#[path = "../tests/debug/mod.rs"]
+mod debug;
+
+library! {
+    use serialize {
+        trait Serialize {
+            fn serialize(&self, &mut Serializer);
+        }
+
+        type Serializer;
+        type SerializeStruct;
+
+        impl Serializer {
+            fn serialize_struct(&mut self, &str) -> SerializeStruct;
+        }
+
+        impl SerializeStruct {
+            fn serialize_field<T: ?Sized + ::serialize::Serialize>(&mut self, &str, &T);
+            fn end(self);
+        }
+    }
+}
+
+/// A struct with `len` fields, each of its own generic type
+fn generic_struct(len: usize) -> TokenStream {
+    let params = (0..len).map(|i| format_ident!("T{}", i));
+    let fields = (0..len).map(|i| {
+        let field = format_ident!("f{}", i);
+        let ty = format_ident!("T{}", i);
+        quote!(#field: #ty)
+    });
+    quote! {
+        struct Generic<#(#params),*> {
+            #(#fields,)*
+        }
+    }
+}
+
+/// Serializes each field with a generic function, which gives every field
+/// its own type param to infer
+fn derive_serialize(ex: Execution) {
+    ex.make_trait_impl(RUNTIME::serialize::Serialize, ex.target_type(), |block| {
+        block.make_function(RUNTIME::serialize::Serialize::serialize, |f| {
+            let receiver = f.arg(0);
+            let serializer = f.arg(1);
+            let type_name = receiver.get_type_name();
+            match receiver.data() {
+                Data::Struct(Struct::Struct(receiver)) => {
+                    let state = RUNTIME::serialize::Serializer::serialize_struct
+                        .INVOKE(serializer, type_name);
+                    let state_ref = state.reference_mut();
+                    for field in receiver.fields() {
+                        RUNTIME::serialize::SerializeStruct::serialize_field.INVOKE(
+                            state_ref,
+                            field.get_name(),
+                            field.get_value(),
+                        );
+                    }
+                    RUNTIME::serialize::SerializeStruct::end.INVOKE(state)
+                }
+                _ => unimplemented!(),
+            }
+        });
+    });
+}
+
+fn bench_derive(c: &mut Criterion, name: &str, derive: fn(Execution)) {
+    let mut group = c.benchmark_group(name);
+    group.sample_size(10);
+    for len in [100, 200, 400] {
+        let input = generic_struct(len);
+        group.bench_with_input(BenchmarkId::from_parameter(len), &input, |b, input| {
+            b.iter(|| reflect::derive(input.clone(), derive))
+        });
+    }
+    group.finish();
+}
+
+fn bench_debug(c: &mut Criterion) {
+    bench_derive(c, "debug_generic_fields", debug::derive);
+}
+
+fn bench_serialize(c: &mut Criterion) {
+    bench_derive(c, "serialize_generic_fields", derive_serialize);
+}
+
+criterion_group!(benches, bench_debug, bench_serialize);
+criterion_main!(benches);
@@ -0,0 +1,970 @@
+use crate::print::print;
+use crate::resolve::Scope;
+use crate::{Error, Unsupported};
+use proc_macro2::TokenStream;
+use quote::{quote, ToTokens};
+use std::fs;
+use std::path::{Path as FsPath, PathBuf};
+use syn::{
+    Attribute, Fields, FnArg, GenericArgument, GenericParam, Generics, Ident, ImplItem, Item, Pat,
+    PathArguments, ReturnType, Signature, TraitItem, Type, TypeParamBound, Visibility,
+    WherePredicate,
+};
+
+/// A module of the generated library
+pub(crate) struct Module {
+    ident: Ident,
+    // Each declaration is a list of lines
+    items: Vec<Vec<String>>,
+    modules: Vec<Module>,
+}
+
+pub(crate) struct Generator {
+    pub(crate) unsupported: Vec<Unsupported>,
+    // `#[macro_export]` macros, which are declared at the crate root
+    macros: Vec<Ident>,
+}
+
+/// The generic type parameters in scope of a signature
+#[derive(Clone, Default)]
+struct Params {
+    types: Vec<Ident>,
+}
+
+impl Generator {
+    pub(crate) fn new() -> Self {
+        Generator {
+            unsupported: Vec::new(),
+            macros: Vec::new(),
+        }
+    }
+
+    /// Generate the crate module. `dir` is the directory to look for
+    /// out-of-line modules in, if the source was read from a file.
+    pub(crate) fn generate_crate(
+        &mut self,
+        crate_name: Ident,
+        items: &[Item],
+        dir: Option<&FsPath>,
+    ) -> Result<Module, Error> {
+        let mut module = self.generate_module(vec![crate_name], items, dir)?;
+        let macros = self
+            .macros
+            .drain(..)
+            .map(|ident| vec![format!("macro {};", ident)]);
+        module.items.extend(macros);
+        Ok(module)
+    }
+
+    fn generate_module(
+        &mut self,
+        path: Vec<Ident>,
+        items: &[Item],
+        dir: Option<&FsPath>,
+    ) -> Result<Module, Error> {
+        let scope = Scope::new(path.clone(), items);
+        let mut module = Module {
+            ident: path.last().unwrap().clone(),
+            items: Vec::new(),
+            modules: Vec::new(),
+        };
+
+        // Types and traits declared in the module
+        let mut declared: Vec<Ident> = Vec::new();
+        let mut impls = Vec::new();
+
+        for item in items {
+            if is_hidden(item) {
+                continue;
+            }
+            let (ident, lines) = match item {
+                Item::Mod(item) if is_public(&item.vis) => {
+                    let mut path = path.clone();
+                    path.push(item.ident.clone());
+                    match self.generate_child_module(path, item, dir)? {
+                        Some(child) if !child.is_empty() => module.modules.push(child),
+                        _ => {}
+                    }
+                    continue;
+                }
+                Item::Struct(item) if is_public(&item.vis) => {
+                    (&item.ident, self.generate_struct(&scope, item))
+                }
+                Item::Enum(item) if is_public(&item.vis) => {
+                    (&item.ident, self.generate_enum(&scope, item))
+                }
+                Item::Union(item) if is_public(&item.vis) => {
+                    (&item.ident, opaque_type(&item.ident, &item.generics))
+                }
+                Item::Trait(item) if is_public(&item.vis) => {
+                    match self.generate_trait(&scope, item) {
+                        Some(lines) => (&item.ident, lines),
+                        None => continue,
+                    }
+                }
+                // Impls are declared after all the types of the module are known
+                Item::Impl(item) if item.trait_.is_none() => {
+                    impls.push(item);
+                    continue;
+                }
+                Item::Macro(item) if is_macro_export(&item.attrs) => {
+                    if let Some(ident) = &item.ident {
+                        self.macros.push(ident.clone());
+                    }
+                    continue;
+                }
+                item => {
+                    self.report_item(&scope, item);
+                    continue;
+                }
+            };
+
+            if declared.contains(ident) {
+                self.report(
+                    &scope,
+                    ident,
+                    "declared more than once, only the first declaration is used",
+                );
+            } else {
+                declared.push(ident.clone());
+                module.items.push(lines);
+            }
+        }
+
+        // library! declares all the methods of a type in one impl block
+        let mut blocks: Vec<ImplBlock> = Vec::new();
+        for item in impls {
+            let block = match self.generate_impl(&scope, item, &declared) {
+                Some(block) => block,
+                None => continue,
+            };
+            match blocks.iter_mut().find(|other| other.ident == block.ident) {
+                Some(other) if other.header == block.header => {
+                    for (name, method) in block.methods {
+                        if other
+                            .methods
+                            .iter()
+                            .any(|(other_name, _)| *other_name == name)
+                        {
+                            let item_path = format!("{}::{}", block.ident, name);
+                            self.report_path(
+                                &scope,
+                                &item_path,
+                                "declared more than once, only the first declaration is used",
+                            );
+                        } else {
+                            other.methods.push((name, method));
+                        }
+                    }
+                }
+                Some(_) => {
+                    for (name, _) in block.methods {
+                        let item_path = format!("{}::{}", block.ident, name);
+                        self.report_path(
+                            &scope,
+                            &item_path,
+                            "declared in an impl block with other generics than the first impl block of the type",
+                        );
+                    }
+                }
+                None => blocks.push(block),
+            }
+        }
+        module
+            .items
+            .extend(blocks.into_iter().map(ImplBlock::into_lines));
+
+        Ok(module)
+    }
+
+    fn generate_child_module(
+        &mut self,
+        path: Vec<Ident>,
+        item: &syn::ItemMod,
+        dir: Option<&FsPath>,
+    ) -> Result<Option<Module>, Error> {
+        let name = item.ident.to_string();
+        if let Some((_, items)) = &item.content {
+            let dir = dir.map(|dir| dir.join(&name));
+            return self.generate_module(path, items, dir.as_deref()).map(Some);
+        }
+
+        let dir = match dir {
+            Some(dir) => dir,
+            None => {
+                self.unsupported.push(Unsupported {
+                    path: idents_to_string(&path),
+                    reason: "out-of-line modules can only be followed when reading from a file"
+                        .to_owned(),
+                });
+                return Ok(None);
+            }
+        };
+
+        let candidates = [
+            (dir.join(format!("{}.rs", name)), dir.join(&name)),
+            (dir.join(&name).join("mod.rs"), dir.join(&name)),
+        ];
+        for (file, child_dir) in &candidates {
+            if file.is_file() {
+                let items = parse_file(file)?.items;
+                return self
+                    .generate_module(path, &items, Some(child_dir))
+                    .map(Some);
+            }
+        }
+
+        self.unsupported.push(Unsupported {
+            path: idents_to_string(&path),
+            reason: format!("the module file was not found in {}", dir.display()),
+        });
+        Ok(None)
+    }
+
+    fn generate_struct(&mut self, scope: &Scope, item: &syn::ItemStruct) -> Vec<String> {
+        self.report_defaults(scope, &item.ident, &item.generics);
+        let all_public = item.fields.iter().all(|field| is_public(&field.vis));
+        if !all_public {
+            return opaque_type(&item.ident, &item.generics);
+        }
+        let result = (|| -> Result<_, String> {
+            let generics = self.generics(scope, &item.generics, &Params::default())?;
+            let params = params_of(&item.generics, &Params::default());
+            let ident = &item.ident;
+            let header = print(quote!(#ident #generics));
+            let where_clause = self.where_clause(scope, &item.generics, &params)?;
+            let fields = self.fields(scope, &item.fields, &params, true)?;
+            Ok(match &item.fields {
+                Fields::Named(_) => {
+                    let mut lines = vec![format!("struct {}{} {{", header, where_clause)];
+                    lines.extend(fields.into_iter().map(|field| format!("    {},", field)));
+                    lines.push("}".to_owned());
+                    lines
+                }
+                Fields::Unnamed(_) => vec![format!(
+                    "struct {}({}){};",
+                    header,
+                    fields.join(", "),
+                    where_clause,
+                )],
+                Fields::Unit => vec![format!("struct {}{};", header, where_clause)],
+            })
+        })();
+        self.or_opaque(scope, &item.ident, &item.generics, result)
+    }
+
+    fn generate_enum(&mut self, scope: &Scope, item: &syn::ItemEnum) -> Vec<String> {
+        self.report_defaults(scope, &item.ident, &item.generics);
+        let result = (|| -> Result<_, String> {
+            let generics = self.generics(scope, &item.generics, &Params::default())?;
+            let params = params_of(&item.generics, &Params::default());
+            let ident = &item.ident;
+            let header = print(quote!(#ident #generics));
+            let where_clause = self.where_clause(scope, &item.generics, &params)?;
+            let mut lines = vec![format!("enum {}{} {{", header, where_clause)];
+            for variant in &item.variants {
+                let fields = self.fields(scope, &variant.fields, &params, false)?;
+                let variant = match &variant.fields {
+                    Fields::Named(_) => format!("{} {{ {} }}", variant.ident, fields.join(", ")),
+                    Fields::Unnamed(_) => format!("{}({})", variant.ident, fields.join(", ")),
+                    Fields::Unit => variant.ident.to_string(),
+                };
+                lines.push(format!("    {},", variant));
+            }
+            lines.push("}".to_owned());
+            Ok(lines)
+        })();
+        self.or_opaque(scope, &item.ident, &item.generics, result)
+    }
+
+    /// Types whose fields can not be declared are declared without fields
+    fn or_opaque(
+        &mut self,
+        scope: &Scope,
+        ident: &Ident,
+        generics: &Generics,
+        result: Result<Vec<String>, String>,
+    ) -> Vec<String> {
+        match result {
+            Ok(lines) => lines,
+            Err(reason) => {
+                self.report(
+                    scope,
+                    ident,
+                    &format!("declared without its fields, because {}", reason),
+                );
+                opaque_type(ident, generics)
+            }
+        }
+    }
+
+    fn fields(
+        &mut self,
+        scope: &Scope,
+        fields: &Fields,
+        params: &Params,
+        public: bool,
+    ) -> Result<Vec<String>, String> {
+        let vis = if public { Some(quote!(pub)) } else { None };
+        fields
+            .iter()
+            .map(|field| {
+                let mut ty = field.ty.clone();
+                self.ty(scope, &mut ty, params, None)?;
+                Ok(match &field.ident {
+                    Some(ident) => print(quote!(#vis #ident: #ty)),
+                    None => print(quote!(#vis #ty)),
+                })
+            })
+            .collect()
+    }
+
+    fn generate_trait(&mut self, scope: &Scope, item: &syn::ItemTrait) -> Option<Vec<String>> {
+        self.report_defaults(scope, &item.ident, &item.generics);
+        let result = (|| -> Result<_, String> {
+            if item.auto_token.is_some() {
+                return Err("auto traits are not supported by library!".to_owned());
+            }
+            let generics = self.generics(scope, &item.generics, &Params::default())?;
+            let params = params_of(&item.generics, &Params::default());
+            let mut supertraits = Vec::new();
+            for bound in &item.supertraits {
+                let mut bound = bound.clone();
+                self.bound(scope, &mut bound, &params, None)?;
+                supertraits.push(bound);
+            }
+            let where_clause = self.where_clause(scope, &item.generics, &params)?;
+            Ok((generics, params, supertraits, where_clause))
+        })();
+        let (generics, params, supertraits, where_clause) = match result {
+            Ok(result) => result,
+            Err(reason) => {
+                self.report(scope, &item.ident, &reason);
+                return None;
+            }
+        };
+
+        let ident = &item.ident;
+        let supertraits = if supertraits.is_empty() {
+            None
+        } else {
+            Some(quote!(: #(#supertraits)+*))
+        };
+        let unsafety = &item.unsafety;
+        let header = print(quote!(#unsafety trait #ident #generics #supertraits));
+        let mut block = ImplBlock {
+            ident: ident.clone(),
+            header: format!("{}{}", header, where_clause),
+            assoc_types: Vec::new(),
+            methods: Vec::new(),
+        };
+
+        for trait_item in &item.items {
+            match trait_item {
+                TraitItem::Method(method) => {
+                    let name = &method.sig.ident;
+                    let item_path = format!("{}::{}", ident, name);
+                    match self.function(scope, &method.sig, &params, None) {
+                        Ok(_) if block.methods.iter().any(|(other, _)| other == name) => self
+                            .report_path(
+                                scope,
+                                &item_path,
+                                "declared more than once, only the first declaration is used",
+                            ),
+                        Ok(function) => block.methods.push((name.clone(), function)),
+                        Err(reason) => self.report_path(scope, &item_path, &reason),
+                    }
+                }
+                TraitItem::Type(ty) => {
+                    let item_path = format!("{}::{}", ident, ty.ident);
+                    if !ty.generics.params.is_empty() {
+                        self.report_path(
+                            scope,
+                            &item_path,
+                            "generic associated types are not supported by library!",
+                        );
+                    } else if !ty.bounds.is_empty() {
+                        self.report_path(
+                            scope,
+                            &item_path,
+                            "bounds on associated types are not supported by library!",
+                        );
+                    } else {
+                        block.assoc_types.push(ty.ident.clone());
+                    }
+                }
+                TraitItem::Const(constant) => self.report_path(
+                    scope,
+                    &format!("{}::{}", ident, constant.ident),
+                    "associated consts are not supported by library!",
+                ),
+                _ => {}
+            }
+        }
+
+        Some(block.into_lines())
+    }
+
+    fn generate_impl(
+        &mut self,
+        scope: &Scope,
+        item: &syn::ItemImpl,
+        declared: &[Ident],
+    ) -> Option<ImplBlock> {
+        let methods: Vec<_> = item
+            .items
+            .iter()
+            .filter_map(|impl_item| match impl_item {
+                ImplItem::Method(method) if is_public(&method.vis) => Some(method),
+                _ => None,
+            })
+            .collect();
+        if methods.is_empty() {
+            return None;
+        }
+
+        let self_ty = &*item.self_ty;
+        let type_path = match self_ty {
+            Type::Path(type_path)
+                if type_path.qself.is_none()
+                    && type_path.path.leading_colon.is_none()
+                    && type_path.path.segments.len() == 1 =>
+            {
+                &type_path.path
+            }
+            _ => {
+                self.report_path(
+                    scope,
+                    &print(self_ty.to_token_stream()),
+                    "inherent impls are only supported for types declared in the same module",
+                );
+                return None;
+            }
+        };
+        let ident = &type_path.segments[0].ident;
+        if !declared.contains(ident) {
+            self.report(
+                scope,
+                ident,
+                "inherent impls are only supported for public types declared in the same module",
+            );
+            return None;
+        }
+
+        let result = (|| -> Result<_, String> {
+            let generics = self.generics(scope, &item.generics, &Params::default())?;
+            let params = params_of(&item.generics, &Params::default());
+            let mut segment = type_path.segments[0].clone();
+            self.path_arguments(scope, &mut segment.arguments, &params, None)?;
+            let where_clause = self.where_clause(scope, &item.generics, &params)?;
+            Ok((generics, params, segment, where_clause))
+        })();
+        let (generics, params, segment, where_clause) = match result {
+            Ok(result) => result,
+            Err(reason) => {
+                self.report(scope, ident, &reason);
+                return None;
+            }
+        };
+
+        let header = print(quote!(impl #generics #segment));
+        let mut block = ImplBlock {
+            ident: ident.clone(),
+            header: format!("{}{}", header, where_clause),
+            assoc_types: Vec::new(),
+            methods: Vec::new(),
+        };
+        for method in methods {
+            let name = &method.sig.ident;
+            let item_path = format!("{}::{}", ident, name);
+            match self.function(scope, &method.sig, &params, Some(self_ty)) {
+                Ok(_) if block.methods.iter().any(|(other, _)| other == name) => self.report_path(
+                    scope,
+                    &item_path,
+                    "declared more than once, only the first declaration is used",
+                ),
+                Ok(function) => block.methods.push((name.clone(), function)),
+                Err(reason) => self.report_path(scope, &item_path, &reason),
+            }
+        }
+        Some(block)
+    }
+
+    /// The declaration of a function. `self_ty` replaces `Self` in inherent
+    /// impls.
+    fn function(
+        &mut self,
+        scope: &Scope,
+        sig: &Signature,
+        parent_params: &Params,
+        self_ty: Option<&Type>,
+    ) -> Result<String, String> {
+        if sig.asyncness.is_some() {
+            return Err("async functions are not supported by library!".to_owned());
+        }
+        if sig.abi.is_some() || sig.variadic.is_some() {
+            return Err("extern functions are not supported by library!".to_owned());
+        }
+
+        let generics = self.generics(scope, &sig.generics, parent_params)?;
+        let params = params_of(&sig.generics, parent_params);
+
+        let mut inputs = Vec::new();
+        for (i, input) in sig.inputs.iter().enumerate() {
+            match input {
+                FnArg::Receiver(receiver) => {
+                    if let Some((_, Some(lifetime))) = &receiver.reference {
+                        return Err(format!(
+                            "receivers with a lifetime like `&{} self` are not supported by library!",
+                            lifetime,
+                        ));
+                    }
+                    let reference = receiver.reference.as_ref().map(|_| quote!(&));
+                    let mutability = receiver.reference.as_ref().and(receiver.mutability);
+                    inputs.push(quote!(#reference #mutability self));
+                }
+                FnArg::Typed(arg) => {
+                    if i == 0 && is_self_pattern(&arg.pat) {
+                        return Err("typed receivers are not supported by library!".to_owned());
+                    }
+                    let mut ty = (*arg.ty).clone();
+                    self.ty(scope, &mut ty, &params, self_ty)?;
+                    inputs.push(ty.into_token_stream());
+                }
+            }
+        }
+
+        let output = match &sig.output {
+            ReturnType::Default => None,
+            ReturnType::Type(_, ty) => {
+                let mut ty = (**ty).clone();
+                self.ty(scope, &mut ty, &params, self_ty)?;
+                Some(quote!(-> #ty))
+            }
+        };
+        let where_clause = self.where_clause(scope, &sig.generics, &params)?;
+
+        let ident = &sig.ident;
+        let unsafety = &sig.unsafety;
+        Ok(format!(
+            "{}{};",
+            print(quote!(#unsafety fn #ident #generics (#(#inputs),*) #output)),
+            where_clause,
+        ))
+    }
+
+    /// The defaults of generic parameters are dropped from the declaration,
+    /// so uses that leave out the defaulted arguments have too few of them
+    fn report_defaults(&mut self, scope: &Scope, ident: &Ident, generics: &Generics) {
+        for param in &generics.params {
+            let name = match param {
+                GenericParam::Type(param) if param.default.is_some() => &param.ident,
+                GenericParam::Const(param) if param.default.is_some() => &param.ident,
+                _ => continue,
+            };
+            let reason = format!(
+                "the default of `{}` is dropped, because defaults of generic parameters are not supported by library!",
+                name,
+            );
+            self.report(scope, ident, &reason);
+        }
+    }
+
+    /// The generic parameters without defaults and with resolved bounds
+    fn generics(
+        &mut self,
+        scope: &Scope,
+        generics: &Generics,
+        parent_params: &Params,
+    ) -> Result<TokenStream, String> {
+        if generics.params.is_empty() {
+            return Ok(TokenStream::new());
+        }
+        let params = params_of(generics, parent_params);
+        let mut declared = Vec::new();
+        for param in &generics.params {
+            let mut param = param.clone();
+            match &mut param {
+                GenericParam::Type(type_param) => {
+                    type_param.attrs.clear();
+                    type_param.eq_token = None;
+                    type_param.default = None;
+                    for bound in &mut type_param.bounds {
+                        self.bound(scope, bound, &params, None)?;
+                    }
+                }
+                GenericParam::Lifetime(lifetime) => lifetime.attrs.clear(),
+                GenericParam::Const(const_param) => {
+                    const_param.attrs.clear();
+                    const_param.eq_token = None;
+                    const_param.default = None;
+                }
+            }
+            declared.push(param);
+        }
+        Ok(quote!(<#(#declared),*>))
+    }
+
+    fn where_clause(
+        &mut self,
+        scope: &Scope,
+        generics: &Generics,
+        params: &Params,
+    ) -> Result<String, String> {
+        let clause = match &generics.where_clause {
+            Some(clause) if !clause.predicates.is_empty() => clause,
+            _ => return Ok(String::new()),
+        };
+        let mut predicates = Vec::new();
+        for predicate in &clause.predicates {
+            let mut predicate = predicate.clone();
+            match &mut predicate {
+                WherePredicate::Type(predicate) => {
+                    self.ty(scope, &mut predicate.bounded_ty, params, None)?;
+                    for bound in &mut predicate.bounds {
+                        self.bound(scope, bound, params, None)?;
+                    }
+                }
+                WherePredicate::Lifetime(_) => {}
+                WherePredicate::Eq(_) => {
+                    return Err("equality predicates are not supported by library!".to_owned())
+                }
+            }
+            predicates.push(predicate);
+        }
+        Ok(format!(" {}", print(quote!(where #(#predicates),*))))
+    }
+
+    fn bound(
+        &mut self,
+        scope: &Scope,
+        bound: &mut TypeParamBound,
+        params: &Params,
+        self_ty: Option<&Type>,
+    ) -> Result<(), String> {
+        match bound {
+            // `?Sized` is left as written, like it is in library!
+            TypeParamBound::Trait(syn::TraitBound {
+                modifier: syn::TraitBoundModifier::Maybe(_),
+                ..
+            }) => Ok(()),
+            TypeParamBound::Trait(bound) => self.path(scope, &mut bound.path, params, self_ty),
+            TypeParamBound::Lifetime(_) => Ok(()),
+        }
+    }
+
+    /// Check that the type can be declared in library!, and resolve its paths
+    fn ty(
+        &mut self,
+        scope: &Scope,
+        ty: &mut Type,
+        params: &Params,
+        self_ty: Option<&Type>,
+    ) -> Result<(), String> {
+        match ty {
+            Type::Path(type_path) => {
+                if type_path.qself.is_some() {
+                    return Err("qualified paths are not supported by library!".to_owned());
+                }
+                if let Some(self_ty) = self_ty {
+                    if type_path.path.is_ident("Self") {
+                        *ty = self_ty.clone();
+                        return self.ty(scope, ty, params, None);
+                    }
+                }
+                self.path(scope, &mut type_path.path, params, self_ty)
+            }
+            Type::Reference(reference) => {
+                if let Some(lifetime) = &reference.lifetime {
+                    if lifetime.ident == "_" {
+                        reference.lifetime = None;
+                    }
+                }
+                self.ty(scope, &mut reference.elem, params, self_ty)
+            }
+            Type::Tuple(tuple) => tuple
+                .elems
+                .iter_mut()
+                .try_for_each(|elem| self.ty(scope, elem, params, self_ty)),
+            Type::Paren(paren) => {
+                *ty = (*paren.elem).clone();
+                self.ty(scope, ty, params, self_ty)
+            }
+            Type::Group(group) => {
+                *ty = (*group.elem).clone();
+                self.ty(scope, ty, params, self_ty)
+            }
+            Type::TraitObject(trait_object) => {
+                if trait_object.dyn_token.is_none() {
+                    trait_object.dyn_token = Some(Default::default());
+                }
+                trait_object
+                    .bounds
+                    .iter_mut()
+                    .try_for_each(|bound| self.bound(scope, bound, params, self_ty))
+            }
+            Type::Array(array) => self.ty(scope, &mut array.elem, params, self_ty),
+            Type::Slice(_) => Err("slice types are not supported by library!".to_owned()),
+            Type::Ptr(_) => Err("raw pointer types are not supported by library!".to_owned()),
+            Type::BareFn(_) => {
+                Err("function pointer types are not supported by library!".to_owned())
+            }
+            Type::ImplTrait(_) => Err("`impl Trait` is not supported by library!".to_owned()),
+            Type::Never(_) => Err("the never type is not supported by library!".to_owned()),
+            _ => Err(format!(
+                "the type `{}` is not supported by library!",
+                print(ty.to_token_stream()),
+            )),
+        }
+    }
+
+    fn path(
+        &mut self,
+        scope: &Scope,
+        path: &mut syn::Path,
+        params: &Params,
+        self_ty: Option<&Type>,
+    ) -> Result<(), String> {
+        for segment in &mut path.segments {
+            self.path_arguments(scope, &mut segment.arguments, params, self_ty)?;
+        }
+        scope.resolve(path, &params.types)
+    }
+
+    fn path_arguments(
+        &mut self,
+        scope: &Scope,
+        arguments: &mut PathArguments,
+        params: &Params,
+        self_ty: Option<&Type>,
+    ) -> Result<(), String> {
+        match arguments {
+            PathArguments::None => Ok(()),
+            PathArguments::AngleBracketed(args) => {
+                for arg in &mut args.args {
+                    match arg {
+                        GenericArgument::Type(ty) => self.ty(scope, ty, params, self_ty)?,
+                        GenericArgument::Binding(binding) => {
+                            self.ty(scope, &mut binding.ty, params, self_ty)?
+                        }
+                        GenericArgument::Constraint(constraint) => {
+                            for bound in &mut constraint.bounds {
+                                self.bound(scope, bound, params, self_ty)?;
+                            }
+                        }
+                        GenericArgument::Lifetime(lifetime) => {
+                            if lifetime.ident == "_" {
+                                return Err(
+                                    "anonymous lifetimes in generic arguments are not supported by library!"
+                                        .to_owned(),
+                                );
+                            }
+                        }
+                        GenericArgument::Const(_) => {}
+                    }
+                }
+                Ok(())
+            }
+            PathArguments::Parenthesized(args) => {
+                for input in &mut args.inputs {
+                    self.ty(scope, input, params, self_ty)?;
+                }
+                if let ReturnType::Type(_, ty) = &mut args.output {
+                    self.ty(scope, ty, params, self_ty)?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Report public items that can not be declared in library!
+    fn report_item(&mut self, scope: &Scope, item: &Item) {
+        let (ident, reason) = match item {
+            Item::Fn(item) if is_public(&item.vis) => (
+                &item.sig.ident,
+                "free functions are not supported by library!",
+            ),
+            Item::Type(item) if is_public(&item.vis) => {
+                (&item.ident, "type aliases are not supported by library!")
+            }
+            Item::Const(item) if is_public(&item.vis) => {
+                (&item.ident, "constants are not supported by library!")
+            }
+            Item::Static(item) if is_public(&item.vis) => {
+                (&item.ident, "statics are not supported by library!")
+            }
+            Item::Use(item) if is_public(&item.vis) => {
+                let tree = print(item.tree.to_token_stream());
+                self.unsupported.push(Unsupported {
+                    path: module_path(scope),
+                    reason: format!("the re-export `{}` is not followed", tree),
+                });
+                return;
+            }
+            _ => return,
+        };
+        self.report(scope, ident, reason);
+    }
+
+    fn report(&mut self, scope: &Scope, ident: &Ident, reason: &str) {
+        self.report_path(scope, &ident.to_string(), reason);
+    }
+
+    fn report_path(&mut self, scope: &Scope, item: &str, reason: &str) {
+        self.unsupported.push(Unsupported {
+            path: format!("{}::{}", module_path(scope), item),
+            reason: reason.to_owned(),
+        });
+    }
+}
+
+/// A trait or an impl block with its methods
+struct ImplBlock {
+    ident: Ident,
+    header: String,
+    // The associated types of a trait
+    assoc_types: Vec<Ident>,
+    methods: Vec<(Ident, String)>,
+}
+
+impl ImplBlock {
+    fn into_lines(self) -> Vec<String> {
+        if self.assoc_types.is_empty() && self.methods.is_empty() {
+            return vec![format!("{} {{}}", self.header)];
+        }
+        let mut lines = vec![format!("{} {{", self.header)];
+        lines.extend(
+            self.assoc_types
+                .into_iter()
+                .map(|ident| format!("    type {};", ident)),
+        );
+        lines.extend(
+            self.methods
+                .into_iter()
+                .map(|(_, method)| format!("    {}", method)),
+        );
+        lines.push("}".to_owned());
+        lines
+    }
+}
+
+impl Module {
+    fn is_empty(&self) -> bool {
+        self.items.is_empty() && self.modules.is_empty()
+    }
+
+    /// Print the module as a `library!` invocation
+    pub(crate) fn print_library(&self) -> String {
+        let mut out = String::from("reflect::library! {\n");
+        self.print("extern crate", 1, &mut out);
+        out.push_str("}\n");
+        out
+    }
+
+    fn print(&self, keyword: &str, depth: usize, out: &mut String) {
+        let indent = "    ".repeat(depth);
+        out.push_str(&format!("{}{} {} {{\n", indent, keyword, self.ident));
+
+        let mut first = true;
+        for item in &self.items {
+            if !first {
+                out.push('\n');
+            }
+            first = false;
+            for line in item {
+                out.push_str(&format!("{}    {}\n", indent, line));
+            }
+        }
+        for module in &self.modules {
+            if !first {
+                out.push('\n');
+            }
+            first = false;
+            module.print("mod", depth + 1, out);
+        }
+
+        out.push_str(&format!("{}}}\n", indent));
+    }
+}
+
+pub(crate) fn parse_file(path: &FsPath) -> Result<syn::File, Error> {
+    let source = fs::read_to_string(path).map_err(|err| Error::Io(PathBuf::from(path), err))?;
+    syn::parse_file(&source).map_err(Error::Parse)
+}
+
+/// Types that can not be constructed or destructured are declared by name
+fn opaque_type(ident: &Ident, generics: &Generics) -> Vec<String> {
+    let args = generics.params.iter().map(|param| match param {
+        GenericParam::Type(param) => param.ident.to_token_stream(),
+        GenericParam::Lifetime(param) => param.lifetime.to_token_stream(),
+        GenericParam::Const(param) => param.ident.to_token_stream(),
+    });
+    let args = if generics.params.is_empty() {
+        None
+    } else {
+        Some(quote!(<#(#args),*>))
+    };
+    vec![print(quote!(type #ident #args;))]
+}
+
+fn params_of(generics: &Generics, parent_params: &Params) -> Params {
+    let mut params = parent_params.clone();
+    for param in &generics.params {
+        match param {
+            GenericParam::Type(param) => params.types.push(param.ident.clone()),
+            // A const param used as generic argument is parsed as a type
+            GenericParam::Const(param) => params.types.push(param.ident.clone()),
+            GenericParam::Lifetime(_) => {}
+        }
+    }
+    params
+}
+
+fn is_public(vis: &Visibility) -> bool {
+    matches!(vis, Visibility::Public(_))
+}
+
+/// Items that are not part of the public API: `#[doc(hidden)]`, or only
+/// compiled for tests
+fn is_hidden(item: &Item) -> bool {
+    let attrs = match item {
+        Item::Struct(item) => &item.attrs,
+        Item::Enum(item) => &item.attrs,
+        Item::Union(item) => &item.attrs,
+        Item::Trait(item) => &item.attrs,
+        Item::Impl(item) => &item.attrs,
+        Item::Mod(item) => &item.attrs,
+        Item::Macro(item) => &item.attrs,
+        Item::Fn(item) => &item.attrs,
+        Item::Type(item) => &item.attrs,
+        Item::Const(item) => &item.attrs,
+        Item::Static(item) => &item.attrs,
+        Item::Use(item) => &item.attrs,
+        _ => return false,
+    };
+    attrs.iter().any(|attr| {
+        let tokens = attr.tokens.to_string();
+        attr.path.is_ident("doc") && tokens == "(hidden)"
+            || attr.path.is_ident("cfg") && (tokens == "(test)" || tokens == "(doctest)")
+    })
+}
+
+fn is_macro_export(attrs: &[Attribute]) -> bool {
+    attrs.iter().any(|attr| attr.path.is_ident("macro_export"))
+}
+
+fn is_self_pattern(pat: &Pat) -> bool {
+    match pat {
+        Pat::Ident(pat) => pat.ident == "self",
+        _ => false,
+    }
+}
+
+fn module_path(scope: &Scope) -> String {
+    idents_to_string(&scope.module)
+}
+
+fn idents_to_string(idents: &[Ident]) -> String {
+    let idents: Vec<_> = idents.iter().map(Ident::to_string).collect();
+    idents.join("::")
+}
@@ -0,0 +1,133 @@
+//! Generate [`reflect::library!`] declarations from the source of an existing
+//! crate, instead of writing them by hand.
+//!
+//! The public types, traits, inherent impls and exported macros of the crate
+//! are declared, with generics and where clauses translated and paths made
+//! absolute. Items that can not be declared in `library!` are left out and
+//! reported, together with the reason.
+//!
+//! [`reflect::library!`]: https://docs.rs/reflect/0.0.7/reflect/macro.library.html
+//!
+//! From a build script the generated library can be written to `OUT_DIR`:
+//!
+//! ```ignore
+//! // build.rs
+//! fn main() {
+//!     reflect_library_gen::build("serde", "vendor/serde/src/lib.rs", "serde_library.rs").unwrap();
+//! }
+//! ```
+//!
+//! and included where the library would otherwise be declared:
+//!
+//! ```ignore
+//! include!(concat!(env!("OUT_DIR"), "/serde_library.rs"));
+//! ```
+//!
+//! The `reflect-library-gen` binary prints the library for a source file:
+//!
+//! ```text
+//! reflect-library-gen serde vendor/serde/src/lib.rs > src/serde_library.rs
+//! ```
+
+#![doc(html_root_url = "https://docs.rs/reflect-library-gen/0.0.7")]
+
+mod generate;
+mod print;
+mod resolve;
+
+use crate::generate::{parse_file, Generator};
+use proc_macro2::Span;
+use std::env;
+use std::fmt::{self, Display};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use syn::Ident;
+
+/// A generated `library!` declaration
+pub struct Library {
+    /// The `library! { ... }` invocation
+    pub source: String,
+    /// The items that were left out of the library, or declared without all
+    /// of their details
+    pub unsupported: Vec<Unsupported>,
+}
+
+/// An item of the crate that could not be fully declared in the library
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Unsupported {
+    /// The path of the item, like `serde::ser::Serializer::serialize_bool`
+    pub path: String,
+    pub reason: String,
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Io(PathBuf, io::Error),
+    Parse(syn::Error),
+}
+
+/// Generate the library of a crate from the root source file of the crate,
+/// usually `src/lib.rs`. Out-of-line modules are read from the files next to
+/// it.
+pub fn generate(crate_name: &str, root: impl AsRef<Path>) -> Result<Library, Error> {
+    let root = root.as_ref();
+    let file = parse_file(root)?;
+    let dir = root.parent().unwrap_or_else(|| Path::new(""));
+    generate_items(crate_name, &file.items, Some(dir))
+}
+
+/// Generate the library of a crate from the source of its root module.
+/// Out-of-line modules are reported as unsupported.
+pub fn generate_from_str(crate_name: &str, source: &str) -> Result<Library, Error> {
+    let file = syn::parse_file(source).map_err(Error::Parse)?;
+    generate_items(crate_name, &file.items, None)
+}
+
+fn generate_items(
+    crate_name: &str,
+    items: &[syn::Item],
+    dir: Option<&Path>,
+) -> Result<Library, Error> {
+    let crate_name = Ident::new(&crate_name.replace('-', "_"), Span::call_site());
+    let mut generator = Generator::new();
+    let module = generator.generate_crate(crate_name, items, dir)?;
+    Ok(Library {
+        source: module.print_library(),
+        unsupported: generator.unsupported,
+    })
+}
+
+/// Generate the library of a crate in a build script, and write it to
+/// `out_file` in `OUT_DIR`. Unsupported items are printed as cargo warnings.
+pub fn build(crate_name: &str, root: impl AsRef<Path>, out_file: &str) -> Result<(), Error> {
+    let root = root.as_ref();
+    // Out-of-line modules are read from the directory of the root file
+    let dir = root.parent().filter(|dir| !dir.as_os_str().is_empty());
+    println!("cargo:rerun-if-changed={}", dir.unwrap_or(root).display());
+    let library = generate(crate_name, root)?;
+    for unsupported in &library.unsupported {
+        println!("cargo:warning={}", unsupported);
+    }
+
+    let out_dir = env::var_os("OUT_DIR").expect("OUT_DIR is set by cargo for build scripts");
+    let out_path = Path::new(&out_dir).join(out_file);
+    fs::write(&out_path, library.source).map_err(|err| Error::Io(out_path, err))
+}
+
+impl Display for Unsupported {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.reason)
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(path, err) => write!(f, "{}: {}", path.display(), err),
+            Error::Parse(err) => write!(f, "failed to parse the source: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
@@ -0,0 +1,28 @@
+use std::env;
+use std::process;
+
+const USAGE: &str = "usage: reflect-library-gen <crate-name> <path/to/lib.rs>";
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let (crate_name, root) = match args.as_slice() {
+        [crate_name, root] => (crate_name, root),
+        _ => {
+            eprintln!("{}", USAGE);
+            process::exit(2);
+        }
+    };
+
+    match reflect_library_gen::generate(crate_name, root) {
+        Ok(library) => {
+            print!("{}", library.source);
+            for unsupported in &library.unsupported {
+                eprintln!("warning: {}", unsupported);
+            }
+        }
+        Err(err) => {
+            eprintln!("error: {}", err);
+            process::exit(1);
+        }
+    }
+}
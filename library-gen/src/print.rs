@@ -0,0 +1,113 @@
+use proc_macro2::{Delimiter, Spacing, TokenStream, TokenTree};
+
+/// Print tokens the way they would be written by hand, instead of with a space
+/// between every token like `TokenStream::to_string`.
+pub(crate) fn print(tokens: TokenStream) -> String {
+    let mut atoms = Vec::new();
+    flatten(tokens, &mut atoms);
+
+    let mut out = String::new();
+    // The `>` of a `for<'a>` binder is followed by a space, unlike the `>` of
+    // generic arguments in a path
+    let mut in_binder = false;
+    let mut after_binder = false;
+    for (i, atom) in atoms.iter().enumerate() {
+        if i > 0 && (after_binder || needs_space(&atoms[i - 1], atom)) {
+            out.push(' ');
+        }
+        out.push_str(atom);
+        after_binder = in_binder && atom == ">";
+        in_binder = if i > 0 && atoms[i - 1] == "for" && atom == "<" {
+            true
+        } else {
+            in_binder && !after_binder
+        };
+    }
+    out
+}
+
+/// Split the tokens into the strings to print, where multi character operators
+/// and lifetimes are joined into one string
+fn flatten(tokens: TokenStream, atoms: &mut Vec<String>) {
+    let mut joint = false;
+    for token in tokens {
+        match token {
+            TokenTree::Group(group) => {
+                let (open, close) = match group.delimiter() {
+                    Delimiter::Parenthesis => ("(", ")"),
+                    Delimiter::Bracket => ("[", "]"),
+                    Delimiter::Brace => ("{", "}"),
+                    Delimiter::None => ("", ""),
+                };
+                if !open.is_empty() {
+                    atoms.push(open.to_owned());
+                }
+                flatten(group.stream(), atoms);
+                if !close.is_empty() {
+                    atoms.push(close.to_owned());
+                }
+                joint = false;
+            }
+            TokenTree::Punct(punct) => {
+                if joint {
+                    atoms.last_mut().unwrap().push(punct.as_char());
+                } else {
+                    atoms.push(punct.as_char().to_string());
+                }
+                joint = punct.spacing() == Spacing::Joint;
+            }
+            TokenTree::Ident(ident) => {
+                if joint {
+                    // The ident of a lifetime
+                    atoms.last_mut().unwrap().push_str(&ident.to_string());
+                } else {
+                    atoms.push(ident.to_string());
+                }
+                joint = false;
+            }
+            TokenTree::Literal(literal) => {
+                atoms.push(literal.to_string());
+                joint = false;
+            }
+        }
+    }
+}
+
+fn is_ident(atom: &str) -> bool {
+    atom.chars().all(|c| c.is_alphanumeric() || c == '_')
+}
+
+/// Keywords that can be followed by an absolute path
+fn is_keyword(atom: &str) -> bool {
+    matches!(atom, "as" | "dyn" | "impl" | "mut" | "where")
+}
+
+fn needs_space(prev: &str, next: &str) -> bool {
+    if matches!(prev, "(" | "[" | "{" | "<" | "&" | "::" | "?") {
+        return false;
+    }
+    match next {
+        "," | ";" | ")" | "]" | "}" | ":" => false,
+        _ if next.bytes().all(|b| b == b'>') => false,
+        "<" | "(" => !(is_ident(prev) || prev == ">"),
+        "::" => !(is_ident(prev) && !is_keyword(prev) || prev == ">"),
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::print;
+    use quote::quote;
+
+    #[test]
+    fn test_print() {
+        let tokens = quote! {
+            fn get<'a, T: ?Sized + ::std::clone::Clone>(&'a mut self, ::std::option::Option<(T,)>, &dyn Fn(T) -> T) -> &'a Vec<Vec<T>> where T: 'a, for<'b> T: ::std::ops::Fn(&'b T);
+        };
+        assert_eq!(
+            print(tokens),
+            "fn get<'a, T: ?Sized + ::std::clone::Clone>(&'a mut self, ::std::option::Option<(T,)>, &dyn Fn(T) -> T) -> &'a Vec<Vec<T>> where T: 'a, for<'b> T: ::std::ops::Fn(&'b T);",
+        );
+    }
+}
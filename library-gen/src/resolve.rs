@@ -0,0 +1,261 @@
+use proc_macro2::Span;
+use std::collections::{BTreeMap, BTreeSet};
+use syn::punctuated::Punctuated;
+use syn::{Ident, Item, Path, PathArguments, PathSegment, Token, UseTree};
+
+const PRIMITIVES: &[&str] = &[
+    "bool", "char", "str", "i8", "i16", "i32", "i64", "i128", "isize", "u8", "u16", "u32", "u64",
+    "u128", "usize", "f32", "f64",
+];
+
+const PRELUDE: &[(&str, &str)] = &[
+    ("Box", "std::boxed::Box"),
+    ("String", "std::string::String"),
+    ("ToString", "std::string::ToString"),
+    ("Vec", "std::vec::Vec"),
+    ("Option", "std::option::Option"),
+    ("Result", "std::result::Result"),
+    ("Clone", "std::clone::Clone"),
+    ("Copy", "std::marker::Copy"),
+    ("Send", "std::marker::Send"),
+    ("Sync", "std::marker::Sync"),
+    ("Sized", "std::marker::Sized"),
+    ("Unpin", "std::marker::Unpin"),
+    ("Default", "std::default::Default"),
+    ("Drop", "std::ops::Drop"),
+    ("Fn", "std::ops::Fn"),
+    ("FnMut", "std::ops::FnMut"),
+    ("FnOnce", "std::ops::FnOnce"),
+    ("PartialEq", "std::cmp::PartialEq"),
+    ("Eq", "std::cmp::Eq"),
+    ("PartialOrd", "std::cmp::PartialOrd"),
+    ("Ord", "std::cmp::Ord"),
+    ("AsRef", "std::convert::AsRef"),
+    ("AsMut", "std::convert::AsMut"),
+    ("From", "std::convert::From"),
+    ("Into", "std::convert::Into"),
+    ("Iterator", "std::iter::Iterator"),
+    ("IntoIterator", "std::iter::IntoIterator"),
+    ("Extend", "std::iter::Extend"),
+    ("ToOwned", "std::borrow::ToOwned"),
+];
+
+/// The names that are visible inside of a module, used to turn the paths in
+/// the source into paths that mean the same thing inside of `library!`
+pub(crate) struct Scope {
+    // The path of the module, starting with the crate name
+    pub(crate) module: Vec<Ident>,
+    // Types, traits and modules declared in the module
+    local: BTreeSet<Ident>,
+    // Names brought into scope with `use`, mapped to their absolute paths
+    imports: BTreeMap<Ident, Vec<Ident>>,
+}
+
+impl Scope {
+    pub(crate) fn new(module: Vec<Ident>, items: &[Item]) -> Self {
+        let mut scope = Scope {
+            module,
+            local: BTreeSet::new(),
+            imports: BTreeMap::new(),
+        };
+
+        for item in items {
+            let ident = match item {
+                Item::Struct(item) => &item.ident,
+                Item::Enum(item) => &item.ident,
+                Item::Union(item) => &item.ident,
+                Item::Trait(item) => &item.ident,
+                Item::Type(item) => &item.ident,
+                Item::Mod(item) => &item.ident,
+                Item::Use(item) => {
+                    scope.add_imports(Vec::new(), item.leading_colon.is_some(), &item.tree);
+                    continue;
+                }
+                _ => continue,
+            };
+            scope.local.insert(ident.clone());
+        }
+        scope
+    }
+
+    fn add_imports(&mut self, mut prefix: Vec<Ident>, global: bool, tree: &UseTree) {
+        match tree {
+            UseTree::Path(path) => {
+                prefix.push(path.ident.clone());
+                self.add_imports(prefix, global, &path.tree);
+            }
+            UseTree::Name(name) => {
+                if name.ident == "self" {
+                    if let Some(last) = prefix.last().cloned() {
+                        self.add_import(last, prefix, global);
+                    }
+                } else {
+                    prefix.push(name.ident.clone());
+                    self.add_import(name.ident.clone(), prefix, global);
+                }
+            }
+            UseTree::Rename(rename) => {
+                if rename.ident != "self" {
+                    prefix.push(rename.ident.clone());
+                }
+                if rename.rename != "_" {
+                    self.add_import(rename.rename.clone(), prefix, global);
+                }
+            }
+            UseTree::Group(group) => {
+                for tree in &group.items {
+                    self.add_imports(prefix.clone(), global, tree);
+                }
+            }
+            // Names from glob imports can not be known without looking at the
+            // imported module
+            UseTree::Glob(_) => {}
+        }
+    }
+
+    fn add_import(&mut self, name: Ident, path: Vec<Ident>, global: bool) {
+        let path = if global {
+            path
+        } else {
+            match self.absolute_path(&path) {
+                Some(path) => path,
+                None => return,
+            }
+        };
+        self.imports.insert(name, path);
+    }
+
+    /// For a path starting with `crate`, `self` or `super`, the number of
+    /// those leading segments and the module path they stand for
+    fn keyword_prefix(&self, path: &[Ident]) -> Option<(usize, Vec<Ident>)> {
+        let first = path.first()?;
+        if first == "crate" {
+            Some((1, vec![self.module[0].clone()]))
+        } else if first == "self" {
+            Some((1, self.module.clone()))
+        } else if first == "super" {
+            let supers = path.iter().take_while(|ident| *ident == "super").count();
+            let len = self
+                .module
+                .len()
+                .checked_sub(supers)
+                .filter(|&len| len > 0)?;
+            Some((supers, self.module[..len].to_vec()))
+        } else {
+            None
+        }
+    }
+
+    /// The absolute path of a relative path in a `use` item
+    fn absolute_path(&self, path: &[Ident]) -> Option<Vec<Ident>> {
+        let first = path.first()?;
+        let (len, mut absolute) = if let Some(prefix) = self.keyword_prefix(path) {
+            prefix
+        } else if let Some(import) = self.imports.get(first) {
+            (1, import.clone())
+        } else if self.local.contains(first) {
+            let mut module = self.module.clone();
+            module.push(first.clone());
+            (1, module)
+        } else {
+            // A path starting with the name of another crate
+            (1, vec![first.clone()])
+        };
+        absolute.extend(path[len..].iter().cloned());
+        Some(absolute)
+    }
+
+    /// Rewrite a path so that it names the same item inside of `library!`,
+    /// where single segment paths are declared in the current module.
+    /// Returns an error message if the path can not be resolved.
+    pub(crate) fn resolve(&self, path: &mut Path, params: &[Ident]) -> Result<(), String> {
+        if path.leading_colon.is_some() {
+            return Ok(());
+        }
+
+        let first = path.segments[0].ident.clone();
+        if params.contains(&first) {
+            if path.segments.len() > 1 {
+                return Err(format!(
+                    "associated type paths like `{}` are not supported by library!",
+                    path_to_string(path),
+                ));
+            }
+            return Ok(());
+        }
+        if first == "Self" {
+            return Err("`Self` types in trait methods are not supported by library!".to_owned());
+        }
+
+        if path.segments.len() == 1 && self.local.contains(&first) {
+            // Declared in the same module inside of library!
+            return Ok(());
+        }
+
+        let idents: Vec<_> = path.segments.iter().map(|s| s.ident.clone()).collect();
+        let absolute = if let Some((len, prefix)) = self.keyword_prefix(&idents) {
+            replace_prefix(path, len, &prefix);
+            return Ok(());
+        } else if path.segments.len() == 1 && PRIMITIVES.contains(&&*first.to_string()) {
+            // A module named like a primitive, such as `core::str`, can not be
+            // used as a type
+            vec![
+                Ident::new("std", Span::call_site()),
+                Ident::new("primitive", Span::call_site()),
+                first.clone(),
+            ]
+        } else if let Some(import) = self.imports.get(&first) {
+            import.clone()
+        } else if self.local.contains(&first) {
+            let mut module = self.module.clone();
+            module.push(first.clone());
+            module
+        } else if let Some((_, std_path)) = PRELUDE.iter().find(|(name, _)| first == name) {
+            std_path
+                .split("::")
+                .map(|segment| Ident::new(segment, Span::call_site()))
+                .collect()
+        } else if path.segments.len() > 1 {
+            // A path starting with the name of another crate
+            vec![first.clone()]
+        } else {
+            return Err(format!(
+                "could not resolve `{}`, it may come from a glob import",
+                first,
+            ));
+        };
+
+        replace_prefix(path, 1, &absolute);
+        Ok(())
+    }
+}
+
+/// Replace the first `len` segments of a path with the given absolute path.
+/// The generic arguments of the last replaced segment are kept.
+fn replace_prefix(path: &mut Path, len: usize, absolute: &[Ident]) {
+    let rest: Vec<PathSegment> = path.segments.iter().skip(len).cloned().collect();
+    let arguments = match len {
+        0 => PathArguments::None,
+        _ => path.segments[len - 1].arguments.clone(),
+    };
+
+    let mut segments = Punctuated::new();
+    for (i, ident) in absolute.iter().enumerate() {
+        segments.push(PathSegment {
+            ident: ident.clone(),
+            arguments: if i + 1 == absolute.len() {
+                arguments.clone()
+            } else {
+                PathArguments::None
+            },
+        });
+    }
+    segments.extend(rest);
+
+    path.leading_colon = Some(Token![::](Span::call_site()));
+    path.segments = segments;
+}
+
+fn path_to_string(path: &Path) -> String {
+    crate::print::print(quote::ToTokens::into_token_stream(path.clone()))
+}
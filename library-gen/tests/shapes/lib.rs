@@ -0,0 +1,32 @@
+// The source of a crate that test_library.rs generates a library for
+
+pub struct Point {
+    pub x: i32,
+    pub y: i32,
+}
+
+pub enum Shape {
+    Circle(Point, u32),
+    Polygon(Vec<Point>),
+}
+
+pub trait Draw {
+    fn draw(&self, canvas: &mut String);
+}
+
+pub trait Outline {
+    type Points;
+    fn len(&self) -> usize;
+}
+
+pub struct Canvas {
+    lines: Vec<String>,
+}
+
+impl Canvas {
+    pub fn new() -> Self {
+        Canvas { lines: Vec::new() }
+    }
+
+    pub fn line(&mut self, from: &Point, to: &Point) {}
+}
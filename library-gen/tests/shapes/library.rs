@@ -0,0 +1,29 @@
+reflect::library! {
+    extern crate shapes {
+        struct Point {
+            pub x: ::std::primitive::i32,
+            pub y: ::std::primitive::i32,
+        }
+
+        enum Shape {
+            Circle(Point, ::std::primitive::u32),
+            Polygon(::std::vec::Vec<Point>),
+        }
+
+        trait Draw {
+            fn draw(&self, &mut ::std::string::String);
+        }
+
+        trait Outline {
+            type Points;
+            fn len(&self) -> ::std::primitive::usize;
+        }
+
+        type Canvas;
+
+        impl Canvas {
+            fn new() -> Canvas;
+            fn line(&mut self, &Point, &Point);
+        }
+    }
+}
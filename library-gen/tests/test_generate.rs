@@ -0,0 +1,319 @@
+use reflect_library_gen::{generate_from_str, Unsupported};
+
+#[test]
+fn test_generate() {
+    let source = r#"
+        use std::collections::BTreeMap;
+        use std::fmt::Display;
+
+        pub struct Point {
+            pub x: i32,
+            pub y: i32,
+        }
+
+        pub struct Cache<K, V = String> {
+            map: BTreeMap<K, V>,
+        }
+
+        pub enum Shape {
+            Circle(Point, u32),
+            Polygon(Vec<Point>),
+        }
+
+        pub trait Draw: Display {
+            fn draw(&self, canvas: &mut String);
+            fn bounds(&self) -> Option<(Point, Point)> {
+                None
+            }
+        }
+
+        impl<K: Ord, V> Cache<K, V> {
+            pub fn new() -> Self {
+                Cache { map: BTreeMap::new() }
+            }
+
+            pub fn get(&self, key: &K) -> Option<&V> {
+                self.map.get(key)
+            }
+
+            fn private(&self) {}
+        }
+
+        impl<K: Ord, V> Cache<K, V> {
+            pub fn insert(&mut self, key: K, value: V) {
+                self.map.insert(key, value);
+            }
+        }
+
+        impl Draw for Point {
+            fn draw(&self, canvas: &mut String) {}
+        }
+
+        pub mod geometry {
+            use super::Point;
+
+            pub struct Line {
+                pub from: Point,
+                pub to: crate::Point,
+            }
+
+            mod private {
+                pub struct Hidden;
+            }
+        }
+
+        #[macro_export]
+        macro_rules! point {
+            ($x:expr, $y:expr) => {};
+        }
+
+        #[cfg(test)]
+        mod tests {}
+    "#;
+
+    let expected = r#"reflect::library! {
+    extern crate shapes {
+        struct Point {
+            pub x: ::std::primitive::i32,
+            pub y: ::std::primitive::i32,
+        }
+
+        type Cache<K, V>;
+
+        enum Shape {
+            Circle(Point, ::std::primitive::u32),
+            Polygon(::std::vec::Vec<Point>),
+        }
+
+        trait Draw: ::std::fmt::Display {
+            fn draw(&self, &mut ::std::string::String);
+            fn bounds(&self) -> ::std::option::Option<(Point, Point)>;
+        }
+
+        impl<K: ::std::cmp::Ord, V> Cache<K, V> {
+            fn new() -> Cache<K, V>;
+            fn get(&self, &K) -> ::std::option::Option<&V>;
+            fn insert(&mut self, K, V);
+        }
+
+        macro point;
+
+        mod geometry {
+            struct Line {
+                pub from: ::shapes::Point,
+                pub to: ::shapes::Point,
+            }
+        }
+    }
+}
+"#;
+
+    let library = generate_from_str("shapes", source).unwrap();
+    assert_eq!(library.source, expected);
+    let unsupported: Vec<_> = library
+        .unsupported
+        .iter()
+        .map(Unsupported::to_string)
+        .collect();
+    let expected = [
+        "shapes::Cache: the default of `V` is dropped, because defaults of generic parameters are not supported by library!",
+    ];
+    assert_eq!(unsupported, expected);
+}
+
+#[test]
+fn test_unsafe() {
+    let source = r#"
+        pub unsafe trait Zeroable {}
+
+        pub trait Reset {
+            unsafe fn reset(&mut self);
+        }
+    "#;
+
+    let expected = r#"reflect::library! {
+    extern crate zeroable {
+        unsafe trait Zeroable {}
+
+        trait Reset {
+            unsafe fn reset(&mut self);
+        }
+    }
+}
+"#;
+
+    let library = generate_from_str("zeroable", source).unwrap();
+    assert_eq!(library.source, expected);
+    assert_eq!(library.unsupported, []);
+}
+
+#[test]
+fn test_associated_types() {
+    let source = r#"
+        pub trait Stream {
+            type Item;
+            type Error;
+            fn is_terminated(&self) -> bool;
+        }
+    "#;
+
+    let expected = r#"reflect::library! {
+    extern crate stream {
+        trait Stream {
+            type Item;
+            type Error;
+            fn is_terminated(&self) -> ::std::primitive::bool;
+        }
+    }
+}
+"#;
+
+    let library = generate_from_str("stream", source).unwrap();
+    assert_eq!(library.source, expected);
+    assert_eq!(library.unsupported, []);
+}
+
+#[test]
+fn test_const_generics() {
+    let source = r#"
+        pub struct Buf<const N: usize = 8> {
+            data: [u8; N],
+        }
+
+        impl<const N: usize> Buf<N> {
+            pub fn from_array(data: [u8; N]) -> Self {
+                Buf { data }
+            }
+
+            pub fn first<T>(items: [T; N]) -> Option<T> {
+                None
+            }
+        }
+    "#;
+
+    let expected = r#"reflect::library! {
+    extern crate buf {
+        type Buf<N>;
+
+        impl<const N: usize> Buf<N> {
+            fn from_array([::std::primitive::u8; N]) -> Buf<N>;
+            fn first<T>([T; N]) -> ::std::option::Option<T>;
+        }
+    }
+}
+"#;
+
+    let library = generate_from_str("buf", source).unwrap();
+    assert_eq!(library.source, expected);
+    let unsupported: Vec<_> = library
+        .unsupported
+        .iter()
+        .map(Unsupported::to_string)
+        .collect();
+    let expected = [
+        "buf::Buf: the default of `N` is dropped, because defaults of generic parameters are not supported by library!",
+    ];
+    assert_eq!(unsupported, expected);
+}
+
+#[test]
+fn test_higher_ranked() {
+    let source = r#"
+        pub trait Deserialize<'de> {}
+
+        pub struct Visitor;
+
+        impl Visitor {
+            pub fn for_each<F>(&self, f: F) where F: for<'a> Fn(&'a str) -> bool {}
+
+            pub fn owned<T>(value: T) -> T where for<'de> T: Deserialize<'de> {
+                value
+            }
+        }
+    "#;
+
+    let expected = r#"reflect::library! {
+    extern crate visit {
+        trait Deserialize<'de> {}
+
+        struct Visitor;
+
+        impl Visitor {
+            fn for_each<F>(&self, F) where F: for<'a> ::std::ops::Fn(&'a ::std::primitive::str) -> ::std::primitive::bool;
+            fn owned<T>(T) -> T where for<'de> T: Deserialize<'de>;
+        }
+    }
+}
+"#;
+
+    let library = generate_from_str("visit", source).unwrap();
+    assert_eq!(library.source, expected);
+    assert_eq!(library.unsupported, []);
+}
+
+#[test]
+fn test_unsupported() {
+    let source = r#"
+        pub struct Wrapper<T: ?Sized>(Box<T>);
+
+        pub trait Parse {
+            type Output: Default;
+            fn parse(input: &str) -> Self::Output;
+        }
+
+        impl<T> Wrapper<T> {
+            pub unsafe fn from_raw(ptr: *mut T) -> Self {
+                Wrapper(Box::from_raw(ptr))
+            }
+        }
+
+        pub fn parse_all(input: &str) {}
+
+        pub mod external;
+    "#;
+
+    let library = generate_from_str("wrapper", source).unwrap();
+    let unsupported: Vec<_> = library
+        .unsupported
+        .iter()
+        .map(Unsupported::to_string)
+        .collect();
+    let expected = [
+        "wrapper::Parse::Output: bounds on associated types are not supported by library!",
+        "wrapper::Parse::parse: `Self` types in trait methods are not supported by library!",
+        "wrapper::parse_all: free functions are not supported by library!",
+        "wrapper::external: out-of-line modules can only be followed when reading from a file",
+        "wrapper::Wrapper::from_raw: raw pointer types are not supported by library!",
+    ];
+    assert_eq!(unsupported, expected);
+}
+
+#[test]
+fn test_maybe_sized() {
+    let source = r#"
+        pub struct Wrapper<T: ?Sized>(Box<T>);
+
+        pub struct Formatter;
+
+        impl Formatter {
+            pub fn field<T: ?Sized + std::fmt::Debug>(&mut self, value: &T) {}
+        }
+    "#;
+
+    let expected = r#"reflect::library! {
+    extern crate fmt {
+        type Wrapper<T>;
+
+        struct Formatter;
+
+        impl Formatter {
+            fn field<T: ?Sized + ::std::fmt::Debug>(&mut self, &T);
+        }
+    }
+}
+"#;
+
+    let library = generate_from_str("fmt", source).unwrap();
+    assert_eq!(library.source, expected);
+    assert_eq!(library.unsupported, []);
+}
@@ -0,0 +1,55 @@
+use quote::quote;
+use reflect::*;
+use reflect_library_gen::generate_from_str;
+
+// The generated library for `shapes/lib.rs`, which must be accepted by
+// library! as it is
+include!("shapes/library.rs");
+
+#[test]
+fn test_generated_library_is_current() {
+    let library = generate_from_str("shapes", include_str!("shapes/lib.rs")).unwrap();
+    assert_eq!(library.source, include_str!("shapes/library.rs"));
+    assert_eq!(library.unsupported, []);
+}
+
+#[test]
+fn test_derive_with_generated_library() {
+    let input = quote! {
+        struct Picture {
+            corner: ::shapes::Point,
+        }
+    };
+
+    fn derive(ex: Execution) {
+        ex.make_trait_impl(RUNTIME::shapes::Draw, ex.target_type(), |block| {
+            block.make_function(RUNTIME::shapes::Draw::draw, |make_function| {
+                let receiver = make_function.arg(0);
+                let canvas = make_function.arg(1);
+                match receiver.data() {
+                    Data::Struct(Struct::Struct(receiver)) => {
+                        let corner = receiver.fields().next().unwrap().get_value();
+                        RUNTIME::shapes::Draw::draw.INVOKE(corner, canvas);
+                        make_function.unit()
+                    }
+                    _ => unimplemented!(),
+                }
+            });
+        });
+    }
+
+    let expected = quote! {
+        #[automatically_derived]
+        impl ::shapes::Draw for Picture {
+            fn draw<'__a1, '__a2>(&'__a1 self, __arg0: &'__a2 mut ::std::string::String) {
+                let __v0 = self;
+                let __v1 = __arg0;
+                let __v2 = &__v0.corner;
+                let _ = ::shapes::Draw::draw(__v2, __v1);
+            }
+        }
+    };
+
+    let output = reflect::derive(input, derive);
+    assert_eq!(output.to_string(), expected.to_string());
+}
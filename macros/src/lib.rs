@@ -7,9 +7,10 @@ use quote::{quote, ToTokens};
 use syn::parse::{Parse, ParseStream, Result};
 use syn::punctuated::Punctuated;
 use syn::{
-    braced, parenthesized, parse_macro_input, token, GenericArgument, GenericParam, Generics,
-    Ident, Lifetime, Path, PathArguments, PathSegment, ReturnType, Token, TypeParamBound,
-    TypeTraitObject, WherePredicate,
+    braced, bracketed, parenthesized, parse_macro_input, token, DeriveInput, Error,
+    GenericArgument, GenericParam, Generics, Ident, Lifetime, Path, PathArguments, PathSegment,
+    ReturnType, Token, TraitBound, TraitBoundModifier, TypeParamBound, TypeTraitObject,
+    WherePredicate,
 };
 
 use self::proc_macro::TokenStream;
@@ -24,6 +25,7 @@ enum Item {
     Impl(ItemImpl),
     Trait(ItemTrait),
     Macro(ItemMacro),
+    Data(ItemData),
 }
 
 struct ItemMod {
@@ -42,12 +44,24 @@ struct ItemImpl {
 }
 
 struct ItemTrait {
+    is_unsafe: bool,
     ident: Ident,
     generics: Generics,
+    supertraits: Punctuated<TypeParamBound, Token![+]>,
+    assoc_types: Vec<Ident>,
     functions: Vec<Function>,
 }
 
+// An enum or struct whose variants or fields are known, so that values of the
+// type can be constructed and destructured
+struct ItemData {
+    input: DeriveInput,
+    // Constructors of the variants, or of the struct itself
+    constructors: Vec<Function>,
+}
+
 struct Function {
+    is_unsafe: bool,
     name: Ident,
     generics: Generics,
     receiver: Receiver,
@@ -68,9 +82,20 @@ enum Receiver {
 
 enum Type {
     Tuple(Vec<Type>),
+    Array {
+        elem: Box<Type>,
+        len: Box<syn::Expr>,
+    },
     Path(Path),
+    // An associated type, like `<T as Iterator>::Item`
+    Projection {
+        self_ty: Box<Type>,
+        trait_path: Path,
+        name: Ident,
+    },
     TraitObject(TypeTraitObject),
     Reference {
+        and_token: Token![&],
         is_mut: bool,
         lifetime: Option<Lifetime>,
         inner: Box<Type>,
@@ -80,6 +105,7 @@ enum Type {
 enum ParentKind {
     Trait,
     Impl,
+    Data,
 }
 
 impl Parse for Input {
@@ -114,15 +140,19 @@ impl Item {
     fn parse(input: ParseStream, mod_path: &Path) -> Result<Self> {
         let lookahead = input.lookahead1();
         if lookahead.peek(Token![mod]) {
-            ItemMod::parse(&input, mod_path).map(Item::Mod)
+            ItemMod::parse(input, mod_path).map(Item::Mod)
         } else if lookahead.peek(Token![type]) {
             input.parse().map(Item::Type)
         } else if lookahead.peek(Token![impl]) {
             input.parse().map(Item::Impl)
-        } else if lookahead.peek(Token![trait]) {
+        } else if lookahead.peek(Token![trait])
+            || lookahead.peek(Token![unsafe]) && input.peek2(Token![trait])
+        {
             input.parse().map(Item::Trait)
         } else if lookahead.peek(Token![macro]) {
             input.parse().map(Item::Macro)
+        } else if lookahead.peek(Token![enum]) || lookahead.peek(Token![struct]) {
+            input.parse().map(Item::Data)
         } else {
             Err(lookahead.error())
         }
@@ -168,6 +198,7 @@ fn has_generics(input: ParseStream) -> bool {
     input.peek(Token![<])
         && (input.peek2(Token![>])
             || input.peek2(Token![#])
+            || input.peek2(Token![const])
             || (input.peek2(Ident) || input.peek2(Lifetime))
                 && (input.peek3(Token![:]) || input.peek3(Token![,]) || input.peek3(Token![>])))
 }
@@ -182,14 +213,14 @@ impl Parse for ItemImpl {
             Generics::default()
         };
         let segment = input.parse()?;
+        if input.peek(Token![for]) {
+            return Err(input.error(
+                "trait impls are not declared in library!, declare the trait and the type instead",
+            ));
+        }
         generics.where_clause = input.parse()?;
 
-        let content;
-        braced!(content in input);
-        let mut functions = Vec::new();
-        while !content.is_empty() {
-            functions.push(content.parse()?);
-        }
+        let functions = Function::parse_functions(input, None)?;
 
         Ok(ItemImpl {
             segment,
@@ -201,6 +232,7 @@ impl Parse for ItemImpl {
 
 impl Parse for ItemTrait {
     fn parse(input: ParseStream) -> Result<Self> {
+        let is_unsafe = input.parse::<Option<Token![unsafe]>>()?.is_some();
         input.parse::<Token![trait]>()?;
 
         let ident = input.parse()?;
@@ -210,25 +242,183 @@ impl Parse for ItemTrait {
         } else {
             Generics::default()
         };
+
+        let mut supertraits = Punctuated::new();
+        if input.peek(Token![:]) {
+            input.parse::<Token![:]>()?;
+            while !(input.peek(Token![where]) || input.peek(token::Brace)) {
+                supertraits.push_value(input.parse()?);
+                if !input.peek(Token![+]) {
+                    break;
+                }
+                supertraits.push_punct(input.parse()?);
+            }
+        }
         generics.where_clause = input.parse()?;
 
+        let mut assoc_types = Vec::new();
+        let mut functions = Function::parse_functions(input, Some(&mut assoc_types))?;
+
+        let mut item_trait = ItemTrait {
+            is_unsafe,
+            ident,
+            generics,
+            supertraits,
+            assoc_types,
+            functions: Vec::new(),
+        };
+        for function in &mut functions {
+            for ty in function.args.iter_mut().chain(&mut function.ret) {
+                item_trait.resolve_self_projections(ty)?;
+            }
+        }
+        item_trait.functions = functions;
+        Ok(item_trait)
+    }
+}
+
+impl ItemTrait {
+    /// Replace `Self::Item` with `<Self as Trait>::Item` for the associated
+    /// types of the trait
+    fn resolve_self_projections(&self, ty: &mut Type) -> Result<()> {
+        match ty {
+            Type::Tuple(types) => types
+                .iter_mut()
+                .try_for_each(|ty| self.resolve_self_projections(ty)),
+            Type::Array { elem, .. } => self.resolve_self_projections(elem),
+            Type::Reference { inner, .. } => self.resolve_self_projections(inner),
+            Type::Projection { self_ty, .. } => self.resolve_self_projections(self_ty),
+            Type::TraitObject(_) => Ok(()),
+            Type::Path(path) => match self.self_projection(path)? {
+                Some((trait_path, name)) => {
+                    *ty = Type::Projection {
+                        self_ty: Box::new(Type::Path(syn::parse_quote!(Self))),
+                        trait_path,
+                        name,
+                    };
+                    Ok(())
+                }
+                None => self.resolve_syn_path_self_projections(path),
+            },
+        }
+    }
+
+    fn resolve_syn_self_projections(&self, ty: &mut syn::Type) -> Result<()> {
+        use syn::Type::*;
+        match ty {
+            Path(type_path) => {
+                if let Some(qself) = &mut type_path.qself {
+                    self.resolve_syn_self_projections(&mut qself.ty)?;
+                } else if let Some((trait_path, name)) = self.self_projection(&type_path.path)? {
+                    *ty = syn::parse_quote!(<Self as #trait_path>::#name);
+                    return Ok(());
+                }
+                self.resolve_syn_path_self_projections(&mut type_path.path)
+            }
+            Reference(reference) => self.resolve_syn_self_projections(&mut reference.elem),
+            Tuple(type_tuple) => type_tuple
+                .elems
+                .iter_mut()
+                .try_for_each(|elem| self.resolve_syn_self_projections(elem)),
+            Array(array) => self.resolve_syn_self_projections(&mut array.elem),
+            Paren(paren) => self.resolve_syn_self_projections(&mut paren.elem),
+            Group(group) => self.resolve_syn_self_projections(&mut group.elem),
+            _ => Ok(()),
+        }
+    }
+
+    fn resolve_syn_path_self_projections(&self, path: &mut Path) -> Result<()> {
+        for segment in &mut path.segments {
+            if let PathArguments::AngleBracketed(args) = &mut segment.arguments {
+                for arg in &mut args.args {
+                    match arg {
+                        GenericArgument::Type(ty) => self.resolve_syn_self_projections(ty)?,
+                        GenericArgument::Binding(binding) => {
+                            self.resolve_syn_self_projections(&mut binding.ty)?
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The path of the trait and the name of the associated type for a path
+    /// like `Self::Item`
+    fn self_projection(&self, path: &Path) -> Result<Option<(Path, Ident)>> {
+        if path.leading_colon.is_some()
+            || path.segments.len() != 2
+            || path.segments[0].ident != "Self"
+            || !path.segments[0].arguments.is_empty()
+        {
+            return Ok(None);
+        }
+        let segment = &path.segments[1];
+        if !self.assoc_types.contains(&segment.ident) || !segment.arguments.is_empty() {
+            return Err(Error::new_spanned(
+                segment,
+                format!(
+                    "cannot find associated type `{}` in trait `{}`",
+                    segment.ident.to_token_stream(),
+                    self.ident,
+                ),
+            ));
+        }
+        let trait_ident = &self.ident;
+        let (_, ty_generics, _) = self.generics.split_for_impl();
+        let trait_path = syn::parse_quote!(#trait_ident #ty_generics);
+        Ok(Some((trait_path, segment.ident.clone())))
+    }
+}
+
+/// Parse an associated type of a trait, like `type Item;`
+fn parse_assoc_type(input: ParseStream) -> Result<Ident> {
+    input.parse::<Token![type]>()?;
+    let ident: Ident = input.parse()?;
+    if input.peek(Token![<]) {
+        return Err(input.error("generic associated types are not supported in library!"));
+    } else if input.peek(Token![:]) {
+        return Err(input.error("bounds on associated types are not supported in library!"));
+    }
+    input.parse::<Token![;]>()?;
+    Ok(ident)
+}
+
+impl Function {
+    /// Parse the functions in the body of an impl or a trait, and the
+    /// associated types of a trait into `assoc_types`
+    fn parse_functions(
+        input: ParseStream,
+        mut assoc_types: Option<&mut Vec<Ident>>,
+    ) -> Result<Vec<Self>> {
         let content;
         braced!(content in input);
         let mut functions = Vec::new();
         while !content.is_empty() {
+            if content.peek(Token![type]) {
+                match &mut assoc_types {
+                    Some(assoc_types) => assoc_types.push(parse_assoc_type(&content)?),
+                    None => {
+                        return Err(content
+                            .error("associated types are only supported in traits in library!"))
+                    }
+                }
+                continue;
+            } else if content.peek(Token![const]) && !content.peek2(Token![fn]) {
+                return Err(content.error("associated consts are not supported in library!"));
+            } else if content.peek(Token![pub]) {
+                return Err(content.error("library functions have no visibility, remove the `pub`"));
+            }
             functions.push(content.parse()?);
         }
-
-        Ok(ItemTrait {
-            ident,
-            generics,
-            functions,
-        })
+        Ok(functions)
     }
 }
 
 impl Parse for Function {
     fn parse(input: ParseStream) -> Result<Self> {
+        let is_unsafe = input.parse::<Option<Token![unsafe]>>()?.is_some();
         input.parse::<Token![fn]>()?;
         let name: Ident = input.parse()?;
 
@@ -248,6 +438,17 @@ impl Parse for Function {
 
         let mut args = Vec::new();
         while !argument_list.is_empty() {
+            if Receiver::peek(&argument_list) {
+                return Err(argument_list.error("`self` must be the first argument"));
+            }
+            if (argument_list.peek(Ident) || argument_list.peek(Token![_]))
+                && argument_list.peek2(Token![:])
+                && !argument_list.peek2(Token![::])
+            {
+                return Err(argument_list.error(
+                    "arguments of library functions are types only, remove the argument name",
+                ));
+            }
             args.push(argument_list.parse()?);
             if argument_list.is_empty() {
                 break;
@@ -264,9 +465,17 @@ impl Parse for Function {
 
         generics.where_clause = input.parse()?;
 
+        if input.peek(token::Brace) {
+            let body: proc_macro2::TokenTree = input.parse()?;
+            return Err(Error::new(
+                body.span(),
+                "library functions are declared without a body, replace the body with `;`",
+            ));
+        }
         input.parse::<Token![;]>()?;
 
         Ok(Function {
+            is_unsafe,
             name,
             generics,
             receiver,
@@ -278,8 +487,13 @@ impl Parse for Function {
 
 impl Parse for Receiver {
     fn parse(input: ParseStream) -> Result<Self> {
-        if input.peek(Token![self]) {
+        if input.peek(Token![mut]) && input.peek2(Token![self]) {
+            Err(input.error("the receiver binding cannot be `mut` in library!, write `self`"))
+        } else if input.peek(Token![self]) {
             input.parse::<Token![self]>()?;
+            if input.peek(Token![:]) && !input.peek(Token![::]) {
+                return Err(input.error("typed receivers are not supported in library!"));
+            }
             Ok(Receiver::ByValue)
         } else if input.peek(Token![&]) && input.peek2(Token![self]) {
             input.parse::<Token![&]>()?;
@@ -310,21 +524,59 @@ impl Parse for Type {
             } else {
                 Ok(Type::Tuple(content.into_iter().collect()))
             }
+        } else if lookahead.peek(token::Bracket) {
+            let content;
+            let fork = input.fork();
+            bracketed!(content in input);
+            let elem: Type = content.parse()?;
+            if !content.peek(Token![;]) {
+                return Err(unsupported_type(&fork.parse()?));
+            }
+            content.parse::<Token![;]>()?;
+            let len: syn::Expr = content.parse()?;
+
+            Ok(Type::Array {
+                elem: Box::new(elem),
+                len: Box::new(len),
+            })
         } else if lookahead.peek(Token![&]) {
-            input.parse::<Token![&]>()?;
+            let and_token: Token![&] = input.parse()?;
             let lifetime: Option<Lifetime> = input.parse()?;
             let mut_token: Option<Token![mut]> = input.parse()?;
             let inner: Type = input.parse()?;
 
             Ok(Type::Reference {
+                and_token,
                 is_mut: mut_token.is_some(),
                 lifetime,
                 inner: Box::new(inner),
             })
         } else if lookahead.peek(Token![dyn]) {
             Ok(Type::TraitObject(input.parse()?))
-        } else if lookahead.peek(Ident) || lookahead.peek(Token![::]) {
+        } else if lookahead.peek(Token![<]) {
+            let type_path: syn::TypePath = input.parse()?;
+            let (qself, mut trait_path) = match type_path.qself {
+                Some(qself)
+                    if qself.position > 0
+                        && qself.position + 1 == type_path.path.segments.len() =>
+                {
+                    (qself, type_path.path)
+                }
+                _ => return Err(unsupported_type(&syn::Type::Path(type_path))),
+            };
+            let name = trait_path.segments.pop().unwrap().into_value().ident;
+            Ok(Type::Projection {
+                self_ty: Box::new(syn::parse2(qself.ty.to_token_stream())?),
+                trait_path,
+                name,
+            })
+        } else if lookahead.peek(Ident)
+            || lookahead.peek(Token![::])
+            || lookahead.peek(Token![Self])
+        {
             input.parse().map(Type::Path)
+        } else if let Ok(ty) = input.fork().parse::<syn::Type>() {
+            Err(unsupported_type(&ty))
         } else {
             Err(lookahead.error())
         }
@@ -342,7 +594,76 @@ impl Parse for ItemMacro {
     }
 }
 
+impl Parse for ItemData {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let input: DeriveInput = input.parse()?;
+
+        let ty = &input.ident;
+        let (_, ty_generics, _) = input.generics.split_for_impl();
+        let constructor = |name: &Ident, fields: &syn::Fields| -> Result<Function> {
+            Ok(Function {
+                is_unsafe: false,
+                name: name.clone(),
+                generics: Generics::default(),
+                receiver: Receiver::None,
+                args: fields
+                    .iter()
+                    .map(|field| syn::parse2(field.ty.to_token_stream()))
+                    .collect::<Result<_>>()?,
+                ret: Some(syn::parse2(quote!(#ty #ty_generics))?),
+            })
+        };
+
+        let constructors = match &input.data {
+            syn::Data::Enum(data) => data
+                .variants
+                .iter()
+                .map(|variant| constructor(&variant.ident, &variant.fields))
+                .collect::<Result<_>>()?,
+            syn::Data::Struct(data) => vec![constructor(ty, &data.fields)?],
+            syn::Data::Union(data) => {
+                return Err(syn::Error::new_spanned(
+                    data.union_token,
+                    "unions are not supported",
+                ))
+            }
+        };
+
+        Ok(ItemData {
+            input,
+            constructors,
+        })
+    }
+}
+
+/// The error for a type that can not be used in a library declaration
+fn unsupported_type(ty: &syn::Type) -> Error {
+    use syn::Type::*;
+    let message = match ty {
+        Slice(_) => "slice types are not supported in library!",
+        Ptr(_) => "raw pointer types are not supported in library!",
+        BareFn(_) => {
+            "function pointer types are not supported in library!, use a generic parameter with an `Fn` bound instead"
+        }
+        ImplTrait(_) => {
+            "`impl Trait` is not supported in library!, use a generic parameter instead"
+        }
+        Never(_) => "the never type is not supported in library!",
+        Infer(_) => "the type placeholder `_` is not allowed in library!, write out the type",
+        Macro(_) => "macros in type position are not supported in library!",
+        _ => "unsupported type in library!",
+    };
+    Error::new_spanned(ty, message)
+}
+
 impl Receiver {
+    fn peek(input: ParseStream) -> bool {
+        input.peek(Token![self])
+            || input.peek(Token![&])
+                && (input.peek2(Token![self])
+                    || input.peek2(Token![mut]) && input.peek3(Token![self]))
+    }
+
     fn is_none(&self) -> bool {
         use self::Receiver::*;
         match self {
@@ -356,18 +677,40 @@ impl Receiver {
 pub fn library(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as Input);
 
-    let modules = input.crates.iter().map(declare_mod);
+    let modules = match input
+        .crates
+        .iter()
+        .map(declare_mod)
+        .collect::<Result<Vec<_>>>()
+    {
+        Ok(modules) => modules,
+        Err(err) => return err.to_compile_error().into(),
+    };
+
+    let mut registrations = Vec::new();
+    for module in &input.crates {
+        collect_registrations(module, &mut registrations);
+    }
 
     TokenStream::from(quote! {
         #[allow(non_snake_case)]
         mod RUNTIME {
-            extern crate reflect as _reflect;
+            use ::reflect as _reflect;
 
             #[allow(dead_code, non_snake_case)]
             pub fn MODULE() -> _reflect::Module {
                 _reflect::Module::root()
             }
 
+            // Makes the data types and traits known to reflect before any
+            // function signature is used
+            #[allow(dead_code, non_snake_case)]
+            pub fn REGISTER() {
+                #(
+                    #registrations;
+                )*
+            }
+
             #(
                 #modules
             )*
@@ -375,18 +718,50 @@ pub fn library(input: TokenStream) -> TokenStream {
     })
 }
 
-fn declare_mod(module: &ItemMod) -> TokenStream2 {
+/// Collect the expressions that register the data types and traits, with
+/// paths relative to the RUNTIME module
+fn collect_registrations(module: &ItemMod, registrations: &mut Vec<TokenStream2>) {
+    let path_to = |ident: &Ident| {
+        let mut path = module.path.clone();
+        path.leading_colon = None;
+        path.segments.push(PathSegment {
+            ident: ident.clone(),
+            arguments: PathArguments::None,
+        });
+        path
+    };
+
+    for item in &module.items {
+        match item {
+            Item::Mod(item) => collect_registrations(item, registrations),
+            Item::Data(item) => {
+                let path = path_to(&item.input.ident);
+                registrations.push(quote!(_reflect::runtime::RuntimeData::DATA(self::#path)));
+            }
+            // Traits without supertraits imply no other bounds, so they are
+            // registered lazily when first used
+            Item::Trait(item) if !item.supertraits.is_empty() => {
+                let path = path_to(&item.ident);
+                registrations.push(quote!(_reflect::runtime::RuntimeParent::SELF(self::#path)));
+            }
+            _ => {}
+        }
+    }
+}
+
+fn declare_mod(module: &ItemMod) -> Result<TokenStream2> {
     let path = &module.path;
     let name = path.segments.last();
     let name_str = name.map(|segment| segment.ident.to_string());
     let items = module
         .items
         .iter()
-        .map(|item| declare_item(item, &module.path));
+        .map(|item| declare_item(item, &module.path))
+        .collect::<Result<Vec<_>>>()?;
 
-    quote! {
+    Ok(quote! {
         pub mod #name {
-            extern crate reflect as _reflect;
+            use ::reflect as _reflect;
 
             #[allow(unused_imports)]
             use self::_reflect::runtime::prelude::*;
@@ -396,30 +771,56 @@ fn declare_mod(module: &ItemMod) -> TokenStream2 {
                 super::MODULE().get_module(#name_str)
             }
 
+            #[allow(dead_code, non_snake_case)]
+            pub fn REGISTER() {
+                super::REGISTER()
+            }
+
             struct __Indirect<T>(T);
 
             #(
                 #items
             )*
         }
-    }
+    })
 }
 
-fn declare_item(item: &Item, mod_path: &Path) -> TokenStream2 {
+fn declare_item(item: &Item, mod_path: &Path) -> Result<TokenStream2> {
     match item {
         Item::Mod(item) => declare_mod(item),
-        Item::Type(item) => declare_type(&item.segment.ident),
+        Item::Type(item) => Ok(declare_type(
+            &item.segment.ident,
+            item.segment.arguments.is_empty(),
+        )),
         Item::Impl(item) => declare_impl(item, mod_path),
         Item::Trait(item) => declare_trait(item, mod_path),
-        Item::Macro(item) => declare_macro(item),
+        Item::Macro(item) => Ok(declare_macro(item)),
+        Item::Data(item) => declare_data(item, mod_path),
     }
 }
 
-fn declare_type(name: &Ident) -> TokenStream2 {
+fn declare_type(name: &Ident, is_runtime_type: bool) -> TokenStream2 {
+    // Types without generic params can be used directly in signatures made
+    // at runtime
+    let runtime_type = if is_runtime_type {
+        let name_str = name.to_string();
+        Some(quote! {
+            impl _reflect::runtime::RuntimeType for #name {
+                fn SELF(self) -> _reflect::Type {
+                    MODULE().get_path_type(#name_str, &mut _reflect::GenericsEnv::default())
+                }
+            }
+        })
+    } else {
+        None
+    };
+
     quote! {
         #[derive(Copy, Clone)]
         #[allow(non_camel_case_types)]
         pub struct #name;
+
+        #runtime_type
     }
 }
 
@@ -429,12 +830,19 @@ fn declare_parent(
     mod_path: &Path,
     params: &[&GenericParam],
     parent_kind: ParentKind,
-) -> TokenStream2 {
+    item_trait: Option<&ItemTrait>,
+    data: Option<&DeriveInput>,
+) -> Result<TokenStream2> {
     let set_parent_params = if !generics.params.is_empty() {
-        let param_strings = generics.params.iter().cloned().map(|mut param| {
-            expand_generic_param(&mut param, mod_path, params);
-            param.to_token_stream().to_string()
-        });
+        let param_strings = generics
+            .params
+            .iter()
+            .cloned()
+            .map(|mut param| {
+                expand_generic_param(&mut param, mod_path, params)?;
+                Ok(param.to_token_stream().to_string())
+            })
+            .collect::<Result<Vec<_>>>()?;
 
         Some(quote! {
             parent_builder.set_generic_params(&[#(#param_strings),*]);
@@ -444,10 +852,7 @@ fn declare_parent(
     };
 
     let set_parent_constraints = if let Some(clause) = &generics.where_clause {
-        let constraint_strings = clause.predicates.iter().cloned().map(|mut predicate| {
-            expand_where_predicate(&mut predicate, mod_path, params);
-            predicate.to_token_stream().to_string()
-        });
+        let constraint_strings = expand_where_clause(clause, mod_path, params)?;
 
         Some(quote! {
             parent_builder.set_generic_constraints(&[#(#constraint_strings),*]);
@@ -456,12 +861,56 @@ fn declare_parent(
         None
     };
 
+    let set_parent_supertraits = match item_trait {
+        Some(item_trait) if !item_trait.supertraits.is_empty() => {
+            let bound_strings = item_trait
+                .supertraits
+                .iter()
+                .cloned()
+                .map(|mut bound| {
+                    expand_bound(&mut bound, mod_path, params)?;
+                    Ok(bound.to_token_stream().to_string())
+                })
+                .collect::<Result<Vec<_>>>()?;
+
+            Some(quote! {
+                parent_builder.set_supertraits(&[#(#bound_strings),*]);
+            })
+        }
+        _ => None,
+    };
+
+    let set_parent_unsafe = if item_trait.is_some_and(|item_trait| item_trait.is_unsafe) {
+        Some(quote! {
+            parent_builder.set_unsafe();
+        })
+    } else {
+        None
+    };
+
+    let set_parent_data = match data {
+        Some(data) => {
+            let mut data = data.clone();
+            expand_data(&mut data, mod_path, params)?;
+            let data_string = data.to_token_stream().to_string();
+
+            Some(quote! {
+                parent_builder.set_data(#data_string);
+            })
+        }
+        None => None,
+    };
+
     let parent = &parent_type.ident;
+    let (runtime_trait, runtime_fn) = match parent_kind {
+        ParentKind::Trait | ParentKind::Impl => (quote!(RuntimeParent), quote!(SELF)),
+        ParentKind::Data => (quote!(RuntimeData), quote!(DATA)),
+    };
     let parent_kind = match parent_kind {
         ParentKind::Trait => quote! {
             _reflect::ParentKind::Trait
         },
-        ParentKind::Impl => quote! {
+        ParentKind::Impl | ParentKind::Data => quote! {
             _reflect::ParentKind::Impl
         },
     };
@@ -475,27 +924,30 @@ fn declare_parent(
         },
         mod_path,
         params,
-    );
+    )?;
 
-    quote! {
-        impl _reflect::runtime::RuntimeParent for #parent {
-            fn SELF(self) -> ::std::rc::Rc<_reflect::Parent> {
+    Ok(quote! {
+        impl _reflect::runtime::#runtime_trait for #parent {
+            fn #runtime_fn(self) -> ::std::rc::Rc<_reflect::Parent> {
                 thread_local! {
                     static PARENT: ::std::rc::Rc<_reflect::Parent> = {
                         let mut parent_builder = _reflect::ParentBuilder::new(#parent_kind);
                         #set_parent_params
                         #set_parent_constraints
-                        parent_builder.set_path(|param_map: &mut _reflect::SynParamMap| #get_runtime_path);
+                        parent_builder.set_path(|env: &mut _reflect::GenericsEnv| #get_runtime_path);
+                        #set_parent_supertraits
+                        #set_parent_unsafe
+                        #set_parent_data
                         ::std::rc::Rc::new(parent_builder.into_parent())
                     };
                 }
                 PARENT.with(::std::rc::Rc::clone)
             }
         }
-    }
+    })
 }
 
-fn declare_impl(item: &ItemImpl, mod_path: &Path) -> TokenStream2 {
+fn declare_impl(item: &ItemImpl, mod_path: &Path) -> Result<TokenStream2> {
     let parent = &item.segment.ident;
     let params: &Vec<_> = &item.generics.params.iter().collect();
 
@@ -505,24 +957,36 @@ fn declare_impl(item: &ItemImpl, mod_path: &Path) -> TokenStream2 {
         mod_path,
         params,
         ParentKind::Impl,
-    );
+        None,
+        None,
+    )?;
 
     let functions = item
         .functions
         .iter()
-        .map(|f| declare_function(parent, !params.is_empty(), f, mod_path, params));
+        .map(|f| {
+            declare_function(
+                parent,
+                ParentKind::Impl,
+                !params.is_empty(),
+                f,
+                mod_path,
+                params,
+            )
+        })
+        .collect::<Result<Vec<_>>>()?;
 
-    quote! {
+    Ok(quote! {
         #declare_parent
         impl _reflect::runtime::RuntimeImpl for #parent {}
         #(
             #functions
         )*
-    }
+    })
 }
 
-fn declare_trait(item: &ItemTrait, mod_path: &Path) -> TokenStream2 {
-    let d_type = declare_type(&item.ident);
+fn declare_trait(item: &ItemTrait, mod_path: &Path) -> Result<TokenStream2> {
+    let d_type = declare_type(&item.ident, false);
     let parent = &item.ident;
     let params: &Vec<_> = &item.generics.params.iter().collect();
 
@@ -535,32 +999,174 @@ fn declare_trait(item: &ItemTrait, mod_path: &Path) -> TokenStream2 {
         mod_path,
         params,
         ParentKind::Trait,
-    );
+        Some(item),
+        None,
+    )?;
 
     let functions = item
         .functions
         .iter()
-        .map(|f| declare_function(parent, !params.is_empty(), f, mod_path, params));
+        .map(|f| {
+            declare_function(
+                parent,
+                ParentKind::Trait,
+                !params.is_empty(),
+                f,
+                mod_path,
+                params,
+            )
+        })
+        .collect::<Result<Vec<_>>>()?;
 
-    quote! {
+    Ok(quote! {
         #d_type
         #declare_parent
         impl _reflect::runtime::RuntimeTrait for #parent {}
         #(
             #functions
         )*
-    }
+    })
+}
+
+fn declare_data(item: &ItemData, mod_path: &Path) -> Result<TokenStream2> {
+    let d_type = declare_type(&item.input.ident, item.input.generics.params.is_empty());
+    let parent = &item.input.ident;
+    let params: &Vec<_> = &item.input.generics.params.iter().collect();
+
+    let (_, ty_generics, _) = item.input.generics.split_for_impl();
+    let segment: PathSegment = syn::parse2(quote!(#parent #ty_generics))?;
+    let declare_parent = declare_parent(
+        &item.input.generics,
+        &segment,
+        mod_path,
+        params,
+        ParentKind::Data,
+        None,
+        Some(&item.input),
+    )?;
+
+    let constructors = item.constructors.iter().map(|f| {
+        if let syn::Data::Struct(_) = item.input.data {
+            // The constructor of a struct is invoked directly on the type
+            let function = runtime_function(
+                parent,
+                ParentKind::Data,
+                !params.is_empty(),
+                f,
+                mod_path,
+                params,
+            )?;
+            let vars = (0..f.args.len()).map(|i| Ident::new(&format!("v{}", i), Span::call_site()));
+            let vars2 = vars.clone();
+
+            Ok(quote! {
+                impl #parent {
+                    pub fn INVOKE(
+                        self,
+                        #(
+                            #vars: _reflect::Value,
+                        )*
+                    ) -> _reflect::Value {
+                        thread_local! {
+                            static FUNCTION: ::std::rc::Rc<_reflect::Function> = #function;
+                        }
+                        FUNCTION.with(::std::rc::Rc::clone).invoke(&[#(#vars2),*])
+                    }
+                }
+            })
+        } else {
+            declare_function(
+                parent,
+                ParentKind::Data,
+                !params.is_empty(),
+                f,
+                mod_path,
+                params,
+            )
+        }
+    });
+    let constructors = constructors.collect::<Result<Vec<_>>>()?;
+
+    Ok(quote! {
+        #d_type
+        #declare_parent
+        #(
+            #constructors
+        )*
+    })
 }
 
 fn declare_function(
     parent: &Ident,
+    parent_kind: ParentKind,
     parent_has_generics: bool,
     function: &Function,
     mod_path: &Path,
     params: &[&GenericParam],
-) -> TokenStream2 {
+) -> Result<TokenStream2> {
     let name = &function.name;
-    let name_str = name.to_string();
+    let function_init = runtime_function(
+        parent,
+        parent_kind,
+        parent_has_generics,
+        function,
+        mod_path,
+        params,
+    )?;
+
+    let vars = (0..(!function.receiver.is_none() as usize + function.args.len()))
+        .map(|i| Ident::new(&format!("v{}", i), Span::call_site()));
+    let vars2 = vars.clone();
+
+    Ok(quote! {
+        impl __Indirect<#parent> {
+            #[allow(dead_code, unknown_lints, non_local_definitions)]
+            fn #name() {
+                #[allow(non_camel_case_types)]
+                #[derive(Copy, Clone)]
+                pub struct #name;
+
+                impl _reflect::runtime::RuntimeFunction for #name {
+                    fn SELF(self) -> ::std::rc::Rc<_reflect::Function> {
+                        thread_local! {
+                            static FUNCTION: ::std::rc::Rc<_reflect::Function> = #function_init;
+                        };
+                        FUNCTION.with(::std::rc::Rc::clone)
+                    }
+                }
+
+                impl #name {
+                    pub fn INVOKE(
+                        self,
+                        #(
+                            #vars: _reflect::Value,
+                        )*
+                    ) -> _reflect::Value {
+                        _reflect::runtime::RuntimeFunction::SELF(self).invoke(&[#(#vars2),*])
+                    }
+                }
+
+                impl #parent {
+                    #[allow(non_upper_case_globals)]
+                    pub const #name: #name = #name;
+                }
+            }
+        }
+    })
+}
+
+/// The expression that builds the runtime `Function` of a library function
+fn runtime_function(
+    parent: &Ident,
+    parent_kind: ParentKind,
+    parent_has_generics: bool,
+    function: &Function,
+    mod_path: &Path,
+    params: &[&GenericParam],
+) -> Result<TokenStream2> {
+    check_elided_lifetimes(function)?;
+
+    let name_str = function.name.to_string();
     let setup_receiver = match function.receiver {
         Receiver::None => None,
         Receiver::ByValue => Some(quote! {
@@ -580,12 +1186,26 @@ fn declare_function(
         .chain(params.iter().copied())
         .collect();
 
+    let set_sig_unsafe = if function.is_unsafe {
+        Some(quote! {
+            sig.set_unsafe();
+        })
+    } else {
+        None
+    };
+
     let function_has_generics = !function.generics.params.is_empty();
     let set_sig_params = if function_has_generics {
-        let param_strings = function.generics.params.iter().cloned().map(|mut param| {
-            expand_generic_param(&mut param, mod_path, params);
-            param.to_token_stream().to_string()
-        });
+        let param_strings = function
+            .generics
+            .params
+            .iter()
+            .cloned()
+            .map(|mut param| {
+                expand_generic_param(&mut param, mod_path, params)?;
+                Ok(param.to_token_stream().to_string())
+            })
+            .collect::<Result<Vec<_>>>()?;
 
         Some(quote! {
             sig.set_generic_params(&[#(#param_strings),*]);
@@ -596,17 +1216,14 @@ fn declare_function(
 
     let add_parent_params = if parent_has_generics {
         Some(quote! {
-            sig.add_parent_params(&mut parent.get_param_map().clone());
+            sig.add_parent_params(parent.get_generics_env());
         })
     } else {
         None
     };
 
     let set_sig_constraints = if let Some(clause) = &function.generics.where_clause {
-        let constraint_strings = clause.predicates.iter().cloned().map(|mut predicate| {
-            expand_where_predicate(&mut predicate, mod_path, params);
-            predicate.to_token_stream().to_string()
-        });
+        let constraint_strings = expand_where_clause(clause, mod_path, params)?;
 
         Some(quote! {
             sig.set_generic_constraints(&[#(#constraint_strings),*]);
@@ -615,68 +1232,50 @@ fn declare_function(
         None
     };
 
-    let setup_inputs = function.args.iter().map(|arg| {
-        let ty = to_runtime_type(arg, mod_path, params);
-        quote!(sig.add_input(|param_map: &mut _reflect::SynParamMap| {#ty});)
-    });
-    let set_output = function.ret.as_ref().map(|ty| {
-        let ty = to_runtime_type(&ty, mod_path, params);
-        quote!(sig.set_output(|param_map: &mut _reflect::SynParamMap| {#ty});)
-    });
-
-    let vars = (0..(!function.receiver.is_none() as usize + function.args.len()))
-        .map(|i| Ident::new(&format!("v{}", i), Span::call_site()));
-    let vars2 = vars.clone();
-
-    quote! {
-        impl __Indirect<#parent> {
-            #[allow(dead_code)]
-            fn #name() {
-                #[allow(non_camel_case_types)]
-                #[derive(Copy, Clone)]
-                pub struct #name;
-
-                impl _reflect::runtime::RuntimeFunction for #name {
-                    fn SELF(self) -> ::std::rc::Rc<_reflect::Function> {
-                        thread_local! {
-                            static FUNCTION: ::std::rc::Rc<_reflect::Function> = {
-                                let mut sig = _reflect::Signature::new();
-                                let parent = _reflect::runtime::RuntimeParent::SELF(#parent);
-                                #set_sig_params
-                                #add_parent_params
-                                #set_sig_constraints
-                                #setup_receiver
-                                #(
-                                    #setup_inputs
-                                )*
-                                #set_output
-                                let mut fun = _reflect::Function::get_function(#name_str, sig);
-                                fun.set_parent(parent);
-                                ::std::rc::Rc::new(fun)
-                            };
-                        };
-                        FUNCTION.with(::std::rc::Rc::clone)
-                    }
-                }
+    let setup_inputs = function
+        .args
+        .iter()
+        .map(|arg| {
+            let ty = to_runtime_type(arg, mod_path, params)?;
+            Ok(quote!(sig.add_input(|env: &mut _reflect::GenericsEnv| {#ty});))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let set_output = match &function.ret {
+        Some(ty) => {
+            let ty = to_runtime_type(ty, mod_path, params)?;
+            Some(quote!(sig.set_output(|env: &mut _reflect::GenericsEnv| {#ty});))
+        }
+        None => None,
+    };
 
-                impl #name {
-                    pub fn INVOKE(
-                        self,
-                        #(
-                            #vars: _reflect::Value,
-                        )*
-                    ) -> _reflect::Value {
-                        _reflect::runtime::RuntimeFunction::SELF(self).invoke(&[#(#vars2),*])
-                    }
-                }
+    let get_parent = match parent_kind {
+        ParentKind::Trait | ParentKind::Impl => quote! {
+            _reflect::runtime::RuntimeParent::SELF(#parent)
+        },
+        ParentKind::Data => quote! {
+            _reflect::runtime::RuntimeData::DATA(#parent)
+        },
+    };
 
-                impl #parent {
-                    #[allow(non_upper_case_globals)]
-                    pub const #name: #name = #name;
-                }
-            }
+    Ok(quote! {
+        {
+            REGISTER();
+            let mut sig = _reflect::Signature::new();
+            let parent = #get_parent;
+            #add_parent_params
+            #set_sig_params
+            #set_sig_constraints
+            #setup_receiver
+            #set_sig_unsafe
+            #(
+                #setup_inputs
+            )*
+            #set_output
+            let mut fun = _reflect::Function::get_function(#name_str, sig);
+            fun.set_parent(parent);
+            ::std::rc::Rc::new(fun)
         }
-    }
+    })
 }
 
 fn declare_macro(item: &ItemMacro) -> TokenStream2 {
@@ -702,171 +1301,403 @@ fn declare_macro(item: &ItemMacro) -> TokenStream2 {
     }
 }
 
-fn to_runtime_type(ty: &Type, mod_path: &Path, params: &[&GenericParam]) -> TokenStream2 {
+fn to_runtime_type(ty: &Type, mod_path: &Path, params: &[&GenericParam]) -> Result<TokenStream2> {
     match ty {
         Type::Tuple(types) => {
-            let types = types.iter().map(|ty| to_runtime_type(ty, mod_path, params));
-            quote! {
+            let types = types
+                .iter()
+                .map(|ty| to_runtime_type(ty, mod_path, params))
+                .collect::<Result<Vec<_>>>()?;
+            Ok(quote! {
                 _reflect::Type::tuple(&[#(#types),*])
-            }
+            })
+        }
+        Type::Array { elem, len } => {
+            let elem = to_runtime_type(elem, mod_path, params)?;
+            let len = len.to_token_stream().to_string();
+            Ok(quote! {
+                #elem.array(#len, env)
+            })
         }
         Type::Path(path) => {
             if let Some(ident) = path.get_ident() {
                 // Check if the path is a generic param
                 if ident_is_param(ident, params) {
                     let type_param = ident.to_string();
-                    return quote! {
-                        _reflect::Type::type_param_from_str(#type_param, param_map)
-                    };
+                    return Ok(quote! {
+                        _reflect::Type::type_param_from_str(#type_param, env)
+                    });
                 }
             }
             to_runtime_path_type(path, mod_path, params)
         }
 
+        Type::Projection {
+            self_ty,
+            trait_path,
+            name,
+        } => {
+            let self_ty = to_runtime_type(self_ty, mod_path, params)?;
+            let trait_path = to_runtime_path(trait_path, mod_path, params)?;
+            let name = name.to_string();
+            Ok(quote! {
+                #self_ty.get_associated_type(#trait_path, #name)
+            })
+        }
+
         Type::TraitObject(trait_object) => {
             let mut trait_object = trait_object.clone();
-            expand_trait_object(&mut trait_object, mod_path, params);
+            expand_trait_object(&mut trait_object, mod_path, params)?;
             let bound_strings = trait_object
                 .bounds
                 .iter()
                 .map(|bound| bound.to_token_stream().to_string());
 
-            quote! {
-                _reflect::Type::get_trait_object(&[#(#bound_strings),*], param_map)
-            }
+            Ok(quote! {
+                _reflect::Type::get_trait_object(&[#(#bound_strings),*], env)
+            })
         }
 
         Type::Reference {
             is_mut,
             lifetime,
             inner,
+            ..
         } if !is_mut => {
-            let inner = to_runtime_type(inner, mod_path, params);
+            let inner = to_runtime_type(inner, mod_path, params)?;
             if let Some(lifetime) = lifetime {
+                check_lifetime(lifetime, params)?;
                 let lifetime_str = lifetime.to_string();
-                quote! {
-                    #inner.reference_with_lifetime(#lifetime_str, param_map)
-                }
+                Ok(quote! {
+                    #inner.reference_with_lifetime(#lifetime_str, env)
+                })
             } else {
-                quote! {
+                Ok(quote! {
                     #inner.reference()
-                }
+                })
             }
         }
         Type::Reference {
             lifetime, inner, ..
         } => {
-            let inner = to_runtime_type(inner, mod_path, params);
+            let inner = to_runtime_type(inner, mod_path, params)?;
             if let Some(lifetime) = lifetime {
+                check_lifetime(lifetime, params)?;
                 let lifetime_str = lifetime.to_string();
-                quote! {
-                    #inner.reference_mut_with_lifetime(#lifetime_str, param_map)
-                }
+                Ok(quote! {
+                    #inner.reference_mut_with_lifetime(#lifetime_str, env)
+                })
             } else {
-                quote! {
+                Ok(quote! {
                     #inner.reference_mut()
-                }
+                })
             }
         }
     }
 }
 
+/// Like in Rust, a lifetime elided in the return type is the lifetime of
+/// `&self` or `&mut self`, or else the lifetime of the only lifetime position
+/// in the arguments
+fn check_elided_lifetimes(function: &Function) -> Result<()> {
+    let elided = match function.ret.as_ref().and_then(Type::elided_reference) {
+        Some(and_token) => and_token,
+        None => return Ok(()),
+    };
+    let borrows_self = match function.receiver {
+        Receiver::ByRef | Receiver::ByMut => true,
+        Receiver::None | Receiver::ByValue => false,
+    };
+    let positions: usize = function.args.iter().map(Type::lifetime_positions).sum();
+    if borrows_self || positions == 1 {
+        Ok(())
+    } else {
+        Err(Error::new(elided.span, "missing lifetime specifier"))
+    }
+}
+
+impl Type {
+    /// The `&` of the first reference with an elided lifetime
+    fn elided_reference(&self) -> Option<Token![&]> {
+        match self {
+            Type::Tuple(types) => types.iter().find_map(Type::elided_reference),
+            Type::Array { elem, .. } => elem.elided_reference(),
+            Type::Path(path) => syn_path_elided_reference(path),
+            Type::Projection {
+                self_ty,
+                trait_path,
+                ..
+            } => self_ty
+                .elided_reference()
+                .or_else(|| syn_path_elided_reference(trait_path)),
+            Type::TraitObject(trait_object) => syn_bounds_elided_reference(&trait_object.bounds),
+            Type::Reference {
+                and_token,
+                lifetime,
+                inner,
+                ..
+            } => match lifetime {
+                Some(_) => inner.elided_reference(),
+                None => Some(*and_token),
+            },
+        }
+    }
+
+    fn lifetime_positions(&self) -> usize {
+        match self {
+            Type::Tuple(types) => types.iter().map(Type::lifetime_positions).sum(),
+            Type::Array { elem, .. } => elem.lifetime_positions(),
+            Type::Path(path) => syn_path_lifetime_positions(path),
+            Type::Projection {
+                self_ty,
+                trait_path,
+                ..
+            } => self_ty.lifetime_positions() + syn_path_lifetime_positions(trait_path),
+            Type::TraitObject(trait_object) => trait_object
+                .bounds
+                .iter()
+                .map(|bound| match bound {
+                    TypeParamBound::Trait(bound) => syn_path_lifetime_positions(&bound.path),
+                    TypeParamBound::Lifetime(_) => 1,
+                })
+                .sum(),
+            Type::Reference { inner, .. } => 1 + inner.lifetime_positions(),
+        }
+    }
+}
+
+fn syn_path_elided_reference(path: &Path) -> Option<Token![&]> {
+    path.segments
+        .iter()
+        .filter_map(|segment| match &segment.arguments {
+            PathArguments::AngleBracketed(args) => Some(&args.args),
+            _ => None,
+        })
+        .flatten()
+        .find_map(|arg| match arg {
+            GenericArgument::Type(ty) => syn_elided_reference(ty),
+            GenericArgument::Binding(binding) => syn_elided_reference(&binding.ty),
+            _ => None,
+        })
+}
+
+// The elided lifetimes in `Fn(&T) -> &U` are higher-ranked, so only the
+// angle bracketed arguments of the bounds are looked at
+fn syn_bounds_elided_reference(
+    bounds: &Punctuated<TypeParamBound, Token![+]>,
+) -> Option<Token![&]> {
+    bounds.iter().find_map(|bound| match bound {
+        TypeParamBound::Trait(bound) => syn_path_elided_reference(&bound.path),
+        TypeParamBound::Lifetime(_) => None,
+    })
+}
+
+fn syn_elided_reference(ty: &syn::Type) -> Option<Token![&]> {
+    match ty {
+        syn::Type::Path(type_path) => syn_path_elided_reference(&type_path.path),
+        syn::Type::Reference(reference) => match reference.lifetime {
+            Some(_) => syn_elided_reference(&reference.elem),
+            None => Some(reference.and_token),
+        },
+        syn::Type::Tuple(type_tuple) => type_tuple.elems.iter().find_map(syn_elided_reference),
+        syn::Type::Array(array) => syn_elided_reference(&array.elem),
+        syn::Type::Paren(paren) => syn_elided_reference(&paren.elem),
+        syn::Type::TraitObject(trait_object) => syn_bounds_elided_reference(&trait_object.bounds),
+        _ => None,
+    }
+}
+
+fn syn_path_lifetime_positions(path: &Path) -> usize {
+    path.segments
+        .iter()
+        .filter_map(|segment| match &segment.arguments {
+            PathArguments::AngleBracketed(args) => Some(&args.args),
+            _ => None,
+        })
+        .flatten()
+        .map(|arg| match arg {
+            GenericArgument::Lifetime(_) => 1,
+            GenericArgument::Type(ty) => syn_lifetime_positions(ty),
+            GenericArgument::Binding(binding) => syn_lifetime_positions(&binding.ty),
+            _ => 0,
+        })
+        .sum()
+}
+
+fn syn_lifetime_positions(ty: &syn::Type) -> usize {
+    match ty {
+        syn::Type::Path(type_path) => syn_path_lifetime_positions(&type_path.path),
+        syn::Type::Reference(reference) => 1 + syn_lifetime_positions(&reference.elem),
+        syn::Type::Tuple(type_tuple) => type_tuple.elems.iter().map(syn_lifetime_positions).sum(),
+        syn::Type::Array(array) => syn_lifetime_positions(&array.elem),
+        syn::Type::Paren(paren) => syn_lifetime_positions(&paren.elem),
+        _ => 0,
+    }
+}
+
 fn ident_is_param(ident: &Ident, params: &[&GenericParam]) -> bool {
-    params.iter().any(|param| {
-        if let GenericParam::Type(type_param) = param {
-            ident == &type_param.ident
+    params.iter().any(|param| match param {
+        GenericParam::Type(type_param) => ident == &type_param.ident,
+        GenericParam::Const(const_param) => ident == &const_param.ident,
+        GenericParam::Lifetime(_) => false,
+    })
+}
+
+/// Lifetimes in a signature must be `'static` or declared as a generic param
+fn check_lifetime(lifetime: &Lifetime, params: &[&GenericParam]) -> Result<()> {
+    let is_declared = params.iter().any(|param| {
+        if let GenericParam::Lifetime(def) = param {
+            def.lifetime == *lifetime
         } else {
             false
         }
-    })
+    });
+
+    if is_declared || lifetime.ident == "static" {
+        Ok(())
+    } else {
+        Err(Error::new_spanned(
+            lifetime,
+            format!("use of undeclared lifetime name `{}`", lifetime),
+        ))
+    }
 }
 
-fn to_runtime_path_type(path: &Path, mod_path: &Path, params: &[&GenericParam]) -> TokenStream2 {
-    let path = to_runtime_path(path, mod_path, params);
-    quote! {
+fn to_runtime_path_type(
+    path: &Path,
+    mod_path: &Path,
+    params: &[&GenericParam],
+) -> Result<TokenStream2> {
+    let path = to_runtime_path(path, mod_path, params)?;
+    Ok(quote! {
         _reflect::runtime::RuntimeType::SELF(
             #path
         )
-    }
+    })
 }
 
-fn to_runtime_path(path: &Path, mod_path: &Path, params: &[&GenericParam]) -> TokenStream2 {
+fn to_runtime_path(path: &Path, mod_path: &Path, params: &[&GenericParam]) -> Result<TokenStream2> {
     let mut path = path.clone();
 
     let arguments = &mut path.segments.last_mut().unwrap().arguments;
-    expand_path_arguments(arguments, mod_path, params);
+    expand_path_arguments(arguments, mod_path, params)?;
+    check_path_lifetimes(arguments, params)?;
 
     // Check if path is defined in current module
-    if path.segments.len() == 1 && path.leading_colon.is_none() {
+    if path.segments.len() == 1 && path.leading_colon.is_none() && !path.is_ident("Self") {
         let segment = path.segments[0].to_token_stream().to_string();
-        quote! {
-            MODULE().get_path(#segment, param_map)
-        }
+        Ok(quote! {
+            MODULE().get_path(#segment, env)
+        })
     } else {
         let path_str = path.to_token_stream().to_string();
-        quote! {
-            _reflect::Path::path_from_str(#path_str, param_map)
+        Ok(quote! {
+            _reflect::Path::path_from_str(#path_str, env)
+        })
+    }
+}
+
+/// Check the lifetimes used in the generic arguments of a path in a signature
+fn check_path_lifetimes(arguments: &PathArguments, params: &[&GenericParam]) -> Result<()> {
+    if let PathArguments::AngleBracketed(generic_args) = arguments {
+        for arg in &generic_args.args {
+            match arg {
+                GenericArgument::Lifetime(lifetime) => check_lifetime(lifetime, params)?,
+                GenericArgument::Type(ty) => check_type_lifetimes(ty, params)?,
+                _ => {}
+            }
         }
     }
+    Ok(())
+}
+
+fn check_type_lifetimes(ty: &syn::Type, params: &[&GenericParam]) -> Result<()> {
+    match ty {
+        syn::Type::Path(type_path) => {
+            let arguments = &type_path.path.segments.last().unwrap().arguments;
+            check_path_lifetimes(arguments, params)
+        }
+        syn::Type::Reference(reference) => {
+            if let Some(lifetime) = &reference.lifetime {
+                check_lifetime(lifetime, params)?;
+            }
+            check_type_lifetimes(&reference.elem, params)
+        }
+        syn::Type::Tuple(type_tuple) => type_tuple
+            .elems
+            .iter()
+            .try_for_each(|elem| check_type_lifetimes(elem, params)),
+        syn::Type::Array(array) => check_type_lifetimes(&array.elem, params),
+        syn::Type::Paren(paren) => check_type_lifetimes(&paren.elem, params),
+        _ => Ok(()),
+    }
 }
 
 /// Expand module defined types inside of a PathArgument as fully qualified paths
-fn expand_path_arguments(arguments: &mut PathArguments, mod_path: &Path, params: &[&GenericParam]) {
+fn expand_path_arguments(
+    arguments: &mut PathArguments,
+    mod_path: &Path,
+    params: &[&GenericParam],
+) -> Result<()> {
     match arguments {
-        PathArguments::None => {}
+        PathArguments::None => Ok(()),
         PathArguments::AngleBracketed(generic_args) => {
-            generic_args.args.iter_mut().for_each(|arg| match arg {
+            generic_args.args.iter_mut().try_for_each(|arg| match arg {
                 GenericArgument::Type(ty) => expand_type(ty, mod_path, params),
 
                 GenericArgument::Binding(binding) => expand_type(&mut binding.ty, mod_path, params),
 
-                GenericArgument::Constraint(constraint) => {
-                    constraint.bounds.iter_mut().for_each(|bound| {
-                        if let TypeParamBound::Trait(bound) = bound {
-                            expand_path(&mut bound.path, mod_path, params)
-                        }
-                    })
-                }
-                GenericArgument::Const(_expr) => unimplemented!(),
-                GenericArgument::Lifetime(_) => {}
+                GenericArgument::Constraint(constraint) => constraint
+                    .bounds
+                    .iter_mut()
+                    .try_for_each(|bound| expand_bound(bound, mod_path, params)),
+                GenericArgument::Const(_) | GenericArgument::Lifetime(_) => Ok(()),
             })
         }
         PathArguments::Parenthesized(generic_args) => {
             generic_args
                 .inputs
                 .iter_mut()
-                .for_each(|ty| expand_type(ty, mod_path, params));
+                .try_for_each(|ty| expand_type(ty, mod_path, params))?;
 
             if let ReturnType::Type(_, ref mut ty) = generic_args.output {
-                expand_type(ty, mod_path, params)
+                expand_type(ty, mod_path, params)?;
             }
+            Ok(())
         }
     }
 }
 
 /// Expand module defined types inside of the PathArguments inside of a type
-fn expand_type(ty: &mut syn::Type, mod_path: &Path, params: &[&GenericParam]) {
+fn expand_type(ty: &mut syn::Type, mod_path: &Path, params: &[&GenericParam]) -> Result<()> {
     use syn::Type::*;
     match ty {
-        Path(type_path) => expand_path(&mut type_path.path, mod_path, params),
+        Path(type_path) => match &mut type_path.qself {
+            Some(qself) => {
+                expand_type(&mut qself.ty, mod_path, params)?;
+                expand_qualified_path(&mut type_path.path, qself, mod_path, params)
+            }
+            None => expand_path(&mut type_path.path, mod_path, params),
+        },
         Reference(reference) => expand_type(&mut reference.elem, mod_path, params),
         TraitObject(trait_object) => expand_trait_object(trait_object, mod_path, params),
         Tuple(type_tuple) => type_tuple
             .elems
             .iter_mut()
-            .for_each(|elem| expand_type(elem, mod_path, params)),
-
-        // TODO: maybe return syn::Error?
-        _ => unimplemented!("expand_type_arguments: Tried to expand unsupported type"),
+            .try_for_each(|elem| expand_type(elem, mod_path, params)),
+        Array(array) => expand_type(&mut array.elem, mod_path, params),
+        Paren(paren) => expand_type(&mut paren.elem, mod_path, params),
+        Group(group) => expand_type(&mut group.elem, mod_path, params),
+        _ => Err(unsupported_type(ty)),
     }
 }
 
 /// If path is an type defined in the current module, make the fully qualified
 /// type for that path
-fn expand_path(path: &mut syn::Path, mod_path: &Path, params: &[&GenericParam]) {
+fn expand_path(path: &mut syn::Path, mod_path: &Path, params: &[&GenericParam]) -> Result<()> {
     // Expand path arguments if any
     let path_arguments = &mut path.segments.last_mut().unwrap().arguments;
-    expand_path_arguments(path_arguments, mod_path, params);
+    expand_path_arguments(path_arguments, mod_path, params)?;
 
     // If the type is defined in the current scope, expand to the fully quallified path
     // FIXME: This test assumes that a type with one path segment and no leading
@@ -876,7 +1707,7 @@ fn expand_path(path: &mut syn::Path, mod_path: &Path, params: &[&GenericParam])
         let segment = &path.segments[0];
         let ident = &segment.ident;
 
-        if !ident_is_param(ident, params) {
+        if !ident_is_param(ident, params) && ident != "Self" {
             let mut segments = Punctuated::new();
             segments.extend(mod_path.segments.iter().cloned());
             segments.push(segment.clone());
@@ -884,48 +1715,134 @@ fn expand_path(path: &mut syn::Path, mod_path: &Path, params: &[&GenericParam])
             path.leading_colon = Some(Token![::](Span::call_site()));
         }
     }
+    Ok(())
+}
+
+/// Expand the trait of a qualified path like `<T as Iterator>::Item`, where
+/// the trait is the part of the path before the associated type
+fn expand_qualified_path(
+    path: &mut syn::Path,
+    qself: &mut syn::QSelf,
+    mod_path: &Path,
+    params: &[&GenericParam],
+) -> Result<()> {
+    for segment in &mut path.segments {
+        expand_path_arguments(&mut segment.arguments, mod_path, params)?;
+    }
+    if qself.position == 1 && path.leading_colon.is_none() {
+        let mut segments: Punctuated<PathSegment, Token![::]> =
+            mod_path.segments.iter().cloned().collect();
+        qself.position += segments.len();
+        segments.extend(path.segments.iter().cloned());
+        path.segments = segments;
+        path.leading_colon = Some(Token![::](Span::call_site()));
+    }
+    Ok(())
+}
+
+fn expand_bound(
+    bound: &mut TypeParamBound,
+    mod_path: &Path,
+    params: &[&GenericParam],
+) -> Result<()> {
+    match bound {
+        // `?Sized` is the only maybe bound, and always refers to the prelude
+        TypeParamBound::Trait(TraitBound {
+            modifier: TraitBoundModifier::Maybe(_),
+            ..
+        }) => Ok(()),
+        TypeParamBound::Trait(bound) => expand_path(&mut bound.path, mod_path, params),
+        TypeParamBound::Lifetime(_) => Ok(()),
+    }
 }
 
 fn expand_trait_object(
     trait_object: &mut TypeTraitObject,
     mod_path: &Path,
     params: &[&GenericParam],
-) {
-    trait_object.bounds.iter_mut().for_each(|bound| {
-        if let TypeParamBound::Trait(bound) = bound {
-            expand_path(&mut bound.path, mod_path, params)
-        }
-    })
+) -> Result<()> {
+    trait_object
+        .bounds
+        .iter_mut()
+        .try_for_each(|bound| expand_bound(bound, mod_path, params))
 }
 
-fn expand_generic_param(param: &mut GenericParam, mod_path: &Path, temp_params: &[&GenericParam]) {
-    if let GenericParam::Type(type_param) = param {
-        type_param.bounds.iter_mut().for_each(|bound| {
-            if let TypeParamBound::Trait(trait_bound) = bound {
-                expand_path(&mut trait_bound.path, mod_path, temp_params)
-            }
-        })
+/// Expand module defined types in the fields and generics of a data type
+fn expand_data(data: &mut DeriveInput, mod_path: &Path, params: &[&GenericParam]) -> Result<()> {
+    data.generics
+        .params
+        .iter_mut()
+        .try_for_each(|param| expand_generic_param(param, mod_path, params))?;
+    if let Some(clause) = &mut data.generics.where_clause {
+        clause
+            .predicates
+            .iter_mut()
+            .try_for_each(|predicate| expand_where_predicate(predicate, mod_path, params))?;
+    }
+
+    let fields: Vec<&mut syn::Field> = match &mut data.data {
+        syn::Data::Struct(data) => data.fields.iter_mut().collect(),
+        syn::Data::Enum(data) => data
+            .variants
+            .iter_mut()
+            .flat_map(|variant| variant.fields.iter_mut())
+            .collect(),
+        syn::Data::Union(data) => data.fields.named.iter_mut().collect(),
+    };
+    for field in fields {
+        expand_type(&mut field.ty, mod_path, params)?;
+    }
+    Ok(())
+}
+
+fn expand_generic_param(
+    param: &mut GenericParam,
+    mod_path: &Path,
+    temp_params: &[&GenericParam],
+) -> Result<()> {
+    match param {
+        GenericParam::Type(type_param) => type_param
+            .bounds
+            .iter_mut()
+            .try_for_each(|bound| expand_bound(bound, mod_path, temp_params)),
+        // The type of a const param is a primitive, so it is left as written
+        GenericParam::Lifetime(_) | GenericParam::Const(_) => Ok(()),
     }
 }
 
+/// Expand the predicates of a where clause into strings for the runtime
+fn expand_where_clause(
+    clause: &syn::WhereClause,
+    mod_path: &Path,
+    temp_params: &[&GenericParam],
+) -> Result<Vec<String>> {
+    clause
+        .predicates
+        .iter()
+        .cloned()
+        .map(|mut predicate| {
+            expand_where_predicate(&mut predicate, mod_path, temp_params)?;
+            Ok(predicate.to_token_stream().to_string())
+        })
+        .collect()
+}
+
 fn expand_where_predicate(
     predicate: &mut WherePredicate,
     mod_path: &Path,
     temp_params: &[&GenericParam],
-) {
+) -> Result<()> {
     match predicate {
         WherePredicate::Type(ty) => {
-            expand_type(&mut ty.bounded_ty, mod_path, temp_params);
-            ty.bounds.iter_mut().for_each(|bound| {
-                if let TypeParamBound::Trait(bound) = bound {
-                    expand_path(&mut bound.path, mod_path, temp_params)
-                }
-            })
+            expand_type(&mut ty.bounded_ty, mod_path, temp_params)?;
+            ty.bounds
+                .iter_mut()
+                .try_for_each(|bound| expand_bound(bound, mod_path, temp_params))
         }
         WherePredicate::Eq(eq) => {
-            expand_type(&mut eq.lhs_ty, mod_path, temp_params);
-            expand_type(&mut eq.rhs_ty, mod_path, temp_params);
+            expand_type(&mut eq.lhs_ty, mod_path, temp_params)?;
+            expand_type(&mut eq.rhs_ty, mod_path, temp_params)
         }
-        _ => {}
+        WherePredicate::Lifetime(_) => Ok(()),
     }
 }
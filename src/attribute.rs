@@ -0,0 +1,239 @@
+use crate::{
+    check_generics_and_type, derive, global_data, Function, Generics, GenericsEnv, MakeFunction,
+    Module, Parent, ParentKind, Path, RuntimeFunction, Signature, Tracker, Type, Value,
+};
+use proc_macro2::TokenStream;
+use quote::ToTokens;
+use std::rc::Rc;
+use syn::{Attribute, ImplItem, Item, ItemImpl, Visibility};
+
+/// Entry point of an attribute macro on a function or an impl, like
+/// `#[instrument]`.
+///
+/// The annotated item is not emitted unless it is emitted again with
+/// `AttributeExecution::emit_original` or `AttributeExecution::emit_item`,
+/// or replaced by a function made with `AttributeExecution::make_function`.
+/// Other items than `fn` and `impl` items are rejected with a compile error.
+pub fn attribute<TokenStream, Run>(args: TokenStream, item: TokenStream, run: Run) -> TokenStream
+where
+    TokenStream: Into<proc_macro2::TokenStream> + From<proc_macro2::TokenStream>,
+    Run: FnOnce(AttributeExecution),
+{
+    let output = attribute2(args.into(), item.into(), run);
+    global_data::clear();
+    output.into()
+}
+
+fn attribute2<Run>(args: TokenStream, item: TokenStream, run: Run) -> TokenStream
+where
+    Run: FnOnce(AttributeExecution),
+{
+    let item: Item = match syn::parse2(item) {
+        Ok(item) => item,
+        Err(error) => return error.to_compile_error(),
+    };
+    let functions = match &item {
+        Item::Fn(item) => match Signature::syn_to_checked_signature(item.sig.clone()) {
+            Ok(sig) => vec![AnnotatedFunction {
+                function: Rc::new(Function::get_function(
+                    &item.sig.ident.to_string(),
+                    sig.clone(),
+                )),
+                sig,
+                vis: item.vis.clone(),
+                attrs: item.attrs.clone(),
+            }],
+            Err(error) => return error.to_compile_error(),
+        },
+        Item::Impl(item) => match impl_methods(item) {
+            Ok(methods) => methods,
+            Err(error) => return error.to_compile_error(),
+        },
+        item => {
+            return syn::Error::new_spanned(item, "expected a `fn` or an `impl`").to_compile_error()
+        }
+    };
+
+    let tracker = Tracker::new();
+    run(AttributeExecution {
+        args: &args,
+        item: &item,
+        functions: &functions,
+        tracker: &tracker,
+    });
+
+    let program = derive::tracker_to_program(tracker);
+    program.compile()
+}
+
+// The annotated `fn` or a method of the annotated `impl`, with the
+// visibility and the attributes that it is declared with
+struct AnnotatedFunction {
+    function: Rc<Function>,
+    // The signature before elided lifetimes are inserted
+    sig: Signature,
+    vis: Visibility,
+    attrs: Vec<Attribute>,
+}
+
+/// The methods of an impl, whose parent is the self type of the impl. The
+/// generic params of a method are inside of the params of the impl.
+fn impl_methods(item: &ItemImpl) -> syn::Result<Vec<AnnotatedFunction>> {
+    let path = match &*item.self_ty {
+        syn::Type::Path(syn::TypePath { qself: None, path }) => path,
+        self_ty => {
+            return Err(syn::Error::new_spanned(
+                self_ty,
+                "expected the path of a type",
+            ))
+        }
+    };
+    check_generics_and_type(&item.generics, &item.self_ty)?;
+    let mut generics = Generics::syn_to_generics(item.generics.clone());
+    let path = Path::syn_to_path(path.clone(), &mut generics.env);
+    let parent = Rc::new(Parent {
+        path,
+        generics,
+        parent_kind: ParentKind::Impl,
+        supertraits: Vec::new(),
+        data: None,
+        is_unsafe: false,
+    });
+
+    let methods = item.items.iter().filter_map(|item| match item {
+        ImplItem::Method(method) => Some(method),
+        _ => None,
+    });
+    let impl_lifetimes: Vec<_> = item
+        .generics
+        .lifetimes()
+        .map(|def| def.lifetime.clone())
+        .collect();
+    methods
+        .map(|method| {
+            let mut env = GenericsEnv::new();
+            env.enclose_in(&parent.generics.env);
+            let sig =
+                Signature::syn_to_checked_signature_in(method.sig.clone(), env, &impl_lifetimes)?;
+            let mut function = Function::get_function(&method.sig.ident.to_string(), sig.clone());
+            function.set_parent(parent.clone());
+            Ok(AnnotatedFunction {
+                function: Rc::new(function),
+                sig,
+                vis: method.vis.clone(),
+                attrs: method.attrs.clone(),
+            })
+        })
+        .collect()
+}
+
+#[derive(Clone, Copy)]
+pub struct AttributeExecution<'a> {
+    args: &'a TokenStream,
+    item: &'a Item,
+    functions: &'a [AnnotatedFunction],
+    tracker: &'a Tracker,
+}
+
+impl<'a> AttributeExecution<'a> {
+    pub fn load_crate(self, name: &str) -> Module {
+        self.tracker.load_crate(name)
+    }
+
+    /// Print the paths into the crate `name` under `path` instead, see
+    /// `Execution::set_crate_path`
+    pub fn set_crate_path(self, name: &str, path: &str) {
+        self.tracker.set_crate_path(name, path);
+    }
+
+    /// Allow the generated code to use `unsafe`, see
+    /// `Execution::allow_unsafe`
+    pub fn allow_unsafe(self) {
+        self.tracker.allow_unsafe.set(true);
+    }
+
+    /// The tokens inside of the attribute, like `level = "debug"` in
+    /// `#[instrument(level = "debug")]`
+    pub fn args(self) -> TokenStream {
+        self.args.clone()
+    }
+
+    /// The annotated function. Pass it to `make_function` to make a function
+    /// with the same name, signature, visibility and attributes. Panics if
+    /// the annotated item is an impl, see `methods`.
+    pub fn function(self) -> Rc<Function> {
+        match self.item {
+            Item::Fn(_) => self.functions[0].function.clone(),
+            _ => panic!("AttributeExecution::function: the annotated item is not a `fn`"),
+        }
+    }
+
+    /// The methods of the annotated impl, which are invoked through the self
+    /// type of the impl. Panics if the annotated item is a function, see
+    /// `function`.
+    pub fn methods(self) -> Vec<Rc<Function>> {
+        match self.item {
+            Item::Impl(_) => self
+                .functions
+                .iter()
+                .map(|annotated| annotated.function.clone())
+                .collect(),
+            _ => panic!("AttributeExecution::methods: the annotated item is not an `impl`"),
+        }
+    }
+
+    /// Emit the annotated function unchanged, except for its name. The
+    /// returned function can be invoked from a function that wraps it.
+    /// Panics if the annotated item is an impl, see `emit_item`.
+    pub fn emit_original(self, name: &str) -> Rc<Function> {
+        let mut item = match self.item {
+            Item::Fn(item) => item.clone(),
+            _ => panic!("AttributeExecution::emit_original: the annotated item is not a `fn`"),
+        };
+        item.sig.ident = syn::Ident::new(name, item.sig.ident.span());
+        let sig = self.functions[0].sig.clone();
+        self.tracker
+            .items
+            .borrow_mut()
+            .push(item.into_token_stream());
+
+        Rc::new(Function::get_function(name, sig))
+    }
+
+    /// Emit the annotated item unchanged
+    pub fn emit_item(self) {
+        self.tracker
+            .items
+            .borrow_mut()
+            .push(self.item.into_token_stream());
+    }
+
+    /// Emit a compile error next to the emitted items
+    pub fn emit_error(self, error: syn::Error) {
+        self.tracker
+            .items
+            .borrow_mut()
+            .push(error.to_compile_error());
+    }
+
+    /// Make a function next to the emitted items. A function made from the
+    /// annotated function, or from a method of the annotated impl, keeps the
+    /// visibility and the attributes that it is declared with.
+    pub fn make_function<F, Run>(self, f: F, run: Run)
+    where
+        F: RuntimeFunction,
+        Run: FnOnce(MakeFunction) -> Value,
+    {
+        let f = f.SELF();
+        let annotated = self
+            .functions
+            .iter()
+            .find(|annotated| Rc::ptr_eq(&annotated.function, &f));
+        self.tracker.make_free_function(Type::unit(), f, |f| {
+            if let Some(annotated) = annotated {
+                f.declare_like(&annotated.vis, &annotated.attrs);
+            }
+            run(f)
+        });
+    }
+}
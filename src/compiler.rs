@@ -1,20 +1,26 @@
 use crate::ident::Ident;
 use crate::{
-    Function, GlobalBorrow, InvokeRef, MacroInvokeRef, Parent, Print, Receiver, SimplePath,
-    TraitInferenceResult, Type, TypeNode, ValueNode, ValueRef, INVOKES, MACROS, VALUES,
+    Data, Field, Function, Invoke, InvokeRef, MacroInvokeRef, Parent, Path, Print, Receiver,
+    SimplePath, Struct, TraitInferenceResult, Type, TypeNode, ValueNode, ValueRef, Variant,
+    CRATE_PATHS, INVOKES, MACROS, VALUES,
 };
 use proc_macro2::{Span, TokenStream};
 use quote::{quote, ToTokens};
 use ref_cast::RefCast;
+use std::collections::BTreeMap as Map;
 use std::collections::BTreeSet as Set;
 use std::ops::Range;
 use std::rc::Rc;
-use syn::Token;
+use syn::{Token, Visibility};
 
 #[derive(Debug)]
 pub(crate) struct Program {
     pub crates: Vec<Ident>,
+    pub crate_paths: Map<Ident, Path>,
+    pub allow_unsafe: bool,
+    pub items: Vec<TokenStream>,
     pub impls: Vec<CompleteImpl>,
+    pub free_functions: Vec<CompleteImpl>,
 }
 
 #[derive(Debug)]
@@ -23,6 +29,7 @@ pub(crate) struct CompleteImpl {
     pub ty: Type,
     pub functions: Vec<CompleteFunction>,
     pub result: Option<TraitInferenceResult>,
+    pub attrs: Vec<TokenStream>,
 }
 
 #[derive(Debug)]
@@ -33,37 +40,145 @@ pub(crate) struct CompleteFunction {
     pub invokes: Range<InvokeRef>,
     pub macros: Range<MacroInvokeRef>,
     pub ret: Option<ValueRef>,
+    pub attrs: Vec<TokenStream>,
+    pub vis: Visibility,
 }
 
 impl Program {
     pub fn compile(&self) -> TokenStream {
-        let impls = self.impls.iter().map(CompleteImpl::compile);
+        // Paths into a crate with a path set by the macro are printed under
+        // that path instead, and the crate is not loaded with `extern crate`
+        CRATE_PATHS.with(|crate_paths| {
+            let mut crate_paths = crate_paths.borrow_mut();
+            for (name, path) in &self.crate_paths {
+                crate_paths.insert((true, name.clone()), path.clone());
+                if self.crates.contains(name) {
+                    crate_paths.insert((false, name.clone()), path.clone());
+                }
+            }
+        });
+
+        // Without Execution::allow_unsafe, an impl or a free function that
+        // needs `unsafe` is replaced by a compile error
+        let unsafe_error = || {
+            syn::Error::new(
+                Span::call_site(),
+                "the generated code needs `unsafe`, which is only emitted after \
+                 Execution::allow_unsafe",
+            )
+            .to_compile_error()
+        };
+        let impls: Vec<_> = self
+            .impls
+            .iter()
+            .map(|imp| {
+                if !self.allow_unsafe && imp.uses_unsafe() {
+                    unsafe_error()
+                } else {
+                    imp.compile()
+                }
+            })
+            .collect();
+        let free_functions = self.free_functions.iter().map(|imp| {
+            if !self.allow_unsafe && imp.uses_unsafe() {
+                unsafe_error()
+            } else {
+                imp.compile_free_functions()
+            }
+        });
+
+        let items = &self.items;
+
+        let mut crates: Vec<_> = self
+            .crates
+            .iter()
+            .filter(|name| !self.crate_paths.contains_key(name))
+            .collect();
+        crates.sort();
+        crates.dedup();
+
+        // Loaded crates are only in scope of the impls, which are wrapped in
+        // an anonymous const so that the `extern crate` items do not clash
+        // with names in the crate of the user
+        let impls = if crates.is_empty() || impls.is_empty() {
+            quote!(#(#impls)*)
+        } else {
+            quote! {
+                const _: () = {
+                    #(extern crate #crates;)*
+                    #(#impls)*
+                };
+            }
+        };
 
-        quote! {
-            #(#impls)*
-        }
+        let output = quote! {
+            #(#items)*
+            #impls
+            #(#free_functions)*
+        };
+        CRATE_PATHS.with(|crate_paths| crate_paths.borrow_mut().clear());
+        output
     }
 }
 
 impl CompleteImpl {
+    /// Whether an unsafe trait is implemented, or an unsafe function is
+    /// declared or invoked
+    fn uses_unsafe(&self) -> bool {
+        let unsafe_trait = self
+            .trait_ty
+            .as_ref()
+            .is_some_and(|parent| parent.is_unsafe);
+        unsafe_trait || self.functions.iter().any(CompleteFunction::uses_unsafe)
+    }
+
     fn compile(&self) -> TokenStream {
-        let functions = self.functions.iter().map(CompleteFunction::compile);
+        let functions = self.functions.iter().map(|f| f.compile(None));
 
-        let name = if let TypeNode::DataStructure(data) = &self.ty.0 {
-            &data.name
+        let (params, self_ty, where_clause, trait_ty) = self.header();
+        let self_ty = Print::ref_cast(self_ty);
+        let attrs = &self.attrs;
+
+        if let Some(trait_ty) = trait_ty {
+            let unsafety = if self.trait_ty.as_ref().unwrap().is_unsafe {
+                Some(quote!(unsafe))
+            } else {
+                None
+            };
+            quote! {
+                // FIXME: assosiated types
+                #(#[#attrs])*
+                #unsafety impl #params #trait_ty for #self_ty #where_clause {
+                    #(#functions)*
+                }
+            }
         } else {
-            panic!()
-        };
-        let (params, self_ty_args, where_clause, trait_ty) = if let Some(result) = &self.result {
-            let params = result.generic_params.iter().map(Print::ref_cast);
-            let params = Some(quote!(<#(#params),*>));
-            let constraints = result.constraints.set.iter().map(Print::ref_cast);
-            let self_ty_args = result.data_struct_args.args.iter().map(Print::ref_cast);
-            let self_ty_args = if result.data_struct_args.args.is_empty() {
+            quote! {
+                #(#[#attrs])*
+                impl #params #self_ty #where_clause {
+                    #(#functions)*
+                }
+            }
+        }
+    }
+
+    /// The generic params, self type, where clause and trait of the impl
+    pub(crate) fn header(
+        &self,
+    ) -> (
+        Option<TokenStream>,
+        &Type,
+        Option<TokenStream>,
+        Option<TokenStream>,
+    ) {
+        if let Some(result) = &self.result {
+            let params = if result.generic_params.is_empty() {
                 None
             } else {
-                Some(quote!(<#(#self_ty_args),*>))
+                let params = result.generic_params.iter().map(Print::ref_cast);
+                Some(quote!(<#(#params),*>))
             };
+            let constraints = where_predicates(result);
             let where_clause = if result.constraints.set.is_empty() {
                 None
             } else {
@@ -81,44 +196,70 @@ impl CompleteImpl {
 
                 quote!(#path #args)
             });
-            (params, self_ty_args, where_clause, trait_ty)
+            (params, &result.self_ty, where_clause, trait_ty)
         } else {
             let trait_ty = self.trait_ty.as_ref().map(|trait_ty| {
                 let path = Print::ref_cast(&trait_ty.path);
                 quote!(#path)
             });
-            (None, None, None, trait_ty)
-        };
+            (None, &self.ty, None, trait_ty)
+        }
+    }
 
-        if let Some(trait_ty) = trait_ty {
-            quote! {
-                // FIXME: assosiated types
-                impl #params #trait_ty for #name #self_ty_args #where_clause {
-                    #(#functions)*
-                }
-            }
-        } else {
-            quote! {
-                impl #params #name #self_ty_args #where_clause {
-                    #(#functions)*
-                }
-            }
+    /// Free functions are compiled with the generic params and constraints
+    /// inferred for them, instead of in an impl block
+    fn compile_free_functions(&self) -> TokenStream {
+        let functions = self
+            .functions
+            .iter()
+            .map(|f| f.compile(self.result.as_ref()));
+        quote! {
+            #(#functions)*
         }
     }
 }
 
+/// The inferred where clause predicates, ordered by their tokens so that the
+/// order does not depend on the history of the hash set
+fn where_predicates(result: &TraitInferenceResult) -> Vec<TokenStream> {
+    let mut predicates: Vec<_> = result
+        .constraints
+        .set
+        .iter()
+        .map(|constraint| Print::ref_cast(constraint).into_token_stream())
+        .collect();
+    predicates.sort_by_cached_key(TokenStream::to_string);
+    predicates
+}
+
 impl CompleteFunction {
-    fn compile(&self) -> TokenStream {
+    fn uses_unsafe(&self) -> bool {
+        self.f.sig.is_unsafe
+            || INVOKES.with_borrow(|invokes| {
+                invokes[self.invokes.start.0..self.invokes.end.0]
+                    .iter()
+                    .any(|invoke| invoke.function.sig.is_unsafe)
+            })
+    }
+
+    fn compile(&self, outer: Option<&TraitInferenceResult>) -> TokenStream {
         let name = Ident::new(&self.f.name);
 
         let generics = &self.f.sig.generics;
-        let (params, where_clause) = if !generics.params.is_empty() {
-            let params = generics.params.iter().map(Print::ref_cast);
+        let outer_params = outer.into_iter().flat_map(|result| &result.generic_params);
+        let outer_constraints = outer.map(where_predicates).unwrap_or_default();
+        let mut params = outer_params.chain(&generics.params).peekable();
+        let (params, where_clause) = if params.peek().is_some() {
+            let params = params.map(Print::ref_cast);
             let params = Some(quote!(<#(#params),*>));
-            let where_clause = if generics.constraints.is_empty() {
+            let constraints = generics.constraints.iter().map(Print::ref_cast);
+            let mut constraints = outer_constraints
+                .into_iter()
+                .chain(constraints.map(ToTokens::into_token_stream))
+                .peekable();
+            let where_clause = if constraints.peek().is_none() {
                 None
             } else {
-                let constraints = generics.constraints.iter().map(Print::ref_cast);
                 Some(quote!(where #(#constraints,)*))
             };
             (params, where_clause)
@@ -146,14 +287,49 @@ impl CompleteFunction {
 
         let reachable = self.compute_reachability();
         let mutable = self.compute_mutability();
+        let arms = self.compute_arm_membership();
+        let body = self.compile_block(self.refs(), self.ret, None, &reachable, &mutable, &arms);
+        let attrs = &self.attrs;
+        let vis = &self.vis;
+        let unsafety = if self.f.sig.is_unsafe {
+            Some(quote!(unsafe))
+        } else {
+            None
+        };
+
+        quote! {
+            #(#[#attrs])*
+            #vis #unsafety fn #name #params (#(#inputs),*) #output #where_clause {
+                #body
+            }
+        }
+    }
+
+    /// Compiles the let bindings of the values that belong directly to a block
+    /// (the function body if `block` is None, otherwise a match arm) followed
+    /// by its return value.
+    fn compile_block(
+        &self,
+        refs: impl Iterator<Item = ValueRef>,
+        ret: Option<ValueRef>,
+        block: Option<(ValueRef, usize)>,
+        reachable: &Set<ValueRef>,
+        mutable: &Set<ValueRef>,
+        arms: &Map<ValueRef, (ValueRef, usize)>,
+    ) -> TokenStream {
         VALUES.with_borrow(|value_nodes| {
-            let values = self.refs().flat_map(|v| {
+            let values = refs.flat_map(|v| {
+                // Values inside nested match arms are compiled with the arm
+                if arms.get(&v).copied() != block {
+                    return None;
+                }
+
                 // Don't create let bindings for inlineable values
                 if value_nodes[v.0].inlineable() {
                     return None;
                 }
 
-                let expr = self.compile_value(v);
+                let expr = self.compile_value_in(v, reachable, mutable, arms);
                 if reachable.contains(&v) {
                     let let_mut = if mutable.contains(&v) {
                         quote!(let mut)
@@ -173,22 +349,42 @@ impl CompleteFunction {
                 }
             });
 
-            let ret = self.ret.and_then(|v| match &value_nodes[v.0] {
+            let ret = ret.and_then(|v| match &value_nodes[v.0] {
                 ValueNode::Tuple(values) if values.is_empty() => None,
                 value if value.inlineable() => Some(self.compile_value(v)),
                 _ => Some(v.binding().to_token_stream()),
             });
 
             quote! {
-                fn #name #params (#(#inputs),*) #output #where_clause {
-                    #(#values)*
-                    #ret
-                }
+                #(#values)*
+                #ret
             }
         })
     }
 
-    fn refs(&self) -> impl Iterator<Item = ValueRef> {
+    /// Maps every value created inside a match arm to the innermost arm that
+    /// contains it, given as the match value and the index of the arm.
+    fn compute_arm_membership(&self) -> Map<ValueRef, (ValueRef, usize)> {
+        let mut membership = Map::new();
+
+        // Outer matches are created after the matches nested inside of them,
+        // so visit the matches in reverse to let the innermost arm win.
+        for v in self.refs().rev() {
+            VALUES.with_borrow(|values| {
+                if let ValueNode::Match { arms, .. } = &values[v.0] {
+                    for (i, arm) in arms.iter().enumerate() {
+                        for inner in arm.values.start.0..arm.values.end.0 {
+                            membership.insert(ValueRef(inner), (v, i));
+                        }
+                    }
+                }
+            })
+        }
+
+        membership
+    }
+
+    fn refs(&self) -> impl DoubleEndedIterator<Item = ValueRef> {
         (self.values.start.0..self.values.end.0).map(ValueRef)
     }
 
@@ -206,7 +402,7 @@ impl CompleteFunction {
         while let Some(v) = stack.pop() {
             VALUES.with_borrow(|values| match &values[v.0] {
                 Tuple(values) => {
-                    for &v in values.iter() {
+                    for &v in values {
                         if reachable.insert(v) {
                             stack.extend(values);
                         }
@@ -238,7 +434,28 @@ impl CompleteFunction {
                         stack.push(*parent);
                     }
                 }
-                DataStructure { .. } => unimplemented!(),
+                PatternBinding { .. } => {}
+                Match {
+                    scrutinee, arms, ..
+                } => {
+                    if reachable.insert(*scrutinee) {
+                        stack.push(*scrutinee);
+                    }
+                    for arm in arms {
+                        if reachable.insert(arm.ret) {
+                            stack.push(arm.ret);
+                        }
+                    }
+                }
+                DataStructure { data, .. } => {
+                    if let Data::Struct(data) = data {
+                        for field in data.fields() {
+                            if reachable.insert(field.element) {
+                                stack.push(field.element);
+                            }
+                        }
+                    }
+                }
             })
         }
 
@@ -263,7 +480,9 @@ impl CompleteFunction {
 
     fn is_important(&self, v: ValueRef) -> bool {
         VALUES.with_borrow(|values| {
-            if let ValueNode::Invoke(_) | ValueNode::MacroInvocation(_) = values[v.0] {
+            if let ValueNode::Invoke(_) | ValueNode::MacroInvocation(_) | ValueNode::Match { .. } =
+                values[v.0]
+            {
                 return true;
             }
             false
@@ -271,6 +490,18 @@ impl CompleteFunction {
     }
 
     fn compile_value(&self, v: ValueRef) -> TokenStream {
+        self.compile_value_in(v, &Set::new(), &Set::new(), &Map::new())
+    }
+
+    // Match values need the analysis results of the function in order to
+    // compile the blocks of their arms
+    fn compile_value_in(
+        &self,
+        v: ValueRef,
+        reachable: &Set<ValueRef>,
+        mutable: &Set<ValueRef>,
+        arms: &Map<ValueRef, (ValueRef, usize)>,
+    ) -> TokenStream {
         VALUES.with_borrow(|values| match &values[v.0] {
             ValueNode::Tuple(values) => {
                 let values = self.make_values_list(values);
@@ -310,18 +541,34 @@ impl CompleteFunction {
             ValueNode::Binding { name, .. } => quote! { #name },
             ValueNode::Invoke(invoke) => INVOKES.with_borrow(|invokes| {
                 let invoke = &invokes[invoke.0];
-                let parent_type = match invoke.function.parent {
-                    Some(ref parent) => {
-                        let print = Print::ref_cast(SimplePath::ref_cast(&parent.path));
-                        Some(quote!(#print ::))
-                    }
-                    None => None,
-                };
-                let name = Ident::new(&invoke.function.name);
+                if let Some(data) = invoke
+                    .function
+                    .parent
+                    .as_ref()
+                    .and_then(|p| p.data.as_ref())
+                {
+                    return self.compile_constructor(invoke, data);
+                }
                 let args = self.make_values_list(&invoke.args);
+                let call = if let Some(path) = &invoke.function.path {
+                    let path = Print::ref_cast(SimplePath::ref_cast(path));
+                    quote!(#path ( #args ))
+                } else {
+                    let parent_type = match invoke.function.parent {
+                        Some(ref parent) => {
+                            let print = Print::ref_cast(SimplePath::ref_cast(&parent.path));
+                            Some(quote!(#print ::))
+                        }
+                        None => None,
+                    };
+                    let name = Ident::new(&invoke.function.name);
+                    quote!(#parent_type #name ( #args ))
+                };
 
-                quote! {
-                    #parent_type #name ( #args )
+                if invoke.function.sig.is_unsafe {
+                    quote!(unsafe { #call })
+                } else {
+                    call
                 }
             }),
             ValueNode::Destructure {
@@ -343,7 +590,77 @@ impl CompleteFunction {
                 }
                 quote!(#references #parent.#accessor)
             }
-            ValueNode::DataStructure { .. } => unimplemented!(),
+            ValueNode::DataStructure { ty, data } => {
+                let name = match &ty.0 {
+                    TypeNode::DataStructure(data) => &data.name,
+                    _ => unreachable!(),
+                };
+                match data {
+                    Data::Struct(Struct::Unit(_)) => quote!(#name),
+                    Data::Struct(Struct::Tuple(data)) => {
+                        let values: Vec<_> = data.fields().map(|field| field.element).collect();
+                        let args = self.make_values_list(&values);
+                        quote!(#name(#args))
+                    }
+                    Data::Struct(Struct::Struct(data)) => {
+                        let fields = data.fields().map(|field| {
+                            let accessor = Print::ref_cast(&field.accessor);
+                            let value = self.make_values_list(&[field.element]);
+                            quote!(#accessor: #value)
+                        });
+                        quote!(#name { #(#fields),* })
+                    }
+                    Data::Enum(_) => unreachable!(),
+                }
+            }
+            ValueNode::PatternBinding { .. } => v.binding().to_token_stream(),
+            ValueNode::Match {
+                scrutinee,
+                path,
+                arms: match_arms,
+            } => {
+                let scrutinee = if values[scrutinee.0].inlineable() {
+                    self.compile_value(*scrutinee)
+                } else {
+                    scrutinee.binding().to_token_stream()
+                };
+                let path = Print::ref_cast(SimplePath::ref_cast(path));
+                let match_arms = match_arms.iter().enumerate().map(|(i, arm)| {
+                    let variant = &arm.pattern.ident();
+                    let pattern = match &arm.pattern {
+                        Variant::Unit(_) => quote!(#path::#variant),
+                        Variant::Tuple(variant_data) => {
+                            let bindings = variant_data.fields.iter().map(|f| f.element.binding());
+                            quote!(#path::#variant(#(#bindings),*))
+                        }
+                        Variant::Struct(variant_data) => {
+                            let bindings = variant_data.fields.iter().map(|f| {
+                                let accessor = Print::ref_cast(&f.accessor);
+                                let binding = f.element.binding();
+                                quote!(#accessor: #binding)
+                            });
+                            quote!(#path::#variant { #(#bindings),* })
+                        }
+                    };
+                    let refs = (arm.values.start.0..arm.values.end.0).map(ValueRef);
+                    let body = self.compile_block(
+                        refs,
+                        Some(arm.ret),
+                        Some((v, i)),
+                        reachable,
+                        mutable,
+                        arms,
+                    );
+                    quote! {
+                        #pattern => { #body }
+                    }
+                });
+                quote! {
+                    match #scrutinee {
+                        #(#match_arms)*
+                    }
+                }
+            }
             ValueNode::MacroInvocation(invoke) => MACROS.with_borrow(|macros| {
                 let invoke = &macros[invoke.0];
                 let path = Print::ref_cast(&invoke.macro_path);
@@ -358,6 +675,47 @@ impl CompleteFunction {
         })
     }
 
+    /// Compiles the invocation of the constructor of a library enum variant or
+    /// struct. Constructors of structs are named after the struct.
+    fn compile_constructor(&self, invoke: &Invoke, data: &Data<Type>) -> TokenStream {
+        let parent = invoke.function.parent.as_ref().unwrap();
+        let path = Print::ref_cast(SimplePath::ref_cast(&parent.path));
+        let name = Ident::new(&invoke.function.name);
+        let args = self.make_values_list(&invoke.args);
+        let fields = |fields: &[Field<Type>]| {
+            let fields = fields.iter().zip(&invoke.args).map(|(field, arg)| {
+                let accessor = Print::ref_cast(&field.accessor);
+                let arg = self.make_values_list(&[*arg]);
+                quote!(#accessor: #arg)
+            });
+            quote!({ #(#fields),* })
+        };
+
+        match data {
+            Data::Struct(Struct::Unit(_)) => quote!(#path),
+            Data::Struct(Struct::Tuple(_)) => quote!(#path(#args)),
+            Data::Struct(Struct::Struct(data)) => {
+                let fields = fields(&data.fields);
+                quote!(#path #fields)
+            }
+            Data::Enum(data) => {
+                let variant = data
+                    .variants
+                    .iter()
+                    .find(|variant| variant.ident().to_string() == invoke.function.name)
+                    .expect("compile_constructor: Not a variant");
+                match variant {
+                    Variant::Unit(_) => quote!(#path::#name),
+                    Variant::Tuple(_) => quote!(#path::#name(#args)),
+                    Variant::Struct(variant) => {
+                        let fields = fields(&variant.fields);
+                        quote!(#path::#name #fields)
+                    }
+                }
+            }
+        }
+    }
+
     /// Makes a list of comma-separated values with string literals inlined
     fn make_values_list(&self, values_refs: &[ValueRef]) -> TokenStream {
         VALUES.with_borrow(|values| {
@@ -376,17 +734,11 @@ fn receiver_tokens(receiver: Receiver) -> Option<TokenStream> {
         Receiver::NoSelf => None,
         Receiver::SelfByValue => Some(quote!(self)),
         Receiver::SelfByReference { is_mut, lifetime } if !is_mut => {
-            let lifetime = lifetime
-                .0
-                .as_ref()
-                .map(|lifetime| Print::ref_cast(lifetime));
+            let lifetime = lifetime.0.as_ref().map(Print::ref_cast);
             Some(quote!(&#lifetime self))
         }
         Receiver::SelfByReference { is_mut, lifetime } => {
-            let lifetime = lifetime
-                .0
-                .as_ref()
-                .map(|lifetime| Print::ref_cast(lifetime));
+            let lifetime = lifetime.0.as_ref().map(Print::ref_cast);
             Some(quote!(&#lifetime mut self))
         }
     }
@@ -401,7 +753,7 @@ impl ValueRef {
 impl ValueNode {
     fn inlineable(&self) -> bool {
         match self {
-            ValueNode::Str(_) => true,
+            ValueNode::Str(_) | ValueNode::PatternBinding { .. } => true,
             ValueNode::Tuple(values) => values.is_empty(),
             _ => false,
         }
@@ -1,7 +1,10 @@
-use crate::{attr, Field, Fields, Value};
+use crate::{
+    attr, Accessor, GenericsEnv, GlobalPush, Ident, MatchArm, Type, Value, ValueNode, ValueRef,
+    VALUES,
+};
+use crate::{AttrSchema, AttrValues, Field, Fields};
 use std::fmt;
 use std::fmt::Debug;
-use std::marker::PhantomData;
 use syn::Attribute;
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -13,10 +16,15 @@ pub enum Data<T> {
 impl<T> Data<T> {
     pub fn attrs(&self) -> &[Attribute] {
         match self {
-            Data::Struct(s) => &s.attrs(),
+            Data::Struct(s) => s.attrs(),
             Data::Enum(e) => &e.attrs,
         }
     }
+
+    /// Parse the container attributes with the schema
+    pub fn parse_attrs(&self, schema: &AttrSchema) -> syn::Result<AttrValues> {
+        schema.parse(self.attrs())
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -129,6 +137,8 @@ impl<T> StructStruct<T> {
 pub struct Enum<T> {
     pub(crate) variants: Vec<Variant<T>>,
     pub(crate) attrs: Vec<Attribute>,
+    // The value being matched on, if the enum was obtained from `Value::data`
+    pub(crate) scrutinee: Option<ValueRef>,
 }
 
 impl<T: Debug> Debug for Enum<T> {
@@ -140,17 +150,46 @@ impl<T: Debug> Debug for Enum<T> {
     }
 }
 
+impl<T> Enum<T> {
+    pub fn variants(&self) -> impl Iterator<Item = &Variant<T>> {
+        self.variants.iter()
+    }
+}
+
 impl Enum<Value> {
+    /// Generate a `match` on the enum value. `run` is called once for every
+    /// variant, and the value it returns becomes the value of that match arm.
     pub fn match_variant<Run>(&self, run: Run) -> Value
     where
         Run: Fn(Variant<Value>) -> Value,
     {
+        let scrutinee = match self.scrutinee {
+            Some(scrutinee) => scrutinee,
+            None => return Value::compile_error("Enum::match_variant: enum is not a value"),
+        };
+        let path = scrutinee.get_type().0.data_path();
+
         let mut arms = Vec::new();
         for variant in self.variants.clone() {
-            arms.push(run(variant));
+            let pattern = variant.clone().map(|field| field.element.index);
+            let start = ValueRef(VALUES.with_borrow(Vec::len));
+            let ret = run(variant).index;
+            let end = ValueRef(VALUES.with_borrow(Vec::len));
+            arms.push(MatchArm {
+                pattern,
+                values: start..end,
+                ret,
+            });
+        }
+
+        let node = ValueNode::Match {
+            scrutinee,
+            path,
+            arms,
+        };
+        Value {
+            index: VALUES.index_push(node),
         }
-        // FIXME introduce a match node
-        unimplemented!()
     }
 
     pub fn attrs(&self) -> &[Attribute] {
@@ -173,16 +212,60 @@ impl<T> Variant<T> {
             Variant::Struct(sv) => &sv.attrs,
         }
     }
+
+    /// Parse the variant attributes with the schema
+    pub fn parse_attrs(&self, schema: &AttrSchema) -> syn::Result<AttrValues> {
+        schema.parse(self.attrs())
+    }
+
+    pub fn fields(&self) -> Fields<T>
+    where
+        T: Clone,
+    {
+        let fields = match self {
+            Variant::Unit(v) => Vec::new(),
+            Variant::Tuple(v) => v.fields.clone(),
+            Variant::Struct(v) => v.fields.clone(),
+        };
+        Fields {
+            fields: fields.into_iter(),
+        }
+    }
+
+    pub(crate) fn ident(&self) -> &Ident {
+        match self {
+            Variant::Unit(uv) => &uv.ident,
+            Variant::Tuple(tv) => &tv.ident,
+            Variant::Struct(sv) => &sv.ident,
+        }
+    }
+}
+
+impl Variant<Value> {
+    pub fn get_name(&self) -> Value {
+        let node = ValueNode::Str(self.ident().to_string());
+        Value {
+            index: VALUES.index_push(node),
+        }
+    }
+}
+
+impl Variant<Type> {
+    pub fn get_name(&self) -> String {
+        self.ident().to_string()
+    }
 }
 
 #[derive(Clone, PartialEq, Eq, Hash)]
 pub struct UnitVariant {
+    pub(crate) ident: Ident,
     pub(crate) attrs: Vec<Attribute>,
 }
 
 impl Debug for UnitVariant {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         f.debug_struct("UnitVariant")
+            .field("ident", &self.ident)
             .field("attrs", attr::debug(&self.attrs))
             .finish()
     }
@@ -190,28 +273,123 @@ impl Debug for UnitVariant {
 
 #[derive(Clone, PartialEq, Eq, Hash)]
 pub struct TupleVariant<T> {
-    pub(crate) phantom: PhantomData<T>,
+    pub(crate) ident: Ident,
+    pub(crate) fields: Vec<Field<T>>,
     pub(crate) attrs: Vec<Attribute>,
 }
 
 impl<T: Debug> Debug for TupleVariant<T> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         f.debug_struct("TupleVariant")
+            .field("ident", &self.ident)
+            .field("fields", &self.fields)
             .field("attrs", attr::debug(&self.attrs))
             .finish()
     }
 }
 
+impl<T> TupleVariant<T> {
+    pub fn fields(&self) -> Fields<T>
+    where
+        T: Clone,
+    {
+        Fields {
+            fields: self.fields.clone().into_iter(),
+        }
+    }
+}
+
 #[derive(Clone, PartialEq, Eq, Hash)]
 pub struct StructVariant<T> {
-    pub(crate) phantom: PhantomData<T>,
+    pub(crate) ident: Ident,
+    pub(crate) fields: Vec<Field<T>>,
     pub(crate) attrs: Vec<Attribute>,
 }
 
 impl<T: Debug> Debug for StructVariant<T> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         f.debug_struct("StructVariant")
+            .field("ident", &self.ident)
+            .field("fields", &self.fields)
             .field("attrs", attr::debug(&self.attrs))
             .finish()
     }
 }
+
+impl<T> StructVariant<T> {
+    pub fn fields(&self) -> Fields<T>
+    where
+        T: Clone,
+    {
+        Fields {
+            fields: self.fields.clone().into_iter(),
+        }
+    }
+}
+
+impl Data<Type> {
+    pub(crate) fn syn_to_data(
+        data: syn::Data,
+        attrs: Vec<Attribute>,
+        env: &mut GenericsEnv,
+    ) -> Self {
+        match data {
+            syn::Data::Struct(data) => Data::Struct(match data.fields {
+                syn::Fields::Named(fields) => Struct::Struct(StructStruct {
+                    fields: syn_to_fields(fields.named, env),
+                    attrs,
+                }),
+                syn::Fields::Unnamed(fields) => Struct::Tuple(TupleStruct {
+                    fields: syn_to_fields(fields.unnamed, env),
+                    attrs,
+                }),
+                syn::Fields::Unit => Struct::Unit(UnitStruct { attrs }),
+            }),
+            syn::Data::Enum(data) => Data::Enum(Enum {
+                variants: data
+                    .variants
+                    .into_iter()
+                    .map(|variant| {
+                        let ident = Ident::from(variant.ident);
+                        let attrs = variant.attrs;
+                        match variant.fields {
+                            syn::Fields::Named(fields) => Variant::Struct(StructVariant {
+                                ident,
+                                fields: syn_to_fields(fields.named, env),
+                                attrs,
+                            }),
+                            syn::Fields::Unnamed(fields) => Variant::Tuple(TupleVariant {
+                                ident,
+                                fields: syn_to_fields(fields.unnamed, env),
+                                attrs,
+                            }),
+                            syn::Fields::Unit => Variant::Unit(UnitVariant { ident, attrs }),
+                        }
+                    })
+                    .collect(),
+                attrs,
+                scrutinee: None,
+            }),
+            syn::Data::Union(_) => unimplemented!("union"),
+        }
+    }
+}
+
+fn syn_to_fields<I>(fields: I, env: &mut GenericsEnv) -> Vec<Field<Type>>
+where
+    I: IntoIterator<Item = syn::Field>,
+{
+    fields
+        .into_iter()
+        .enumerate()
+        .map(|(i, field)| Field {
+            attrs: field.attrs,
+            vis: field.vis,
+            accessor: match field.ident {
+                Some(ident) => Accessor::Name(Ident::from(ident)),
+                None => Accessor::Index(i),
+            },
+            element: Type::syn_to_type(field.ty, env),
+        })
+        .collect()
+}
@@ -1,106 +1,125 @@
 use crate::{
-    global_data, Accessor, CompleteFunction, CompleteImpl, Data, DataStructure, Enum, Execution,
-    Field, Generics, Ident, Program, Struct, StructStruct, Tracker, TupleStruct, Type, TypeNode,
-    UnitStruct, WipFunction, WipImpl,
+    global_data, CompleteFunction, CompleteImpl, Data, DataStructure, Execution, Generics, Ident,
+    InferenceOptions, Program, Report, Tracker, Type, TypeNode, WipFunction, WipImpl,
 };
 use proc_macro2::TokenStream;
+use quote::quote;
 use syn::DeriveInput;
 
-pub fn derive<TokenStream>(input: TokenStream, run: fn(Execution)) -> TokenStream
+pub fn derive<TokenStream, Run>(input: TokenStream, run: Run) -> TokenStream
 where
     TokenStream: Into<proc_macro2::TokenStream> + From<proc_macro2::TokenStream>,
+    Run: FnOnce(Execution),
 {
     let input = input.into();
-    let output = derive2(input, run);
+    let (output, _) = derive2(input, run, false);
     global_data::clear();
     output.into()
 }
 
-fn derive2(input: TokenStream, run: fn(Execution)) -> TokenStream {
+/// Like `derive`, but also returns a report that explains why each where
+/// clause predicate, type equality and lifetime outlives fact of the
+/// generated impls was inferred, like which invoke and argument introduced
+/// it.
+///
+/// ```
+/// # use reflect::*;
+/// # use quote::quote;
+/// let input = quote!(struct Unit;);
+/// let (_output, report) = derive_with_report(input, |_ex| {});
+/// println!("{}", report);
+/// ```
+pub fn derive_with_report<TokenStream, Run>(input: TokenStream, run: Run) -> (TokenStream, Report)
+where
+    TokenStream: Into<proc_macro2::TokenStream> + From<proc_macro2::TokenStream>,
+    Run: FnOnce(Execution),
+{
+    let input = input.into();
+    let (output, report) = derive2(input, run, true);
+    global_data::clear();
+    (output.into(), report.unwrap())
+}
+
+fn derive2<Run>(input: TokenStream, run: Run, report: bool) -> (TokenStream, Option<Report>)
+where
+    Run: FnOnce(Execution),
+{
     let input = syn::parse2(input).unwrap();
     let ty = syn_to_type(input);
 
     let tracker = Tracker::new();
+    tracker.report.set(report);
     run(Execution {
         ty: &ty,
         tracker: &tracker,
     });
 
-    let program = tracker_to_program(tracker);
-    program.compile()
+    let mut program = tracker_to_program(tracker);
+    // Like the impls of the built-in derives, so that lints treat them the
+    // same way
+    for imp in &mut program.impls {
+        if imp.trait_ty.is_some() {
+            imp.attrs.insert(0, quote!(automatically_derived));
+        }
+    }
+    let report = if report {
+        Some(Report::new(&program.impls, &program.free_functions))
+    } else {
+        None
+    };
+    (program.compile(), report)
 }
 
 fn syn_to_type(input: DeriveInput) -> Type {
-    let attrs: Vec<_> = input
-        .attrs
-        .into_iter()
-        .map(std::convert::Into::into)
-        .collect();
+    let attrs: Vec<_> = input.attrs.into_iter().collect();
     let mut generics = Generics::syn_to_generics(input.generics);
 
-    let data = match input.data {
-        syn::Data::Struct(data) => match data.fields {
-            syn::Fields::Named(fields) => Data::Struct(Struct::Struct(StructStruct {
-                fields: fields
-                    .named
-                    .into_iter()
-                    .map(|field| Field {
-                        attrs: field.attrs,
-                        accessor: Accessor::Name(Ident::from(field.ident.unwrap())),
-                        element: Type::syn_to_type(field.ty, &mut generics.param_map),
-                    })
-                    .collect(),
-                attrs,
-            })),
-            syn::Fields::Unnamed(fields) => Data::Struct(Struct::Tuple(TupleStruct {
-                fields: fields
-                    .unnamed
-                    .into_iter()
-                    .enumerate()
-                    .map(|(i, field)| Field {
-                        attrs: field.attrs,
-                        accessor: Accessor::Index(i),
-                        element: Type::syn_to_type(field.ty, &mut generics.param_map),
-                    })
-                    .collect(),
-                attrs,
-            })),
-            syn::Fields::Unit => Data::Struct(Struct::Unit(UnitStruct { attrs })),
-        },
-        syn::Data::Enum(data) => {
-            // FIXME convert enum variants
-            Data::Enum(Enum {
-                variants: Vec::new(),
-                attrs,
-            })
-        }
-        syn::Data::Union(_) => unimplemented!("union"),
-    };
+    let data = Data::syn_to_data(input.data, attrs, &mut generics.env);
 
     Type(TypeNode::DataStructure(Box::new(DataStructure {
         name: Ident::from(input.ident),
+        vis: input.vis,
         generics,
         data,
     })))
 }
 
-fn tracker_to_program(tracker: Tracker) -> Program {
+pub(crate) fn tracker_to_program(tracker: Tracker) -> Program {
+    let options = InferenceOptions {
+        never_specialize: tracker.never_specialize.get(),
+        perfect_derive: tracker.perfect_derive.get(),
+        report: tracker.report.get(),
+    };
+    // An impl that trait inference rejects is replaced by the compile error
+    let mut items = tracker.items.into_inner();
+    let mut complete = |imps: Vec<WipImpl>| -> Vec<CompleteImpl> {
+        imps.into_iter()
+            .filter_map(|imp| match into_complete_impl(imp, options) {
+                Ok(imp) => Some(imp),
+                Err(error) => {
+                    items.push(error.to_compile_error());
+                    None
+                }
+            })
+            .collect()
+    };
+    let impls = complete(tracker.impls.into_inner());
+    let free_functions = complete(tracker.free_functions.into_inner());
     Program {
         crates: tracker.crates.into_inner(),
-        impls: tracker
-            .impls
-            .into_inner()
-            .into_iter()
-            .map(into_complete_impl)
-            .collect(),
+        crate_paths: tracker.crate_paths.into_inner(),
+        allow_unsafe: tracker.allow_unsafe.get(),
+        items,
+        impls,
+        free_functions,
     }
 }
 
-fn into_complete_impl(imp: WipImpl) -> CompleteImpl {
+fn into_complete_impl(imp: WipImpl, options: InferenceOptions) -> syn::Result<CompleteImpl> {
     if imp.has_generics() {
-        return imp.compute_trait_bounds();
+        return imp.compute_trait_bounds(options);
     }
-    CompleteImpl {
+    Ok(CompleteImpl {
         trait_ty: imp.trait_ty,
         ty: imp.ty,
         functions: imp
@@ -119,9 +138,12 @@ fn into_complete_impl(imp: WipImpl) -> CompleteImpl {
                     invokes: invokes.unwrap(),
                     macros: macros.unwrap(),
                     ret: function.ret,
+                    attrs: function.attrs.into_inner(),
+                    vis: function.vis.into_inner(),
                 }
             })
             .collect(),
         result: None,
-    }
+        attrs: imp.attrs.into_inner(),
+    })
 }
@@ -1,5 +1,10 @@
-use crate::{Ident, MakeImpl, Module, Parent, Path, RuntimeTrait, RuntimeType, Type, WipImpl};
-use std::cell::RefCell;
+use crate::{
+    Function, Ident, MakeFunction, MakeImpl, Module, Parent, Path, Receiver, RuntimeFunction,
+    RuntimeTrait, RuntimeType, Type, Value, WipImpl,
+};
+use proc_macro2::TokenStream;
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeMap;
 use std::rc::Rc;
 
 #[derive(Clone, Copy)]
@@ -11,7 +16,22 @@ pub struct Execution<'a> {
 #[derive(Debug, Clone)]
 pub(crate) struct Tracker {
     pub(crate) crates: RefCell<Vec<Ident>>,
+    // Paths that replace the crate name at the start of paths into a crate
+    pub(crate) crate_paths: RefCell<BTreeMap<Ident, Path>>,
+    // Set by the macro before any unsafe code may be generated
+    pub(crate) allow_unsafe: Cell<bool>,
+    // Forbids trait inference from narrowing the generic params of self types
+    pub(crate) never_specialize: Cell<bool>,
+    // Keeps the bounds on the types of visible fields, like `Vec<T>: Debug`
+    pub(crate) perfect_derive: Cell<bool>,
+    // Records where the bounds came from, set by `derive_with_report`
+    pub(crate) report: Cell<bool>,
     pub(crate) impls: RefCell<Vec<WipImpl>>,
+    // Items that are emitted unchanged, like the original function of an
+    // attribute macro
+    pub(crate) items: RefCell<Vec<TokenStream>>,
+    // Every free function is inferred like an impl of its own
+    pub(crate) free_functions: RefCell<Vec<WipImpl>>,
 }
 
 impl<'a> Execution<'a> {
@@ -19,19 +39,93 @@ impl<'a> Execution<'a> {
         self.tracker.load_crate(name)
     }
 
-    pub fn make_trait_impl<TraitType, SelfType>(
+    /// Print the paths into the crate `name` under `path` instead, like
+    /// `my_serde::Serialize` for `::serde::Serialize` after
+    /// `set_crate_path("serde", "my_serde")`. This is for macros that let the
+    /// user name the crate, as in `#[serde(crate = "my_serde")]`.
+    pub fn set_crate_path(self, name: &str, path: &str) {
+        self.tracker.set_crate_path(name, path);
+    }
+
+    /// Allow the generated code to implement unsafe traits and to invoke
+    /// unsafe functions, which is the responsibility of the macro. Without
+    /// this, an impl or a function that needs `unsafe` is replaced by a
+    /// compile error.
+    pub fn allow_unsafe(self) {
+        self.tracker.allow_unsafe.set(true);
+    }
+
+    /// Keep the impls generic over every instantiation of their self type.
+    /// By default trait inference narrows the generic params of the self
+    /// type when the invoked functions only accept some instantiations, like
+    /// `impl<T> Trait for Point<T, String>` for `Point<T, U>`. With this, such
+    /// an impl is replaced by a compile error naming the params that would be
+    /// narrowed.
+    pub fn never_specialize(self) {
+        self.tracker.never_specialize.set(true);
+    }
+
+    /// Keep the bounds on the types of fields, like `Vec<T>: Debug` for a
+    /// field `v: Vec<T>`, instead of only the bounds on type params. A bound
+    /// is only kept if the field is at least as visible as the target type,
+    /// and if its type does not mention the target type, like `Vec<Tree<T>>`
+    /// in `Tree<T>`. The visibility of the field type itself is not known, so
+    /// a `pub` field of a private type, like `pub v: Private<T>`, puts a bound
+    /// on the private type into the impl. Other bounds on types that are not
+    /// type params are left out like before.
+    pub fn perfect_derive(self) {
+        self.tracker.perfect_derive.set(true);
+    }
+
+    /// Make a trait impl. The self type does not need to be the target type,
+    /// it may also be a reference to it, like `&Point<T>`, or a library type.
+    /// The generic params of the impl come from every type involved.
+    pub fn make_trait_impl<TraitType, SelfType, Run>(
         self,
         trait_type: TraitType,
         self_type: SelfType,
-        run: fn(MakeImpl),
+        run: Run,
     ) where
         TraitType: RuntimeTrait,
         SelfType: RuntimeType,
+        Run: FnOnce(MakeImpl),
     {
         self.tracker
             .make_trait_impl(trait_type.SELF(), self_type.SELF(), run);
     }
 
+    /// Make an inherent impl, like `impl<T> Point<T> { ... }`. The functions
+    /// of the impl are made from a signature, for example with
+    /// `Function::get_function`.
+    pub fn make_impl<SelfType, Run>(self, self_type: SelfType, run: Run)
+    where
+        SelfType: RuntimeType,
+        Run: FnOnce(MakeImpl),
+    {
+        self.tracker.make_impl(None, self_type.SELF(), run);
+    }
+
+    /// Make a function next to the impls. The function has the generic
+    /// params of the target type that it needs, with the bounds inferred from
+    /// its body.
+    pub fn make_free_function<F, Run>(self, f: F, run: Run)
+    where
+        F: RuntimeFunction,
+        Run: FnOnce(MakeFunction) -> Value,
+    {
+        self.tracker
+            .make_free_function(self.ty.clone(), f.SELF(), run);
+    }
+
+    /// Emit a compile error, like one from parsing attributes with an
+    /// `AttrSchema`, next to the generated impls
+    pub fn emit_error(self, error: syn::Error) {
+        self.tracker
+            .items
+            .borrow_mut()
+            .push(error.to_compile_error());
+    }
+
     pub fn target_type(self) -> Type {
         self.ty.clone()
     }
@@ -41,24 +135,55 @@ impl Tracker {
     pub(crate) fn new() -> Self {
         Tracker {
             crates: RefCell::new(Vec::new()),
+            crate_paths: RefCell::new(BTreeMap::new()),
+            allow_unsafe: Cell::new(false),
+            never_specialize: Cell::new(false),
+            perfect_derive: Cell::new(false),
+            report: Cell::new(false),
             impls: RefCell::new(Vec::new()),
+            items: RefCell::new(Vec::new()),
+            free_functions: RefCell::new(Vec::new()),
         }
     }
 
-    fn load_crate(&self, name: &str) -> Module {
+    pub(crate) fn load_crate(&self, name: &str) -> Module {
         self.crates.borrow_mut().push(Ident::new(name));
         Module {
             path: Path::empty().get_simple_path(name),
         }
     }
 
-    fn make_trait_impl(&self, trait_ty: Rc<Parent>, ty: Type, run: fn(MakeImpl)) {
-        let wip = WipImpl {
-            trait_ty: Some(trait_ty),
-            ty,
-            functions: RefCell::new(Vec::new()),
-        };
+    pub(crate) fn set_crate_path(&self, name: &str, path: &str) {
+        self.crate_paths
+            .borrow_mut()
+            .insert(Ident::new(name), Path::crate_path_from_str(path));
+    }
+
+    fn make_trait_impl(&self, trait_ty: Rc<Parent>, ty: Type, run: impl FnOnce(MakeImpl)) {
+        self.make_impl(Some(trait_ty), ty, run);
+    }
+
+    fn make_impl(&self, trait_ty: Option<Rc<Parent>>, mut ty: Type, run: impl FnOnce(MakeImpl)) {
+        // Elided lifetimes in the self type, like in `&Point<T>`, become
+        // lifetime params of the impl
+        ty.0.insert_new_lifetimes(&mut Vec::new());
+        let wip = WipImpl::new(trait_ty, ty);
         run(MakeImpl { wip: &wip });
         self.impls.borrow_mut().push(wip);
     }
+
+    pub(crate) fn make_free_function(
+        &self,
+        ty: Type,
+        f: Rc<Function>,
+        run: impl FnOnce(MakeFunction) -> Value,
+    ) {
+        if let Receiver::SelfByValue | Receiver::SelfByReference { .. } = f.sig.receiver {
+            panic!("Execution::make_free_function: a free function can not have a self receiver");
+        }
+        // The target type provides the generic params of the function
+        let wip = WipImpl::new(None, ty);
+        wip.make_function(f, None, run);
+        self.free_functions.borrow_mut().push(wip);
+    }
 }
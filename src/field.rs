@@ -1,7 +1,7 @@
-use crate::{attr, GlobalPush, Ident, Type, Value, ValueNode, VALUES};
+use crate::{attr, AttrSchema, AttrValues, GlobalPush, Ident, Type, Value, ValueNode, VALUES};
 use std::fmt::{self, Debug, Display};
 use std::vec;
-use syn::Attribute;
+use syn::{Attribute, Visibility};
 
 #[derive(Debug, Clone)]
 pub struct Fields<T> {
@@ -27,6 +27,7 @@ pub struct Field<T> {
     pub(crate) accessor: Accessor,
     pub(crate) element: T,
     pub(crate) attrs: Vec<Attribute>,
+    pub(crate) vis: Visibility,
 }
 
 impl<T: Debug> Debug for Field<T> {
@@ -35,6 +36,7 @@ impl<T: Debug> Debug for Field<T> {
             .field("accessor", &self.accessor)
             .field("element", &self.element)
             .field("attrs", attr::debug(&self.attrs))
+            .field("vis", &self.vis)
             .finish()
     }
 }
@@ -62,10 +64,21 @@ impl Field<Value> {
     }
 }
 
+impl<T> Field<T> {
+    /// Parse the field attributes with the schema
+    pub fn parse_attrs(&self, schema: &AttrSchema) -> syn::Result<AttrValues> {
+        schema.parse(&self.attrs)
+    }
+}
+
 impl Field<Type> {
     pub fn get_name(&self) -> String {
         self.accessor.to_string()
     }
+
+    pub fn get_type(&self) -> Type {
+        self.element.clone()
+    }
 }
 
 impl Field<Value> {
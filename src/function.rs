@@ -1,16 +1,25 @@
-use crate::{Generics, GlobalPush, Invoke, Parent, Signature, Value, ValueNode, INVOKES, VALUES};
+use crate::{
+    self_trait_bound, Generics, GenericsEnv, GlobalCounter, GlobalPush, Invoke, Parent, ParentKind,
+    Path, Receiver, Signature, Type, TypeNode, Value, ValueNode, INVOKES, TYPE_PARAMS, VALUES,
+};
 use std::rc::Rc;
 
 #[derive(Debug, Clone)]
 pub struct Function {
     pub(crate) parent: Option<Rc<Parent>>,
     pub(crate) name: String,
+    // The path of a function that is not declared in a library, like the
+    // path in `#[serde(serialize_with = "my::func")]`
+    pub(crate) path: Option<Path>,
     pub(crate) sig: Signature,
 }
 
 impl Function {
     pub fn invoke(self: Rc<Function>, args: &[Value]) -> Value {
-        let function = self.clone_with_fresh_generics();
+        let mut function = self.clone_with_fresh_generics();
+        if function.sig.mentions_self() {
+            function = Rc::new(function.with_self_ty(args));
+        }
         let invoke = INVOKES.index_push(Invoke {
             function,
             args: args.iter().map(|value| value.index).collect(),
@@ -26,10 +35,63 @@ impl Function {
         Function {
             parent: None,
             name: name.to_owned(),
+            path: None,
             sig,
         }
     }
 
+    /// Parse a named signature into a function in the module at `path`
+    ///
+    /// ```
+    /// # use reflect::*;
+    /// let path = Path::path_from_str("my", &mut GenericsEnv::default());
+    /// let func = Function::parse(&path, "fn func<T>(&T) -> ::std::string::String").unwrap();
+    /// ```
+    pub fn parse(path: &Path, sig: &str) -> syn::Result<Rc<Function>> {
+        let (name, sig) = Signature::parse_with_name(sig)?;
+        Ok(Function::get_function_at_path(
+            path.get_simple_path(&name),
+            sig,
+        ))
+    }
+
+    /// A function at a path that is only known at runtime, like one named in
+    /// an attribute. The generic params of the signature are inferred where
+    /// the function is invoked, like those of library functions.
+    ///
+    /// ```
+    /// # use reflect::*;
+    /// let mut sig = Signature::new();
+    /// sig.set_generic_params(&["T"]);
+    /// sig.add_input(|env: &mut GenericsEnv| {
+    ///     Type::type_param_from_str("T", env).reference()
+    /// });
+    /// let path = Path::path_from_str("my::func", &mut GenericsEnv::default());
+    /// let func = Function::get_function_at_path(path, sig);
+    /// ```
+    pub fn get_function_at_path(path: Path, sig: Signature) -> Rc<Function> {
+        let name = path
+            .path
+            .last()
+            .expect("Function::get_function_at_path: empty path")
+            .ident
+            .to_string();
+        let mut function = Function::get_function(&name, sig);
+        function.path = Some(path);
+        Rc::new(function)
+    }
+
+    /// Like `get_function_at_path`, with the path in a string like the one
+    /// in `#[serde(serialize_with = "my::func")]`. Errors if the string is not
+    /// a path.
+    pub fn get_function_at_path_str(path: &str, sig: Signature) -> syn::Result<Rc<Function>> {
+        let path = syn::parse_str(path).map_err(|error| {
+            syn::Error::new(error.span(), format!("expected a path, found `{}`", path))
+        })?;
+        let path = Path::syn_to_path(path, &mut GenericsEnv::default());
+        Ok(Function::get_function_at_path(path, sig))
+    }
+
     /// When calling `set_parent` it is important to use a reference to the
     /// same Parent struct for all functions declared inside of the same impl
     /// or trait definition. Otherwise the trait inference may not work
@@ -38,12 +100,42 @@ impl Function {
         self.parent = Some(parent);
     }
 
+    /// Replace `Self` in the signature with the type it stands for when the
+    /// function is invoked with `args`
+    fn with_self_ty(&self, args: &[Value]) -> Function {
+        let mut function = self.clone();
+        let parent = self
+            .parent
+            .as_ref()
+            .expect("Function::invoke: `Self` outside of a trait or impl");
+        let self_ty = match (parent.parent_kind, self.sig.receiver) {
+            (ParentKind::Impl, _) => TypeNode::Path(parent.path.clone()),
+            (ParentKind::Trait, Receiver::SelfByValue) => args[0].index.node().get_type().0,
+            (ParentKind::Trait, Receiver::SelfByReference { .. }) => {
+                args[0].index.node().get_type().dereference().0
+            }
+            (ParentKind::Trait, Receiver::NoSelf) => {
+                // Inferred from where the return value is used, like the
+                // implementing type of `Default::default()`
+                let self_ty = Type(TypeNode::TypeParam(TYPE_PARAMS.count()));
+                let bound = self_trait_bound(parent, self_ty.clone());
+                function.sig.generics.constraints.push(bound);
+                self_ty.0
+            }
+        };
+        function.sig.substitute_self(&self_ty);
+        function
+    }
+
     pub(crate) fn clone_with_fresh_generics(self: Rc<Self>) -> Rc<Self> {
-        if let Some((parent, mut param_map)) = self
+        if let Some((parent, mut env)) = self
             .parent
             .as_ref()
             .map(|parent| parent.clone_with_fresh_generics())
         {
+            // The params of the function are in a scope of their own, inside of
+            // the params of the parent
+            env.push_scope();
             let generics = &self.sig.generics;
             let sig_generics = Generics {
                 params: generics
@@ -51,16 +143,16 @@ impl Function {
                     .iter()
                     .map(|param| {
                         let new_param = param.get_fresh_param();
-                        param_map.insert(*param, new_param);
+                        env.insert_fresh(*param, new_param);
                         new_param
                     })
                     .collect(),
                 constraints: generics
                     .constraints
                     .iter()
-                    .map(|constraint| constraint.clone_with_fresh_generics(&param_map))
+                    .map(|constraint| constraint.clone_with_fresh_generics(&env))
                     .collect(),
-                param_map: generics.param_map.clone_with_fresh_generics(&param_map),
+                env: generics.env.clone_with_fresh_generics(&env),
             };
             let old_parent = self.parent.as_ref().unwrap();
             let old_sig = &self.sig;
@@ -68,33 +160,37 @@ impl Function {
             Rc::new(Function {
                 parent: Some(Rc::new(parent)),
                 name: self.name.clone(),
+                path: self.path.clone(),
                 sig: Signature {
                     generics: sig_generics,
-                    receiver: old_sig.receiver.clone_with_fresh_generics(&param_map),
+                    receiver: old_sig.receiver.clone_with_fresh_generics(&env),
                     inputs: old_sig
                         .inputs
                         .iter()
-                        .map(|ty| ty.clone_with_fresh_generics(&param_map))
+                        .map(|ty| ty.clone_with_fresh_generics(&env))
                         .collect(),
-                    output: old_sig.output.clone_with_fresh_generics(&param_map),
+                    output: old_sig.output.clone_with_fresh_generics(&env),
+                    is_unsafe: old_sig.is_unsafe,
                 },
             })
         } else if !self.sig.generics.params.is_empty() {
-            let (sig_generics, param_map) = self.sig.generics.clone_with_fresh_generics();
+            let (sig_generics, env) = self.sig.generics.clone_with_fresh_generics();
             let old_sig = &self.sig;
 
             Rc::new(Function {
                 parent: self.parent.clone(),
                 name: self.name.clone(),
+                path: self.path.clone(),
                 sig: Signature {
                     generics: sig_generics,
-                    receiver: old_sig.receiver.clone_with_fresh_generics(&param_map),
+                    receiver: old_sig.receiver.clone_with_fresh_generics(&env),
                     inputs: old_sig
                         .inputs
                         .iter()
-                        .map(|ty| ty.clone_with_fresh_generics(&param_map))
+                        .map(|ty| ty.clone_with_fresh_generics(&env))
                         .collect(),
-                    output: old_sig.output.clone_with_fresh_generics(&param_map),
+                    output: old_sig.output.clone_with_fresh_generics(&env),
+                    is_unsafe: old_sig.is_unsafe,
                 },
             })
         } else {
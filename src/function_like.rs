@@ -0,0 +1,89 @@
+use crate::{check_generics_and_type, derive, global_data, Execution, Generics, Tracker, Type};
+use proc_macro2::TokenStream;
+use syn::parse::{Parse, ParseStream, Parser};
+use syn::punctuated::Punctuated;
+use syn::Token;
+
+/// Entry point of a function-like macro that takes a comma separated list of
+/// types, like `impl_for_tuples!(<A> (A,), <A, B> (A, B))`. The generic
+/// params of a type are declared in front of it. The `run` function is called
+/// once for every type, with that type as the target type.
+pub fn function_like<TokenStream, Run>(input: TokenStream, run: Run) -> TokenStream
+where
+    TokenStream: Into<proc_macro2::TokenStream> + From<proc_macro2::TokenStream>,
+    Run: FnMut(Execution),
+{
+    let input = input.into();
+    let output = function_like2(input, run);
+    global_data::clear();
+    output.into()
+}
+
+fn function_like2<Run>(input: TokenStream, mut run: Run) -> TokenStream
+where
+    Run: FnMut(Execution),
+{
+    let types = match Punctuated::<TargetType, Token![,]>::parse_terminated.parse2(input) {
+        Ok(types) => types,
+        Err(error) => return error.to_compile_error(),
+    };
+    let mut converted = Vec::new();
+    for TargetType { generics, ty } in types {
+        if let Err(error) = check_target_type(&generics, &ty) {
+            return error.to_compile_error();
+        }
+        let mut generics = Generics::syn_to_generics(generics);
+        converted.push(Type::syn_to_type(ty, &mut generics.env));
+    }
+
+    let tracker = Tracker::new();
+    for ty in &converted {
+        run(Execution {
+            ty,
+            tracker: &tracker,
+        });
+    }
+
+    let program = derive::tracker_to_program(tracker);
+    program.compile()
+}
+
+// A type with the generic params that it is declared with, like `<A> (A,)`
+struct TargetType {
+    generics: syn::Generics,
+    ty: syn::Type,
+}
+
+impl Parse for TargetType {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        // A type that starts with `<` may be a qualified path like
+        // `<A as Trait>::Output` instead
+        let fork = input.fork();
+        let generics = if input.peek(Token![<]) && fork.parse::<syn::Generics>().is_ok() {
+            input.parse()?
+        } else {
+            syn::Generics::default()
+        };
+        let ty = input.parse()?;
+        Ok(TargetType { generics, ty })
+    }
+}
+
+/// The bounds of the impl are inferred, so the params are declared without
+/// bounds
+fn check_target_type(generics: &syn::Generics, ty: &syn::Type) -> syn::Result<()> {
+    for param in &generics.params {
+        let has_bounds = match param {
+            syn::GenericParam::Type(param) => !param.bounds.is_empty() || param.default.is_some(),
+            syn::GenericParam::Lifetime(def) => !def.bounds.is_empty(),
+            syn::GenericParam::Const(_) => false,
+        };
+        if has_bounds {
+            return Err(syn::Error::new_spanned(
+                param,
+                "expected a generic parameter without bounds, the bounds of the impl are inferred",
+            ));
+        }
+    }
+    check_generics_and_type(generics, ty)
+}
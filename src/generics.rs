@@ -1,9 +1,16 @@
-use crate::{GlobalCounter, Ident, Path, Type, TypeNode, LIFETIMES, STATIC_LIFETIME, TYPE_PARAMS};
+use crate::{
+    GlobalCounter, GlobalPush, Ident, Path, Type, TypeNode, TypedIndex, CONST_PARAMS, LIFETIMES,
+    STATIC_LIFETIME, TYPE_PARAMS,
+};
+use proc_macro2::TokenStream;
+use quote::ToTokens;
+use std::borrow::Cow;
 use std::collections::BTreeMap;
 use std::default::Default;
+use std::mem;
 use syn::{parse_str, BoundLifetimes, PredicateLifetime, WhereClause, WherePredicate};
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
 pub struct Generics {
     /// Represents the generic params without bounds.
     /// The bounds are moved to constraints.
@@ -12,16 +19,14 @@ pub struct Generics {
     /// Essentially represents the where clause
     pub(crate) constraints: Vec<GenericConstraint>,
 
-    // A mapping between the parameter identifiers and their GenericParam
-    // representation
-    pub(crate) param_map: SynParamMap,
+    // The params in scope by name, including those of the enclosing parent
+    pub(crate) env: GenericsEnv,
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub(crate) enum GenericParam {
     Lifetime(Lifetime),
     Type(TypeParam),
-    // Not supported
     Const(ConstParam),
 }
 
@@ -49,6 +54,8 @@ pub(crate) struct PredicateType {
 pub(crate) enum TypeParamBound {
     Trait(TraitBound),
     Lifetime(Lifetime),
+    /// A maybe bound: `?Sized`
+    Maybe(Path),
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -64,9 +71,15 @@ pub(crate) struct LifetimeDef {
     pub(crate) bounds: Vec<Lifetime>,
 }
 
+/// A const param, like `N` in `struct Buf<const N: usize>`. Its type and
+/// default value are kept in `CONST_PARAMS`.
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
-pub(crate) struct ConstParam {
-    pub(crate) private: (),
+pub(crate) struct ConstParam(pub usize);
+
+#[derive(Debug, Clone)]
+pub(crate) struct ConstParamDecl {
+    pub(crate) ty: Type,
+    pub(crate) default: Option<Expr>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -95,121 +108,213 @@ pub(crate) struct Constraint {
     pub(crate) bounds: Vec<TypeParamBound>,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
-pub(crate) struct Expr {
-    pub(crate) private: (),
+/// A const argument, like the `N` or `3` in `[T; N]` and `Buf<3>`
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub(crate) enum Expr {
+    Param(ConstParam),
+    // A literal or a block, like `3` or `{ 1 + 2 }`, printed as written
+    Value(String),
 }
 
+/// The generic params in scope, like the params of a method inside of the
+/// params of its impl or trait, and the lifetimes of a `for<..>` binder
+/// inside of those. Params are looked up by name while parsing, and by id
+/// while cloning with fresh params, from the innermost scope outwards, so an
+/// inner param shadows an outer param with the same name.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
-pub struct SynParamMap {
-    // TODO: Unify SynParamMap and ParamMap
-    pub(crate) map: BTreeMap<String, GenericParam>,
-}
-
-pub struct ParamMap {
-    pub(crate) map: BTreeMap<GenericParam, GenericParam>,
+pub struct GenericsEnv {
+    scopes: Vec<Scope>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+struct Scope {
+    // The params declared in the scope, by name
+    names: BTreeMap<String, GenericParam>,
+    // The fresh params that the params declared in the scope are cloned to
+    fresh: BTreeMap<GenericParam, GenericParam>,
+}
+
+/// Replaces generic parameters with the corresponding generic arguments
+pub(crate) struct Substitution {
+    pub(crate) types: BTreeMap<TypeParam, TypeNode>,
+    pub(crate) lifetimes: BTreeMap<Lifetime, Lifetime>,
+    pub(crate) consts: BTreeMap<ConstParam, Expr>,
+    // Replaces the `Self` type, if set
+    pub(crate) self_ty: Option<TypeNode>,
+}
+
+impl Substitution {
+    /// Only replaces the `Self` type
+    pub(crate) fn self_ty(self_ty: TypeNode) -> Self {
+        Substitution {
+            types: BTreeMap::new(),
+            lifetimes: BTreeMap::new(),
+            consts: BTreeMap::new(),
+            self_ty: Some(self_ty),
+        }
+    }
 }
 
-impl SynParamMap {
+impl GenericsEnv {
     pub(crate) fn new() -> Self {
-        let static_lifetime = "'static".to_string();
-        let mut param_map = BTreeMap::new();
-        param_map.insert(static_lifetime, GenericParam::Lifetime(STATIC_LIFETIME));
-        SynParamMap { map: param_map }
+        GenericsEnv {
+            scopes: vec![Scope::default()],
+        }
     }
 
-    pub(crate) fn insert(&mut self, key: String, value: GenericParam) -> Option<GenericParam> {
-        self.map.insert(key, value)
+    /// Enter a nested scope, whose params shadow the params in scope so far
+    pub(crate) fn push_scope(&mut self) {
+        self.scopes.push(Scope::default());
     }
 
-    pub(crate) fn get(&self, key: &str) -> Option<&GenericParam> {
-        self.map.get(key)
+    /// Leave the innermost scope, and the params declared in it
+    pub(crate) fn pop_scope(&mut self) {
+        self.scopes.pop();
     }
 
-    pub fn append(&mut self, other: &mut SynParamMap) {
-        self.map.append(&mut other.map)
+    /// Make the scopes of `outer` enclose the scopes of this environment,
+    /// like the params of an impl enclose the params of its methods
+    pub(crate) fn enclose_in(&mut self, outer: &GenericsEnv) {
+        let inner = mem::take(&mut self.scopes);
+        self.scopes = outer.scopes.iter().cloned().chain(inner).collect();
     }
 
-    pub(crate) fn clone_with_fresh_generics(&self, param_map: &ParamMap) -> Self {
-        let mut syn_param_map = SynParamMap::new();
-        for key in self.map.keys() {
-            let value = self.get(key).unwrap();
+    /// Declare a param by name in the innermost scope
+    pub(crate) fn insert(&mut self, name: String, param: GenericParam) -> Option<GenericParam> {
+        self.innermost().names.insert(name, param)
+    }
 
-            if let Some(value) = param_map.get(value) {
-                syn_param_map.insert(key.clone(), *value);
-            }
+    /// Declare the fresh param that a param is cloned to in the innermost
+    /// scope
+    pub(crate) fn insert_fresh(
+        &mut self,
+        param: GenericParam,
+        fresh: GenericParam,
+    ) -> Option<GenericParam> {
+        self.innermost().fresh.insert(param, fresh)
+    }
+
+    /// The param with the name, from the innermost scope that declares it
+    pub(crate) fn get(&self, name: &str) -> Option<GenericParam> {
+        if name == "'static" {
+            return Some(GenericParam::Lifetime(STATIC_LIFETIME));
         }
-        syn_param_map
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.names.get(name).copied())
     }
 
-    pub(crate) fn get_lifetime(&self, ident: &str) -> Lifetime {
-        self.get(ident)
-            .and_then(|param| param.lifetime())
-            .expect("Lifetime::get_lifetime: ident is not a lifetime")
+    /// The fresh param that a param is cloned to
+    pub(crate) fn get_fresh(&self, param: GenericParam) -> Option<GenericParam> {
+        if param == GenericParam::Lifetime(STATIC_LIFETIME) {
+            return Some(param);
+        }
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.fresh.get(&param).copied())
     }
-}
 
-impl ParamMap {
-    pub(crate) fn new() -> Self {
-        let mut map = BTreeMap::new();
-        map.insert(
-            GenericParam::Lifetime(STATIC_LIFETIME),
-            GenericParam::Lifetime(STATIC_LIFETIME),
-        );
-        ParamMap { map }
+    /// The name that a param is declared with, unless it is shadowed
+    pub(crate) fn name_of(&self, param: GenericParam) -> Option<&str> {
+        self.scopes.iter().rev().find_map(|scope| {
+            scope
+                .names
+                .iter()
+                .find(|(_, &declared)| declared == param)
+                .map(|(name, _)| name.as_str())
+                .filter(|name| self.get(name) == Some(param))
+        })
     }
 
-    pub(crate) fn insert(
-        &mut self,
-        key: GenericParam,
-        value: GenericParam,
-    ) -> Option<GenericParam> {
-        self.map.insert(key, value)
+    /// The same scopes, with the params that are cloned to fresh params in
+    /// `env` replaced by them
+    pub(crate) fn clone_with_fresh_generics(&self, env: &GenericsEnv) -> Self {
+        GenericsEnv {
+            scopes: self
+                .scopes
+                .iter()
+                .map(|scope| Scope {
+                    names: scope
+                        .names
+                        .iter()
+                        .filter_map(|(name, &param)| Some((name.clone(), env.get_fresh(param)?)))
+                        .collect(),
+                    fresh: BTreeMap::new(),
+                })
+                .collect(),
+        }
+    }
+
+    pub(crate) fn get_lifetime(&self, ident: &str) -> Lifetime {
+        self.get(ident)
+            .and_then(|param| param.lifetime())
+            .expect("Lifetime::get_lifetime: ident is not a lifetime")
     }
 
-    pub(crate) fn get(&self, key: &GenericParam) -> Option<&GenericParam> {
-        self.map.get(key)
+    fn innermost(&mut self) -> &mut Scope {
+        self.scopes
+            .last_mut()
+            .expect("GenericsEnv::innermost: No scope")
     }
 }
 
 impl TypeParamBound {
-    pub(crate) fn get_type_param_bound(
-        type_param_bound: &str,
-        param_map: &mut SynParamMap,
-    ) -> Self {
+    pub(crate) fn get_type_param_bound(type_param_bound: &str, env: &mut GenericsEnv) -> Self {
         syn_to_type_param_bound(
             parse_str(type_param_bound)
                 .expect("TypeParamBound::get_type_param_bound: Not a TypeParamBound"),
-            param_map,
+            env,
         )
     }
 
-    pub(crate) fn clone_with_fresh_generics(&self, param_map: &ParamMap) -> Self {
+    pub(crate) fn clone_with_fresh_generics(&self, env: &GenericsEnv) -> Self {
         match self {
             TypeParamBound::Lifetime(lifetime) => {
-                TypeParamBound::Lifetime(lifetime.clone_with_fresh_generics(param_map))
+                TypeParamBound::Lifetime(lifetime.clone_with_fresh_generics(env))
             }
 
-            TypeParamBound::Trait(bound) => TypeParamBound::Trait(TraitBound {
-                lifetimes: bound
-                    .lifetimes
-                    .iter()
-                    .map(|lifetime| lifetime.clone_with_fresh_generics(param_map))
-                    .collect(),
+            TypeParamBound::Trait(bound) => {
+                let (lifetimes, env) = fresh_bound_lifetimes(&bound.lifetimes, env);
+                TypeParamBound::Trait(TraitBound {
+                    lifetimes,
+                    path: bound.path.clone_with_fresh_generics(&env),
+                })
+            }
+
+            TypeParamBound::Maybe(path) => TypeParamBound::Maybe(path.clone()),
+        }
+    }
+}
 
-                path: bound.path.clone_with_fresh_generics(param_map),
+impl TypeParamBound {
+    pub(crate) fn substitute(&self, substitution: &Substitution) -> Self {
+        match self {
+            TypeParamBound::Lifetime(lifetime) => {
+                TypeParamBound::Lifetime(lifetime.substitute(substitution))
+            }
+
+            TypeParamBound::Trait(bound) => TypeParamBound::Trait(TraitBound {
+                lifetimes: bound.lifetimes.clone(),
+                path: bound.path.substitute(substitution),
             }),
+
+            TypeParamBound::Maybe(path) => TypeParamBound::Maybe(path.clone()),
         }
     }
 }
 
 impl Lifetime {
-    pub(crate) fn clone_with_fresh_generics(self, param_map: &ParamMap) -> Self {
-        param_map
-            .get(&GenericParam::Lifetime(self))
+    pub(crate) fn clone_with_fresh_generics(self, env: &GenericsEnv) -> Self {
+        env.get_fresh(GenericParam::Lifetime(self))
             .and_then(|param| param.lifetime())
             .unwrap()
     }
+
+    pub(crate) fn substitute(self, substitution: &Substitution) -> Self {
+        substitution.lifetimes.get(&self).copied().unwrap_or(self)
+    }
 }
 
 impl GenericParam {
@@ -227,39 +332,107 @@ impl GenericParam {
         }
     }
 
+    pub(crate) fn const_param(self) -> Option<ConstParam> {
+        match self {
+            Self::Const(const_param) => Some(const_param),
+            _ => None,
+        }
+    }
+
     pub(crate) fn get_fresh_param(self) -> Self {
         match self {
             Self::Type(type_param) => Self::Type(TYPE_PARAMS.count()),
 
             Self::Lifetime(lifetime) => Self::Lifetime(LIFETIMES.count()),
 
-            Self::Const(_const) => unimplemented!("GenericParam::get_fresh_param: Const"),
+            Self::Const(const_param) => Self::Const(CONST_PARAMS.index_push(const_param.decl())),
+        }
+    }
+}
+
+impl ConstParam {
+    /// The type and default value of the param
+    pub(crate) fn decl(self) -> ConstParamDecl {
+        CONST_PARAMS.with_borrow(|const_params| const_params[self.0].clone())
+    }
+}
+
+impl TypedIndex for ConstParamDecl {
+    type Index = ConstParam;
+
+    fn index(i: usize) -> Self::Index {
+        ConstParam(i)
+    }
+}
+
+impl Expr {
+    /// A const param if the expression names one, otherwise the expression
+    /// as written
+    pub(crate) fn syn_to_expr(expr: syn::Expr, env: &GenericsEnv) -> Self {
+        if let syn::Expr::Path(path) = &expr {
+            if let Some(ident) = path.path.get_ident() {
+                if let Some(param) = env.get(&ident.to_string()) {
+                    return Expr::Param(
+                        param
+                            .const_param()
+                            .expect("Expr::syn_to_expr: Not a const param"),
+                    );
+                }
+            }
+        }
+        Expr::Value(expr.into_token_stream().to_string())
+    }
+
+    pub(crate) fn value_tokens(value: &str) -> TokenStream {
+        value
+            .parse()
+            .expect("Expr::value_tokens: Not a const expression")
+    }
+
+    pub(crate) fn substitute(&self, substitution: &Substitution) -> Self {
+        match self {
+            Expr::Param(param) => substitution
+                .consts
+                .get(param)
+                .cloned()
+                .unwrap_or(Expr::Param(*param)),
+            Expr::Value(value) => Expr::Value(value.clone()),
+        }
+    }
+
+    pub(crate) fn clone_with_fresh_generics(&self, env: &GenericsEnv) -> Self {
+        match self {
+            Expr::Param(param) => Expr::Param(
+                env.get_fresh(GenericParam::Const(*param))
+                    .and_then(|param| param.const_param())
+                    .unwrap_or(*param),
+            ),
+            Expr::Value(value) => Expr::Value(value.clone()),
         }
     }
 }
 
 impl GenericConstraint {
-    pub(crate) fn clone_with_fresh_generics(&self, param_map: &ParamMap) -> Self {
+    pub(crate) fn clone_with_fresh_generics(&self, env: &GenericsEnv) -> Self {
         match self {
-            Self::Type(predicate) => Self::Type(PredicateType {
-                lifetimes: predicate
-                    .lifetimes
-                    .iter()
-                    .map(|lifetime| lifetime.clone_with_fresh_generics(param_map))
-                    .collect(),
-                bounded_ty: predicate.bounded_ty.clone_with_fresh_generics(param_map),
-                bounds: predicate
-                    .bounds
-                    .iter()
-                    .map(|bound| bound.clone_with_fresh_generics(param_map))
-                    .collect(),
-            }),
+            Self::Type(predicate) => {
+                let (lifetimes, env) = fresh_bound_lifetimes(&predicate.lifetimes, env);
+                Self::Type(PredicateType {
+                    lifetimes,
+                    bounded_ty: predicate.bounded_ty.clone_with_fresh_generics(&env),
+                    bounds: predicate
+                        .bounds
+                        .iter()
+                        .map(|bound| bound.clone_with_fresh_generics(&env))
+                        .collect(),
+                })
+            }
             Self::Lifetime(lifetime_def) => Self::Lifetime(LifetimeDef {
-                lifetime: lifetime_def.lifetime.clone_with_fresh_generics(param_map),
+                lifetime: lifetime_def.lifetime.clone_with_fresh_generics(env),
                 bounds: lifetime_def
                     .bounds
                     .iter()
-                    .map(|lifetime| lifetime.clone_with_fresh_generics(param_map))
+                    .map(|lifetime| lifetime.clone_with_fresh_generics(env))
                     .collect(),
             }),
         }
@@ -269,10 +442,9 @@ impl GenericConstraint {
 impl Generics {
     pub fn set_generic_params(&mut self, params: &[&str]) {
         let syn_params = params.iter().map(|param| parse_str(param).unwrap());
-        let (params, constraints, mut param_map) = syn_to_generic_params(syn_params);
+        let (params, constraints) = syn_to_generic_params(syn_params, &mut self.env);
         self.params.extend(params);
         self.constraints.extend(constraints);
-        self.param_map.append(&mut param_map);
     }
 
     pub fn set_generic_constraints(&mut self, constraints: &[&str]) {
@@ -280,27 +452,33 @@ impl Generics {
             .iter()
             .map(|constraint| parse_str(constraint).unwrap());
         let constraints =
-            syn_where_predicates_to_generic_constraints(syn_constraints, &mut self.param_map);
+            syn_where_predicates_to_generic_constraints(syn_constraints, &mut self.env);
         self.constraints.extend(constraints);
     }
 
     pub(crate) fn syn_to_generics(generics: syn::Generics) -> Self {
-        let (params, mut constraints, mut param_map) = syn_to_generic_params(generics.params);
+        Generics::syn_to_generics_in(generics, GenericsEnv::new())
+    }
+
+    /// Convert generics whose params are declared in the innermost scope of
+    /// `env`, like the params of a method inside of the params of its impl
+    pub(crate) fn syn_to_generics_in(generics: syn::Generics, mut env: GenericsEnv) -> Self {
+        let (params, mut constraints) = syn_to_generic_params(generics.params, &mut env);
         if let Some(where_clause) = generics.where_clause {
             constraints.extend(syn_where_clause_to_generic_constraints(
                 where_clause,
-                &mut param_map,
+                &mut env,
             ));
         };
         Generics {
             params,
             constraints,
-            param_map,
+            env,
         }
     }
 
-    pub(crate) fn clone_with_fresh_generics(&self) -> (Self, ParamMap) {
-        let mut param_map = ParamMap::new();
+    pub(crate) fn clone_with_fresh_generics(&self) -> (Self, GenericsEnv) {
+        let mut env = GenericsEnv::new();
         (
             Generics {
                 params: self
@@ -308,35 +486,107 @@ impl Generics {
                     .iter()
                     .map(|param| {
                         let new_param = param.get_fresh_param();
-                        param_map.insert(*param, new_param);
+                        env.insert_fresh(*param, new_param);
                         new_param
                     })
                     .collect(),
                 constraints: self
                     .constraints
                     .iter()
-                    .map(|constraint| constraint.clone_with_fresh_generics(&param_map))
+                    .map(|constraint| constraint.clone_with_fresh_generics(&env))
                     .collect(),
-                param_map: self.param_map.clone_with_fresh_generics(&param_map),
+                env: self.env.clone_with_fresh_generics(&env),
             },
-            param_map,
+            env,
         )
     }
 }
 
-impl Default for Generics {
-    fn default() -> Self {
-        Generics {
-            params: Vec::new(),
-            constraints: Vec::new(),
-            param_map: SynParamMap::new(),
+impl Generics {
+    /// Maps the generic params to the generic arguments of a path. Type
+    /// parameters without a corresponding argument are left to be inferred,
+    /// and const parameters get their default value, if any.
+    pub(crate) fn get_substitution(&self, args: &[GenericArgument]) -> Substitution {
+        let mut lifetime_args = args.iter().filter_map(|arg| match arg {
+            GenericArgument::Lifetime(lifetime) => Some(*lifetime),
+            _ => None,
+        });
+        // Type and const arguments are given in the order of their params
+        let mut type_and_const_args = args
+            .iter()
+            .filter(|arg| matches!(arg, GenericArgument::Type(_) | GenericArgument::Const(_)));
+
+        let mut substitution = Substitution {
+            types: BTreeMap::new(),
+            lifetimes: BTreeMap::new(),
+            consts: BTreeMap::new(),
+            self_ty: None,
+        };
+        for param in &self.params {
+            match param {
+                GenericParam::Lifetime(lifetime) => {
+                    if let Some(arg) = lifetime_args.next() {
+                        substitution.lifetimes.insert(*lifetime, arg);
+                    }
+                }
+                GenericParam::Type(type_param) => {
+                    let arg = match type_and_const_args.next() {
+                        Some(GenericArgument::Type(ty)) => ty.0.clone(),
+                        _ => TypeNode::Infer,
+                    };
+                    substitution.types.insert(*type_param, arg);
+                }
+                GenericParam::Const(const_param) => {
+                    let arg = match type_and_const_args.next() {
+                        Some(GenericArgument::Const(expr)) => Some(expr.clone()),
+                        _ => const_param.decl().default,
+                    };
+                    if let Some(arg) = arg {
+                        substitution.consts.insert(*const_param, arg);
+                    }
+                }
+            }
         }
+        substitution
+    }
+}
+
+impl Default for GenericsEnv {
+    fn default() -> Self {
+        GenericsEnv::new()
     }
 }
 
+/// The lifetimes of a `for<..>` binder are only in scope of the bound, so
+/// they get fresh lifetimes in a nested scope
+fn fresh_bound_lifetimes<'a>(
+    lifetimes: &[Lifetime],
+    env: &'a GenericsEnv,
+) -> (Vec<Lifetime>, Cow<'a, GenericsEnv>) {
+    if lifetimes.is_empty() {
+        return (Vec::new(), Cow::Borrowed(env));
+    }
+    let mut env = env.clone();
+    env.push_scope();
+    let lifetimes = lifetimes
+        .iter()
+        .map(|&lifetime| {
+            let fresh = LIFETIMES.count();
+            env.insert_fresh(
+                GenericParam::Lifetime(lifetime),
+                GenericParam::Lifetime(fresh),
+            );
+            fresh
+        })
+        .collect();
+    (lifetimes, Cow::Owned(env))
+}
+
+/// Declares the lifetimes of a `for<..>` binder in the innermost scope, which
+/// the caller enters for the bound
 fn syn_to_bound_lifetimes(
     lifetimes: Option<BoundLifetimes>,
-    param_map: &mut SynParamMap,
+    env: &mut GenericsEnv,
 ) -> Vec<Lifetime> {
     lifetimes.map_or_else(Vec::new, |lifetimes| {
         lifetimes
@@ -348,7 +598,7 @@ fn syn_to_bound_lifetimes(
                      ..
                  }| {
                     let lifetime = LIFETIMES.count();
-                    param_map.insert(syn_lifetime.to_string(), GenericParam::Lifetime(lifetime));
+                    env.insert(syn_lifetime.to_string(), GenericParam::Lifetime(lifetime));
                     lifetime
                 },
             )
@@ -358,14 +608,14 @@ fn syn_to_bound_lifetimes(
 
 fn syn_where_clause_to_generic_constraints<'a>(
     where_clause: WhereClause,
-    param_map: &'a mut SynParamMap,
+    env: &'a mut GenericsEnv,
 ) -> impl Iterator<Item = GenericConstraint> + 'a {
-    syn_where_predicates_to_generic_constraints(where_clause.predicates.into_iter(), param_map)
+    syn_where_predicates_to_generic_constraints(where_clause.predicates.into_iter(), env)
 }
 
 pub(crate) fn syn_where_predicates_to_generic_constraints<'a, I>(
     where_predicates: I,
-    param_map: &'a mut SynParamMap,
+    env: &'a mut GenericsEnv,
 ) -> impl Iterator<Item = GenericConstraint> + 'a
 where
     I: Iterator<Item = WherePredicate> + 'a,
@@ -376,41 +626,46 @@ where
             bounded_ty,
             bounds,
             ..
-        }) => GenericConstraint::Type(PredicateType {
-            lifetimes: syn_to_bound_lifetimes(lifetimes, param_map),
-            bounded_ty: Type::syn_to_type(bounded_ty, param_map),
-            bounds: syn_to_type_param_bounds(bounds, param_map).collect(),
-        }),
+        }) => {
+            env.push_scope();
+            let predicate = PredicateType {
+                lifetimes: syn_to_bound_lifetimes(lifetimes, env),
+                bounded_ty: Type::syn_to_type(bounded_ty, env),
+                bounds: syn_to_type_param_bounds(bounds, env).collect(),
+            };
+            env.pop_scope();
+            GenericConstraint::Type(predicate)
+        }
         WherePredicate::Lifetime(PredicateLifetime {
             lifetime, bounds, ..
         }) => GenericConstraint::Lifetime(LifetimeDef {
-            lifetime: param_map.get_lifetime(&lifetime.to_string()),
+            lifetime: env.get_lifetime(&lifetime.to_string()),
             bounds: bounds
                 .into_iter()
-                .map(|lifetime| param_map.get_lifetime(&lifetime.to_string()))
+                .map(|lifetime| env.get_lifetime(&lifetime.to_string()))
                 .collect(),
         }),
         WherePredicate::Eq(_eq) => unimplemented!("Generics::syn_to_generics: Eq"),
     })
 }
 
+/// Declares the params in the innermost scope of `env`, so that the bounds
+/// may refer to each other and to the params of the enclosing scopes
 pub(crate) fn syn_to_generic_params<T>(
     params: T,
-) -> (Vec<GenericParam>, Vec<GenericConstraint>, SynParamMap)
+    env: &mut GenericsEnv,
+) -> (Vec<GenericParam>, Vec<GenericConstraint>)
 where
     T: IntoIterator<Item = syn::GenericParam>,
 {
-    let mut param_map = SynParamMap::new();
     let mut constraints = Vec::new();
     let params: Vec<_> = params.into_iter().collect();
-    params
-        .iter()
-        .for_each(|param| param_mapping(param, &mut param_map));
+    params.iter().for_each(|param| declare_param(param, env));
     let params = params
         .into_iter()
         .map(|param| match param {
             syn::GenericParam::Type(syn::TypeParam { ident, bounds, .. }) => {
-                let &param = param_map.get(&ident.to_string()).unwrap();
+                let param = env.get(&ident.to_string()).unwrap();
                 if !bounds.is_empty() {
                     constraints.push(GenericConstraint::Type(PredicateType {
                         lifetimes: Vec::new(),
@@ -419,7 +674,7 @@ where
                                 .type_param()
                                 .expect("syn_to_generic_params: Not a type param ref"),
                         )),
-                        bounds: syn_to_type_param_bounds(bounds, &mut param_map).collect(),
+                        bounds: syn_to_type_param_bounds(bounds, env).collect(),
                     }));
                 }
                 param
@@ -428,132 +683,192 @@ where
                 lifetime, bounds, ..
             }) => {
                 let lifetime = lifetime.to_string();
-                let &param = param_map.get(&lifetime).unwrap();
+                let param = env.get(&lifetime).unwrap();
                 if !bounds.is_empty() {
                     constraints.push(GenericConstraint::Lifetime(LifetimeDef {
-                        lifetime: param_map.get_lifetime(&lifetime),
+                        lifetime: env.get_lifetime(&lifetime),
                         bounds: bounds
                             .into_iter()
-                            .map(|lifetime| param_map.get_lifetime(&lifetime.to_string()))
+                            .map(|lifetime| env.get_lifetime(&lifetime.to_string()))
                             .collect(),
                     }));
                 }
                 param
             }
-            syn::GenericParam::Const(_const) => unimplemented!("Generics::syn_to_generics: Const"),
+            syn::GenericParam::Const(syn::ConstParam { ident, .. }) => {
+                env.get(&ident.to_string()).unwrap()
+            }
         })
         .collect();
-    (params, constraints, param_map)
+    (params, constraints)
 }
 
-pub(crate) fn param_mapping(param: &syn::GenericParam, param_map: &mut SynParamMap) {
+pub(crate) fn declare_param(param: &syn::GenericParam, env: &mut GenericsEnv) {
     match &param {
         syn::GenericParam::Type(syn::TypeParam { ident, .. }) => {
             let param = GenericParam::Type(TYPE_PARAMS.count());
-            param_map.insert(ident.to_string(), param);
+            env.insert(ident.to_string(), param);
         }
         syn::GenericParam::Lifetime(syn::LifetimeDef { lifetime, .. }) => {
             let param = GenericParam::Lifetime(LIFETIMES.count());
-            param_map.insert(lifetime.to_string(), param);
+            env.insert(lifetime.to_string(), param);
+        }
+        syn::GenericParam::Const(syn::ConstParam {
+            ident, ty, default, ..
+        }) => {
+            let decl = ConstParamDecl {
+                ty: Type::syn_to_type(ty.clone(), env),
+                default: default
+                    .clone()
+                    .map(|default| Expr::syn_to_expr(default, env)),
+            };
+            let param = GenericParam::Const(CONST_PARAMS.index_push(decl));
+            env.insert(ident.to_string(), param);
         }
-        syn::GenericParam::Const(_const) => unimplemented!("Generics::param_mapping: Const"),
     }
 }
 
 pub(crate) fn syn_to_type_param_bounds<'a, T>(
     bounds: T,
-    param_map: &'a mut SynParamMap,
+    env: &'a mut GenericsEnv,
 ) -> impl Iterator<Item = TypeParamBound> + 'a
 where
     T: IntoIterator<Item = syn::TypeParamBound> + 'a,
 {
     bounds
         .into_iter()
-        .map(move |type_param_bound| syn_to_type_param_bound(type_param_bound, param_map))
+        .map(move |type_param_bound| syn_to_type_param_bound(type_param_bound, env))
 }
 
 pub(crate) fn syn_to_type_param_bound(
     type_param_bound: syn::TypeParamBound,
-    param_map: &mut SynParamMap,
+    env: &mut GenericsEnv,
 ) -> TypeParamBound {
     match type_param_bound {
+        syn::TypeParamBound::Trait(syn::TraitBound {
+            modifier: syn::TraitBoundModifier::Maybe(_),
+            path,
+            ..
+        }) => TypeParamBound::Maybe(Path::syn_to_path(path, env)),
         syn::TypeParamBound::Trait(syn::TraitBound {
             lifetimes, path, ..
-        }) => TypeParamBound::Trait(TraitBound {
-            lifetimes: syn_to_bound_lifetimes(lifetimes, param_map),
-            path: Path::syn_to_path(path, param_map),
-        }),
+        }) => {
+            env.push_scope();
+            let bound = TraitBound {
+                lifetimes: syn_to_bound_lifetimes(lifetimes, env),
+                path: Path::syn_to_path(path, env),
+            };
+            env.pop_scope();
+            TypeParamBound::Trait(bound)
+        }
         syn::TypeParamBound::Lifetime(lifetime) => {
-            TypeParamBound::Lifetime(param_map.get_lifetime(&lifetime.to_string()))
+            TypeParamBound::Lifetime(env.get_lifetime(&lifetime.to_string()))
+        }
+    }
+}
+
+impl GenericArguments {
+    pub(crate) fn clone_with_fresh_generics(&self, env: &GenericsEnv) -> Self {
+        GenericArguments {
+            args: self
+                .args
+                .iter()
+                .map(|arg| arg.clone_with_fresh_generics(env))
+                .collect(),
         }
     }
 }
 
 impl GenericArguments {
-    pub(crate) fn clone_with_fresh_generics(&self, param_map: &ParamMap) -> Self {
+    pub(crate) fn substitute(&self, substitution: &Substitution) -> Self {
         GenericArguments {
             args: self
                 .args
                 .iter()
-                .map(|arg| arg.clone_with_fresh_generics(param_map))
+                .map(|arg| arg.substitute(substitution))
                 .collect(),
         }
     }
 }
 
 impl GenericArgument {
+    pub(crate) fn substitute(&self, substitution: &Substitution) -> Self {
+        match self {
+            Self::Type(ty) => Self::Type(Type(ty.0.substitute(substitution))),
+            Self::Lifetime(lifetime) => Self::Lifetime(lifetime.substitute(substitution)),
+            Self::Binding(binding) => Self::Binding(Binding {
+                ident: binding.ident.clone(),
+                ty: Type(binding.ty.0.substitute(substitution)),
+            }),
+            Self::Constraint(constraint) => Self::Constraint(Constraint {
+                ident: constraint.ident.clone(),
+                bounds: constraint
+                    .bounds
+                    .iter()
+                    .map(|bound| bound.substitute(substitution))
+                    .collect(),
+            }),
+            Self::Const(expr) => Self::Const(expr.substitute(substitution)),
+        }
+    }
+
     pub(crate) fn syn_to_generic_argument(
         arg: syn::GenericArgument,
-        param_map: &mut SynParamMap,
+        env: &mut GenericsEnv,
     ) -> Self {
         match arg {
             syn::GenericArgument::Type(ty) => {
-                GenericArgument::Type(Type::syn_to_type(ty, param_map))
+                // A const param as argument, like `N` in `Buf<N>`, is parsed
+                // as a type
+                if let syn::Type::Path(syn::TypePath { qself: None, path }) = &ty {
+                    if let Some(ident) = path.get_ident() {
+                        if let Some(GenericParam::Const(param)) = env.get(&ident.to_string()) {
+                            return GenericArgument::Const(Expr::Param(param));
+                        }
+                    }
+                }
+                GenericArgument::Type(Type::syn_to_type(ty, env))
             }
 
             syn::GenericArgument::Lifetime(lifetime) => {
-                GenericArgument::Lifetime(param_map.get_lifetime(&lifetime.to_string()))
+                GenericArgument::Lifetime(env.get_lifetime(&lifetime.to_string()))
             }
 
             syn::GenericArgument::Binding(binding) => GenericArgument::Binding(Binding {
                 ident: Ident::from(binding.ident),
-                ty: Type::syn_to_type(binding.ty, param_map),
+                ty: Type::syn_to_type(binding.ty, env),
             }),
 
             syn::GenericArgument::Constraint(constraint) => {
                 GenericArgument::Constraint(Constraint {
                     ident: Ident::from(constraint.ident),
-                    bounds: syn_to_type_param_bounds(constraint.bounds, param_map).collect(),
+                    bounds: syn_to_type_param_bounds(constraint.bounds, env).collect(),
                 })
             }
 
-            syn::GenericArgument::Const(_expr) => {
-                unimplemented!("GenericArguments::syn_to_generic_arguments: Const")
+            syn::GenericArgument::Const(expr) => {
+                GenericArgument::Const(Expr::syn_to_expr(expr, env))
             }
         }
     }
 
-    pub(crate) fn clone_with_fresh_generics(&self, param_map: &ParamMap) -> Self {
+    pub(crate) fn clone_with_fresh_generics(&self, env: &GenericsEnv) -> Self {
         match self {
-            Self::Type(ty) => Self::Type(ty.clone_with_fresh_generics(param_map)),
-            Self::Lifetime(lifetime) => {
-                Self::Lifetime(lifetime.clone_with_fresh_generics(param_map))
-            }
+            Self::Type(ty) => Self::Type(ty.clone_with_fresh_generics(env)),
+            Self::Lifetime(lifetime) => Self::Lifetime(lifetime.clone_with_fresh_generics(env)),
             Self::Binding(binding) => Self::Binding(Binding {
                 ident: binding.ident.clone(),
-                ty: binding.ty.clone_with_fresh_generics(param_map),
+                ty: binding.ty.clone_with_fresh_generics(env),
             }),
             Self::Constraint(constraint) => Self::Constraint(Constraint {
                 ident: constraint.ident.clone(),
                 bounds: constraint
                     .bounds
                     .iter()
-                    .map(|bound| bound.clone_with_fresh_generics(param_map))
+                    .map(|bound| bound.clone_with_fresh_generics(env))
                     .collect(),
             }),
-            Self::Const(expr) => {
-                unimplemented!("GenericArgument::clone_with_fresh_generics: const expr")
-            }
+            Self::Const(expr) => Self::Const(expr.clone_with_fresh_generics(env)),
         }
     }
 }
@@ -1,43 +1,33 @@
-use crate::{Invoke, Lifetime, MacroInvoke, Push, TypeParam, TypedIndex, ValueNode};
+use crate::{
+    ConstParamDecl, Ident, Invoke, Lifetime, MacroInvoke, Parent, Path, Push, TypeParam,
+    TypedIndex, ValueNode,
+};
 use std::cell::{Cell, RefCell};
+use std::collections::BTreeMap;
+use std::rc::Rc;
 use std::thread::LocalKey;
 
 thread_local! {
-    pub(crate) static VALUES: RefCell<Vec<ValueNode>> = RefCell::new(Vec::new());
-    pub(crate) static INVOKES: RefCell<Vec<Invoke>> = RefCell::new(Vec::new());
-    pub(crate) static MACROS: RefCell<Vec<MacroInvoke>> = RefCell::new(Vec::new());
-    pub(crate) static TYPE_PARAMS: Cell<usize> = Cell::new(0);
-    pub(crate) static LIFETIMES: Cell<usize> = Cell::new(1);
+    pub(crate) static VALUES: RefCell<Vec<ValueNode>> = const { RefCell::new(Vec::new()) };
+    pub(crate) static INVOKES: RefCell<Vec<Invoke>> = const { RefCell::new(Vec::new()) };
+    pub(crate) static MACROS: RefCell<Vec<MacroInvoke>> = const { RefCell::new(Vec::new()) };
+    pub(crate) static TYPE_PARAMS: Cell<usize> = const { Cell::new(0) };
+    pub(crate) static LIFETIMES: Cell<usize> = const { Cell::new(1) };
+    // The types and default values of const params, indexed by ConstParam
+    pub(crate) static CONST_PARAMS: RefCell<Vec<ConstParamDecl>> = const { RefCell::new(Vec::new()) };
+    // Enums and structs declared in the reflect! macro
+    pub(crate) static DATA_TYPES: RefCell<Vec<Rc<Parent>>> = const { RefCell::new(Vec::new()) };
+    // Traits declared in the reflect! macro
+    pub(crate) static TRAITS: RefCell<Vec<Rc<Parent>>> = const { RefCell::new(Vec::new()) };
+    // Crate paths set with `Execution::set_crate_path`, keyed by whether the
+    // paths to rebase are global and the name of the crate. Only set while a
+    // program is compiled.
+    pub(crate) static CRATE_PATHS: RefCell<BTreeMap<(bool, Ident), Path>> =
+        const { RefCell::new(BTreeMap::new()) };
 }
 
 pub(crate) const STATIC_LIFETIME: Lifetime = Lifetime(0);
 
-pub(crate) trait GlobalBorrow<T> {
-    fn with_borrow<R, F>(&'static self, f: F) -> R
-    where
-        F: FnOnce(&Vec<T>) -> R;
-
-    fn with_borrow_mut<R, F>(&'static self, f: F) -> R
-    where
-        F: FnOnce(&mut Vec<T>) -> R;
-}
-
-impl<T> GlobalBorrow<T> for LocalKey<RefCell<Vec<T>>> {
-    fn with_borrow<R, F>(&'static self, f: F) -> R
-    where
-        F: FnOnce(&Vec<T>) -> R,
-    {
-        self.with(|data| f(&*data.borrow()))
-    }
-
-    fn with_borrow_mut<R, F>(&'static self, f: F) -> R
-    where
-        F: FnOnce(&mut Vec<T>) -> R,
-    {
-        self.with(|data| f(&mut *data.borrow_mut()))
-    }
-}
-
 pub(crate) trait GlobalPush {
     type Element: TypedIndex;
 
@@ -80,9 +70,9 @@ impl GlobalCounter<TypeParam> for LocalKey<Cell<usize>> {
 }
 
 pub(crate) fn clear() {
-    // It's not safe to reset TYPE_PARAMS and LIFETIMES as this might
-    // interfere with cached values in generic parameters in functions in the
-    // reflect! macro
+    // It's not safe to reset TYPE_PARAMS, LIFETIMES, CONST_PARAMS, DATA_TYPES and TRAITS as this
+    // might interfere with cached values in generic parameters in functions in
+    // the reflect! macro
     VALUES.with(|data| data.borrow_mut().clear());
     INVOKES.with(|data| data.borrow_mut().clear());
     MACROS.with(|data| data.borrow_mut().clear());
@@ -1,4 +1,4 @@
-use crate::{Invoke, MacroInvoke, TypeEqualitySet, ValueNode};
+use crate::{Invoke, MacroInvoke, ValueNode};
 
 pub(crate) trait Push {
     type Element: TypedIndex;
@@ -22,7 +22,7 @@ pub(crate) trait TypedIndex {
     fn index(i: usize) -> Self::Index;
 }
 
-#[derive(Debug, Clone, Copy, Ord, PartialOrd, Eq, PartialEq)]
+#[derive(Debug, Clone, Copy, Ord, PartialOrd, Eq, PartialEq, Hash)]
 pub(crate) struct ValueRef(pub usize);
 
 impl TypedIndex for ValueNode {
@@ -55,14 +55,6 @@ impl TypedIndex for MacroInvoke {
     }
 }
 
+/// The id of the root type of a set in the `TypeEqualitySets`
 #[derive(Debug, Clone, Copy, Ord, PartialOrd, Eq, PartialEq)]
-
 pub(crate) struct TypeEqualitySetRef(pub usize);
-
-impl TypedIndex for TypeEqualitySet {
-    type Index = TypeEqualitySetRef;
-
-    fn index(i: usize) -> Self::Index {
-        TypeEqualitySetRef(i)
-    }
-}
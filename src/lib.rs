@@ -326,15 +326,23 @@
 #[doc(hidden)]
 pub use reflect_internal::*;
 
+// The code generated by `library!` refers to this crate as `reflect`
+#[cfg(feature = "library-std")]
+extern crate self as reflect;
+
+#[cfg(feature = "library-std")]
+pub mod library;
 pub mod runtime;
 
 mod attr;
+mod attribute;
 mod compiler;
 mod data;
 mod derive;
 mod execution;
 mod field;
 mod function;
+mod function_like;
 mod generics;
 mod global_data;
 mod ident;
@@ -345,24 +353,31 @@ mod node;
 mod parent;
 mod path;
 mod print;
+mod report;
+mod schema;
 mod signature;
 mod trait_inference;
 mod ty;
 mod value;
+mod variance;
 mod wip;
 
+pub use crate::attribute::{attribute, AttributeExecution};
 pub use crate::data::{
     Data, Enum, Struct, StructStruct, StructVariant, TupleStruct, TupleVariant, UnitStruct,
     UnitVariant, Variant,
 };
-pub use crate::derive::derive;
+pub use crate::derive::{derive, derive_with_report};
 pub use crate::execution::Execution;
 pub use crate::field::{Field, Fields};
 pub use crate::function::Function;
-pub use crate::generics::{Generics, SynParamMap};
+pub use crate::function_like::function_like;
+pub use crate::generics::{Generics, GenericsEnv};
 pub use crate::module::Module;
 pub use crate::parent::{Parent, ParentBuilder, ParentKind};
 pub use crate::path::Path;
+pub use crate::report::Report;
+pub use crate::schema::{AttrKey, AttrSchema, AttrValues};
 pub use crate::signature::Signature;
 pub use crate::ty::Type;
 pub use crate::value::Value;
@@ -372,20 +387,26 @@ use crate::compiler::{CompleteFunction, CompleteImpl, Program};
 use crate::execution::Tracker;
 use crate::field::Accessor;
 use crate::generics::{
-    GenericArgument, GenericArguments, GenericConstraint, GenericParam, Lifetime, LifetimeDef,
-    ParamMap, PredicateType, TraitBound, TypeParam, TypeParamBound,
+    ConstParam, ConstParamDecl, Expr, GenericArgument, GenericArguments, GenericConstraint,
+    GenericParam, Lifetime, LifetimeDef, PredicateType, Substitution, TraitBound, TypeParam,
+    TypeParamBound,
 };
 use crate::global_data::{
-    GlobalBorrow, GlobalCounter, GlobalPush, INVOKES, LIFETIMES, MACROS, STATIC_LIFETIME,
-    TYPE_PARAMS, VALUES,
+    GlobalCounter, GlobalPush, CONST_PARAMS, CRATE_PATHS, DATA_TYPES, INVOKES, LIFETIMES, MACROS,
+    STATIC_LIFETIME, TRAITS, TYPE_PARAMS, VALUES,
 };
 use crate::ident::Ident;
 use crate::index::{InvokeRef, MacroInvokeRef, Push, TypeEqualitySetRef, TypedIndex, ValueRef};
-use crate::node::ValueNode;
+use crate::node::{MatchArm, ValueNode};
 use crate::path::{AngleBracketedGenericArguments, PathArguments, SimplePath};
 use crate::print::Print;
+use crate::report::{
+    invoke_path, to_report_string, to_report_string_erased, Equality, Fact, ImplReport, Origin,
+    Origins,
+};
 use crate::runtime::{RuntimeFunction, RuntimeTrait, RuntimeType};
-use crate::signature::Receiver;
-use crate::trait_inference::{TraitInferenceResult, TypeEqualitySet};
+use crate::signature::{check_generics_and_type, Receiver};
+use crate::trait_inference::{self_trait_bound, InferenceOptions, TraitInferenceResult};
 use crate::ty::{DataStructure, TypeNode};
+use crate::variance::Variance;
 use crate::wip::{Invoke, MacroInvoke, WipFunction, WipImpl};
@@ -0,0 +1,21 @@
+//! Ready-made `library!` declarations of commonly used libraries.
+//!
+//! Instead of declaring the standard library traits in every macro, import
+//! them from here:
+//!
+//! ```
+//! use reflect::library::std::*;
+//! use reflect::*;
+//!
+//! fn derive(ex: Execution) {
+//!     ex.make_trait_impl(fmt::Debug, ex.target_type(), |block| {
+//!         block.make_function(fmt::Debug::fmt, debug_fmt);
+//!     });
+//! }
+//! #
+//! # fn debug_fmt(f: MakeFunction) -> Value {
+//! #     unimplemented!()
+//! # }
+//! ```
+
+pub mod std;
@@ -0,0 +1,289 @@
+//! The parts of the standard library used by common derives.
+//!
+//! The modules mirror the standard library, so `fmt::Debug::fmt` refers to
+//! `std::fmt::Debug::fmt`. The macros `write!`, `writeln!`, `format!` and
+//! `format_args!` are available as [`write`](struct@write),
+//! [`writeln`](struct@writeln), [`format`](struct@format) and
+//! [`format_args`](struct@format_args).
+
+crate::library! {
+    extern crate std {
+        mod clone {
+            trait Clone {
+                fn clone(&self) -> Self;
+                fn clone_from(&mut self, &Self);
+            }
+        }
+
+        mod cmp {
+            enum Ordering {
+                Less,
+                Equal,
+                Greater,
+            }
+
+            trait PartialEq {
+                fn eq(&self, &Self) -> ::std::primitive::bool;
+                fn ne(&self, &Self) -> ::std::primitive::bool;
+            }
+
+            trait Eq: PartialEq {}
+
+            trait PartialOrd: PartialEq {
+                fn partial_cmp(&self, &Self) -> ::std::option::Option<Ordering>;
+                fn lt(&self, &Self) -> ::std::primitive::bool;
+                fn le(&self, &Self) -> ::std::primitive::bool;
+                fn gt(&self, &Self) -> ::std::primitive::bool;
+                fn ge(&self, &Self) -> ::std::primitive::bool;
+            }
+
+            trait Ord: Eq + PartialOrd {
+                fn cmp(&self, &Self) -> Ordering;
+                fn max(self, Self) -> Self;
+                fn min(self, Self) -> Self;
+            }
+        }
+
+        mod convert {
+            trait From<T> {
+                fn from(T) -> Self;
+            }
+
+            trait Into<T> {
+                fn into(self) -> T;
+            }
+
+            trait AsRef<T> {
+                fn as_ref(&self) -> &T;
+            }
+
+            trait AsMut<T> {
+                fn as_mut(&mut self) -> &mut T;
+            }
+        }
+
+        mod default {
+            trait Default {
+                fn default() -> Self;
+            }
+        }
+
+        mod fmt {
+            type Arguments;
+            type Error;
+            type Result;
+            type Formatter;
+            type DebugStruct;
+            type DebugTuple;
+            type DebugList;
+            type DebugSet;
+            type DebugMap;
+
+            trait Debug {
+                fn fmt(&self, &mut Formatter) -> Result;
+            }
+
+            trait Display {
+                fn fmt(&self, &mut Formatter) -> Result;
+            }
+
+            trait Write {
+                fn write_str(&mut self, &::std::primitive::str) -> Result;
+                fn write_char(&mut self, ::std::primitive::char) -> Result;
+                fn write_fmt(&mut self, Arguments) -> Result;
+            }
+
+            impl Formatter {
+                fn write_str(&mut self, &::std::primitive::str) -> Result;
+                fn write_fmt(&mut self, Arguments) -> Result;
+                fn alternate(&self) -> ::std::primitive::bool;
+                fn debug_struct(&mut self, &::std::primitive::str) -> DebugStruct;
+                fn debug_tuple(&mut self, &::std::primitive::str) -> DebugTuple;
+                fn debug_list(&mut self) -> DebugList;
+                fn debug_set(&mut self) -> DebugSet;
+                fn debug_map(&mut self) -> DebugMap;
+            }
+
+            impl DebugStruct {
+                fn field(&mut self, &::std::primitive::str, &dyn Debug) -> &mut DebugStruct;
+                fn finish(&mut self) -> Result;
+                fn finish_non_exhaustive(&mut self) -> Result;
+            }
+
+            impl DebugTuple {
+                fn field(&mut self, &dyn Debug) -> &mut DebugTuple;
+                fn finish(&mut self) -> Result;
+            }
+
+            impl DebugList {
+                fn entry(&mut self, &dyn Debug) -> &mut DebugList;
+                fn finish(&mut self) -> Result;
+            }
+
+            impl DebugSet {
+                fn entry(&mut self, &dyn Debug) -> &mut DebugSet;
+                fn finish(&mut self) -> Result;
+            }
+
+            impl DebugMap {
+                fn entry(&mut self, &dyn Debug, &dyn Debug) -> &mut DebugMap;
+                fn key(&mut self, &dyn Debug) -> &mut DebugMap;
+                fn value(&mut self, &dyn Debug) -> &mut DebugMap;
+                fn finish(&mut self) -> Result;
+            }
+        }
+
+        mod hash {
+            trait Hasher {
+                fn finish(&self) -> ::std::primitive::u64;
+                fn write_u8(&mut self, ::std::primitive::u8);
+                fn write_u16(&mut self, ::std::primitive::u16);
+                fn write_u32(&mut self, ::std::primitive::u32);
+                fn write_u64(&mut self, ::std::primitive::u64);
+                fn write_usize(&mut self, ::std::primitive::usize);
+                fn write_isize(&mut self, ::std::primitive::isize);
+            }
+
+            trait Hash {
+                fn hash<H: Hasher>(&self, &mut H);
+            }
+        }
+
+        mod iter {
+            trait Iterator {
+                type Item;
+
+                fn next(&mut self) -> ::std::option::Option<Self::Item>;
+                fn size_hint(&self) -> (::std::primitive::usize, ::std::option::Option<::std::primitive::usize>);
+                fn count(self) -> ::std::primitive::usize;
+            }
+
+            trait DoubleEndedIterator: Iterator {
+                fn next_back(&mut self) -> ::std::option::Option<<Self as Iterator>::Item>;
+            }
+
+            trait ExactSizeIterator: Iterator {
+                fn len(&self) -> ::std::primitive::usize;
+            }
+
+            trait IntoIterator {
+                type Item;
+                type IntoIter;
+
+                fn into_iter(self) -> Self::IntoIter;
+            }
+
+            trait FromIterator<A> {
+                fn from_iter<I: IntoIterator<Item = A>>(I) -> Self;
+            }
+
+            trait Extend<A> {
+                fn extend<I: IntoIterator<Item = A>>(&mut self, I);
+            }
+        }
+
+        mod ops {
+            trait Drop {
+                fn drop(&mut self);
+            }
+
+            trait Add<Rhs> {
+                type Output;
+
+                fn add(self, Rhs) -> Self::Output;
+            }
+
+            trait Sub<Rhs> {
+                type Output;
+
+                fn sub(self, Rhs) -> Self::Output;
+            }
+
+            trait Mul<Rhs> {
+                type Output;
+
+                fn mul(self, Rhs) -> Self::Output;
+            }
+
+            trait Div<Rhs> {
+                type Output;
+
+                fn div(self, Rhs) -> Self::Output;
+            }
+
+            trait Rem<Rhs> {
+                type Output;
+
+                fn rem(self, Rhs) -> Self::Output;
+            }
+
+            trait Neg {
+                type Output;
+
+                fn neg(self) -> Self::Output;
+            }
+
+            trait Not {
+                type Output;
+
+                fn not(self) -> Self::Output;
+            }
+
+            trait AddAssign<Rhs> {
+                fn add_assign(&mut self, Rhs);
+            }
+
+            trait SubAssign<Rhs> {
+                fn sub_assign(&mut self, Rhs);
+            }
+
+            trait MulAssign<Rhs> {
+                fn mul_assign(&mut self, Rhs);
+            }
+
+            trait DivAssign<Rhs> {
+                fn div_assign(&mut self, Rhs);
+            }
+
+            trait RemAssign<Rhs> {
+                fn rem_assign(&mut self, Rhs);
+            }
+
+            trait Deref {
+                type Target;
+
+                fn deref(&self) -> &Self::Target;
+            }
+
+            trait DerefMut: Deref {
+                fn deref_mut(&mut self) -> &mut <Self as Deref>::Target;
+            }
+
+            trait Index<Idx> {
+                type Output;
+
+                fn index(&self, Idx) -> &Self::Output;
+            }
+
+            trait IndexMut<Idx>: Index<Idx> {
+                fn index_mut(&mut self, Idx) -> &mut <Self as Index<Idx>>::Output;
+            }
+        }
+
+        mod option {
+            enum Option<T> {
+                Some(T),
+                None,
+            }
+        }
+
+        macro format;
+        macro format_args;
+        macro write;
+        macro writeln;
+    }
+}
+
+pub use self::RUNTIME::std::{
+    clone, cmp, convert, default, fmt, format, format_args, hash, iter, ops, option, write, writeln,
+};
@@ -1,7 +1,6 @@
 use crate::{
     Data, Enum, Field, Struct, StructStruct, StructVariant, TupleStruct, TupleVariant, Variant,
 };
-use std::marker::PhantomData;
 
 impl<T> Data<T> {
     pub(crate) fn map<F, R>(self, f: F) -> Data<R>
@@ -67,9 +66,11 @@ impl<T> Field<T> {
     {
         let accessor = self.accessor.clone();
         let attrs = self.attrs.clone();
+        let vis = self.vis.clone();
 
         Field {
             attrs,
+            vis,
             accessor,
             element: f(self),
         }
@@ -84,6 +85,7 @@ impl<T> Enum<T> {
         Enum {
             variants: self.variants.into_iter().map(|v| v.map(&mut f)).collect(),
             attrs: self.attrs,
+            scrutinee: self.scrutinee,
         }
     }
 }
@@ -102,25 +104,35 @@ impl<T> Variant<T> {
 }
 
 impl<T> TupleVariant<T> {
-    pub(crate) fn map<F, R>(self, f: F) -> TupleVariant<R>
+    pub(crate) fn map<F, R>(self, mut f: F) -> TupleVariant<R>
     where
         F: FnMut(Field<T>) -> R,
     {
         TupleVariant {
+            ident: self.ident,
+            fields: self
+                .fields
+                .into_iter()
+                .map(|field| field.map(&mut f))
+                .collect(),
             attrs: self.attrs,
-            phantom: PhantomData,
         }
     }
 }
 
 impl<T> StructVariant<T> {
-    pub(crate) fn map<F, R>(self, f: F) -> StructVariant<R>
+    pub(crate) fn map<F, R>(self, mut f: F) -> StructVariant<R>
     where
         F: FnMut(Field<T>) -> R,
     {
         StructVariant {
+            ident: self.ident,
+            fields: self
+                .fields
+                .into_iter()
+                .map(|field| field.map(&mut f))
+                .collect(),
             attrs: self.attrs,
-            phantom: PhantomData,
         }
     }
 }
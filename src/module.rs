@@ -1,5 +1,5 @@
 use crate::{
-    GlobalPush, MacroInvoke, Path, RuntimeType, SynParamMap, Type, Value, ValueNode, MACROS, VALUES,
+    GenericsEnv, GlobalPush, MacroInvoke, Path, RuntimeType, Type, Value, ValueNode, MACROS, VALUES,
 };
 
 #[derive(Debug, Clone)]
@@ -24,13 +24,13 @@ impl Module {
     }
 
     /// Get a path by appending a path segment at the end of a module path
-    pub fn get_path(&self, segment: &str, param_map: &mut SynParamMap) -> Path {
-        self.path.get_path(segment, param_map)
+    pub fn get_path(&self, segment: &str, env: &mut GenericsEnv) -> Path {
+        self.path.get_path(segment, env)
     }
 
     /// Get a path type by appending a path segment at the end of a module path
-    pub fn get_path_type(&self, segment: &str, param_map: &mut SynParamMap) -> Type {
-        self.get_path(segment, param_map).SELF()
+    pub fn get_path_type(&self, segment: &str, env: &mut GenericsEnv) -> Type {
+        self.get_path(segment, env).SELF()
     }
 
     pub fn invoke_macro(&self, name: &str, values: &[Value]) -> Value {
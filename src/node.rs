@@ -1,7 +1,8 @@
 use crate::{
-    Accessor, Data, GlobalBorrow, Ident, InvokeRef, MacroInvokeRef, Type, TypeNode, ValueRef,
-    INVOKES, VALUES,
+    Accessor, Data, Ident, InvokeRef, MacroInvokeRef, Path, Type, TypeNode, ValueRef, Variant,
+    INVOKES, STATIC_LIFETIME, VALUES,
 };
+use std::ops::Range;
 
 #[derive(Debug, Clone)]
 pub(crate) enum ValueNode {
@@ -17,8 +18,9 @@ pub(crate) enum ValueNode {
         name: Ident,
         ty: Type,
     },
+    // A value of a struct of the input item, constructed from its fields
     DataStructure {
-        name: String,
+        ty: Type,
         data: Data<ValueRef>,
     },
     Invoke(InvokeRef),
@@ -28,6 +30,24 @@ pub(crate) enum ValueNode {
         ty: Type,
     },
     MacroInvocation(MacroInvokeRef),
+    // A variable bound by the pattern of a match arm
+    PatternBinding {
+        ty: Type,
+    },
+    Match {
+        scrutinee: ValueRef,
+        // Path of the enum, without generic arguments
+        path: Path,
+        arms: Vec<MatchArm>,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct MatchArm {
+    pub pattern: Variant<ValueRef>,
+    // Values created while generating the body of the arm
+    pub values: Range<ValueRef>,
+    pub ret: ValueRef,
 }
 
 impl ValueNode {
@@ -36,7 +56,11 @@ impl ValueNode {
             ValueNode::Tuple(types) => Type(TypeNode::Tuple(
                 types.iter().map(|type_ref| type_ref.get_type().0).collect(),
             )),
-            ValueNode::Str(_) => Type(TypeNode::PrimitiveStr),
+            ValueNode::Str(_) => Type(TypeNode::Reference {
+                is_mut: false,
+                lifetime: Some(STATIC_LIFETIME),
+                inner: Box::new(TypeNode::PrimitiveStr),
+            }),
             ValueNode::Reference { is_mut, value } => Type(TypeNode::Reference {
                 is_mut: *is_mut,
                 lifetime: None,
@@ -51,6 +75,12 @@ impl ValueNode {
             ValueNode::Invoke(invoke_ref) => {
                 INVOKES.with_borrow(|invokes| invokes[invoke_ref.0].function.sig.output.clone())
             }
+            ValueNode::PatternBinding { ty } => ty.clone(),
+            ValueNode::DataStructure { ty, .. } => ty.clone(),
+            ValueNode::Match { arms, .. } => match arms.first() {
+                Some(arm) => arm.ret.get_type(),
+                None => Type(TypeNode::Infer),
+            },
 
             node => panic!("ValueNode::get_type"),
         }
@@ -76,9 +106,11 @@ impl ValueNode {
                 ValueNode::Str(types)
             }
             ValueNode::Str(_) => ValueNode::Str(String::from("str")),
-            ValueNode::DataStructure { name, .. } => ValueNode::Str(name.to_owned()),
+            ValueNode::DataStructure { ty, .. } => ValueNode::Str(ty.0.get_name()),
             ValueNode::Reference { value, .. } => value.get_type_name(),
-            ValueNode::Binding { ty, .. } => ValueNode::Str(ty.0.get_name()),
+            ValueNode::Binding { ty, .. } | ValueNode::PatternBinding { ty } => {
+                ValueNode::Str(ty.0.get_name())
+            }
             ValueNode::Destructure {
                 parent,
                 accessor,
@@ -1,17 +1,31 @@
-use crate::{Generics, ParamMap, Path, SynParamMap};
+use crate::{
+    Data, Generics, GenericsEnv, Path, Type, TypeNode, TypeParamBound, DATA_TYPES, TRAITS,
+};
 use std::default::Default;
+use std::rc::Rc;
+use syn::{parse_str, DeriveInput};
 
 #[derive(Debug, Clone)]
 pub struct Parent {
     pub(crate) path: Path,
     pub(crate) generics: Generics,
     pub(crate) parent_kind: ParentKind,
+    // The bounds after the colon in `trait Ord: Eq + PartialOrd`
+    pub(crate) supertraits: Vec<TypeParamBound>,
+    // The fields of a library enum or struct, whose constructors have this
+    // Parent
+    pub(crate) data: Option<Data<Type>>,
+    // An `unsafe trait`, whose impls are `unsafe impl`
+    pub(crate) is_unsafe: bool,
 }
 
 pub struct ParentBuilder {
     pub(crate) path: Option<Path>,
     pub(crate) generics: Generics,
     pub(crate) parent_kind: ParentKind,
+    pub(crate) supertraits: Vec<TypeParamBound>,
+    pub(crate) data: Option<Data<Type>>,
+    pub(crate) is_unsafe: bool,
 }
 
 pub trait SetPath<'a, P> {
@@ -26,10 +40,10 @@ impl<'a> SetPath<'a, Path> for ParentBuilder {
 
 impl<'a, F> SetPath<'a, F> for ParentBuilder
 where
-    F: FnOnce(&'a mut SynParamMap) -> Path,
+    F: FnOnce(&'a mut GenericsEnv) -> Path,
 {
     fn set_path(&'a mut self, into_path: F) {
-        self.path = Some((into_path)(&mut self.generics.param_map));
+        self.path = Some((into_path)(&mut self.generics.env));
     }
 }
 
@@ -39,19 +53,33 @@ impl ParentBuilder {
             path: None,
             generics: Default::default(),
             parent_kind,
+            supertraits: Vec::new(),
+            data: None,
+            is_unsafe: false,
         }
     }
 
+    /// Build the Parent. Traits and Parents with data are registered, so that
+    /// supertraits and `Type::data` can be looked up by path.
     pub fn into_parent(self) -> Parent {
-        Parent {
+        let parent = Parent {
             path: self.path.unwrap(),
             generics: self.generics,
             parent_kind: self.parent_kind,
+            supertraits: self.supertraits,
+            data: self.data,
+            is_unsafe: self.is_unsafe,
+        };
+        if parent.data.is_some() {
+            DATA_TYPES.with_borrow_mut(|data_types| data_types.push(Rc::new(parent.clone())));
+        } else if parent.parent_kind == ParentKind::Trait {
+            TRAITS.with_borrow_mut(|traits| traits.push(Rc::new(parent.clone())));
         }
+        parent
     }
 
     /// Set the path of the Parent.
-    /// P can be either a Path or a type implementing `FnOnce(&'a mut ParamMap) -> Path`
+    /// P can be either a Path or a type implementing `FnOnce(&'a mut GenericsEnv) -> Path`
     pub fn set_path<'a, P>(&'a mut self, into_path: P)
     where
         Self: SetPath<'a, P>,
@@ -66,6 +94,32 @@ impl ParentBuilder {
     pub fn set_generic_constraints(&mut self, constraints: &[&str]) {
         self.generics.set_generic_constraints(constraints)
     }
+
+    /// Set the supertraits of a trait. `Self` may be used in the bounds.
+    pub fn set_supertraits(&mut self, bounds: &[&str]) {
+        self.supertraits = bounds
+            .iter()
+            .map(|bound| TypeParamBound::get_type_param_bound(bound, &mut self.generics.env))
+            .collect();
+    }
+
+    /// Mark the trait as an `unsafe trait`
+    pub fn set_unsafe(&mut self) {
+        self.is_unsafe = true;
+    }
+
+    /// Set the fields of a data type from an enum or struct declaration, like
+    /// `enum Option<T> { Some(T), None }`. The generic params must already be
+    /// set.
+    pub fn set_data(&mut self, declaration: &str) {
+        let input: DeriveInput =
+            parse_str(declaration).expect("ParentBuilder::set_data: Not an enum or struct");
+        self.data = Some(Data::syn_to_data(
+            input.data,
+            input.attrs,
+            &mut self.generics.env,
+        ));
+    }
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
@@ -75,19 +129,87 @@ pub enum ParentKind {
 }
 
 impl Parent {
-    pub fn get_param_map(&self) -> &SynParamMap {
-        &self.generics.param_map
+    pub fn get_generics_env(&self) -> &GenericsEnv {
+        &self.generics.env
     }
 
-    pub(crate) fn clone_with_fresh_generics(&self) -> (Self, ParamMap) {
-        let (generics, param_map) = self.generics.clone_with_fresh_generics();
+    pub(crate) fn clone_with_fresh_generics(&self) -> (Self, GenericsEnv) {
+        let (generics, env) = self.generics.clone_with_fresh_generics();
         (
             Parent {
-                path: self.path.clone_with_fresh_generics(&param_map),
+                path: self.path.clone_with_fresh_generics(&env),
                 generics,
                 parent_kind: self.parent_kind,
+                supertraits: self
+                    .supertraits
+                    .iter()
+                    .map(|bound| bound.clone_with_fresh_generics(&env))
+                    .collect(),
+                data: self
+                    .data
+                    .clone()
+                    .map(|data| data.map(|field| field.element.clone_with_fresh_generics(&env))),
+                is_unsafe: self.is_unsafe,
             },
-            param_map,
+            env,
         )
     }
+
+    /// Look up a data type declared in the reflect! macro.
+    pub(crate) fn get_data_parent(path: &Path) -> Option<Rc<Parent>> {
+        DATA_TYPES.with_borrow(|data_types| find_parent(data_types, path))
+    }
+
+    /// Look up a trait declared in the reflect! macro.
+    pub(crate) fn get_trait_parent(path: &Path) -> Option<Rc<Parent>> {
+        TRAITS.with_borrow(|traits| find_parent(traits, path))
+    }
+
+    /// The supertraits of the trait at `path`, and all of their supertraits,
+    /// with the generic arguments of `path` and `self_ty` substituted in.
+    pub(crate) fn get_all_supertraits(path: &Path, self_ty: &TypeNode) -> Vec<Path> {
+        let mut supertraits = Vec::new();
+        let mut stack = vec![path.clone()];
+        while let Some(path) = stack.pop() {
+            let parent = match Parent::get_trait_parent(&path) {
+                Some(parent) => parent,
+                None => continue,
+            };
+            let mut substitution = parent.generics.get_substitution(path.get_generic_args());
+            substitution.self_ty = Some(self_ty.clone());
+            for bound in &parent.supertraits {
+                if let TypeParamBound::Trait(bound) = bound {
+                    let supertrait = bound.path.substitute(&substitution);
+                    if !supertraits.contains(&supertrait) {
+                        supertraits.push(supertrait.clone());
+                        stack.push(supertrait);
+                    }
+                }
+            }
+        }
+        supertraits
+    }
+}
+
+/// Paths that are not fully qualified match by their last segment, if that is
+/// unambiguous.
+fn find_parent(parents: &[Rc<Parent>], path: &Path) -> Option<Rc<Parent>> {
+    let path = path.without_generic_args();
+    if let Some(parent) = parents
+        .iter()
+        .find(|parent| parent.path.without_generic_args() == path)
+    {
+        return Some(Rc::clone(parent));
+    }
+    if path.global {
+        return None;
+    }
+    let ident = &path.path.last()?.ident;
+    let mut candidates = parents
+        .iter()
+        .filter(|parent| parent.path.path.last().map(|segment| &segment.ident) == Some(ident));
+    match (candidates.next(), candidates.next()) {
+        (Some(parent), None) => Some(Rc::clone(parent)),
+        _ => None,
+    }
 }
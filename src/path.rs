@@ -1,6 +1,10 @@
-use crate::{GenericArgument, GenericArguments, Ident, ParamMap, SynParamMap, Type};
+use crate::ty::{push_lifetime_param, push_param};
+use crate::{
+    Expr, GenericArgument, GenericArguments, GenericParam, GenericsEnv, Ident, Substitution, Type,
+    CRATE_PATHS,
+};
 use ref_cast::RefCast;
-use syn::parse::{Parse, ParseStream, Result};
+use syn::parse::{Parse, ParseStream, Parser, Result};
 use syn::{parse_str, ReturnType, Token};
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -69,35 +73,58 @@ impl Path {
         path
     }
 
-    pub(crate) fn get_path(&self, segment: &str, param_map: &mut SynParamMap) -> Self {
+    pub(crate) fn get_path(&self, segment: &str, env: &mut GenericsEnv) -> Self {
         let mut path = self.clone();
         path.path.push(Path::syn_to_path_segment(
             parse_str(segment).expect("Path::get_path: Not a PathSegment"),
-            param_map,
+            env,
         ));
         path
     }
 
-    pub fn path_from_str(path: &str, param_map: &mut SynParamMap) -> Self {
+    pub fn path_from_str(path: &str, env: &mut GenericsEnv) -> Self {
         Self::syn_to_path(
             parse_str(path).expect("Path::path_from_str: Not a Path"),
-            param_map,
+            env,
         )
     }
 
-    pub(crate) fn syn_to_path(path: syn::Path, param_map: &mut SynParamMap) -> Self {
+    /// A crate path set with `Execution::set_crate_path`, like `my_serde` for
+    /// `#[serde(crate = "my_serde")]`
+    pub(crate) fn crate_path_from_str(path: &str) -> Self {
+        let path = syn::Path::parse_mod_style
+            .parse_str(path)
+            .expect("set_crate_path: Not a Path");
+        Self::syn_to_path(path, &mut GenericsEnv::default())
+    }
+
+    /// The path with its crate replaced by the path set by the macro, if any
+    pub(crate) fn rebase_crate(&self) -> Option<Self> {
+        let first = self.path.first()?;
+        let root = CRATE_PATHS.with(|crate_paths| {
+            crate_paths
+                .borrow()
+                .get(&(self.global, first.ident.clone()))
+                .cloned()
+        })?;
+        let mut path = root;
+        path.path.extend(self.path[1..].iter().cloned());
+        Some(path)
+    }
+
+    pub(crate) fn syn_to_path(path: syn::Path, env: &mut GenericsEnv) -> Self {
         let global = path.leading_colon.is_some();
         let path: Vec<_> = path
             .segments
             .into_iter()
-            .map(|segment| Self::syn_to_path_segment(segment, param_map))
+            .map(|segment| Self::syn_to_path_segment(segment, env))
             .collect();
         Path { global, path }
     }
 
     pub(crate) fn syn_to_path_segment(
         path_segment: syn::PathSegment,
-        param_map: &mut SynParamMap,
+        env: &mut GenericsEnv,
     ) -> PathSegment {
         let syn::PathSegment { ident, arguments } = path_segment;
         let ident = Ident::from(ident);
@@ -114,7 +141,7 @@ impl Path {
                         args: generic_args
                             .args
                             .into_iter()
-                            .map(|arg| GenericArgument::syn_to_generic_argument(arg, param_map))
+                            .map(|arg| GenericArgument::syn_to_generic_argument(arg, env))
                             .collect(),
                     },
                 }),
@@ -126,11 +153,11 @@ impl Path {
                     inputs: parenthesized
                         .inputs
                         .into_iter()
-                        .map(|input| Type::syn_to_type(input, param_map))
+                        .map(|input| Type::syn_to_type(input, env))
                         .collect(),
                     output: match parenthesized.output {
                         ReturnType::Default => None,
-                        ReturnType::Type(_, ty) => Some(Type::syn_to_type(*ty, param_map)),
+                        ReturnType::Type(_, ty) => Some(Type::syn_to_type(*ty, env)),
                     },
                 }),
             },
@@ -147,7 +174,7 @@ impl Path {
         }
     }
 
-    pub(crate) fn clone_with_fresh_generics(&self, param_map: &ParamMap) -> Self {
+    pub(crate) fn clone_with_fresh_generics(&self, env: &GenericsEnv) -> Self {
         Path {
             global: self.global,
             path: self
@@ -161,16 +188,104 @@ impl Path {
 
                     PathArguments::AngleBracketed(ref args) => PathSegment {
                         ident: segment.ident.clone(),
-                        args: PathArguments::AngleBracketed(
-                            args.clone_with_fresh_generics(param_map),
-                        ),
+                        args: PathArguments::AngleBracketed(args.clone_with_fresh_generics(env)),
                     },
 
                     PathArguments::Parenthesized(ref args) => PathSegment {
                         ident: segment.ident.clone(),
-                        args: PathArguments::Parenthesized(
-                            args.clone_with_fresh_generics(param_map),
-                        ),
+                        args: PathArguments::Parenthesized(args.clone_with_fresh_generics(env)),
+                    },
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Path {
+    /// The generic arguments of the last path segment
+    pub(crate) fn get_generic_args(&self) -> &[GenericArgument] {
+        match self.path.last().map(|segment| &segment.args) {
+            Some(PathArguments::AngleBracketed(args)) => &args.args.args,
+            _ => &[],
+        }
+    }
+
+    pub(crate) fn is_self(&self) -> bool {
+        !self.global
+            && self.path.len() == 1
+            && self.path[0].ident.to_string() == "Self"
+            && self.path[0].args == PathArguments::None
+    }
+
+    pub(crate) fn generic_params(&self, params: &mut Vec<GenericParam>) {
+        for segment in &self.path {
+            match &segment.args {
+                PathArguments::None => {}
+                PathArguments::AngleBracketed(args) => {
+                    for arg in &args.args.args {
+                        match arg {
+                            GenericArgument::Type(ty) => ty.0.generic_params(params),
+                            GenericArgument::Lifetime(lifetime) => {
+                                push_lifetime_param(params, *lifetime)
+                            }
+                            GenericArgument::Const(Expr::Param(param)) => {
+                                push_param(params, GenericParam::Const(*param))
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+                PathArguments::Parenthesized(args) => {
+                    for ty in args.inputs.iter().chain(&args.output) {
+                        ty.0.generic_params(params);
+                    }
+                }
+            }
+        }
+    }
+
+    pub(crate) fn without_generic_args(&self) -> Self {
+        Path {
+            global: self.global,
+            path: self
+                .path
+                .iter()
+                .map(|segment| PathSegment {
+                    ident: segment.ident.clone(),
+                    args: PathArguments::None,
+                })
+                .collect(),
+        }
+    }
+
+    pub(crate) fn substitute(&self, substitution: &Substitution) -> Self {
+        Path {
+            global: self.global,
+            path: self
+                .path
+                .iter()
+                .map(|segment| PathSegment {
+                    ident: segment.ident.clone(),
+                    args: match &segment.args {
+                        PathArguments::None => PathArguments::None,
+                        PathArguments::AngleBracketed(args) => {
+                            PathArguments::AngleBracketed(AngleBracketedGenericArguments {
+                                args: args.args.substitute(substitution),
+                            })
+                        }
+                        PathArguments::Parenthesized(args) => {
+                            PathArguments::Parenthesized(ParenthesizedGenericArguments {
+                                inputs: args
+                                    .inputs
+                                    .iter()
+                                    .map(|ty| Type(ty.0.substitute(substitution)))
+                                    .collect(),
+                                output: args
+                                    .output
+                                    .as_ref()
+                                    .map(|ty| Type(ty.0.substitute(substitution))),
+                            })
+                        }
                     },
                 })
                 .collect(),
@@ -179,25 +294,25 @@ impl Path {
 }
 
 impl AngleBracketedGenericArguments {
-    pub(crate) fn clone_with_fresh_generics(&self, param_map: &ParamMap) -> Self {
+    pub(crate) fn clone_with_fresh_generics(&self, env: &GenericsEnv) -> Self {
         AngleBracketedGenericArguments {
-            args: self.args.clone_with_fresh_generics(param_map),
+            args: self.args.clone_with_fresh_generics(env),
         }
     }
 }
 
 impl ParenthesizedGenericArguments {
-    pub(crate) fn clone_with_fresh_generics(&self, param_map: &ParamMap) -> Self {
+    pub(crate) fn clone_with_fresh_generics(&self, env: &GenericsEnv) -> Self {
         ParenthesizedGenericArguments {
             inputs: self
                 .inputs
                 .iter()
-                .map(|ty| ty.clone_with_fresh_generics(param_map))
+                .map(|ty| ty.clone_with_fresh_generics(env))
                 .collect(),
             output: self
                 .output
                 .as_ref()
-                .map(|ty| ty.clone_with_fresh_generics(param_map)),
+                .map(|ty| ty.clone_with_fresh_generics(env)),
         }
     }
 }
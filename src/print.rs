@@ -39,13 +39,18 @@ impl ToTokens for Print<TypeNode> {
                     quote!((#(#types),*))
                 }
             }
+            Array { elem, len } => {
+                let elem = Print::ref_cast(&**elem);
+                let len = Print::ref_cast(len);
+                quote!([#elem; #len])
+            }
             PrimitiveStr => quote!(str),
             Reference {
                 is_mut,
                 lifetime,
                 inner,
             } => {
-                let lifetime = lifetime.as_ref().map(|lifetime| Print::ref_cast(lifetime));
+                let lifetime = lifetime.as_ref().map(Print::ref_cast);
                 let inner = Print::ref_cast(&**inner);
                 let token_mut = if *is_mut {
                     Some(Token![mut](Span::call_site()))
@@ -58,7 +63,18 @@ impl ToTokens for Print<TypeNode> {
             Dereference(inner) => panic!("Type::Dereference::to_tokens"),
             DataStructure(data) => {
                 let name = &data.name;
-                quote!(#name)
+                let params = &data.generics.params;
+                if params.is_empty() {
+                    quote!(#name)
+                } else {
+                    let params = params.iter().map(|param| match param {
+                        GenericParam::Const(const_param) => {
+                            Print::ref_cast(const_param).into_token_stream()
+                        }
+                        param => Print::ref_cast(param).into_token_stream(),
+                    });
+                    quote!(#name<#(#params),*>)
+                }
             }
             TraitObject(bounds) => {
                 let bounds = bounds.iter().map(Print::ref_cast);
@@ -72,6 +88,15 @@ impl ToTokens for Print<TypeNode> {
                 let type_param = Print::ref_cast(type_param);
                 quote!(#type_param)
             }
+            Projection {
+                self_ty,
+                trait_path,
+                name,
+            } => {
+                let self_ty = Print::ref_cast(&**self_ty);
+                let trait_path = Print::ref_cast(trait_path);
+                quote!(<#self_ty as #trait_path>::#name)
+            }
         });
     }
 }
@@ -88,7 +113,27 @@ impl ToTokens for Print<GenericParam> {
         match &self.0 {
             GenericParam::Type(type_param) => Print::ref_cast(type_param).to_tokens(tokens),
             GenericParam::Lifetime(lifetime) => Print::ref_cast(lifetime).to_tokens(tokens),
-            GenericParam::Const(_const) => unimplemented!("const generics"),
+            GenericParam::Const(const_param) => {
+                let param = Print::ref_cast(const_param);
+                let decl = const_param.decl();
+                let ty = Print::ref_cast(&decl.ty);
+                tokens.append_all(quote!(const #param: #ty))
+            }
+        }
+    }
+}
+
+impl ToTokens for Print<ConstParam> {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        Ident::new(&format!("__C{}", (self.0).0), Span::call_site()).to_tokens(tokens);
+    }
+}
+
+impl ToTokens for Print<Expr> {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        match &self.0 {
+            Expr::Param(const_param) => Print::ref_cast(const_param).to_tokens(tokens),
+            Expr::Value(value) => tokens.append_all(Expr::value_tokens(value)),
         }
     }
 }
@@ -114,6 +159,11 @@ impl ToTokens for Print<TypeParamBound> {
             TypeParamBound::Trait(trait_bound) => Print::ref_cast(trait_bound).to_tokens(tokens),
 
             TypeParamBound::Lifetime(lifetime) => Print::ref_cast(lifetime).to_tokens(tokens),
+
+            TypeParamBound::Maybe(path) => {
+                let path = Print::ref_cast(path);
+                quote!(?#path).to_tokens(tokens)
+            }
         }
     }
 }
@@ -125,7 +175,7 @@ impl ToTokens for Print<TraitBound> {
         let lifetimes = if self.0.lifetimes.is_empty() {
             None
         } else {
-            Some(quote!(for <#(#lifetimes)+*>))
+            Some(quote!(for <#(#lifetimes),*>))
         };
         tokens.append_all(quote!(#lifetimes #path))
     }
@@ -137,7 +187,7 @@ impl ToTokens for Print<PredicateType> {
         let lifetimes = if self.0.lifetimes.is_empty() {
             None
         } else {
-            Some(quote!(for <#(#lifetimes)+*>))
+            Some(quote!(for <#(#lifetimes),*>))
         };
         let ty = Print::ref_cast(&self.0.bounded_ty);
         let bounds = self.0.bounds.iter().map(Print::ref_cast);
@@ -209,7 +259,7 @@ impl ToTokens for Print<GenericArgument> {
                 Print::ref_cast(constraint).to_tokens(tokens)
             }
 
-            GenericArgument::Const(_expr) => unimplemented!(),
+            GenericArgument::Const(expr) => Print::ref_cast(expr).to_tokens(tokens),
         }
     }
 }
@@ -217,12 +267,10 @@ impl ToTokens for Print<GenericArgument> {
 impl ToTokens for Print<path::Path> {
     fn to_tokens(&self, tokens: &mut TokenStream) {
         use path::PathArguments;
-        let leading = if self.0.global {
-            Some(quote!(::))
-        } else {
-            None
-        };
-        let path = self.0.path.iter().map(|segment| {
+        let rebased = self.0.rebase_crate();
+        let path = rebased.as_ref().unwrap_or(&self.0);
+        let leading = if path.global { Some(quote!(::)) } else { None };
+        let path = path.path.iter().map(|segment| {
             let ident = &segment.ident;
             let args = match &segment.args {
                 PathArguments::None => None,
@@ -234,7 +282,7 @@ impl ToTokens for Print<path::Path> {
                     let inputs = args.inputs.iter().map(Print::ref_cast);
                     let output = args.output.as_ref().map(|output| {
                         let output = Print::ref_cast(output);
-                        quote! {-> output}
+                        quote! {-> #output}
                     });
                     Some(quote! { (#(#inputs),*) #output})
                 }
@@ -247,12 +295,10 @@ impl ToTokens for Print<path::Path> {
 
 impl ToTokens for Print<SimplePath> {
     fn to_tokens(&self, tokens: &mut TokenStream) {
-        let leading = if self.0.path.global {
-            Some(quote!(::))
-        } else {
-            None
-        };
-        let path = self.0.path.path.iter().map(|segment| &segment.ident);
+        let rebased = self.0.path.rebase_crate();
+        let path = rebased.as_ref().unwrap_or(&self.0.path);
+        let leading = if path.global { Some(quote!(::)) } else { None };
+        let path = path.path.iter().map(|segment| &segment.ident);
         tokens.append_all(quote!(#leading #(#path)::*));
     }
 }
@@ -0,0 +1,335 @@
+use crate::{
+    Accessor, CompleteImpl, GenericConstraint, GenericParam, Invoke, InvokeRef, Print, SimplePath,
+    TypeNode,
+};
+use fxhash::FxHashMap;
+use proc_macro2::{Delimiter, Group, Ident, Spacing, TokenStream, TokenTree};
+use quote::ToTokens;
+use ref_cast::RefCast;
+use std::fmt::{self, Display};
+use std::hash::Hash;
+
+/// An explanation of the bounds of the generated impls, returned by
+/// `derive_with_report`
+///
+/// For each impl, every where clause predicate, type equality and lifetime
+/// outlives fact is listed together with what introduced it, like an argument
+/// of an invoked function or a bound in its signature.
+#[derive(Debug, Clone, Default)]
+pub struct Report {
+    impls: Vec<ImplReport>,
+}
+
+/// The facts of the trait inference of one impl
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ImplReport {
+    pub(crate) header: String,
+    pub(crate) predicates: Vec<Fact>,
+    pub(crate) equalities: Vec<Fact>,
+    pub(crate) outlives: Vec<Fact>,
+}
+
+/// A fact, and descriptions of where it came from
+#[derive(Debug, Clone)]
+pub(crate) struct Fact {
+    pub(crate) fact: String,
+    pub(crate) origins: Vec<String>,
+}
+
+/// Where a fact of trait inference came from
+#[derive(Debug, Clone)]
+pub(crate) enum Origin {
+    /// Declared on the data structure or on the implemented trait
+    Declared,
+    /// A constraint in the signature of an invoked function
+    Constraint {
+        invoke: InvokeRef,
+        constraint: GenericConstraint,
+    },
+    /// An argument of an invoked function. The receiver is argument 0.
+    Argument { invoke: InvokeRef, index: usize },
+    /// An arm of a match, which must have the type of the first arm
+    MatchArm { function: String, index: usize },
+    /// A field of a constructed struct, which must have the type of the field
+    Field {
+        function: String,
+        accessor: Accessor,
+    },
+    /// The value returned from a function
+    Return { function: String },
+    /// The supertraits of the implemented trait
+    Supertrait,
+    /// A reference in the signature of an invoked function, which bounds its
+    /// referent by its lifetime
+    Reference { invoke: InvokeRef },
+}
+
+/// Two types that are inserted as equal, or the first as a subtype of the
+/// second
+#[derive(Debug, Clone)]
+pub(crate) struct Equality {
+    pub(crate) origin: Origin,
+    pub(crate) subtype: TypeNode,
+    pub(crate) supertype: TypeNode,
+}
+
+/// The origins of the facts in a set. They are only recorded while `current`
+/// is set, which is only done when a report is made.
+#[derive(Debug)]
+pub(crate) struct Origins<K> {
+    pub(crate) current: Option<Origin>,
+    pub(crate) map: FxHashMap<K, Vec<Origin>>,
+}
+
+impl<K> Origins<K>
+where
+    K: Clone + Eq + Hash,
+{
+    pub(crate) fn new() -> Self {
+        Origins {
+            current: None,
+            map: FxHashMap::default(),
+        }
+    }
+
+    pub(crate) fn record(&mut self, fact: &K) {
+        if let Some(origin) = &self.current {
+            self.map
+                .entry(fact.clone())
+                .or_default()
+                .push(origin.clone());
+        }
+    }
+}
+
+impl Report {
+    pub(crate) fn new(impls: &[CompleteImpl], free_functions: &[CompleteImpl]) -> Self {
+        let impls = impls
+            .iter()
+            .map(|imp| {
+                let (params, self_ty, _, trait_ty) = imp.header();
+                let params = params.map(tokens_to_string).unwrap_or_default();
+                let self_ty = to_report_string(self_ty);
+                let header = match trait_ty {
+                    Some(trait_ty) => format!(
+                        "impl{} {} for {}",
+                        params,
+                        tokens_to_string(trait_ty),
+                        self_ty
+                    ),
+                    None => format!("impl{} {}", params, self_ty),
+                };
+                ImplReport::with_header(imp, header)
+            })
+            .chain(free_functions.iter().map(|imp| {
+                let names: Vec<_> = imp
+                    .functions
+                    .iter()
+                    .map(|function| format!("fn {}", function.f.name))
+                    .collect();
+                ImplReport::with_header(imp, names.join(", "))
+            }))
+            .collect();
+        Report { impls }
+    }
+}
+
+impl ImplReport {
+    fn with_header(imp: &CompleteImpl, header: String) -> Self {
+        let report = imp
+            .result
+            .as_ref()
+            .and_then(|result| result.report.clone())
+            .unwrap_or_default();
+        ImplReport { header, ..report }
+    }
+}
+
+impl Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, imp) in self.impls.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            writeln!(f, "{}", imp.header)?;
+            if imp.predicates.is_empty() && imp.equalities.is_empty() && imp.outlives.is_empty() {
+                writeln!(f, "  no bounds were inferred")?;
+                continue;
+            }
+            write_facts(f, "where clause predicates", &imp.predicates)?;
+            write_facts(f, "type equalities", &imp.equalities)?;
+            write_facts(f, "lifetime outlives", &imp.outlives)?;
+        }
+        Ok(())
+    }
+}
+
+fn write_facts(f: &mut fmt::Formatter, title: &str, facts: &[Fact]) -> fmt::Result {
+    if facts.is_empty() {
+        return Ok(());
+    }
+    writeln!(f, "  {}:", title)?;
+    for fact in facts {
+        writeln!(f, "    {}", fact.fact)?;
+        for origin in &fact.origins {
+            writeln!(f, "      from {}", origin)?;
+        }
+    }
+    Ok(())
+}
+
+/// The path of an invoked function, like it is printed in the generated code
+pub(crate) fn invoke_path(invoke: &Invoke) -> String {
+    let function = &invoke.function;
+    if let Some(path) = &function.path {
+        return to_report_string(SimplePath::ref_cast(path));
+    }
+    match &function.parent {
+        Some(parent) => {
+            let parent = to_report_string(SimplePath::ref_cast(&parent.path));
+            format!("{}::{}", parent, function.name)
+        }
+        None => function.name.clone(),
+    }
+}
+
+/// Print a type, a path or a predicate like it is written in Rust code
+pub(crate) fn to_report_string<T>(value: &T) -> String
+where
+    Print<T>: ToTokens,
+{
+    tokens_to_string(Print::ref_cast(value).to_token_stream())
+}
+
+/// Print a type like `to_report_string`, with `_` in place of the type
+/// params in `erased` and `'_` in place of the lifetimes in `erased`
+pub(crate) fn to_report_string_erased(ty: &TypeNode, erased: &[GenericParam]) -> String {
+    let names: Vec<String> = erased
+        .iter()
+        .map(|param| match param {
+            GenericParam::Lifetime(lifetime) => Print::ref_cast(lifetime)
+                .to_token_stream()
+                .into_iter()
+                .last()
+                .unwrap()
+                .to_string(),
+            param => Print::ref_cast(param).to_token_stream().to_string(),
+        })
+        .collect();
+    let tokens = Print::ref_cast(ty).to_token_stream();
+    tokens_to_string(erase_names(tokens, &names))
+}
+
+fn erase_names(tokens: TokenStream, names: &[String]) -> TokenStream {
+    tokens
+        .into_iter()
+        .map(|token| match token {
+            TokenTree::Ident(ident) if names.contains(&ident.to_string()) => {
+                TokenTree::Ident(Ident::new("_", ident.span()))
+            }
+            TokenTree::Group(group) => {
+                let stream = erase_names(group.stream(), names);
+                TokenTree::Group(Group::new(group.delimiter(), stream))
+            }
+            token => token,
+        })
+        .collect()
+}
+
+/// Print tokens without the space that `TokenStream::to_string` puts between
+/// every two tokens, like `&'a Vec<T>` instead of `& 'a Vec < T >`
+fn tokens_to_string(tokens: TokenStream) -> String {
+    let mut string = String::new();
+    write_tokens(&mut string, tokens);
+    string
+}
+
+/// The previous token, which decides if a space goes before the next token
+#[derive(Clone, Copy)]
+enum Prev {
+    Start,
+    Ident,
+    // A keyword that is followed by a space, or the name of a lifetime
+    Keyword,
+    Punct(char, Spacing),
+    // The first and the last char of an operator like `::` or `->`
+    Operator(char, char),
+    Group,
+}
+
+fn write_tokens(string: &mut String, tokens: TokenStream) {
+    let mut prev = Prev::Start;
+    for token in tokens {
+        if needs_space(prev, &token) {
+            string.push(' ');
+        }
+        prev = match &token {
+            TokenTree::Ident(ident) => {
+                let ident = ident.to_string();
+                string.push_str(&ident);
+                let is_keyword = matches!(
+                    &*ident,
+                    "as" | "const" | "dyn" | "extern" | "for" | "impl" | "mut" | "unsafe" | "where"
+                );
+                match prev {
+                    Prev::Punct('\'', Spacing::Joint) => Prev::Keyword,
+                    _ if is_keyword => Prev::Keyword,
+                    _ => Prev::Ident,
+                }
+            }
+            TokenTree::Punct(punct) => {
+                let ch = punct.as_char();
+                string.push(ch);
+                match prev {
+                    Prev::Punct(first, Spacing::Joint) | Prev::Operator(first, _) => {
+                        Prev::Operator(first, ch)
+                    }
+                    _ => Prev::Punct(ch, punct.spacing()),
+                }
+            }
+            TokenTree::Literal(literal) => {
+                string.push_str(&literal.to_string());
+                Prev::Ident
+            }
+            TokenTree::Group(group) => {
+                let (open, close) = match group.delimiter() {
+                    Delimiter::Parenthesis => ("(", ")"),
+                    Delimiter::Bracket => ("[", "]"),
+                    Delimiter::Brace => ("{ ", " }"),
+                    Delimiter::None => ("", ""),
+                };
+                string.push_str(open);
+                write_tokens(string, group.stream());
+                string.push_str(close);
+                Prev::Group
+            }
+        };
+    }
+}
+
+fn needs_space(prev: Prev, next: &TokenTree) -> bool {
+    match prev {
+        Prev::Start | Prev::Punct(_, Spacing::Joint) | Prev::Operator(':', ':') => return false,
+        Prev::Punct('<' | '&' | '*' | '?' | '!' | '#', _) => return false,
+        _ => {}
+    }
+    match next {
+        TokenTree::Punct(punct) => match (punct.as_char(), punct.spacing()) {
+            (',' | ';' | '>', _) | (':', Spacing::Alone) => false,
+            // A path that starts with `::` after a keyword, a lifetime or a
+            // bound, or the rest of a path
+            (':', Spacing::Joint) => !matches!(
+                prev,
+                Prev::Ident | Prev::Punct('>', _) | Prev::Operator('>', '>')
+            ),
+            ('<', _) => !matches!(prev, Prev::Ident | Prev::Keyword),
+            ('!', _) => !matches!(prev, Prev::Ident),
+            _ => true,
+        },
+        TokenTree::Group(group) => match group.delimiter() {
+            Delimiter::Parenthesis | Delimiter::Bracket => !matches!(prev, Prev::Ident),
+            _ => true,
+        },
+        _ => true,
+    }
+}
@@ -16,6 +16,13 @@ pub trait RuntimeParent {
     fn SELF(self) -> Rc<Parent>;
 }
 
+/// The Parent of the constructors of an enum or struct declared in the
+/// reflect! macro.
+pub trait RuntimeData {
+    #[allow(non_snake_case)]
+    fn DATA(self) -> Rc<Parent>;
+}
+
 pub trait RuntimeTrait: RuntimeParent {}
 
 pub trait RuntimeImpl: RuntimeParent {}
@@ -26,6 +33,18 @@ impl RuntimeType for Type {
     }
 }
 
+impl RuntimeFunction for Function {
+    fn SELF(self) -> Rc<Function> {
+        Rc::new(self)
+    }
+}
+
+impl RuntimeFunction for Rc<Function> {
+    fn SELF(self) -> Rc<Function> {
+        self
+    }
+}
+
 impl RuntimeType for Path {
     fn SELF(self) -> Type {
         Type(TypeNode::Path(self))
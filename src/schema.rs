@@ -0,0 +1,229 @@
+use crate::{Function, GenericsEnv, Path, Signature};
+use std::collections::btree_map::{BTreeMap, Entry};
+use std::marker::PhantomData;
+use std::rc::Rc;
+use syn::spanned::Spanned;
+use syn::{Attribute, Error, Lit, Meta, NestedMeta, Result};
+
+/// The keys accepted inside of a helper attribute, like `rename`, `skip` and
+/// `with` in `#[myderive(rename = "x", skip, with = "path::to::fn")]`.
+///
+/// ```
+/// # use reflect::AttrSchema;
+/// let mut schema = AttrSchema::new("myderive");
+/// let rename = schema.add_string("rename");
+/// let skip = schema.add_flag("skip");
+/// let with = schema.add_path("with");
+/// ```
+#[derive(Debug, Clone)]
+pub struct AttrSchema {
+    name: String,
+    keys: BTreeMap<String, AttrKind>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AttrKind {
+    // `skip`
+    Flag,
+    // `rename = "x"`
+    Str,
+    // `with = "path::to::fn"`
+    Path,
+}
+
+/// The values of the helper attributes of a data structure, field or variant,
+/// parsed with an `AttrSchema`
+#[derive(Debug, Clone)]
+pub struct AttrValues {
+    values: BTreeMap<String, AttrValue>,
+}
+
+/// A key of an `AttrSchema`, that reads the value of the key from the
+/// `AttrValues` as a `T`, like `bool` for a flag
+#[derive(Debug, Clone)]
+pub struct AttrKey<T> {
+    name: String,
+    get: fn(Option<&AttrValue>) -> T,
+    marker: PhantomData<T>,
+}
+
+#[derive(Debug, Clone)]
+enum AttrValue {
+    Flag,
+    Str(String),
+    Path(Path),
+}
+
+impl AttrSchema {
+    /// A schema for the attributes named `name`
+    pub fn new(name: &str) -> Self {
+        AttrSchema {
+            name: name.to_owned(),
+            keys: BTreeMap::new(),
+        }
+    }
+
+    /// Accept a key without a value, like `skip`
+    pub fn add_flag(&mut self, key: &str) -> AttrKey<bool> {
+        self.add(key, AttrKind::Flag, |value| {
+            matches!(value, Some(AttrValue::Flag))
+        })
+    }
+
+    /// Accept a key with a string value, like `rename = "x"`
+    pub fn add_string(&mut self, key: &str) -> AttrKey<Option<String>> {
+        self.add(key, AttrKind::Str, |value| match value {
+            Some(AttrValue::Str(s)) => Some(s.clone()),
+            _ => None,
+        })
+    }
+
+    /// Accept a key with a path in a string, like `with = "path::to::fn"`
+    pub fn add_path(&mut self, key: &str) -> AttrKey<Option<Path>> {
+        self.add(key, AttrKind::Path, |value| match value {
+            Some(AttrValue::Path(path)) => Some(path.clone()),
+            _ => None,
+        })
+    }
+
+    fn add<T>(
+        &mut self,
+        key: &str,
+        kind: AttrKind,
+        get: fn(Option<&AttrValue>) -> T,
+    ) -> AttrKey<T> {
+        self.keys.insert(key.to_owned(), kind);
+        AttrKey {
+            name: key.to_owned(),
+            get,
+            marker: PhantomData,
+        }
+    }
+
+    /// Parse the attributes with the name of the schema. Unknown keys,
+    /// duplicate keys and values of the wrong kind are reported together.
+    pub fn parse(&self, attrs: &[Attribute]) -> Result<AttrValues> {
+        let mut values = BTreeMap::new();
+        let mut errors: Option<Error> = None;
+        let mut report = |error| match &mut errors {
+            Some(errors) => errors.combine(error),
+            None => errors = Some(error),
+        };
+
+        for attr in attrs {
+            if !attr.path.is_ident(&self.name) {
+                continue;
+            }
+            let nested = match attr.parse_meta() {
+                Ok(Meta::List(list)) => list.nested,
+                Ok(meta) => {
+                    report(Error::new(
+                        meta.span(),
+                        format!("expected #[{}(...)]", self.name),
+                    ));
+                    continue;
+                }
+                Err(error) => {
+                    report(error);
+                    continue;
+                }
+            };
+
+            for meta in nested {
+                match self.parse_nested(meta) {
+                    Ok((key, span, value)) => match values.entry(key) {
+                        Entry::Vacant(entry) => {
+                            entry.insert(value);
+                        }
+                        Entry::Occupied(entry) => report(Error::new(
+                            span,
+                            format!("duplicate {} attribute `{}`", self.name, entry.key()),
+                        )),
+                    },
+                    Err(error) => report(error),
+                }
+            }
+        }
+
+        match errors {
+            Some(errors) => Err(errors),
+            None => Ok(AttrValues { values }),
+        }
+    }
+
+    fn parse_nested(&self, meta: NestedMeta) -> Result<(String, proc_macro2::Span, AttrValue)> {
+        let meta = match meta {
+            NestedMeta::Meta(meta) => meta,
+            NestedMeta::Lit(lit) => {
+                return Err(Error::new(
+                    lit.span(),
+                    format!("expected a key in #[{}(...)]", self.name),
+                ))
+            }
+        };
+
+        let key_path = meta.path();
+        let key = match key_path.get_ident() {
+            Some(ident) => ident.to_string(),
+            None => {
+                return Err(Error::new(
+                    key_path.span(),
+                    format!("unknown {} attribute", self.name),
+                ))
+            }
+        };
+        let span = key_path.span();
+        let kind = match self.keys.get(&key) {
+            Some(kind) => *kind,
+            None => {
+                return Err(Error::new(
+                    span,
+                    format!("unknown {} attribute `{}`", self.name, key),
+                ))
+            }
+        };
+
+        let value = match (kind, meta) {
+            (AttrKind::Flag, Meta::Path(_)) => AttrValue::Flag,
+            (AttrKind::Flag, meta) => {
+                return Err(Error::new(
+                    meta.span(),
+                    format!("`{}` does not take a value", key),
+                ))
+            }
+            (AttrKind::Str, Meta::NameValue(name_value)) => match name_value.lit {
+                Lit::Str(lit) => AttrValue::Str(lit.value()),
+                lit => return Err(Error::new(lit.span(), "expected a string")),
+            },
+            (AttrKind::Path, Meta::NameValue(name_value)) => match name_value.lit {
+                Lit::Str(lit) => {
+                    let path = lit.parse::<syn::Path>()?;
+                    AttrValue::Path(Path::syn_to_path(path, &mut GenericsEnv::default()))
+                }
+                lit => return Err(Error::new(lit.span(), "expected a path in a string")),
+            },
+            (AttrKind::Str, meta) | (AttrKind::Path, meta) => {
+                return Err(Error::new(
+                    meta.span(),
+                    format!("expected `{} = \"...\"`", key),
+                ))
+            }
+        };
+
+        Ok((key, span, value))
+    }
+}
+
+impl AttrValues {
+    /// The value given for the key, like whether a flag was given, or the
+    /// string or the path given for a key with a value
+    pub fn get<T>(&self, key: &AttrKey<T>) -> T {
+        (key.get)(self.values.get(&key.name))
+    }
+
+    /// The function at the path given for the key, with the given signature
+    pub fn function(&self, key: &AttrKey<Option<Path>>, sig: Signature) -> Option<Rc<Function>> {
+        self.get(key)
+            .map(|path| Function::get_function_at_path(path, sig))
+    }
+}
@@ -1,10 +1,13 @@
 use crate::{
-    GenericArgument, GenericParam, Generics, GlobalCounter, Lifetime, ParamMap, Path,
-    PathArguments, SynParamMap, Type,
+    GenericArgument, GenericParam, Generics, GenericsEnv, GlobalCounter, Lifetime, Path,
+    PathArguments, Substitution, Type,
     TypeNode::{self, *},
     TypeParamBound, LIFETIMES,
 };
 use std::default::Default;
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::{parenthesized, Token};
 
 #[derive(Debug, Clone)]
 pub struct Signature {
@@ -12,6 +15,8 @@ pub struct Signature {
     pub(crate) receiver: Receiver,
     pub(crate) inputs: Vec<Type>,
     pub(crate) output: Type,
+    // Invocations are wrapped in `unsafe { }`
+    pub(crate) is_unsafe: bool,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -37,16 +42,14 @@ pub trait SetOutput<'a, T> {
 }
 
 impl Receiver {
-    pub(crate) fn clone_with_fresh_generics(&self, param_map: &ParamMap) -> Self {
+    pub(crate) fn clone_with_fresh_generics(&self, env: &GenericsEnv) -> Self {
         use Receiver::*;
         match *self {
             NoSelf => NoSelf,
             SelfByValue => SelfByValue,
             SelfByReference { is_mut, lifetime } => SelfByReference {
                 is_mut,
-                lifetime: OptionLifetime(Some(
-                    lifetime.0.unwrap().clone_with_fresh_generics(param_map),
-                )),
+                lifetime: OptionLifetime(Some(lifetime.0.unwrap().clone_with_fresh_generics(env))),
             },
         }
     }
@@ -60,10 +63,10 @@ impl<'a> AddInput<'a, Type> for Signature {
 
 impl<'a, F> AddInput<'a, F> for Signature
 where
-    F: FnOnce(&'a mut SynParamMap) -> Type,
+    F: FnOnce(&'a mut GenericsEnv) -> Type,
 {
     fn add_input(&'a mut self, into_input: F) {
-        self.inputs.push((into_input)(&mut self.generics.param_map))
+        self.inputs.push((into_input)(&mut self.generics.env))
     }
 }
 
@@ -75,10 +78,10 @@ impl<'a> SetOutput<'a, Type> for Signature {
 
 impl<'a, F> SetOutput<'a, F> for Signature
 where
-    F: FnOnce(&'a mut SynParamMap) -> Type,
+    F: FnOnce(&'a mut GenericsEnv) -> Type,
 {
     fn set_output(&'a mut self, into_output: F) {
-        self.output = (into_output)(&mut self.generics.param_map);
+        self.output = (into_output)(&mut self.generics.env);
     }
 }
 
@@ -89,9 +92,15 @@ impl Signature {
             receiver: Receiver::NoSelf,
             inputs: Vec::new(),
             output: Type::unit(),
+            is_unsafe: false,
         }
     }
 
+    /// Mark the function as an `unsafe fn`
+    pub fn set_unsafe(&mut self) {
+        self.is_unsafe = true;
+    }
+
     pub fn set_self_by_value(&mut self) {
         self.receiver = Receiver::SelfByValue;
     }
@@ -111,7 +120,7 @@ impl Signature {
     }
 
     /// Add input type to signature.
-    /// T can be either a Type or a type implementing `FnOnce(&'a mut ParamMap) -> Type`
+    /// T can be either a Type or a type implementing `FnOnce(&'a mut GenericsEnv) -> Type`
     pub fn add_input<'a, T>(&'a mut self, into_input: T)
     where
         Self: AddInput<'a, T>,
@@ -120,7 +129,7 @@ impl Signature {
     }
 
     /// Set output type to signature.
-    /// T can be either a Type or a type implementing `FnOnce(&'a mut ParamMap) -> Type`
+    /// T can be either a Type or a type implementing `FnOnce(&'a mut GenericsEnv) -> Type`
     pub fn set_output<'a, T>(&'a mut self, into_output: T)
     where
         Self: SetOutput<'a, T>,
@@ -132,78 +141,198 @@ impl Signature {
         self.generics.set_generic_params(params)
     }
 
-    pub fn add_parent_params(&mut self, param_map: &mut SynParamMap) {
-        self.generics.param_map.append(param_map);
+    /// Bring the params of the parent into scope, enclosing the params of
+    /// the function, which shadow them
+    pub fn add_parent_params(&mut self, parent_env: &GenericsEnv) {
+        self.generics.env.enclose_in(parent_env);
     }
 
     pub fn set_generic_constraints(&mut self, constraints: &[&str]) {
         self.generics.set_generic_constraints(constraints);
     }
 
-    /// Explicitly insert elided lifetimes
+    /// Parse a signature written like in `library!`, where the name of the
+    /// function and the names of the inputs are optional. Paths are used as
+    /// written, so types outside of the prelude should be written with their
+    /// full path.
+    ///
+    /// ```
+    /// # use reflect::Signature;
+    /// let sig = Signature::parse(
+    ///     "fn<T>(&self, &T, &mut ::std::fmt::Formatter) -> ::std::fmt::Result \
+    ///      where T: ::std::fmt::Debug",
+    /// )
+    /// .unwrap();
+    /// ```
+    pub fn parse(sig: &str) -> syn::Result<Self> {
+        let SignatureSyntax { sig, .. } = syn::parse_str(sig)?;
+        Signature::syn_to_checked_signature(sig)
+    }
+
+    /// Parse a signature that includes the name of the function
+    pub(crate) fn parse_with_name(sig: &str) -> syn::Result<(String, Self)> {
+        let SignatureSyntax { named, sig } = syn::parse_str(sig)?;
+        if !named {
+            return Err(syn::Error::new(
+                sig.fn_token.span,
+                "expected the name of the function",
+            ));
+        }
+        let name = sig.ident.to_string();
+        Ok((name, Signature::syn_to_checked_signature(sig)?))
+    }
+
+    /// Convert a signature, unless it has types that are not supported,
+    /// undeclared lifetimes, or lifetimes elided in the output can
+    /// not be inferred from the inputs
+    pub(crate) fn syn_to_checked_signature(sig: syn::Signature) -> syn::Result<Self> {
+        Signature::syn_to_checked_signature_in(sig, GenericsEnv::new(), &[])
+    }
+
+    /// Convert the signature of a method like `syn_to_checked_signature`. The
+    /// params of `env` and the lifetimes in `outer_lifetimes` are declared
+    /// around the method.
+    pub(crate) fn syn_to_checked_signature_in(
+        sig: syn::Signature,
+        env: GenericsEnv,
+        outer_lifetimes: &[syn::Lifetime],
+    ) -> syn::Result<Self> {
+        check_signature(&sig, outer_lifetimes)?;
+        let output = sig.output.clone();
+        let sig = Signature::syn_to_signature_in(sig, env);
+        if sig.has_missing_lifetime() {
+            return Err(syn::Error::new_spanned(
+                output,
+                "missing lifetime specifier",
+            ));
+        }
+        Ok(sig)
+    }
+
+    /// Convert the signature of a function item. Elided lifetimes are
+    /// inserted later by `Function::get_function`.
+    pub(crate) fn syn_to_signature(sig: syn::Signature) -> Self {
+        Signature::syn_to_signature_in(sig, GenericsEnv::new())
+    }
+
+    /// Convert the signature of a method, whose generic params are declared
+    /// in the innermost scope of `env`
+    pub(crate) fn syn_to_signature_in(sig: syn::Signature, env: GenericsEnv) -> Self {
+        let is_unsafe = sig.unsafety.is_some();
+        let mut generics = Generics::syn_to_generics_in(sig.generics, env);
+        let mut receiver = Receiver::NoSelf;
+        let mut inputs = Vec::new();
+        for input in sig.inputs {
+            match input {
+                syn::FnArg::Receiver(syn::Receiver {
+                    reference: None, ..
+                }) => receiver = Receiver::SelfByValue,
+                syn::FnArg::Receiver(syn::Receiver {
+                    reference: Some((_, lifetime)),
+                    mutability,
+                    ..
+                }) => {
+                    let lifetime =
+                        lifetime.map(|lifetime| generics.env.get_lifetime(&lifetime.to_string()));
+                    receiver = Receiver::SelfByReference {
+                        is_mut: mutability.is_some(),
+                        lifetime: OptionLifetime(lifetime),
+                    };
+                }
+                syn::FnArg::Typed(pat_type) => {
+                    inputs.push(Type::syn_to_type(*pat_type.ty, &mut generics.env));
+                }
+            }
+        }
+        let output = match sig.output {
+            syn::ReturnType::Default => Type::unit(),
+            syn::ReturnType::Type(_, ty) => Type::syn_to_type(*ty, &mut generics.env),
+        };
+        Signature {
+            generics,
+            receiver,
+            inputs,
+            output,
+            is_unsafe,
+        }
+    }
+
+    /// Whether `Self` is used in the inputs or the output
+    pub(crate) fn mentions_self(&self) -> bool {
+        let substitution = Substitution::self_ty(Infer);
+        self.inputs
+            .iter()
+            .chain(Some(&self.output))
+            .any(|ty| ty.0.substitute(&substitution) != ty.0)
+    }
+
+    /// Replace `Self` in the inputs and the output
+    pub(crate) fn substitute_self(&mut self, self_ty: &TypeNode) {
+        let substitution = Substitution::self_ty(self_ty.clone());
+        for ty in self.inputs.iter_mut().chain(Some(&mut self.output)) {
+            ty.0 = ty.0.substitute(&substitution);
+        }
+    }
+
+    /// Explicitly insert elided lifetimes, following the lifetime elision
+    /// rules of Rust. Each elided lifetime in the inputs becomes a lifetime
+    /// param of its own. The elided lifetimes in the output are the lifetime
+    /// of `&self` or `&mut self`, or else the lifetime of the only lifetime
+    /// position in the inputs.
     /// Should be called by `Function::get_function` after the all paramters are inserted
     pub(crate) fn insert_elided_lifetimes(&mut self) {
-        use Receiver::*;
         let generics = &mut self.generics;
         // We need to insert the elided lifetimes first in the params so we
         // temporarily swap the params with an empty Vec, and then extend that
         // Vec with the old params in the end
-        let params = std::mem::replace(&mut generics.params, Vec::new());
+        let params = std::mem::take(&mut generics.params);
 
-        match &mut self.receiver {
-            NoSelf => {
-                for ty in &mut self.inputs {
-                    ty.0.insert_new_lifetimes(&mut generics.params);
-                }
-                if self.inputs.len() == 1 {
-                    match &mut self.inputs[0].0 {
-                        Reference {
-                            lifetime: Some(lifetime),
-                            inner,
-                            ..
-                        } if !inner.has_lifetimes() => self
-                            .output
-                            .0
-                            .insert_new_lifetimes2(*lifetime, &mut generics.params),
-                        _ => {}
-                    }
-                } else {
-                    self.output.0.insert_new_lifetimes(&mut generics.params);
-                }
-            }
-            SelfByValue => {
-                for ty in &mut self.inputs {
-                    ty.0.insert_new_lifetimes(&mut generics.params);
-                }
-                self.output.0.insert_new_lifetimes(&mut generics.params);
-            }
-            SelfByReference {
-                lifetime: option_lifetime,
-                ..
-            } => {
-                let lifetime = if let Some(lifetime) = option_lifetime.0 {
-                    lifetime
-                } else {
+        let self_lifetime = match &mut self.receiver {
+            Receiver::SelfByReference { lifetime, .. } => {
+                Some(*lifetime.0.get_or_insert_with(|| {
                     let lifetime = LIFETIMES.count();
                     generics.params.push(GenericParam::Lifetime(lifetime));
                     lifetime
-                };
-                option_lifetime.0 = Some(lifetime);
-                for ty in &mut self.inputs {
-                    ty.0.insert_new_lifetimes(&mut generics.params);
-                }
-                self.output
-                    .0
-                    .insert_new_lifetimes2(lifetime, &mut generics.params);
+                }))
             }
+            Receiver::NoSelf | Receiver::SelfByValue => None,
+        };
+        for ty in &mut self.inputs {
+            ty.0.insert_new_lifetimes(&mut generics.params);
         }
+        let output_lifetime = self_lifetime.or_else(|| match self.input_lifetimes()[..] {
+            [lifetime] => lifetime,
+            _ => None,
+        });
+        if output_lifetime.is_none() && self.output.0.has_elided_lifetimes() {
+            panic!("Signature::insert_elided_lifetimes: missing lifetime specifier in the output");
+        }
+        self.output.0.insert_output_lifetimes(output_lifetime);
+
         // Insert the old params back into place
-        generics.params.extend(params);
+        self.generics.params.extend(params);
+    }
+
+    /// Whether an elided lifetime in the output can not be inferred from the
+    /// inputs, which is an error in Rust
+    fn has_missing_lifetime(&self) -> bool {
+        let borrows_self = matches!(self.receiver, Receiver::SelfByReference { .. });
+        !borrows_self && self.input_lifetimes().len() != 1 && self.output.0.has_elided_lifetimes()
+    }
+
+    /// The lifetime positions in the inputs, which are `None` where the
+    /// lifetime is elided
+    fn input_lifetimes(&self) -> Vec<Option<Lifetime>> {
+        let mut lifetimes = Vec::new();
+        for ty in &self.inputs {
+            ty.0.lifetime_positions(&mut lifetimes);
+        }
+        lifetimes
     }
 }
 
 impl TypeNode {
-    fn insert_new_lifetimes(&mut self, params: &mut Vec<GenericParam>) {
+    pub(crate) fn insert_new_lifetimes(&mut self, params: &mut Vec<GenericParam>) {
         match self {
             Reference {
                 inner, lifetime, ..
@@ -221,6 +350,7 @@ impl TypeNode {
                     ty.insert_new_lifetimes(params);
                 }
             }
+            Array { elem, .. } => elem.insert_new_lifetimes(params),
             Dereference(node) => node.insert_new_lifetimes(params),
             TraitObject(bounds) => {
                 for bound in bounds.iter_mut() {
@@ -230,48 +360,118 @@ impl TypeNode {
                 }
             }
             Path(path) => path.insert_new_lifetimes(params),
+            Projection {
+                self_ty,
+                trait_path,
+                ..
+            } => {
+                self_ty.insert_new_lifetimes(params);
+                trait_path.insert_new_lifetimes(params);
+            }
             _ => {}
         }
     }
 
-    fn insert_new_lifetimes2(&mut self, new_lifetime: Lifetime, params: &mut Vec<GenericParam>) {
+    /// Insert the lifetime that the elided lifetimes of the output stand for
+    fn insert_output_lifetimes(&mut self, elided: Option<Lifetime>) {
         match self {
             Reference {
                 inner, lifetime, ..
             } => {
                 if lifetime.is_none() {
-                    *lifetime = Some(new_lifetime);
+                    *lifetime = elided;
                 };
-                inner.insert_new_lifetimes(params);
+                inner.insert_output_lifetimes(elided);
             }
             Tuple(types) => {
                 for ty in types.iter_mut() {
-                    ty.insert_new_lifetimes2(new_lifetime, params);
+                    ty.insert_output_lifetimes(elided);
                 }
             }
-            Dereference(node) => node.insert_new_lifetimes2(new_lifetime, params),
+            Array { elem, .. } => elem.insert_output_lifetimes(elided),
+            Dereference(node) => node.insert_output_lifetimes(elided),
             TraitObject(bounds) => {
                 for bound in bounds.iter_mut() {
                     if let TypeParamBound::Trait(bound) = bound {
-                        bound.path.insert_new_lifetimes2(new_lifetime, params);
+                        bound.path.insert_output_lifetimes(elided);
                     }
                 }
             }
-            Path(path) => path.insert_new_lifetimes2(new_lifetime, params),
+            Path(path) => path.insert_output_lifetimes(elided),
+            Projection {
+                self_ty,
+                trait_path,
+                ..
+            } => {
+                self_ty.insert_output_lifetimes(elided);
+                trait_path.insert_output_lifetimes(elided);
+            }
+            _ => {}
+        }
+    }
+
+    /// The lifetimes of the references and the lifetime arguments in the
+    /// type, in order
+    fn lifetime_positions(&self, lifetimes: &mut Vec<Option<Lifetime>>) {
+        match self {
+            Reference {
+                inner, lifetime, ..
+            } => {
+                lifetimes.push(*lifetime);
+                inner.lifetime_positions(lifetimes);
+            }
+            Tuple(types) => {
+                for ty in types {
+                    ty.lifetime_positions(lifetimes);
+                }
+            }
+            Array { elem, .. } => elem.lifetime_positions(lifetimes),
+            Dereference(node) => node.lifetime_positions(lifetimes),
+            TraitObject(bounds) => {
+                for bound in bounds {
+                    match bound {
+                        TypeParamBound::Trait(bound) => bound.path.lifetime_positions(lifetimes),
+                        TypeParamBound::Lifetime(lifetime) => lifetimes.push(Some(*lifetime)),
+                        TypeParamBound::Maybe(_) => {}
+                    }
+                }
+            }
+            Path(path) => path.lifetime_positions(lifetimes),
+            Projection {
+                self_ty,
+                trait_path,
+                ..
+            } => {
+                self_ty.lifetime_positions(lifetimes);
+                trait_path.lifetime_positions(lifetimes);
+            }
             _ => {}
         }
     }
 
+    fn has_elided_lifetimes(&self) -> bool {
+        let mut lifetimes = Vec::new();
+        self.lifetime_positions(&mut lifetimes);
+        lifetimes.contains(&None)
+    }
+
     pub(crate) fn has_lifetimes(&self) -> bool {
         match self {
             Reference { .. } => true,
             Tuple(types) => types.iter().any(TypeNode::has_lifetimes),
+            Array { elem, .. } => elem.has_lifetimes(),
             Dereference(node) => node.has_lifetimes(),
             TraitObject(bounds) => bounds.iter().any(|bound| match bound {
                 TypeParamBound::Trait(bound) => bound.path.has_lifetimes(),
                 TypeParamBound::Lifetime(_) => true,
+                TypeParamBound::Maybe(_) => false,
             }),
             Path(path) => path.has_lifetimes(),
+            Projection {
+                self_ty,
+                trait_path,
+                ..
+            } => self_ty.has_lifetimes() || trait_path.has_lifetimes(),
             _ => false,
         }
     }
@@ -289,26 +489,42 @@ impl Path {
                         }
                     }
                 }
-                PathArguments::Parenthesized(args) => {
-                    unimplemented!("Path::insert_elided_lifetimes: PathArguments::Parenthesized")
-                }
+                // The elided lifetimes in `Fn(&T) -> &U` are higher-ranked,
+                // like in `for<'a> Fn(&'a T) -> &'a U`, so they are not
+                // params of the function
+                PathArguments::Parenthesized(_) => {}
             }
         }
     }
 
-    fn insert_new_lifetimes2(&mut self, lifetime: Lifetime, params: &mut Vec<GenericParam>) {
+    fn insert_output_lifetimes(&mut self, elided: Option<Lifetime>) {
         for segment in &mut self.path {
             match &mut segment.args {
                 PathArguments::None => {}
                 PathArguments::AngleBracketed(args) => {
                     for arg in &mut args.args.args {
                         if let GenericArgument::Type(ty) = arg {
-                            ty.0.insert_new_lifetimes2(lifetime, params)
+                            ty.0.insert_output_lifetimes(elided)
                         }
                     }
                 }
-                PathArguments::Parenthesized(args) => {
-                    unimplemented!("Path::insert_elided_lifetimes2: PathArguments::Parenthesized")
+                PathArguments::Parenthesized(_) => {}
+            }
+        }
+    }
+
+    fn lifetime_positions(&self, lifetimes: &mut Vec<Option<Lifetime>>) {
+        for segment in &self.path {
+            if let PathArguments::AngleBracketed(args) = &segment.args {
+                for arg in &args.args.args {
+                    match arg {
+                        GenericArgument::Type(ty) => ty.0.lifetime_positions(lifetimes),
+                        GenericArgument::Lifetime(lifetime) => lifetimes.push(Some(*lifetime)),
+                        GenericArgument::Binding(binding) => {
+                            binding.ty.0.lifetime_positions(lifetimes)
+                        }
+                        GenericArgument::Constraint(_) | GenericArgument::Const(_) => {}
+                    }
                 }
             }
         }
@@ -320,11 +536,289 @@ impl Path {
             PathArguments::AngleBracketed(args) => args.args.args.iter().any(|arg| match arg {
                 GenericArgument::Type(ty) => ty.0.has_lifetimes(),
                 GenericArgument::Lifetime(_) => true,
+                GenericArgument::Const(_) => false,
                 _ => unimplemented!(),
             }),
-            PathArguments::Parenthesized(args) => {
-                unimplemented!("Path::has_lifetimes: PathArguments::Parenthesized")
+            PathArguments::Parenthesized(args) => args
+                .inputs
+                .iter()
+                .chain(&args.output)
+                .any(|ty| ty.0.has_lifetimes()),
+        })
+    }
+}
+
+/// Rejects the types that are not supported in `library!` and the lifetimes
+/// that are not declared, before the signature is converted
+fn check_signature(sig: &syn::Signature, outer_lifetimes: &[syn::Lifetime]) -> syn::Result<()> {
+    let mut check = SignatureCheck {
+        lifetimes: outer_lifetimes.to_vec(),
+    };
+    check.generics(&sig.generics)?;
+    for input in &sig.inputs {
+        match input {
+            syn::FnArg::Receiver(syn::Receiver {
+                reference: Some((_, Some(lifetime))),
+                ..
+            }) => check.lifetime(lifetime)?,
+            syn::FnArg::Receiver(_) => {}
+            syn::FnArg::Typed(pat_type) => check.ty(&pat_type.ty)?,
+        }
+    }
+    match &sig.output {
+        syn::ReturnType::Default => Ok(()),
+        syn::ReturnType::Type(_, ty) => check.ty(ty),
+    }
+}
+
+/// Rejects the types that are not supported like `check_signature`, in the
+/// generics of an impl and a type declared with them, like the self type of
+/// the impl
+pub(crate) fn check_generics_and_type(generics: &syn::Generics, ty: &syn::Type) -> syn::Result<()> {
+    let mut check = SignatureCheck {
+        lifetimes: Vec::new(),
+    };
+    check.generics(generics)?;
+    check.ty(ty)
+}
+
+struct SignatureCheck {
+    // The declared lifetimes, and those of the `for<..>` binders around the
+    // checked bound
+    lifetimes: Vec<syn::Lifetime>,
+}
+
+impl SignatureCheck {
+    /// Declares the lifetimes of the generics, and checks their bounds
+    fn generics(&mut self, generics: &syn::Generics) -> syn::Result<()> {
+        let lifetimes = generics.lifetimes().map(|def| def.lifetime.clone());
+        self.lifetimes.extend(lifetimes);
+        for param in &generics.params {
+            match param {
+                syn::GenericParam::Type(param) => param
+                    .bounds
+                    .iter()
+                    .try_for_each(|bound| self.bound(bound))?,
+                syn::GenericParam::Lifetime(def) => def
+                    .bounds
+                    .iter()
+                    .try_for_each(|bound| self.lifetime(bound))?,
+                syn::GenericParam::Const(param) => self.ty(&param.ty)?,
+            }
+        }
+        if let Some(where_clause) = &generics.where_clause {
+            for predicate in &where_clause.predicates {
+                self.predicate(predicate)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn ty(&mut self, ty: &syn::Type) -> syn::Result<()> {
+        match ty {
+            syn::Type::Path(type_path) => {
+                if let Some(qself) = &type_path.qself {
+                    self.ty(&qself.ty)?;
+                }
+                self.path(&type_path.path)
+            }
+            syn::Type::Reference(reference) => {
+                if let Some(lifetime) = &reference.lifetime {
+                    self.lifetime(lifetime)?;
+                }
+                self.ty(&reference.elem)
+            }
+            syn::Type::TraitObject(trait_object) => trait_object
+                .bounds
+                .iter()
+                .try_for_each(|bound| self.bound(bound)),
+            syn::Type::Tuple(tuple) => tuple.elems.iter().try_for_each(|elem| self.ty(elem)),
+            syn::Type::Array(array) => self.ty(&array.elem),
+            ty => Err(unsupported_type(ty)),
+        }
+    }
+
+    fn path(&mut self, path: &syn::Path) -> syn::Result<()> {
+        for segment in &path.segments {
+            match &segment.arguments {
+                syn::PathArguments::None => {}
+                syn::PathArguments::AngleBracketed(args) => {
+                    for arg in &args.args {
+                        match arg {
+                            syn::GenericArgument::Lifetime(lifetime) => self.lifetime(lifetime)?,
+                            syn::GenericArgument::Type(ty) => self.ty(ty)?,
+                            syn::GenericArgument::Binding(binding) => self.ty(&binding.ty)?,
+                            syn::GenericArgument::Constraint(constraint) => constraint
+                                .bounds
+                                .iter()
+                                .try_for_each(|bound| self.bound(bound))?,
+                            syn::GenericArgument::Const(_) => {}
+                        }
+                    }
+                }
+                syn::PathArguments::Parenthesized(args) => {
+                    args.inputs.iter().try_for_each(|input| self.ty(input))?;
+                    if let syn::ReturnType::Type(_, output) = &args.output {
+                        self.ty(output)?;
+                    }
+                }
             }
+        }
+        Ok(())
+    }
+
+    fn bound(&mut self, bound: &syn::TypeParamBound) -> syn::Result<()> {
+        match bound {
+            syn::TypeParamBound::Trait(bound) => {
+                self.with_binder(&bound.lifetimes, |check| check.path(&bound.path))
+            }
+            syn::TypeParamBound::Lifetime(lifetime) => self.lifetime(lifetime),
+        }
+    }
+
+    fn predicate(&mut self, predicate: &syn::WherePredicate) -> syn::Result<()> {
+        match predicate {
+            syn::WherePredicate::Type(predicate) => {
+                self.with_binder(&predicate.lifetimes, |check| {
+                    check.ty(&predicate.bounded_ty)?;
+                    predicate
+                        .bounds
+                        .iter()
+                        .try_for_each(|bound| check.bound(bound))
+                })
+            }
+            syn::WherePredicate::Lifetime(predicate) => {
+                self.lifetime(&predicate.lifetime)?;
+                predicate
+                    .bounds
+                    .iter()
+                    .try_for_each(|bound| self.lifetime(bound))
+            }
+            syn::WherePredicate::Eq(predicate) => Err(syn::Error::new_spanned(
+                predicate,
+                "equality constraints are not supported in library!",
+            )),
+        }
+    }
+
+    fn with_binder(
+        &mut self,
+        binder: &Option<syn::BoundLifetimes>,
+        check: impl FnOnce(&mut Self) -> syn::Result<()>,
+    ) -> syn::Result<()> {
+        let len = self.lifetimes.len();
+        if let Some(binder) = binder {
+            let lifetimes = binder.lifetimes.iter().map(|def| def.lifetime.clone());
+            self.lifetimes.extend(lifetimes);
+        }
+        let result = check(self);
+        self.lifetimes.truncate(len);
+        result
+    }
+
+    fn lifetime(&self, lifetime: &syn::Lifetime) -> syn::Result<()> {
+        if lifetime.ident == "static" || self.lifetimes.contains(lifetime) {
+            Ok(())
+        } else {
+            Err(syn::Error::new_spanned(
+                lifetime,
+                format!("use of undeclared lifetime name `{}`", lifetime),
+            ))
+        }
+    }
+}
+
+/// The error for a type that can not be used in a signature, with the
+/// messages of `library!`
+fn unsupported_type(ty: &syn::Type) -> syn::Error {
+    use syn::Type::*;
+    let message = match ty {
+        Slice(_) => "slice types are not supported in library!",
+        Ptr(_) => "raw pointer types are not supported in library!",
+        BareFn(_) => {
+            "function pointer types are not supported in library!, use a generic parameter with an `Fn` bound instead"
+        }
+        ImplTrait(_) => {
+            "`impl Trait` is not supported in library!, use a generic parameter instead"
+        }
+        Never(_) => "the never type is not supported in library!",
+        Infer(_) => "the type placeholder `_` is not allowed in library!, write out the type",
+        Macro(_) => "macros in type position are not supported in library!",
+        _ => "unsupported type in library!",
+    };
+    syn::Error::new_spanned(ty, message)
+}
+
+/// The syntax of a signature in `library!`, like
+/// `fn(&self, &mut Formatter) -> Result`
+struct SignatureSyntax {
+    named: bool,
+    sig: syn::Signature,
+}
+
+impl Parse for SignatureSyntax {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let unsafety: Option<Token![unsafe]> = input.parse()?;
+        let fn_token: Token![fn] = input.parse()?;
+        let ident: Option<syn::Ident> = input.parse()?;
+        let named = ident.is_some();
+        let ident = ident.unwrap_or_else(|| syn::Ident::new("__f", fn_token.span));
+        let mut generics: syn::Generics = input.parse()?;
+
+        let content;
+        let paren_token = parenthesized!(content in input);
+        let mut inputs = Punctuated::new();
+        while !content.is_empty() {
+            let fork = content.fork();
+            let is_receiver = inputs.is_empty()
+                && fork.parse::<syn::Receiver>().is_ok()
+                && (fork.is_empty() || fork.peek(Token![,]));
+            let arg = if is_receiver {
+                syn::FnArg::Receiver(content.parse()?)
+            } else {
+                // The name of an input is optional
+                if content.peek(syn::Ident)
+                    && content.peek2(Token![:])
+                    && !content.peek2(Token![::])
+                {
+                    content.parse::<syn::Ident>()?;
+                    content.parse::<Token![:]>()?;
+                }
+                syn::FnArg::Typed(syn::PatType {
+                    attrs: Vec::new(),
+                    pat: Box::new(syn::Pat::Wild(syn::PatWild {
+                        attrs: Vec::new(),
+                        underscore_token: Default::default(),
+                    })),
+                    colon_token: Default::default(),
+                    ty: Box::new(content.parse()?),
+                })
+            };
+            inputs.push_value(arg);
+            if content.is_empty() {
+                break;
+            }
+            inputs.push_punct(content.parse()?);
+        }
+
+        let output = input.parse()?;
+        generics.where_clause = input.parse()?;
+
+        Ok(SignatureSyntax {
+            named,
+            sig: syn::Signature {
+                constness: None,
+                asyncness: None,
+                unsafety,
+                abi: None,
+                fn_token,
+                ident,
+                generics,
+                paren_token,
+                inputs,
+                variadic: None,
+                output,
+            },
         })
     }
 }
@@ -1,8 +1,10 @@
 use crate::{
-    AngleBracketedGenericArguments, CompleteFunction, CompleteImpl, Function, GenericArgument,
-    GenericArguments, GenericConstraint, GenericParam, GlobalBorrow, Lifetime, LifetimeDef, Parent,
-    ParentKind, Path, PathArguments, PredicateType, Push, Receiver, TraitBound, Type,
-    TypeEqualitySetRef, TypeNode, TypeParamBound, WipFunction, WipImpl, INVOKES, STATIC_LIFETIME,
+    invoke_path, to_report_string, to_report_string_erased, AngleBracketedGenericArguments,
+    CompleteFunction, CompleteImpl, ConstParam, Data, Equality, Expr, Fact, Function,
+    GenericArgument, GenericArguments, GenericConstraint, GenericParam, Ident, ImplReport, Invoke,
+    InvokeRef, Lifetime, LifetimeDef, Origin, Origins, Parent, ParentKind, Path, PathArguments,
+    PredicateType, Receiver, TraitBound, Type, TypeEqualitySetRef, TypeNode, TypeParam,
+    TypeParamBound, ValueNode, Variance, Variant, WipFunction, WipImpl, INVOKES, STATIC_LIFETIME,
     VALUES,
 };
 // FxHasher is used because it is a faster hashing algorithm than the
@@ -10,19 +12,26 @@ use crate::{
 // seed, which is useful for testing purposes, and consistent output between
 // compiles.
 use fxhash::{FxHashMap, FxHashSet};
+use proc_macro2::Span;
+use std::borrow::Cow;
 use std::cmp::Ordering;
 use std::collections::{btree_map, BTreeMap, BTreeSet, HashMap, HashSet};
-use std::convert::identity;
-use std::hash::Hash;
-use std::iter::Extend;
+use std::iter::{self, Extend};
+use std::mem;
 use std::ops::{Index, IndexMut};
 use std::rc::Rc;
 
-pub(crate) struct EqualitySet<T> {
-    pub(crate) set: FxHashSet<T>,
+/// A set of constraints used in the where clause in the final impl
+#[derive(Debug)]
+pub(crate) struct ConstraintSet {
+    pub(crate) set: FxHashSet<GenericConstraint>,
+    origins: Origins<GenericConstraint>,
+    /// Types inferred for the type params of invoked functions, which must be
+    /// `Sized` unless the param has a `?Sized` bound
+    sized: FxHashSet<TypeNode>,
 }
 
-/// A set of types that are considered to be equal. An example of how it is
+/// Sets of types that are considered to be equal. An example of how they are
 /// used: Say we have a function: `fn func<T>(maybe: Option<T>) {}`, and we
 /// call this function with a value of type `::std::option::Option<String>`.
 /// Then we need at least two sets.
@@ -30,22 +39,20 @@ pub(crate) struct EqualitySet<T> {
 /// In set two, we have the inner types: { T, String, .. }. Both sets may
 /// contain more than two types, since more than two types may be considered
 /// equal
-pub(crate) type TypeEqualitySet = EqualitySet<TypeNode>;
-
-/// A set of constraints used in the where clause in the final impl
-#[derive(Debug)]
-pub(crate) struct ConstraintSet {
-    pub(crate) set: FxHashSet<GenericConstraint>,
-}
-
-pub(crate) struct EqualitySets<SetRef, T> {
-    pub(crate) set_map: FxHashMap<T, SetRef>,
-    pub(crate) sets: Vec<EqualitySet<T>>,
+///
+/// The sets are a union-find over interned types. Each type gets an id the
+/// first time it is seen, and a set is referred to by the id of its root.
+/// Merging two sets moves the ids of the smaller set into the larger one, so
+/// no type is cloned after it has been interned.
+pub(crate) struct TypeEqualitySets {
+    ids: FxHashMap<TypeNode, usize>,
+    types: Vec<TypeNode>,
+    // The parent of each id in the union-find forest. A root is its own parent
+    parents: Vec<usize>,
+    // The ids in the set of each root, which is empty for the other ids
+    members: Vec<Vec<usize>>,
 }
 
-// A mapping between types and their corresponding equality sets
-pub(crate) type TypeEqualitySets = EqualitySets<TypeEqualitySetRef, TypeNode>;
-
 /// A conventient wrapper struct that is sent around quite a bit
 ///
 /// The `most_concrete_type_map` is a mapping between references to a type-
@@ -59,25 +66,49 @@ pub(crate) type TypeEqualitySets = EqualitySets<TypeEqualitySetRef, TypeNode>;
 pub(crate) struct ConcreteMapAndSets {
     most_concrete_type_map: BTreeMap<TypeEqualitySetRef, TypeNode>,
     type_equality_sets: TypeEqualitySets,
+    const_equalities: ConstEqualities,
 }
 
 pub(crate) struct OriginalGenercs {
     original_generic_params: Vec<GenericParam>,
-    original_data_struct_args: Vec<GenericParam>,
+    original_self_ty: TypeNode,
     original_trait_args: Vec<GenericParam>,
 }
 
+/// The opt-in policies of trait inference, set on the `Execution`
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct InferenceOptions {
+    // Forbids narrowing the generic params of the self type
+    pub(crate) never_specialize: bool,
+    // Keeps the bounds on the types of visible fields
+    pub(crate) perfect_derive: bool,
+    // Records where the bounds came from, for `derive_with_report`
+    pub(crate) report: bool,
+}
+
 #[derive(Debug)]
 pub(crate) struct TraitInferenceResult {
     pub(crate) constraints: ConstraintSet,
     pub(crate) generic_params: BTreeSet<GenericParam>,
-    pub(crate) data_struct_args: GenericArguments,
+    pub(crate) self_ty: Type,
     pub(crate) trait_args: GenericArguments,
+    pub(crate) report: Option<ImplReport>,
 }
 
 /// A mapping between a lifetime and it's subtypes
 pub(crate) struct LifetimeSubtypeMap {
     subtypes: BTreeSet<(Lifetime, Lifetime)>,
+    origins: Origins<(Lifetime, Lifetime)>,
+}
+
+/// A mapping between const params and the expressions they are equal to. An
+/// example of how it is used: Say we have a function:
+/// `fn func<T, const M: usize>(array: [T; M]) {}`, and we call it with a
+/// value of type `[u8; N]`. Then `M` is mapped to `N`. Chains of params are
+/// resolved to the smallest param, or to a value like `4` if one of the
+/// params is equal to a value.
+pub(crate) struct ConstEqualities {
+    map: BTreeMap<ConstParam, Expr>,
 }
 
 /// N x N bool matrix
@@ -105,6 +136,9 @@ pub(crate) struct TransitiveClosure {
     index_lifetime_mapping: BTreeMap<usize, Lifetime>,
     lifetime_index_mapping: BTreeMap<Lifetime, usize>,
     most_concrete_lifetime_map: BTreeMap<Lifetime, Lifetime>,
+    // The lifetimes of `for<..>` binders, which are only in scope of their
+    // bound, and so are never equal to any other lifetime
+    bound_lifetimes: BTreeSet<Lifetime>,
 }
 
 /// A temprary struct that contains the output from the `create_mapping` method
@@ -115,27 +149,6 @@ struct Mapping {
     size: usize,
 }
 
-/// A mapping between a supertype and its subtypes
-struct SupertypeMap {
-    map: BTreeMap<Lifetime, Vec<Lifetime>>,
-}
-
-impl SupertypeMap {
-    fn new() -> Self {
-        let mut map = BTreeMap::new();
-        map.insert(STATIC_LIFETIME, vec![STATIC_LIFETIME]);
-        SupertypeMap { map }
-    }
-
-    fn get(&self, lifetime: &Lifetime) -> Option<&Vec<Lifetime>> {
-        self.map.get(lifetime)
-    }
-
-    fn entry(&mut self, lifetime: Lifetime) -> btree_map::Entry<Lifetime, Vec<Lifetime>> {
-        self.map.entry(lifetime)
-    }
-}
-
 impl BoolMatrix {
     fn new(size: usize) -> Self {
         BoolMatrix {
@@ -158,14 +171,55 @@ impl IndexMut<(usize, usize)> for BoolMatrix {
     }
 }
 
+impl ConstEqualities {
+    fn new() -> Self {
+        ConstEqualities {
+            map: BTreeMap::new(),
+        }
+    }
+
+    fn resolve(&self, expr: &Expr) -> Expr {
+        let mut expr = expr.clone();
+        while let Expr::Param(param) = expr {
+            match self.map.get(&param) {
+                Some(next) => expr = next.clone(),
+                None => break,
+            }
+        }
+        expr
+    }
+
+    fn insert_as_equal(&mut self, expr1: &Expr, expr2: &Expr) {
+        match (self.resolve(expr1), self.resolve(expr2)) {
+            (Expr::Param(param1), Expr::Param(param2)) => match param1.cmp(&param2) {
+                Ordering::Less => {
+                    self.map.insert(param2, Expr::Param(param1));
+                }
+                Ordering::Greater => {
+                    self.map.insert(param1, Expr::Param(param2));
+                }
+                Ordering::Equal => {}
+            },
+            (Expr::Param(param), value) | (value, Expr::Param(param)) => {
+                self.map.insert(param, value);
+            }
+            // Two different values can not be made equal, which the compiler
+            // reports when the generated code is type checked
+            (Expr::Value(_), Expr::Value(_)) => {}
+        }
+    }
+}
+
 impl LifetimeSubtypeMap {
     fn new() -> Self {
         LifetimeSubtypeMap {
             subtypes: BTreeSet::new(),
+            origins: Origins::new(),
         }
     }
 
     fn insert(&mut self, subtype: Lifetime, supertype: Lifetime) {
+        self.origins.record(&(subtype, supertype));
         self.subtypes.insert((subtype, supertype));
     }
 
@@ -177,6 +231,16 @@ impl LifetimeSubtypeMap {
     fn add_lifetime_bounds(&mut self, constraints: &ConstraintSet) {
         constraints.set.iter().for_each(|constraint| {
             if let GenericConstraint::Lifetime(lifetime_def) = constraint {
+                if self.origins.current.is_some() {
+                    self.origins.current = Some(
+                        constraints
+                            .origins
+                            .map
+                            .get(constraint)
+                            .and_then(|origins| origins.first().cloned())
+                            .unwrap_or(Origin::Declared),
+                    );
+                }
                 lifetime_def
                     .bounds
                     .iter()
@@ -187,7 +251,13 @@ impl LifetimeSubtypeMap {
 
     /// Find the transitive closure of the subtype relation.
     /// If 'a: 'b and 'b: 'c, the 'a: 'c
-    fn transitive_closure(self) -> TransitiveClosure {
+    ///
+    /// The `bound_lifetimes` are locally quantified by a `for<..>` binder, so
+    /// the facts about them are left out.
+    fn transitive_closure(mut self, bound_lifetimes: BTreeSet<Lifetime>) -> TransitiveClosure {
+        self.subtypes.retain(|(subtype, supertype)| {
+            !bound_lifetimes.contains(subtype) && !bound_lifetimes.contains(supertype)
+        });
         let Mapping {
             mapping,
             index_lifetime_mapping,
@@ -217,6 +287,7 @@ impl LifetimeSubtypeMap {
             lifetime_index_mapping,
             index_lifetime_mapping,
             most_concrete_lifetime_map: BTreeMap::new(),
+            bound_lifetimes,
         }
     }
 
@@ -284,7 +355,10 @@ impl TransitiveClosure {
             *lifetime = most_concrete;
             return;
         }
-        if let Some(&lifetime_index) = self.lifetime_index_mapping.get(&lifetime) {
+        if self.bound_lifetimes.contains(lifetime) {
+            return;
+        }
+        if let Some(&lifetime_index) = self.lifetime_index_mapping.get(lifetime) {
             // Find the first lifetime self is equal to
             let equal_lifetimes: Vec<_> = (0..self.transitive_closure.size)
                 .filter_map(|i| {
@@ -313,10 +387,13 @@ impl ConstraintSet {
     fn new() -> Self {
         ConstraintSet {
             set: HashSet::default(),
+            origins: Origins::new(),
+            sized: HashSet::default(),
         }
     }
 
     fn insert(&mut self, constraint: GenericConstraint) -> bool {
+        self.origins.record(&constraint);
         self.set.insert(constraint)
     }
 
@@ -324,6 +401,22 @@ impl ConstraintSet {
         self.set.contains(constraint)
     }
 
+    /// The lifetimes declared by the `for<..>` binders of the constraints
+    fn bound_lifetimes(&self) -> BTreeSet<Lifetime> {
+        let mut lifetimes = BTreeSet::new();
+        for constraint in &self.set {
+            if let GenericConstraint::Type(pred_ty) = constraint {
+                lifetimes.extend(&pred_ty.lifetimes);
+                for bound in &pred_ty.bounds {
+                    if let TypeParamBound::Trait(bound) = bound {
+                        lifetimes.extend(&bound.lifetimes);
+                    }
+                }
+            }
+        }
+        lifetimes
+    }
+
     fn add_subtypes(&mut self, transitive_closure: &mut TransitiveClosure) {
         let lifetime_index_mapping = &mut transitive_closure.index_lifetime_mapping;
         let transitive_closure = &mut transitive_closure.transitive_closure;
@@ -346,57 +439,182 @@ impl ConstraintSet {
         }
     }
 
+    /// Keep only the relevant constraints. If `supertrait_impl` is given as
+    /// the self type and the implemented trait, the supertraits of the trait
+    /// are required for the self type. Bounds that are implied by the
+    /// supertraits of other bounds on the same type are removed.
+    ///
+    /// If `visible_field_types` is given, bounds on those field types are
+    /// kept as well, see `Execution::perfect_derive`.
     fn filter_constraints(
         self,
         relevant_generic_params: &BTreeSet<GenericParam>,
         concrete_maps_and_sets: &mut ConcreteMapAndSets,
         transitive_closure: &mut TransitiveClosure,
+        supertrait_impl: Option<(Type, Path)>,
+        visible_field_types: Option<&[TypeNode]>,
     ) -> Self {
-        ConstraintSet {
-            set: self
-                .set
-                .into_iter()
-                .map(|mut constraint| {
-                    let is_relevant = constraint.make_relevant(
-                        concrete_maps_and_sets,
-                        relevant_generic_params,
-                        transitive_closure,
-                    );
-                    if is_relevant {
-                        Some(constraint)
-                    } else {
-                        None
+        let sized: FxHashSet<_> = self
+            .sized
+            .into_iter()
+            .map(|mut ty| {
+                ty.make_most_concrete(concrete_maps_and_sets, transitive_closure);
+                ty
+            })
+            .collect();
+        let mut origins = self.origins.map;
+        let mut kept_origins: FxHashMap<_, Vec<_>> = FxHashMap::default();
+        let mut set: FxHashSet<_> = self
+            .set
+            .into_iter()
+            .filter_map(|mut constraint| {
+                let constraint_origins = origins.remove(&constraint);
+                let is_relevant = constraint.make_relevant(
+                    concrete_maps_and_sets,
+                    relevant_generic_params,
+                    transitive_closure,
+                ) || visible_field_types.is_some_and(|types| {
+                    constraint.is_field_type_bound(types, relevant_generic_params)
+                });
+                if !is_relevant {
+                    return None;
+                }
+                // A declared `?Sized` bound is dropped if the type is required
+                // to be `Sized` after all
+                if let GenericConstraint::Type(pred_ty) = &mut constraint {
+                    if sized.contains(&pred_ty.bounded_ty.0) {
+                        pred_ty
+                            .bounds
+                            .retain(|bound| !matches!(bound, TypeParamBound::Maybe(_)));
+                        if pred_ty.bounds.is_empty() {
+                            return None;
+                        }
                     }
+                }
+                if let Some(constraint_origins) = constraint_origins {
+                    kept_origins
+                        .entry(constraint.clone())
+                        .or_default()
+                        .extend(constraint_origins);
+                }
+                Some(constraint)
+            })
+            .collect();
+
+        if let Some((self_ty, trait_path)) = supertrait_impl {
+            let bounds: Vec<_> = Parent::get_all_supertraits(&trait_path, &self_ty.0)
+                .into_iter()
+                .map(|path| {
+                    TypeParamBound::Trait(TraitBound {
+                        lifetimes: Vec::new(),
+                        path,
+                    })
                 })
-                .filter_map(identity)
-                .collect(),
+                .collect();
+            if !bounds.is_empty() {
+                let constraint = GenericConstraint::Type(PredicateType {
+                    lifetimes: Vec::new(),
+                    bounded_ty: self_ty,
+                    bounds,
+                });
+                kept_origins
+                    .entry(constraint.clone())
+                    .or_default()
+                    .push(Origin::Supertrait);
+                set.insert(constraint);
+            }
         }
-    }
-}
 
-impl<T> EqualitySet<T>
-where
-    T: Eq + Hash,
-{
-    fn new() -> Self {
-        EqualitySet {
-            set: HashSet::default(),
+        remove_implied_bounds(&mut set);
+
+        // Implied bounds are removed from predicates, so the origins of those
+        // are found by the bounded type
+        let mut origins = Origins::new();
+        for constraint in &set {
+            let constraint_origins = match (kept_origins.get(constraint), constraint) {
+                (Some(constraint_origins), _) => constraint_origins.clone(),
+                (None, GenericConstraint::Type(pred_ty)) => kept_origins
+                    .iter()
+                    .filter(|(kept, _)| match kept {
+                        GenericConstraint::Type(kept) => kept.bounded_ty == pred_ty.bounded_ty,
+                        GenericConstraint::Lifetime(_) => false,
+                    })
+                    .flat_map(|(_, origins)| origins.iter().cloned())
+                    .collect(),
+                (None, GenericConstraint::Lifetime(_)) => continue,
+            };
+            origins.map.insert(constraint.clone(), constraint_origins);
+        }
+        ConstraintSet {
+            set,
+            origins,
+            sized: HashSet::default(),
         }
     }
+}
 
-    fn contains(&self, t: &T) -> bool {
-        self.set.contains(t)
+/// Remove trait bounds that are supertraits of another trait bound on the same
+/// type, like `T: PartialEq` when `T: Eq` is also required.
+fn remove_implied_bounds(set: &mut FxHashSet<GenericConstraint>) {
+    let mut implied: FxHashMap<Type, Vec<Path>> = FxHashMap::default();
+    for constraint in set.iter() {
+        if let GenericConstraint::Type(pred_ty) = constraint {
+            if !pred_ty.lifetimes.is_empty() {
+                continue;
+            }
+            for bound in &pred_ty.bounds {
+                if let TypeParamBound::Trait(bound) = bound {
+                    implied
+                        .entry(pred_ty.bounded_ty.clone())
+                        .or_default()
+                        .extend(Parent::get_all_supertraits(
+                            &bound.path,
+                            &pred_ty.bounded_ty.0,
+                        ));
+                }
+            }
+        }
     }
 
-    fn insert(&mut self, t: T) -> bool {
-        self.set.insert(t)
+    let is_implied = |pred_ty: &PredicateType, bound: &TypeParamBound| match bound {
+        TypeParamBound::Trait(bound) => implied
+            .get(&pred_ty.bounded_ty)
+            .is_some_and(|implied| implied.contains(&bound.path)),
+        TypeParamBound::Lifetime(_) | TypeParamBound::Maybe(_) => false,
+    };
+    let redundant: Vec<_> = set
+        .iter()
+        .filter(|constraint| match constraint {
+            GenericConstraint::Type(pred_ty) => {
+                pred_ty.lifetimes.is_empty()
+                    && pred_ty
+                        .bounds
+                        .iter()
+                        .any(|bound| is_implied(pred_ty, bound))
+            }
+            GenericConstraint::Lifetime(_) => false,
+        })
+        .cloned()
+        .collect();
+    for constraint in redundant {
+        set.remove(&constraint);
+        if let GenericConstraint::Type(mut pred_ty) = constraint {
+            let bounds = pred_ty.bounds.clone();
+            pred_ty.bounds = bounds
+                .into_iter()
+                .filter(|bound| !is_implied(&pred_ty, bound))
+                .collect();
+            if !pred_ty.bounds.is_empty() {
+                set.insert(GenericConstraint::Type(pred_ty));
+            }
+        }
     }
 }
 
 impl TypeEqualitySetRef {
     /// The most concrete type is what the inferred type for a value must be.
     /// What is meant by making something more concrete, is essentially making
-    /// it less generic. Say we have a `TypeEqualitySet` with these types:
+    /// it less generic. Say we have an equality set with these types:
     /// { T, Option<U> }. The most concrete type of these, are Option<U>.
     /// Imagine then that we have another set: { U, String }. String is more
     /// concrete than U, and thus Option<String> is more concrete than Option<U>,
@@ -427,30 +645,34 @@ impl TypeEqualitySetRef {
                     .most_concrete_type_map
                     .insert(self, TypeNode::Infer);
 
-                // Temprorarily take the set out of the equlity set to avoid borrowing issues
-                let set =
-                    std::mem::take(&mut concrete_maps_and_sets.type_equality_sets.sets[self.0].set);
-                let mut iterator = set.iter().peekable();
-                let mut first = iterator.next().unwrap().clone();
-                let most_concrete = if iterator.peek().is_none() {
+                // Temprorarily take the ids out of the equlity set to avoid borrowing issues
+                let members =
+                    mem::take(&mut concrete_maps_and_sets.type_equality_sets.members[self.0]);
+                let ty = |concrete_maps_and_sets: &ConcreteMapAndSets, id: usize| {
+                    concrete_maps_and_sets.type_equality_sets.types[id].clone()
+                };
+                let mut first = ty(concrete_maps_and_sets, members[0]);
+                let most_concrete = if members.len() == 1 {
                     first.make_most_concrete_inner(concrete_maps_and_sets, transitive_closure);
                     first
                 } else {
-                    iterator.fold(first, |current_most_concrete, ty| {
-                        TypeNode::make_most_concrete_from_pair(
-                            current_most_concrete,
-                            ty.clone(),
-                            concrete_maps_and_sets,
-                            transitive_closure,
-                        )
-                    })
+                    members[1..]
+                        .iter()
+                        .fold(first, |current_most_concrete, &id| {
+                            TypeNode::make_most_concrete_from_pair(
+                                current_most_concrete,
+                                ty(concrete_maps_and_sets, id),
+                                concrete_maps_and_sets,
+                                transitive_closure,
+                            )
+                        })
                 };
                 concrete_maps_and_sets
                     .most_concrete_type_map
                     .insert(self, most_concrete.clone());
 
-                // insert the set back in again
-                concrete_maps_and_sets.type_equality_sets.sets[self.0].set = set;
+                // insert the ids back in again
+                concrete_maps_and_sets.type_equality_sets.members[self.0] = members;
                 most_concrete
             }
         }
@@ -459,62 +681,66 @@ impl TypeEqualitySetRef {
 
 impl TypeEqualitySets {
     fn new() -> Self {
-        EqualitySets {
-            set_map: HashMap::default(),
-            sets: Vec::new(),
+        TypeEqualitySets {
+            ids: HashMap::default(),
+            types: Vec::new(),
+            parents: Vec::new(),
+            members: Vec::new(),
         }
     }
 
-    fn contains_key(&self, ty: &TypeNode) -> bool {
-        self.set_map.contains_key(ty)
-    }
-
-    fn get_set(&self, ty: &TypeNode) -> Option<&TypeEqualitySet> {
-        self.set_map.get(ty).map(|set_ref| &self.sets[set_ref.0])
-    }
-
-    pub(crate) fn get_set_ref(&self, ty: &TypeNode) -> Option<TypeEqualitySetRef> {
-        self.set_map.get(ty).copied()
+    pub(crate) fn get_set_ref(&mut self, ty: &TypeNode) -> Option<TypeEqualitySetRef> {
+        let id = *self.ids.get(ty)?;
+        Some(TypeEqualitySetRef(self.find(id)))
     }
 
     fn new_set(&mut self, ty: TypeNode) -> TypeEqualitySetRef {
-        let mut set = EqualitySet::new();
-        set.insert(ty.clone());
-        let set_ref = self.sets.index_push(set);
+        let id = self.intern(ty);
+        TypeEqualitySetRef(self.find(id))
+    }
 
-        self.set_map.insert(ty, set_ref);
-        set_ref
+    /// The id of a type, which starts out in a set of its own
+    fn intern(&mut self, ty: TypeNode) -> usize {
+        if let Some(&id) = self.ids.get(&ty) {
+            return id;
+        }
+        let id = self.types.len();
+        self.ids.insert(ty.clone(), id);
+        self.types.push(ty);
+        self.parents.push(id);
+        self.members.push(vec![id]);
+        id
+    }
+
+    /// The root of the set containing `id`. Every id on the way is pointed
+    /// directly at the root, so that later lookups are short.
+    fn find(&mut self, id: usize) -> usize {
+        let mut root = id;
+        while self.parents[root] != root {
+            root = self.parents[root];
+        }
+        let mut id = id;
+        while self.parents[id] != root {
+            id = mem::replace(&mut self.parents[id], root);
+        }
+        root
     }
 
     fn insert_as_equal(&mut self, ty1: TypeNode, ty2: TypeNode) {
-        match (self.set_map.get(&ty1), self.set_map.get(&ty2)) {
-            (Some(&set_ref1), Some(&set_ref2)) => {
-                let set2 = std::mem::replace(&mut self.sets[set_ref2.0], EqualitySet::new());
-                let set1 = &mut self.sets[set_ref1.0];
-                for t in &set2.set {
-                    if let Some(set_ref) = self.set_map.get_mut(t) {
-                        *set_ref = set_ref1
-                    }
-                }
-                set1.set.extend(set2.set);
-            }
-            (Some(&set_ref), None) => {
-                self.sets[set_ref.0].insert(ty2.clone());
-                self.set_map.insert(ty2, set_ref);
-            }
-            (None, Some(&set_ref)) => {
-                self.sets[set_ref.0].insert(ty1.clone());
-                self.set_map.insert(ty1, set_ref);
-            }
-            (None, None) => {
-                let mut set = EqualitySet::new();
-                set.insert(ty2.clone());
-                set.insert(ty1.clone());
-                let set_ref = self.sets.index_push(set);
-                self.set_map.insert(ty2, set_ref);
-                self.set_map.insert(ty1, set_ref);
-            }
+        let id1 = self.intern(ty1);
+        let id2 = self.intern(ty2);
+        let (root1, root2) = (self.find(id1), self.find(id2));
+        if root1 == root2 {
+            return;
         }
+        let (root, other) = if self.members[root1].len() >= self.members[root2].len() {
+            (root1, root2)
+        } else {
+            (root2, root1)
+        };
+        self.parents[other] = root;
+        let members = mem::take(&mut self.members[other]);
+        self.members[root].extend(members);
     }
 
     /// Insert two types as equal to each other. In case one of the types is a
@@ -526,6 +752,7 @@ impl TypeEqualitySets {
         supertype: TypeNode,
         constraints: &mut ConstraintSet,
         subtypes: &mut LifetimeSubtypeMap,
+        consts: &mut ConstEqualities,
     ) {
         use TypeNode::*;
         match (subtype, supertype) {
@@ -538,6 +765,7 @@ impl TypeEqualitySets {
                     &TraitObject(bounds2),
                     constraints,
                     subtypes,
+                    consts,
                 );
             }
             (TraitObject(bounds), supertype) => {
@@ -571,10 +799,16 @@ impl TypeEqualitySets {
                 if let (Some(lifetime1), Some(lifetime2)) = (lifetime1, lifetime2) {
                     subtypes.insert_as_equal(lifetime1, lifetime2);
                 }
-                self.insert_types_as_equal(*inner1, *inner2, constraints, subtypes);
+                self.insert_types_as_equal(*inner1, *inner2, constraints, subtypes, consts);
             }
             (subtype, supertype) => {
-                self.insert_inner_type_as_equal(&subtype, &supertype, constraints, subtypes);
+                self.insert_inner_type_as_equal(
+                    &subtype,
+                    &supertype,
+                    constraints,
+                    subtypes,
+                    consts,
+                );
                 self.insert_as_equal(subtype, supertype)
             }
         }
@@ -586,7 +820,7 @@ impl TypeEqualitySets {
         supertype: TypeNode,
         constraints: &mut ConstraintSet,
         subtypes: &mut LifetimeSubtypeMap,
-        supertype_map: &mut SupertypeMap,
+        consts: &mut ConstEqualities,
     ) {
         use TypeNode::*;
         match (subtype, supertype) {
@@ -594,14 +828,14 @@ impl TypeEqualitySets {
                 if types1.len() == types2.len() {
                     types1
                         .into_iter()
-                        .zip(types2.into_iter())
+                        .zip(types2)
                         .for_each(|(subtype, supertype)| {
                             self.insert_as_subtype_or_equal(
                                 subtype,
                                 supertype,
                                 constraints,
                                 subtypes,
-                                supertype_map,
+                                consts,
                             )
                         })
                 } else {
@@ -617,6 +851,7 @@ impl TypeEqualitySets {
                     &TraitObject(bounds2),
                     constraints,
                     subtypes,
+                    consts,
                 );
             }
             (TraitObject(bounds), supertype) => {
@@ -649,28 +884,76 @@ impl TypeEqualitySets {
                     if let (Some(subtype), Some(supertype)) = (lifetime1, lifetime2) {
                         subtypes.insert(subtype, supertype);
                     }
-                    self.insert_inner_type_as_equal(&*inner1, &*inner2, constraints, subtypes);
+                    self.insert_inner_type_as_equal(
+                        &inner1,
+                        &inner2,
+                        constraints,
+                        subtypes,
+                        consts,
+                    );
                     self.insert_as_equal(*inner1, *inner2);
                 } else if !is_mut1 && !is_mut2 {
                     if let (Some(subtype), Some(supertype)) = (lifetime1, lifetime2) {
                         subtypes.insert(subtype, supertype);
                     }
-                    self.insert_as_subtype_or_equal(
-                        *inner1,
-                        *inner2,
-                        constraints,
-                        subtypes,
-                        supertype_map,
-                    )
+                    self.insert_as_subtype_or_equal(*inner1, *inner2, constraints, subtypes, consts)
                 } else {
                     panic!("TypeEqualitySets::insert_as_subtype_or_equal: Cannot use a mutable reference in this context")
                 }
-                if let (Some(subtype), Some(supertype)) = (lifetime1, lifetime2) {
-                    supertype_map.entry(supertype).or_default().push(subtype);
+            }
+            // The arguments of a data type declared with its fields are
+            // related by the variance of the type in their params
+            (Path(path1), Path(path2)) if path1.has_variances(&path2) => {
+                let args = path1
+                    .get_generic_args()
+                    .iter()
+                    .zip(path2.get_generic_args());
+                for ((arg1, arg2), variance) in args.zip(path1.variances()) {
+                    match (arg1, arg2) {
+                        (GenericArgument::Type(ty1), GenericArgument::Type(ty2))
+                            if variance == Variance::Covariant =>
+                        {
+                            self.insert_as_subtype_or_equal(
+                                ty1.0.clone(),
+                                ty2.0.clone(),
+                                constraints,
+                                subtypes,
+                                consts,
+                            )
+                        }
+                        (GenericArgument::Type(ty1), GenericArgument::Type(ty2)) => self
+                            .insert_types_as_equal(
+                                ty1.0.clone(),
+                                ty2.0.clone(),
+                                constraints,
+                                subtypes,
+                                consts,
+                            ),
+                        (
+                            GenericArgument::Lifetime(lifetime1),
+                            GenericArgument::Lifetime(lifetime2),
+                        ) => {
+                            if variance == Variance::Covariant {
+                                subtypes.insert(*lifetime1, *lifetime2);
+                            } else {
+                                subtypes.insert_as_equal(*lifetime1, *lifetime2);
+                            }
+                        }
+                        (GenericArgument::Const(expr1), GenericArgument::Const(expr2)) => {
+                            consts.insert_as_equal(expr1, expr2);
+                        }
+                        _ => unimplemented!("TypeEqualitySets::insert_as_subtype_or_equal: Path"),
+                    }
                 }
             }
             (subtype, supertype) => {
-                self.insert_inner_type_as_equal(&subtype, &supertype, constraints, subtypes);
+                self.insert_inner_type_as_equal(
+                    &subtype,
+                    &supertype,
+                    constraints,
+                    subtypes,
+                    consts,
+                );
                 self.insert_as_equal(subtype, supertype)
             }
         }
@@ -685,13 +968,20 @@ impl TypeEqualitySets {
         ty2: &TypeNode,
         constraints: &mut ConstraintSet,
         subtypes: &mut LifetimeSubtypeMap,
+        consts: &mut ConstEqualities,
     ) {
         use TypeNode::*;
         match (ty1, ty2) {
             (Tuple(types1), Tuple(types2)) => {
                 if types1.len() == types2.len() {
                     types1.iter().zip(types2.iter()).for_each(|(ty1, ty2)| {
-                        self.insert_types_as_equal(ty1.clone(), ty2.clone(), constraints, subtypes)
+                        self.insert_types_as_equal(
+                            ty1.clone(),
+                            ty2.clone(),
+                            constraints,
+                            subtypes,
+                            consts,
+                        )
                     })
                 } else {
                     panic!("TypeEqualitySets::insert_inner_type_as_equal: Tuples have different number of arguments")
@@ -712,10 +1002,35 @@ impl TypeEqualitySets {
                 if let (Some(lifetime1), Some(lifetime2)) = (lifetime1, lifetime2) {
                     subtypes.insert_as_equal(*lifetime1, *lifetime2);
                 }
-                self.insert_types_as_equal(*inner1.clone(), *inner2.clone(), constraints, subtypes)
+                self.insert_types_as_equal(
+                    *inner1.clone(),
+                    *inner2.clone(),
+                    constraints,
+                    subtypes,
+                    consts,
+                )
+            }
+            (
+                Array {
+                    elem: elem1,
+                    len: len1,
+                },
+                Array {
+                    elem: elem2,
+                    len: len2,
+                },
+            ) => {
+                consts.insert_as_equal(len1, len2);
+                self.insert_types_as_equal(
+                    *elem1.clone(),
+                    *elem2.clone(),
+                    constraints,
+                    subtypes,
+                    consts,
+                )
             }
             (Path(path1), Path(path2)) => {
-                self.insert_path_arguments_as_equal(path1, path2, constraints, subtypes);
+                self.insert_path_arguments_as_equal(path1, path2, constraints, subtypes, consts);
             }
             (TraitObject(bounds1), TraitObject(bounds2)) => bounds1
                 .iter()
@@ -727,11 +1042,13 @@ impl TypeEqualitySets {
                             &trait_bound2.path,
                             constraints,
                             subtypes,
+                            consts,
                         );
                     }
                     (TypeParamBound::Lifetime(lifetime1), TypeParamBound::Lifetime(lifetime2)) => {
                         subtypes.insert_as_equal(*lifetime1, *lifetime2);
                     }
+                    (TypeParamBound::Maybe(_), TypeParamBound::Maybe(_)) => {}
                     _ => panic!("TraitObjects have different bound types"),
                 }),
             _ => (),
@@ -760,6 +1077,7 @@ impl TypeEqualitySets {
         path2: &Path,
         constraints: &mut ConstraintSet,
         subtypes: &mut LifetimeSubtypeMap,
+        consts: &mut ConstEqualities,
     ) {
         let (segment1, segment2) = (
             &path1.path[path1.path.len() - 1],
@@ -781,6 +1099,7 @@ impl TypeEqualitySets {
                                 ty2.0.clone(),
                                 constraints,
                                 subtypes,
+                                consts,
                             ),
                         (
                             GenericArgument::Lifetime(lifetime1),
@@ -788,10 +1107,35 @@ impl TypeEqualitySets {
                         ) => {
                             subtypes.insert_as_equal(*lifetime1, *lifetime2);
                         }
+                        (GenericArgument::Const(expr1), GenericArgument::Const(expr2)) => {
+                            consts.insert_as_equal(expr1, expr2);
+                        }
                         _ => unimplemented!("TypeEqualitySets::insert_inner_type_as_equal: Path"),
                     })
             }
-            (PathArguments::Parenthesized(args1), _) | (_, PathArguments::Parenthesized(args1)) => {
+            (PathArguments::Parenthesized(args1), PathArguments::Parenthesized(args2))
+                if args1.inputs.len() == args2.inputs.len() =>
+            {
+                let outputs = match (&args1.output, &args2.output) {
+                    (Some(output1), Some(output2)) => Some((output1, output2)),
+                    _ => None,
+                };
+                args1
+                    .inputs
+                    .iter()
+                    .zip(&args2.inputs)
+                    .chain(outputs)
+                    .for_each(|(ty1, ty2)| {
+                        self.insert_types_as_equal(
+                            ty1.0.clone(),
+                            ty2.0.clone(),
+                            constraints,
+                            subtypes,
+                            consts,
+                        )
+                    })
+            }
+            (PathArguments::Parenthesized(_), _) | (_, PathArguments::Parenthesized(_)) => {
                 unimplemented!(
                     "TypeEqualitySets::insert_inner_type_as_equal: ParenthesizedGenericArgument"
                 )
@@ -802,48 +1146,94 @@ impl TypeEqualitySets {
 }
 
 impl WipImpl {
-    pub(crate) fn compute_trait_bounds(mut self) -> CompleteImpl {
+    pub(crate) fn compute_trait_bounds(
+        mut self,
+        options: InferenceOptions,
+    ) -> syn::Result<CompleteImpl> {
         let mut constraints = ConstraintSet::new();
         let mut type_equality_sets = TypeEqualitySets::new();
         let mut subtypes = LifetimeSubtypeMap::new();
+        let mut consts = ConstEqualities::new();
+        let mut equalities = Vec::new();
+        if options.report {
+            constraints.origins.current = Some(Origin::Declared);
+            subtypes.origins.current = Some(Origin::Declared);
+        }
 
         let OriginalGenercs {
             original_generic_params,
-            original_data_struct_args,
+            original_self_ty,
             original_trait_args,
         } = self.get_original_generics(&mut constraints);
 
         let functions = self.functions.into_inner();
-        functions.iter().for_each(|function| {
-            function.compute_trait_bounds(&mut constraints, &mut type_equality_sets, &mut subtypes)
-        });
+        for function in &functions {
+            function.compute_trait_bounds(
+                &mut constraints,
+                &mut type_equality_sets,
+                &mut subtypes,
+                &mut consts,
+                options.report.then_some(&mut equalities),
+            );
+        }
 
         subtypes.add_lifetime_bounds(&constraints);
-        let mut transitive_closure = subtypes.transitive_closure();
+        let outlives = mem::take(&mut subtypes.origins.map);
+        let mut transitive_closure = subtypes.transitive_closure(constraints.bound_lifetimes());
         constraints.add_subtypes(&mut transitive_closure);
 
         let (mut relevant_generic_params, mut concrete_maps_and_sets) = get_relevant_generic_params(
             &original_generic_params,
             type_equality_sets,
+            consts,
             &mut transitive_closure,
         );
 
-        let constraints = constraints.filter_constraints(
-            &relevant_generic_params,
-            &mut concrete_maps_and_sets,
-            &mut transitive_closure,
-        );
+        let mut self_ty = original_self_ty;
+        self_ty.make_most_concrete(&mut concrete_maps_and_sets, &mut transitive_closure);
+
+        if options.never_specialize {
+            check_not_specialized(
+                &self.ty,
+                &mut concrete_maps_and_sets,
+                &mut transitive_closure,
+            )?;
+        }
 
-        let data_struct_args = get_args(
-            original_data_struct_args,
+        let trait_args = get_args(
+            original_trait_args,
             &mut concrete_maps_and_sets,
             &mut transitive_closure,
         );
 
-        let trait_args = get_args(
-            original_trait_args,
+        // Supertrait bounds on the self type are only needed when the impl is
+        // generic, otherwise they are checked without a where clause
+        let supertrait_impl = match &self.trait_ty {
+            Some(trait_ty) if !original_generic_params.is_empty() => {
+                let mut trait_path = trait_ty.path.clone();
+                trait_path.path.last_mut().unwrap().args = generic_args_to_path_args(&trait_args);
+                Some((Type(self_ty.clone()), trait_path))
+            }
+            _ => None,
+        };
+
+        let visible_field_types = if options.perfect_derive {
+            let mut types = Vec::new();
+            visible_field_types(&self.ty.0, &mut types);
+            for ty in &mut types {
+                ty.make_most_concrete(&mut concrete_maps_and_sets, &mut transitive_closure);
+            }
+            Some(types)
+        } else {
+            None
+        };
+
+        let mut constraints = constraints.filter_constraints(
+            &relevant_generic_params,
             &mut concrete_maps_and_sets,
             &mut transitive_closure,
+            supertrait_impl,
+            visible_field_types.as_deref(),
         );
 
         let functions: Vec<_> = functions
@@ -854,37 +1244,67 @@ impl WipImpl {
             })
             .collect();
 
+        // The bounds that make the references in the signatures of the
+        // functions and in the fields of the self type well-formed are implied
+        let mut implied = Vec::new();
+        reference_bounds(&self.ty.0, &mut implied);
+        for function in &functions {
+            let sig = &function.f.sig;
+            for ty in sig.inputs.iter().chain(iter::once(&sig.output)) {
+                reference_bounds(&ty.0, &mut implied);
+            }
+        }
+        for mut bound in implied {
+            bound.make_most_concrete(&mut concrete_maps_and_sets, &mut transitive_closure);
+            constraints.set.remove(&bound);
+            constraints.origins.map.remove(&bound);
+        }
+
+        let report = if options.report {
+            // The params of the impl and of its functions are printed in the
+            // report, the other params only exist during inference
+            let mut printed_params: Vec<_> = relevant_generic_params.iter().copied().collect();
+            for function in &functions {
+                printed_params.extend(&function.f.sig.generics.params);
+            }
+            Some(make_report(
+                &constraints,
+                &printed_params,
+                equalities,
+                outlives,
+                &mut concrete_maps_and_sets,
+                &mut transitive_closure,
+            ))
+        } else {
+            None
+        };
+
         // We remove the static lifetime since it is not a part of the paramater list
         relevant_generic_params.remove(&GenericParam::Lifetime(STATIC_LIFETIME));
 
-        CompleteImpl {
+        Ok(CompleteImpl {
             trait_ty: self.trait_ty,
             ty: self.ty,
             functions,
+            attrs: self.attrs.into_inner(),
             result: Some(TraitInferenceResult {
                 constraints,
                 generic_params: relevant_generic_params,
-                data_struct_args,
+                self_ty: Type(self_ty),
                 trait_args,
+                report,
             }),
-        }
+        })
     }
 
     fn get_original_generics(&mut self, constraints: &mut ConstraintSet) -> OriginalGenercs {
         let mut original_generic_params = Vec::new();
-        let mut original_data_struct_args = Vec::new();
         let mut original_trait_args = Vec::new();
 
-        // data structure generics
-        if let Type(TypeNode::DataStructure(data)) = &mut self.ty {
-            data.generics.constraints.drain(..).for_each(|constraint| {
-                constraints.insert(constraint);
-            });
-            data.generics.params.iter().for_each(|&param| {
-                original_generic_params.push(param);
-                original_data_struct_args.push(param);
-            })
-        };
+        // self type generics, which includes the generics of any data
+        // structure inside of the self type
+        self.ty.0.generic_params(&mut original_generic_params);
+        let original_self_ty = data_structures_to_paths(&self.ty.0, constraints);
 
         // trait generics
         if let Some((generics, path)) = self.trait_ty.as_ref().and_then(|trait_ty| {
@@ -906,34 +1326,30 @@ impl WipImpl {
 
         OriginalGenercs {
             original_generic_params,
-            original_data_struct_args,
+            original_self_ty,
             original_trait_args,
         }
     }
 }
 
-macro_rules! supertype_map_iterator {
-    ( $lifetime_map:expr, $lifetime:expr ) => {
-        $lifetime_map
-            .get(&$lifetime)
-            .iter()
-            .map(|&subtypes| subtypes.iter().cloned())
-            .flatten()
-    };
-}
-
 impl WipFunction {
     fn compute_trait_bounds(
         &self,
         constraints: &mut ConstraintSet,
         type_equality_sets: &mut TypeEqualitySets,
         subtypes: &mut LifetimeSubtypeMap,
+        consts: &mut ConstEqualities,
+        mut equalities: Option<&mut Vec<Equality>>,
     ) {
         use Receiver::*;
-        let mut supertype_map = SupertypeMap::new();
 
         INVOKES.with_borrow(|invokes| {
-            for invoke in invokes[self.invokes.start.0..self.invokes.end.unwrap().0].iter() {
+            let start = self.invokes.start.0;
+            for (i, invoke) in invokes[start..self.invokes.end.unwrap().0]
+                .iter()
+                .enumerate()
+            {
+                let invoke_ref = InvokeRef(start + i);
                 let parent = &invoke.function.parent;
                 let sig = &invoke.function.sig;
                 let args_iter = match sig.receiver {
@@ -946,106 +1362,279 @@ impl WipFunction {
                         let mut args_iter = invoke.args.iter();
                         let parent = parent.as_ref().unwrap();
                         let first_type = args_iter.next().unwrap().node().get_type();
-
-                        match reciever {
-                            SelfByValue => match parent.parent_kind {
-                                ParentKind::Trait => {
-                                    if let TypeNode::TypeParam(_) = &first_type.0 {
-                                        add_self_trait_bound(parent, first_type, constraints)
-                                    }
+                        let origin = Origin::Argument {
+                            invoke: invoke_ref,
+                            index: 0,
+                        };
+
+                        let supertype = match (reciever, parent.parent_kind) {
+                            (SelfByValue, ParentKind::Trait) => {
+                                enter_origin(origin, None, constraints, subtypes, &mut equalities);
+                                if first_type.0.is_generic() {
+                                    add_self_trait_bound(parent, first_type.clone(), constraints)
                                 }
-                                ParentKind::Impl => type_equality_sets.insert_as_subtype_or_equal(
-                                    first_type.0,
-                                    TypeNode::Path(parent.path.clone()),
-                                    constraints,
-                                    subtypes,
-                                    &mut supertype_map,
-                                ),
-                            },
-                            SelfByReference { is_mut, lifetime } => match parent.parent_kind {
-                                ParentKind::Trait => {
-                                    let first_type = first_type.dereference();
-                                    if let TypeNode::TypeParam(_) = &first_type.0 {
-                                        add_self_trait_bound(parent, first_type, constraints)
-                                    }
+                                None
+                            }
+                            (SelfByReference { .. }, ParentKind::Trait) => {
+                                enter_origin(origin, None, constraints, subtypes, &mut equalities);
+                                let first_type = first_type.dereference();
+                                if first_type.0.is_generic() {
+                                    add_self_trait_bound(parent, first_type, constraints)
                                 }
-                                ParentKind::Impl => type_equality_sets.insert_as_subtype_or_equal(
-                                    first_type.0,
-                                    TypeNode::Reference {
-                                        is_mut,
-                                        inner: Box::new(TypeNode::Path(parent.path.clone())),
-                                        lifetime: lifetime.0,
-                                    },
-                                    constraints,
-                                    subtypes,
-                                    &mut supertype_map,
-                                ),
-                            },
-                            NoSelf => unreachable!(),
+                                None
+                            }
+                            (SelfByValue, ParentKind::Impl) => {
+                                Some(TypeNode::Path(parent.path.clone()))
+                            }
+                            (SelfByReference { is_mut, lifetime }, ParentKind::Impl) => {
+                                Some(TypeNode::Reference {
+                                    is_mut,
+                                    inner: Box::new(TypeNode::Path(parent.path.clone())),
+                                    lifetime: lifetime.0,
+                                })
+                            }
+                            (NoSelf, _) => unreachable!(),
+                        };
+                        if let Some(supertype) = supertype {
+                            let origin = Origin::Argument {
+                                invoke: invoke_ref,
+                                index: 0,
+                            };
+                            enter_origin(
+                                origin,
+                                Some((&first_type.0, &supertype)),
+                                constraints,
+                                subtypes,
+                                &mut equalities,
+                            );
+                            type_equality_sets.insert_as_subtype_or_equal(
+                                first_type.0,
+                                supertype,
+                                constraints,
+                                subtypes,
+                                consts,
+                            )
                         }
                         args_iter
                     }
                 };
 
-                sig.inputs.iter().zip(args_iter).for_each(|(ty, val)| {
+                let offset = invoke.args.len() - sig.inputs.len();
+                for (index, (ty, val)) in sig.inputs.iter().zip(args_iter).enumerate() {
+                    let val_ty = val.node().get_type().0;
+                    let origin = Origin::Argument {
+                        invoke: invoke_ref,
+                        index: offset + index,
+                    };
+                    enter_origin(
+                        origin,
+                        Some((&val_ty, &ty.0)),
+                        constraints,
+                        subtypes,
+                        &mut equalities,
+                    );
                     type_equality_sets.insert_as_subtype_or_equal(
-                        val.node().get_type().0,
+                        val_ty,
                         ty.0.clone(),
                         constraints,
                         subtypes,
-                        &mut supertype_map,
+                        consts,
                     )
-                });
-
-                Self::add_constraints(&invoke.function, constraints, subtypes, &supertype_map);
-            }
-        });
+                }
 
-        self.set_last_value_subtype_to_output(
+                Self::add_constraints(
+                    invoke_ref,
+                    &invoke.function,
+                    constraints,
+                    subtypes,
+                    equalities.is_some(),
+                );
+                Self::add_reference_bounds(
+                    invoke_ref,
+                    &invoke.function,
+                    constraints,
+                    equalities.is_some(),
+                );
+            }
+        });
+
+        // The values of all arms of a match have the type of the first arm
+        VALUES.with_borrow(|values| {
+            for value in &values[self.values.start.0..self.values.end.unwrap().0] {
+                if let ValueNode::Match { arms, .. } = value {
+                    let ty = value.get_type();
+                    for (index, arm) in arms.iter().enumerate().skip(1) {
+                        let arm_ty = arm.ret.get_type().0;
+                        let origin = Origin::MatchArm {
+                            function: self.f.name.clone(),
+                            index,
+                        };
+                        enter_origin(
+                            origin,
+                            Some((&arm_ty, &ty.0)),
+                            constraints,
+                            subtypes,
+                            &mut equalities,
+                        );
+                        type_equality_sets.insert_as_subtype_or_equal(
+                            arm_ty,
+                            ty.0.clone(),
+                            constraints,
+                            subtypes,
+                            consts,
+                        )
+                    }
+                }
+            }
+        });
+
+        // The values of the fields of a constructed struct have the types of
+        // its fields
+        VALUES.with_borrow(|values| {
+            for value in &values[self.values.start.0..self.values.end.unwrap().0] {
+                if let ValueNode::DataStructure { ty, data } = value {
+                    let data = match (ty.data(), data) {
+                        (Data::Struct(types), Data::Struct(data)) => {
+                            types.fields().zip(data.fields())
+                        }
+                        _ => continue,
+                    };
+                    for (field_ty, field) in data {
+                        let val_ty = field.element.get_type().0;
+                        let origin = Origin::Field {
+                            function: self.f.name.clone(),
+                            accessor: field.accessor,
+                        };
+                        enter_origin(
+                            origin,
+                            Some((&val_ty, &field_ty.element.0)),
+                            constraints,
+                            subtypes,
+                            &mut equalities,
+                        );
+                        type_equality_sets.insert_as_subtype_or_equal(
+                            val_ty,
+                            field_ty.element.0,
+                            constraints,
+                            subtypes,
+                            consts,
+                        )
+                    }
+                }
+            }
+        });
+
+        self.set_last_value_subtype_to_output(
             constraints,
             type_equality_sets,
             subtypes,
-            &mut supertype_map,
+            consts,
+            equalities,
         );
     }
 
     fn constraint_iterator(f: &Function) -> impl Iterator<Item = &GenericConstraint> {
         f.parent
             .iter()
-            .map(|parent| parent.generics.constraints.iter())
-            .flatten()
+            .flat_map(|parent| parent.generics.constraints.iter())
             .chain(f.sig.generics.constraints.iter())
     }
 
     fn add_constraints(
+        invoke: InvokeRef,
         function: &Function,
         constraints: &mut ConstraintSet,
         subtypes: &mut LifetimeSubtypeMap,
-        supertype_map: &SupertypeMap,
+        report: bool,
+    ) {
+        Self::sized_params(function).for_each(|param| {
+            constraints.sized.insert(TypeNode::TypeParam(param));
+        });
+        Self::constraint_iterator(function).for_each(|constraint| {
+            if report {
+                let origin = Origin::Constraint {
+                    invoke,
+                    constraint: constraint.clone(),
+                };
+                constraints.origins.current = Some(origin.clone());
+                subtypes.origins.current = Some(origin);
+            }
+            Self::add_constraint(constraint, constraints, subtypes)
+        });
+    }
+
+    fn add_constraint(
+        constraint: &GenericConstraint,
+        constraints: &mut ConstraintSet,
+        subtypes: &mut LifetimeSubtypeMap,
     ) {
-        Self::constraint_iterator(&function).for_each(|constraint| match constraint {
+        match constraint {
             GenericConstraint::Lifetime(lifetime_def) => {
                 lifetime_def
                     .bounds
                     .iter()
                     .for_each(|&supertype| subtypes.insert(lifetime_def.lifetime, supertype));
-
-                // Make sure that the subtypes have the same lifetime
-                // constraints as the supertypes have in the signature.
-                supertype_map_iterator!(supertype_map, &lifetime_def.lifetime).for_each(|subtype| {
-                    lifetime_def.bounds.iter().for_each(|lifetime| {
-                        supertype_map_iterator!(supertype_map, lifetime).for_each(|supertype| {
-                            subtypes.insert(subtype, supertype);
-                        })
-                    })
-                })
             }
+            // A `?Sized` bound only keeps the type from being required to be
+            // `Sized`, so it is not added to the impl
             GenericConstraint::Type(pred_ty) => {
-                if !constraints.contains(constraint) {
-                    constraints.insert(constraint.clone());
+                let mut pred_ty = pred_ty.clone();
+                pred_ty
+                    .bounds
+                    .retain(|bound| !matches!(bound, TypeParamBound::Maybe(_)));
+                if !pred_ty.bounds.is_empty() {
+                    constraints.insert(GenericConstraint::Type(pred_ty));
                 }
             }
-        });
+        }
+    }
+
+    /// The references in the signature of a function must be well-formed, so
+    /// the referent of `&'a T` is bounded by `T: 'a`
+    fn add_reference_bounds(
+        invoke: InvokeRef,
+        function: &Function,
+        constraints: &mut ConstraintSet,
+        report: bool,
+    ) {
+        if report {
+            constraints.origins.current = Some(Origin::Reference { invoke });
+        }
+        let mut bounds = Vec::new();
+        for ty in function
+            .sig
+            .inputs
+            .iter()
+            .chain(iter::once(&function.sig.output))
+        {
+            reference_bounds(&ty.0, &mut bounds);
+        }
+        for bound in bounds {
+            constraints.insert(bound);
+        }
+    }
+
+    /// The type params of a function and its parent, that have no `?Sized`
+    /// bound
+    fn sized_params(f: &Function) -> impl Iterator<Item = TypeParam> + '_ {
+        let is_maybe_sized = move |param: TypeParam| {
+            Self::constraint_iterator(f).any(|constraint| match constraint {
+                GenericConstraint::Type(pred_ty) => {
+                    pred_ty.bounded_ty.0 == TypeNode::TypeParam(param)
+                        && pred_ty
+                            .bounds
+                            .iter()
+                            .any(|bound| matches!(bound, TypeParamBound::Maybe(_)))
+                }
+                GenericConstraint::Lifetime(_) => false,
+            })
+        };
+        f.parent
+            .iter()
+            .flat_map(|parent| parent.generics.params.iter())
+            .chain(f.sig.generics.params.iter())
+            .filter_map(|param| param.type_param())
+            .filter(move |&param| !is_maybe_sized(param))
     }
 
     fn set_last_value_subtype_to_output(
@@ -1053,19 +1642,31 @@ impl WipFunction {
         constraints: &mut ConstraintSet,
         type_equality_sets: &mut TypeEqualitySets,
         subtypes: &mut LifetimeSubtypeMap,
-        supertype_map: &mut SupertypeMap,
+        consts: &mut ConstEqualities,
+        mut equalities: Option<&mut Vec<Equality>>,
     ) {
         // The type of the outgoing value must be the same as the return value
         if self.values.end.unwrap().0 > self.values.start.0 {
             let return_value_type =
                 VALUES.with_borrow(|values| values[self.values.end.unwrap().0 - 1].get_type());
+            let output = self.with_self_ty(&self.f.sig.output).0;
+            let origin = Origin::Return {
+                function: self.f.name.clone(),
+            };
+            enter_origin(
+                origin,
+                Some((&return_value_type.0, &output)),
+                constraints,
+                subtypes,
+                &mut equalities,
+            );
 
             type_equality_sets.insert_as_subtype_or_equal(
                 return_value_type.0,
-                self.f.sig.output.0.clone(),
+                output,
                 constraints,
                 subtypes,
-                supertype_map,
+                consts,
             )
         }
     }
@@ -1093,7 +1694,8 @@ impl WipFunction {
                         .most_concrete_lifetime_map
                         .insert(*param, *param);
                 }
-                _ => unimplemented!("WipFunction::make_concrete_function: Const"),
+                // Const params of the function are resolved as they are used
+                GenericParam::Const(_) => {}
             }
         }
 
@@ -1125,23 +1727,583 @@ impl WipFunction {
             invokes: invokes.unwrap(),
             macros: macros.unwrap(),
             ret: self.ret,
+            attrs: self.attrs.into_inner(),
+            vis: self.vis.into_inner(),
         }
     }
 }
 
 fn add_self_trait_bound(parent: &Rc<Parent>, first_type: Type, constraints: &mut ConstraintSet) {
+    constraints.insert(self_trait_bound(parent, first_type));
+}
+
+/// The bound `Self: Trait<..>` of a trait function, with the generic params
+/// of the trait as arguments
+pub(crate) fn self_trait_bound(parent: &Parent, self_ty: Type) -> GenericConstraint {
     assert_eq!(parent.parent_kind, ParentKind::Trait);
 
     let mut path = parent.path.clone();
     path.path.last_mut().unwrap().args = params_to_args(&parent.generics.params);
-    constraints.insert(GenericConstraint::Type(PredicateType {
+    GenericConstraint::Type(PredicateType {
         lifetimes: Vec::new(),
-        bounded_ty: first_type,
+        bounded_ty: self_ty,
         bounds: vec![TypeParamBound::Trait(TraitBound {
             lifetimes: Vec::new(),
             path,
         })],
-    }));
+    })
+}
+
+/// Replace the data structures in a self type with paths that have the
+/// generic params of the data structure as arguments, so that the arguments
+/// can be made most concrete like those of any other path
+fn data_structures_to_paths(node: &TypeNode, constraints: &mut ConstraintSet) -> TypeNode {
+    match node {
+        TypeNode::DataStructure(data) => {
+            data.generics.constraints.iter().for_each(|constraint| {
+                constraints.insert(constraint.clone());
+            });
+            let mut path = Path::ident_to_path(data.name.clone());
+            path.path[0].args = params_to_args(&data.generics.params);
+            TypeNode::Path(path)
+        }
+        TypeNode::Tuple(types) => TypeNode::Tuple(
+            types
+                .iter()
+                .map(|ty| data_structures_to_paths(ty, constraints))
+                .collect(),
+        ),
+        TypeNode::Reference {
+            is_mut,
+            lifetime,
+            inner,
+        } => TypeNode::Reference {
+            is_mut: *is_mut,
+            lifetime: *lifetime,
+            inner: Box::new(data_structures_to_paths(inner, constraints)),
+        },
+        node => node.clone(),
+    }
+}
+
+/// Errors if the impl would only apply to some instantiations of the self
+/// type, because a generic param of the self type was inferred to be a
+/// more concrete type, or to be equal to another param
+fn check_not_specialized(
+    ty: &Type,
+    concrete_maps_and_sets: &mut ConcreteMapAndSets,
+    transitive_closure: &mut TransitiveClosure,
+) -> syn::Result<()> {
+    let mut params = Vec::new();
+    ty.0.generic_params(&mut params);
+    let mut names = BTreeMap::new();
+    let mut static_lifetimes = BTreeSet::new();
+    param_declarations(&ty.0, &mut names, &mut static_lifetimes);
+    let name = |param: &GenericParam| match names.get(param) {
+        Some(name) => name.clone(),
+        None => to_report_string(&param_to_arg(*param)),
+    };
+
+    let mut first_names = BTreeMap::new();
+    let mut specializations = Vec::new();
+    for param in &params {
+        let arg = match param_to_arg(*param) {
+            GenericArgument::Type(mut ty) => {
+                ty.0.make_most_concrete(concrete_maps_and_sets, transitive_closure);
+                GenericArgument::Type(ty)
+            }
+            GenericArgument::Lifetime(mut lifetime) => {
+                lifetime.make_most_concrete(transitive_closure);
+                GenericArgument::Lifetime(lifetime)
+            }
+            GenericArgument::Const(mut expr) => {
+                expr.make_most_concrete(concrete_maps_and_sets);
+                GenericArgument::Const(expr)
+            }
+            arg => arg,
+        };
+        let concrete_param = match &arg {
+            GenericArgument::Type(Type(TypeNode::TypeParam(type_param))) => {
+                Some(GenericParam::Type(*type_param))
+            }
+            // Declared like `'b: 'static`, so it is `'static` in every
+            // instantiation
+            GenericArgument::Lifetime(lifetime)
+                if *lifetime == STATIC_LIFETIME
+                    && param
+                        .lifetime()
+                        .is_some_and(|param| static_lifetimes.contains(&param)) =>
+            {
+                continue;
+            }
+            GenericArgument::Lifetime(lifetime) if *lifetime != STATIC_LIFETIME => {
+                Some(GenericParam::Lifetime(*lifetime))
+            }
+            GenericArgument::Const(Expr::Param(const_param)) => {
+                Some(GenericParam::Const(*const_param))
+            }
+            _ => None,
+        };
+        match concrete_param {
+            Some(concrete_param) => match first_names.entry(concrete_param) {
+                btree_map::Entry::Vacant(entry) => {
+                    entry.insert(name(param));
+                }
+                btree_map::Entry::Occupied(entry) => {
+                    specializations.push(format!("`{}` is `{}`", name(param), entry.get()));
+                }
+            },
+            None => {
+                specializations.push(format!("`{}` is `{}`", name(param), to_report_string(&arg),))
+            }
+        }
+    }
+
+    if specializations.is_empty() {
+        Ok(())
+    } else {
+        Err(syn::Error::new(
+            Span::call_site(),
+            format!(
+                "the impl for `{}` would only apply when {}",
+                ty.0.get_name(),
+                specializations.join(", "),
+            ),
+        ))
+    }
+}
+
+/// The names that the params of the data structures in a type were declared
+/// with, and the lifetime params that are declared to outlive `'static`
+fn param_declarations(
+    node: &TypeNode,
+    names: &mut BTreeMap<GenericParam, String>,
+    static_lifetimes: &mut BTreeSet<Lifetime>,
+) {
+    match node {
+        TypeNode::DataStructure(data) => {
+            for &param in &data.generics.params {
+                if let Some(name) = data.generics.env.name_of(param) {
+                    names.insert(param, name.to_owned());
+                }
+            }
+            for constraint in &data.generics.constraints {
+                if let GenericConstraint::Lifetime(def) = constraint {
+                    if def.bounds.contains(&STATIC_LIFETIME) {
+                        static_lifetimes.insert(def.lifetime);
+                    }
+                }
+            }
+        }
+        TypeNode::Tuple(types) => {
+            for ty in types {
+                param_declarations(ty, names, static_lifetimes);
+            }
+        }
+        TypeNode::Reference { inner, .. } => param_declarations(inner, names, static_lifetimes),
+        _ => {}
+    }
+}
+
+/// The types of the fields that are at least as visible as their data
+/// structure, like every field of a private struct, or the `pub` fields of a
+/// `pub` struct. The fields of an enum are as visible as the enum. Recursive
+/// fields, like `children: Vec<Tree<T>>` of `Tree<T>`, are left out, since a
+/// bound on their type would require the impl that is being generated.
+fn visible_field_types(node: &TypeNode, types: &mut Vec<TypeNode>) {
+    use syn::Visibility;
+    match node {
+        TypeNode::DataStructure(data) => {
+            let is_visible = |vis: &Visibility| match (vis, &data.vis) {
+                (_, Visibility::Inherited) | (Visibility::Public(_), _) => true,
+                (vis, data_vis) => vis == data_vis,
+            };
+            let is_recursive = |ty: &TypeNode| mentions_data_structure(ty, &data.name);
+            match &data.data {
+                Data::Struct(data) => types.extend(
+                    data.fields()
+                        .filter(|field| is_visible(&field.vis))
+                        .map(|field| field.element.0)
+                        .filter(|ty| !is_recursive(ty)),
+                ),
+                Data::Enum(data) => types.extend(
+                    data.variants()
+                        .flat_map(Variant::fields)
+                        .map(|field| field.element.0)
+                        .filter(|ty| !is_recursive(ty)),
+                ),
+            }
+        }
+        TypeNode::Tuple(elems) => {
+            for ty in elems {
+                visible_field_types(ty, types);
+            }
+        }
+        TypeNode::Reference { inner, .. } => visible_field_types(inner, types),
+        _ => {}
+    }
+}
+
+/// Whether a type mentions the data structure named `name`, or `Self`
+fn mentions_data_structure(node: &TypeNode, name: &Ident) -> bool {
+    let in_path = |path: &Path| {
+        path.path.iter().any(|segment| match &segment.args {
+            PathArguments::None => false,
+            PathArguments::AngleBracketed(args) => args.args.args.iter().any(|arg| match arg {
+                GenericArgument::Type(ty) => mentions_data_structure(&ty.0, name),
+                GenericArgument::Binding(binding) => mentions_data_structure(&binding.ty.0, name),
+                _ => false,
+            }),
+            PathArguments::Parenthesized(args) => args
+                .inputs
+                .iter()
+                .chain(&args.output)
+                .any(|ty| mentions_data_structure(&ty.0, name)),
+        })
+    };
+    match node {
+        TypeNode::Path(path) => {
+            let is_self = !path.global
+                && path.path.len() == 1
+                && (path.path[0].ident == *name || path.path[0].ident.to_string() == "Self");
+            is_self || in_path(path)
+        }
+        TypeNode::Tuple(types) => types.iter().any(|ty| mentions_data_structure(ty, name)),
+        TypeNode::Reference { inner, .. } | TypeNode::Dereference(inner) => {
+            mentions_data_structure(inner, name)
+        }
+        TypeNode::Array { elem, .. } => mentions_data_structure(elem, name),
+        TypeNode::TraitObject(bounds) => bounds.iter().any(|bound| match bound {
+            TypeParamBound::Trait(bound) => in_path(&bound.path),
+            TypeParamBound::Lifetime(_) | TypeParamBound::Maybe(_) => false,
+        }),
+        TypeNode::Projection {
+            self_ty,
+            trait_path,
+            ..
+        } => mentions_data_structure(self_ty, name) || in_path(trait_path),
+        TypeNode::DataStructure(data) => data.name == *name,
+        TypeNode::Infer | TypeNode::PrimitiveStr | TypeNode::TypeParam(_) => false,
+    }
+}
+
+/// The bounds `T: 'a` that make the references `&'a T` in a type well-formed.
+/// The fields of a data structure are included, since its declaration implies
+/// the bounds for them.
+fn reference_bounds(node: &TypeNode, bounds: &mut Vec<GenericConstraint>) {
+    match node {
+        TypeNode::Reference {
+            lifetime, inner, ..
+        } => {
+            if let Some(lifetime) = lifetime {
+                bounds.push(GenericConstraint::Type(PredicateType {
+                    lifetimes: Vec::new(),
+                    bounded_ty: Type((**inner).clone()),
+                    bounds: vec![TypeParamBound::Lifetime(*lifetime)],
+                }));
+            }
+            reference_bounds(inner, bounds);
+        }
+        TypeNode::Tuple(types) => {
+            for ty in types {
+                reference_bounds(ty, bounds);
+            }
+        }
+        TypeNode::Array { elem, .. } => reference_bounds(elem, bounds),
+        TypeNode::Path(path) => {
+            for segment in &path.path {
+                if let PathArguments::AngleBracketed(args) = &segment.args {
+                    for arg in &args.args.args {
+                        if let GenericArgument::Type(ty) = arg {
+                            reference_bounds(&ty.0, bounds);
+                        }
+                    }
+                }
+            }
+        }
+        TypeNode::DataStructure(data) => {
+            let fields: Vec<_> = match &data.data {
+                Data::Struct(data) => data.fields().collect(),
+                Data::Enum(data) => data.variants().flat_map(Variant::fields).collect(),
+            };
+            for field in fields {
+                reference_bounds(&field.element.0, bounds);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Record that the facts inserted next come from `origin`, and that the types
+/// in `equality` are inserted as equal, if a report is made
+fn enter_origin(
+    origin: Origin,
+    equality: Option<(&TypeNode, &TypeNode)>,
+    constraints: &mut ConstraintSet,
+    subtypes: &mut LifetimeSubtypeMap,
+    equalities: &mut Option<&mut Vec<Equality>>,
+) {
+    if let Some(equalities) = equalities {
+        if let Some((subtype, supertype)) = equality {
+            equalities.push(Equality {
+                origin: origin.clone(),
+                subtype: subtype.clone(),
+                supertype: supertype.clone(),
+            });
+        }
+        constraints.origins.current = Some(origin.clone());
+        subtypes.origins.current = Some(origin);
+    }
+}
+
+/// Describe the where clause predicates, type equalities and lifetime
+/// outlives facts of an impl, and where they came from
+fn make_report(
+    constraints: &ConstraintSet,
+    printed_params: &[GenericParam],
+    equalities: Vec<Equality>,
+    outlives: FxHashMap<(Lifetime, Lifetime), Vec<Origin>>,
+    concrete_maps_and_sets: &mut ConcreteMapAndSets,
+    transitive_closure: &mut TransitiveClosure,
+) -> ImplReport {
+    // Only the facts that end up in the where clause, or that make a lifetime
+    // 'static are of interest
+    let lifetime_bounds: Vec<_> = constraints
+        .set
+        .iter()
+        .filter_map(|constraint| match constraint {
+            GenericConstraint::Lifetime(lifetime_def) => Some(lifetime_def),
+            GenericConstraint::Type(_) => None,
+        })
+        .flat_map(|lifetime_def| {
+            let lifetime = lifetime_def.lifetime;
+            lifetime_def
+                .bounds
+                .iter()
+                .map(move |&bound| (lifetime, bound))
+        })
+        .collect();
+    let mut outlives: Vec<_> = outlives
+        .into_iter()
+        .filter_map(|((subtype, supertype), origins)| {
+            let mut concrete = (subtype, supertype);
+            transitive_closure.make_most_concrete(&mut concrete.0);
+            transitive_closure.make_most_concrete(&mut concrete.1);
+            let is_static = supertype == STATIC_LIFETIME
+                || (concrete.0 == STATIC_LIFETIME && subtype != STATIC_LIFETIME);
+            if is_static || lifetime_bounds.contains(&concrete) {
+                Some(((subtype, supertype), concrete, origins))
+            } else {
+                None
+            }
+        })
+        .collect();
+    outlives.sort_by_key(|(fact, ..)| *fact);
+
+    let describe = |origins: &[Origin],
+                    concrete_maps_and_sets: &mut ConcreteMapAndSets,
+                    transitive_closure: &mut TransitiveClosure| {
+        let mut descriptions = Vec::new();
+        for origin in origins {
+            let description = describe_origin(origin, concrete_maps_and_sets, transitive_closure);
+            if !descriptions.contains(&description) {
+                descriptions.push(description);
+            }
+        }
+        descriptions
+    };
+
+    let mut predicates: Vec<_> = constraints
+        .set
+        .iter()
+        .map(|constraint| {
+            let origins = match (constraints.origins.map.get(constraint), constraint) {
+                (Some(origins), _) => describe(origins, concrete_maps_and_sets, transitive_closure),
+                // Lifetime bounds come from the transitive closure of the
+                // outlives facts
+                (None, GenericConstraint::Lifetime(lifetime_def)) => {
+                    let origins: Vec<_> = outlives
+                        .iter()
+                        .filter(|(_, (subtype, supertype), _)| {
+                            *subtype == lifetime_def.lifetime
+                                && lifetime_def.bounds.contains(supertype)
+                        })
+                        .flat_map(|(.., origins)| origins.iter().cloned())
+                        .collect();
+                    describe(&origins, concrete_maps_and_sets, transitive_closure)
+                }
+                (None, GenericConstraint::Type(_)) => Vec::new(),
+            };
+            Fact {
+                fact: format!("`{}`", to_report_string(constraint)),
+                origins,
+            }
+        })
+        .collect();
+    predicates.sort_by(|fact1, fact2| fact1.fact.cmp(&fact2.fact));
+
+    let mut equality_facts: Vec<Fact> = Vec::new();
+    for equality in equalities {
+        // Print the types with the inferred types in place of the params
+        // that only exist during inference, and `_` in place of the params
+        // that remain
+        let mut subtype = equality.subtype;
+        let mut supertype = equality.supertype;
+        subtype.make_most_concrete(concrete_maps_and_sets, transitive_closure);
+        supertype.make_most_concrete(concrete_maps_and_sets, transitive_closure);
+        let mut erased = Vec::new();
+        subtype.generic_params(&mut erased);
+        supertype.generic_params(&mut erased);
+        erased.retain(|param| !printed_params.contains(param));
+        let subtype = to_report_string_erased(&subtype, &erased);
+        let supertype = to_report_string_erased(&supertype, &erased);
+        if subtype == supertype {
+            continue;
+        }
+        let fact = format!("`{}` = `{}`", subtype, supertype);
+        let origins = describe(
+            &[equality.origin],
+            concrete_maps_and_sets,
+            transitive_closure,
+        );
+        match equality_facts.iter_mut().find(|known| known.fact == fact) {
+            Some(known) => known.origins.extend(origins),
+            None => equality_facts.push(Fact { fact, origins }),
+        }
+    }
+
+    let outlives = outlives
+        .into_iter()
+        .map(|((lifetime, bound), _, origins)| {
+            let lifetime_def = LifetimeDef {
+                lifetime,
+                bounds: vec![bound],
+            };
+            Fact {
+                fact: format!("`{}`", to_report_string(&lifetime_def)),
+                origins: describe(&origins, concrete_maps_and_sets, transitive_closure),
+            }
+        })
+        .collect();
+
+    ImplReport {
+        predicates,
+        equalities: equality_facts,
+        outlives,
+        header: String::new(),
+    }
+}
+
+fn describe_origin(
+    origin: &Origin,
+    concrete_maps_and_sets: &mut ConcreteMapAndSets,
+    transitive_closure: &mut TransitiveClosure,
+) -> String {
+    match origin {
+        Origin::Declared => "a bound declared on the type or the trait".to_owned(),
+        Origin::Constraint { invoke, constraint } => {
+            let mut concrete = constraint.clone();
+            concrete.make_most_concrete(concrete_maps_and_sets, transitive_closure);
+            let concrete = to_report_string(&concrete);
+            INVOKES.with_borrow(|invokes| {
+                let invoke = &invokes[invoke.0];
+                let indexes: Vec<_> = constraint_arguments(invoke, constraint)
+                    .iter()
+                    .map(usize::to_string)
+                    .collect();
+                let through = match indexes.len() {
+                    0 => String::new(),
+                    1 => format!(" through argument {}", indexes[0]),
+                    _ => format!(" through arguments {}", indexes.join(", ")),
+                };
+                format!(
+                    "the bound `{}` of `{}`{}",
+                    concrete,
+                    invoke_path(invoke),
+                    through
+                )
+            })
+        }
+        Origin::Argument { invoke, index } => INVOKES.with_borrow(|invokes| {
+            format!(
+                "argument {} of `{}`",
+                index,
+                invoke_path(&invokes[invoke.0])
+            )
+        }),
+        Origin::MatchArm { function, index } => {
+            format!("arm {} of a match in `{}`", index, function)
+        }
+        Origin::Field { function, accessor } => {
+            format!(
+                "field `{}` of a struct constructed in `{}`",
+                accessor, function
+            )
+        }
+        Origin::Return { function } => format!("the value returned from `{}`", function),
+        Origin::Supertrait => "a supertrait of the implemented trait".to_owned(),
+        Origin::Reference { invoke } => INVOKES.with_borrow(|invokes| {
+            format!(
+                "a reference in the signature of `{}`",
+                invoke_path(&invokes[invoke.0])
+            )
+        }),
+    }
+}
+
+/// The indexes of the arguments of an invoke with types that mention the
+/// params of a constraint. The receiver is argument 0.
+fn constraint_arguments(invoke: &Invoke, constraint: &GenericConstraint) -> Vec<usize> {
+    let mut params = Vec::new();
+    match constraint {
+        GenericConstraint::Type(pred_ty) => pred_ty.bounded_ty.0.generic_params(&mut params),
+        GenericConstraint::Lifetime(lifetime_def) => params.extend(
+            iter::once(lifetime_def.lifetime)
+                .chain(lifetime_def.bounds.iter().copied())
+                .map(GenericParam::Lifetime),
+        ),
+    }
+
+    let function = &invoke.function;
+    let mut indexes = Vec::new();
+    let mut receiver_params = Vec::new();
+    match function.sig.receiver {
+        Receiver::NoSelf => {}
+        Receiver::SelfByValue => receiver_params.extend(parent_params(function)),
+        Receiver::SelfByReference { lifetime, .. } => {
+            receiver_params.extend(parent_params(function));
+            receiver_params.extend(lifetime.0.map(GenericParam::Lifetime));
+        }
+    }
+    if receiver_params.iter().any(|param| params.contains(param)) {
+        indexes.push(0);
+    }
+
+    let offset = invoke.args.len() - function.sig.inputs.len();
+    for (index, input) in function.sig.inputs.iter().enumerate() {
+        let mut input_params = Vec::new();
+        input.0.generic_params(&mut input_params);
+        if input_params.iter().any(|param| params.contains(param)) {
+            indexes.push(offset + index);
+        }
+    }
+    indexes
+}
+
+fn parent_params(function: &Function) -> Vec<GenericParam> {
+    function
+        .parent
+        .iter()
+        .flat_map(|parent| parent.generics.params.iter().copied())
+        .collect()
+}
+
+fn param_to_arg(param: GenericParam) -> GenericArgument {
+    match param {
+        GenericParam::Type(ty) => GenericArgument::Type(Type(TypeNode::TypeParam(ty))),
+        GenericParam::Lifetime(lifetime) => GenericArgument::Lifetime(lifetime),
+        GenericParam::Const(param) => GenericArgument::Const(Expr::Param(param)),
+    }
 }
 
 fn params_to_args(params: &[GenericParam]) -> PathArguments {
@@ -1150,18 +2312,19 @@ fn params_to_args(params: &[GenericParam]) -> PathArguments {
     }
     PathArguments::AngleBracketed(AngleBracketedGenericArguments {
         args: GenericArguments {
-            args: params
-                .iter()
-                .map(|param| match param {
-                    GenericParam::Type(ty) => GenericArgument::Type(Type(TypeNode::TypeParam(*ty))),
-                    GenericParam::Lifetime(lifetime) => GenericArgument::Lifetime(*lifetime),
-                    GenericParam::Const(_) => unimplemented!(),
-                })
-                .collect(),
+            args: params.iter().copied().map(param_to_arg).collect(),
         },
     })
 }
 
+fn generic_args_to_path_args(args: &GenericArguments) -> PathArguments {
+    if args.args.is_empty() {
+        PathArguments::None
+    } else {
+        PathArguments::AngleBracketed(AngleBracketedGenericArguments { args: args.clone() })
+    }
+}
+
 /// Find generic parameters of the most concrete types for the generic
 /// parameters related to the `DataStructure` or trait that is being
 /// implemented. Then return a set containing all the inner generic type
@@ -1172,6 +2335,7 @@ fn params_to_args(params: &[GenericParam]) -> PathArguments {
 fn get_relevant_generic_params(
     original_generic_params: &[GenericParam],
     type_equality_sets: TypeEqualitySets,
+    const_equalities: ConstEqualities,
     transitive_closure: &mut TransitiveClosure,
 ) -> (BTreeSet<GenericParam>, ConcreteMapAndSets) {
     use TypeNode::*;
@@ -1181,9 +2345,10 @@ fn get_relevant_generic_params(
     let mut concrete_maps_and_sets = ConcreteMapAndSets {
         most_concrete_type_map,
         type_equality_sets,
+        const_equalities,
     };
 
-    for param in original_generic_params.iter() {
+    for param in original_generic_params {
         match param {
             GenericParam::Type(type_param) => {
                 let type_param = param.type_param().unwrap();
@@ -1210,7 +2375,14 @@ fn get_relevant_generic_params(
                 relevant_generic_params.insert(GenericParam::Lifetime(lifetime));
             }
 
-            GenericParam::Const(_) => unimplemented!(),
+            GenericParam::Const(const_param) => {
+                let expr = concrete_maps_and_sets
+                    .const_equalities
+                    .resolve(&Expr::Param(*const_param));
+                if let Expr::Param(const_param) = expr {
+                    relevant_generic_params.insert(GenericParam::Const(const_param));
+                }
+            }
         }
     }
 
@@ -1235,7 +2407,11 @@ fn get_args(
                     lifetime.make_most_concrete(transitive_closure);
                     GenericArgument::Lifetime(lifetime)
                 }
-                GenericParam::Const(_) => unimplemented!(),
+                GenericParam::Const(const_param) => GenericArgument::Const(
+                    concrete_maps_and_sets
+                        .const_equalities
+                        .resolve(&Expr::Param(const_param)),
+                ),
             })
             .collect(),
     }
@@ -1270,20 +2446,44 @@ impl GenericConstraint {
         transitive_closure: &mut TransitiveClosure,
     ) -> bool {
         self.make_most_concrete(concrete_maps_and_sets, transitive_closure);
-        self.is_relevant(
-            &concrete_maps_and_sets.type_equality_sets,
-            &relevant_generic_params,
-        )
+        self.is_relevant(relevant_generic_params)
     }
 
-    fn is_relevant(
+    /// A bound on the type of a visible field, like `Vec<T>: Debug`, or on a
+    /// reference to it. The type must mention a relevant param, and no
+    /// params that are not relevant.
+    fn is_field_type_bound(
         &self,
-        type_equality_sets: &TypeEqualitySets,
+        visible_field_types: &[TypeNode],
         relevant_generic_params: &BTreeSet<GenericParam>,
     ) -> bool {
+        let pred_ty = match self {
+            GenericConstraint::Type(pred_ty) => pred_ty,
+            GenericConstraint::Lifetime(_) => return false,
+        };
+
+        let mut field_ty = &pred_ty.bounded_ty.0;
+        while let TypeNode::Reference { inner, .. } = field_ty {
+            field_ty = inner;
+        }
+        let mut params = Vec::new();
+        pred_ty.bounded_ty.0.generic_params(&mut params);
+
+        visible_field_types.contains(field_ty)
+            && params.iter().any(|param| param.type_param().is_some())
+            && params
+                .iter()
+                .all(|param| relevant_generic_params.contains(param))
+            && pred_ty
+                .bounds
+                .iter()
+                .all(|bound| bound.is_relevant_for_constraint(relevant_generic_params))
+    }
+
+    fn is_relevant(&self, relevant_generic_params: &BTreeSet<GenericParam>) -> bool {
         match self {
             GenericConstraint::Type(pred_ty) => {
-                pred_ty.is_relevant_for_constraint(type_equality_sets, relevant_generic_params)
+                pred_ty.is_relevant_for_constraint(relevant_generic_params)
             }
 
             GenericConstraint::Lifetime(lifetime) => {
@@ -1309,17 +2509,16 @@ impl GenericConstraint {
 }
 
 impl PredicateType {
-    fn is_relevant_for_constraint(
-        &self,
-        type_equality_sets: &TypeEqualitySets,
-        relevant_generic_params: &BTreeSet<GenericParam>,
-    ) -> bool {
+    fn is_relevant_for_constraint(&self, relevant_generic_params: &BTreeSet<GenericParam>) -> bool {
+        let relevant_generic_params =
+            &with_bound_lifetimes(relevant_generic_params, &self.lifetimes);
         self.bounded_ty
             .0
-            .is_relevant_for_constraint(&type_equality_sets, &relevant_generic_params)
-            && self.bounds.iter().all(|bound| {
-                bound.is_relevant_for_constraint(&type_equality_sets, &relevant_generic_params)
-            })
+            .is_relevant_for_constraint(relevant_generic_params)
+            && self
+                .bounds
+                .iter()
+                .all(|bound| bound.is_relevant_for_constraint(relevant_generic_params))
     }
 
     fn make_most_concrete(
@@ -1341,12 +2540,29 @@ impl PredicateType {
     }
 }
 
+/// The lifetimes of a `for<..>` binder are relevant in the bound, since they
+/// are declared by the binder itself
+fn with_bound_lifetimes<'a>(
+    relevant_generic_params: &'a BTreeSet<GenericParam>,
+    lifetimes: &[Lifetime],
+) -> Cow<'a, BTreeSet<GenericParam>> {
+    if lifetimes.is_empty() {
+        Cow::Borrowed(relevant_generic_params)
+    } else {
+        let mut relevant_generic_params = relevant_generic_params.clone();
+        relevant_generic_params.extend(lifetimes.iter().copied().map(GenericParam::Lifetime));
+        Cow::Owned(relevant_generic_params)
+    }
+}
+
 impl TypeNode {
-    fn is_relevant_for_constraint(
-        &self,
-        type_equality_sets: &TypeEqualitySets,
-        relevant_generic_params: &BTreeSet<GenericParam>,
-    ) -> bool {
+    /// Whether the type is only known through its bounds, like a type param
+    /// or an associated type
+    fn is_generic(&self) -> bool {
+        matches!(self, TypeNode::TypeParam(_) | TypeNode::Projection { .. })
+    }
+
+    fn is_relevant_for_constraint(&self, relevant_generic_params: &BTreeSet<GenericParam>) -> bool {
         use TypeNode::*;
         match self {
             TypeParam(type_param) => {
@@ -1354,7 +2570,19 @@ impl TypeNode {
             }
             Reference {
                 lifetime, inner, ..
-            } => inner.is_relevant_for_constraint(type_equality_sets, relevant_generic_params),
+            } => inner.is_relevant_for_constraint(relevant_generic_params),
+            Array { elem, len } => {
+                elem.is_relevant_for_constraint(relevant_generic_params)
+                    && len.is_relevant_for_constraint(relevant_generic_params)
+            }
+            Projection {
+                self_ty,
+                trait_path,
+                ..
+            } => {
+                self_ty.is_relevant_for_constraint(relevant_generic_params)
+                    && trait_path.is_relevant_for_constraint(relevant_generic_params)
+            }
 
             _ => false,
         }
@@ -1387,7 +2615,6 @@ impl TypeNode {
                 node.make_most_concrete_inner(concrete_maps_and_sets, transitive_closure);
                 node
             }
-            (PrimitiveStr, _) | (_, PrimitiveStr) => PrimitiveStr,
             (Path(path1), Path(path2)) => crate::Path::make_most_concrete_from_pair(
                 path1,
                 path2,
@@ -1398,10 +2625,11 @@ impl TypeNode {
                 path.make_most_concrete_inner(concrete_maps_and_sets, transitive_closure);
                 Path(path)
             }
+            (PrimitiveStr, _) | (_, PrimitiveStr) => PrimitiveStr,
             (Tuple(types1), Tuple(types2)) if types1.len() == types2.len() => Tuple(
                 types1
                     .into_iter()
-                    .zip(types2.into_iter())
+                    .zip(types2)
                     .map(|(ty1, ty2)| {
                         TypeNode::make_most_concrete_from_pair(
                             ty1,
@@ -1437,6 +2665,24 @@ impl TypeNode {
                     .or(lifetime1)
                     .or(lifetime2),
             },
+            (
+                Array {
+                    elem: elem1,
+                    len: len1,
+                },
+                Array {
+                    elem: elem2,
+                    len: len2,
+                },
+            ) => Array {
+                elem: Box::new(TypeNode::make_most_concrete_from_pair(
+                    *elem1,
+                    *elem2,
+                    concrete_maps_and_sets,
+                    transitive_closure,
+                )),
+                len: Expr::make_most_concrete_from_pair(len1, len2, concrete_maps_and_sets),
+            },
             (TraitObject(_), mut node) | (mut node, TraitObject(_)) => {
                 node.make_most_concrete_inner(concrete_maps_and_sets, transitive_closure);
                 node
@@ -1446,6 +2692,12 @@ impl TypeNode {
                 node.make_most_concrete_inner(concrete_maps_and_sets, transitive_closure);
                 node
             }
+            // A projection is only known to be some type, so any other type
+            // is more concrete
+            (Projection { .. }, mut node) | (mut node, Projection { .. }) => {
+                node.make_most_concrete_inner(concrete_maps_and_sets, transitive_closure);
+                node
+            }
             (node1, node2) => panic!(
                 "TypeNode: make_most_concrete_pair: incompatible types \n{:#?}\nand\n{:#?}",
                 node1, node2
@@ -1472,7 +2724,19 @@ impl TypeNode {
                     lifetime.make_most_concrete(transitive_closure);
                 }
             }
+            Array { elem, len } => {
+                elem.make_most_concrete(concrete_maps_and_sets, transitive_closure);
+                len.make_most_concrete(concrete_maps_and_sets);
+            }
             Path(path) => path.make_most_concrete_inner(concrete_maps_and_sets, transitive_closure),
+            Projection {
+                self_ty,
+                trait_path,
+                ..
+            } => {
+                self_ty.make_most_concrete(concrete_maps_and_sets, transitive_closure);
+                trait_path.make_most_concrete_inner(concrete_maps_and_sets, transitive_closure);
+            }
             node => {}
         }
     }
@@ -1485,24 +2749,71 @@ impl TypeNode {
         use TypeNode::*;
         match self {
             Tuple(types) => {
-                for ty in types.iter() {
+                for ty in types {
                     ty.inner_params(type_equality_sets, relevant_generic_params)
                 }
             }
             Reference { inner, .. } => {
                 inner.inner_params(type_equality_sets, relevant_generic_params)
             }
+            Array { elem, len } => {
+                elem.inner_params(type_equality_sets, relevant_generic_params);
+                len.inner_params(relevant_generic_params);
+            }
             Path(path) => {
                 path.inner_params(type_equality_sets, relevant_generic_params);
             }
             TypeParam(type_param) => {
                 relevant_generic_params.insert(GenericParam::Type(*type_param));
             }
+            Projection {
+                self_ty,
+                trait_path,
+                ..
+            } => {
+                self_ty.inner_params(type_equality_sets, relevant_generic_params);
+                trait_path.inner_params(type_equality_sets, relevant_generic_params);
+            }
             _ => {}
         }
     }
 }
 
+impl Expr {
+    fn make_most_concrete(&mut self, concrete_maps_and_sets: &ConcreteMapAndSets) {
+        *self = concrete_maps_and_sets.const_equalities.resolve(self);
+    }
+
+    /// Prefers a value over a param, and the smallest of two params
+    fn make_most_concrete_from_pair(
+        expr1: Expr,
+        expr2: Expr,
+        concrete_maps_and_sets: &ConcreteMapAndSets,
+    ) -> Self {
+        let const_equalities = &concrete_maps_and_sets.const_equalities;
+        match (
+            const_equalities.resolve(&expr1),
+            const_equalities.resolve(&expr2),
+        ) {
+            (Expr::Param(param1), Expr::Param(param2)) => Expr::Param(param1.min(param2)),
+            (Expr::Value(value), _) | (_, Expr::Value(value)) => Expr::Value(value),
+        }
+    }
+
+    fn inner_params(&self, relevant_generic_params: &mut BTreeSet<GenericParam>) {
+        if let Expr::Param(param) = self {
+            relevant_generic_params.insert(GenericParam::Const(*param));
+        }
+    }
+
+    fn is_relevant_for_constraint(&self, relevant_generic_params: &BTreeSet<GenericParam>) -> bool {
+        match self {
+            Expr::Param(param) => relevant_generic_params.contains(&GenericParam::Const(*param)),
+            Expr::Value(_) => true,
+        }
+    }
+}
+
 impl Lifetime {
     fn make_most_concrete(&mut self, transitive_closure: &mut TransitiveClosure) {
         transitive_closure.make_most_concrete(self);
@@ -1541,19 +2852,17 @@ impl LifetimeDef {
 }
 
 impl TypeParamBound {
-    fn is_relevant_for_constraint(
-        &self,
-        type_equality_sets: &TypeEqualitySets,
-        relevant_generic_params: &BTreeSet<GenericParam>,
-    ) -> bool {
+    fn is_relevant_for_constraint(&self, relevant_generic_params: &BTreeSet<GenericParam>) -> bool {
         match self {
-            TypeParamBound::Trait(bound) => bound
-                .path
-                .is_relevant_for_constraint(type_equality_sets, relevant_generic_params),
+            TypeParamBound::Trait(bound) => bound.path.is_relevant_for_constraint(
+                &with_bound_lifetimes(relevant_generic_params, &bound.lifetimes),
+            ),
 
             TypeParamBound::Lifetime(lifetime) => {
                 lifetime.is_relevant_for_constraint(relevant_generic_params)
             }
+
+            TypeParamBound::Maybe(_) => true,
         }
     }
 
@@ -1570,33 +2879,46 @@ impl TypeParamBound {
             }
 
             TypeParamBound::Lifetime(lifetime) => lifetime.make_most_concrete(transitive_closure),
+
+            TypeParamBound::Maybe(_) => {}
         }
     }
 }
 
 impl Path {
-    fn is_relevant_for_constraint(
-        &self,
-        type_equality_sets: &TypeEqualitySets,
-        relevant_generic_params: &BTreeSet<GenericParam>,
-    ) -> bool {
+    fn is_relevant_for_constraint(&self, relevant_generic_params: &BTreeSet<GenericParam>) -> bool {
         self.path.iter().all(|segment| match &segment.args {
             PathArguments::None => true,
 
             PathArguments::AngleBracketed(args) => args.args.args.iter().all(|arg| match arg {
                 GenericArgument::Type(ty) => {
-                    ty.0.is_relevant_for_constraint(type_equality_sets, relevant_generic_params)
+                    ty.0.is_relevant_for_constraint(relevant_generic_params)
                 }
 
                 GenericArgument::Lifetime(lifetime) => {
                     lifetime.is_relevant_for_constraint(relevant_generic_params)
                 }
 
+                GenericArgument::Const(expr) => {
+                    expr.is_relevant_for_constraint(relevant_generic_params)
+                }
+
                 _ => unimplemented!("is_relevant_for_constraint: PathArguments::AngleBracketed"),
             }),
 
-            PathArguments::Parenthesized(_) => {
-                unimplemented!("is_relevant_for_constraint: PathArguments::Parenthesized")
+            // The inputs and output of a function trait, like the `&'a str` in
+            // `for<'a> Fn(&'a str)`, may be any type with relevant params
+            PathArguments::Parenthesized(args) => {
+                args.inputs.iter().chain(&args.output).all(|ty| {
+                    let mut params = Vec::new();
+                    ty.0.generic_params(&mut params);
+                    params.iter().all(|param| match param {
+                        GenericParam::Lifetime(lifetime) => {
+                            lifetime.is_relevant_for_constraint(relevant_generic_params)
+                        }
+                        param => relevant_generic_params.contains(param),
+                    })
+                })
             }
         })
     }
@@ -1618,12 +2940,17 @@ impl Path {
                             GenericArgument::Lifetime(lifetime) => {
                                 relevant_generic_params.insert(GenericParam::Lifetime(*lifetime));
                             }
+                            GenericArgument::Const(expr) => {
+                                expr.inner_params(relevant_generic_params)
+                            }
                             _ => unimplemented!(),
                         }
                     }
                 }
-                PathArguments::Parenthesized(_) => {
-                    unimplemented!("Path::inner_param_set_ref: Parenthesized")
+                PathArguments::Parenthesized(args) => {
+                    for ty in args.inputs.iter().chain(&args.output) {
+                        ty.0.inner_params(type_equality_sets, relevant_generic_params)
+                    }
                 }
             }
         }
@@ -1647,11 +2974,17 @@ impl Path {
                     GenericArgument::Lifetime(lifetime) => {
                         lifetime.make_most_concrete(transitive_closure)
                     }
+                    GenericArgument::Const(expr) => expr.make_most_concrete(concrete_maps_and_sets),
                     _ => unimplemented!(),
                 });
             }
-            PathArguments::Parenthesized(_) => {
-                unimplemented!("Path::make_most_concrete_inner: Parenthesized")
+            PathArguments::Parenthesized(args) => {
+                args.inputs
+                    .iter_mut()
+                    .chain(&mut args.output)
+                    .for_each(|ty| {
+                        ty.0.make_most_concrete(concrete_maps_and_sets, transitive_closure)
+                    });
             }
         };
     }
@@ -1733,6 +3066,13 @@ impl Path {
                                     GenericArgument::Lifetime(lifetime1),
                                     GenericArgument::Lifetime(lifetime2),
                                 ) => GenericArgument::Lifetime((*lifetime1).min(*lifetime2)),
+                                (GenericArgument::Const(expr1), GenericArgument::Const(expr2)) => {
+                                    GenericArgument::Const(Expr::make_most_concrete_from_pair(
+                                        expr1.clone(),
+                                        expr2.clone(),
+                                        concrete_maps_and_sets,
+                                    ))
+                                }
                                 _ => unimplemented!(
                                     "Path::make_most_concrete_from_pair: GenericArgument"
                                 ),
@@ -1749,8 +3089,39 @@ impl Path {
                     }
                 }
             }
-            (PathArguments::Parenthesized(args1), PathArguments::Parenthesized(args2)) => {
-                unimplemented!("Path::make_most_concrete_from_pair: Parenthesized")
+            (PathArguments::Parenthesized(args1), PathArguments::Parenthesized(args2))
+                if args1.inputs.len() == args2.inputs.len() =>
+            {
+                // Assume we are dealing with the same function trait
+                let inputs = args1
+                    .inputs
+                    .iter()
+                    .zip(&args2.inputs)
+                    .map(|(ty1, ty2)| {
+                        Type(TypeNode::make_most_concrete_from_pair(
+                            ty1.0.clone(),
+                            ty2.0.clone(),
+                            concrete_maps_and_sets,
+                            transitive_closure,
+                        ))
+                    })
+                    .collect();
+                let output = match (args1.output.take(), args2.output.take()) {
+                    (Some(ty1), Some(ty2)) => Some(Type(TypeNode::make_most_concrete_from_pair(
+                        ty1.0,
+                        ty2.0,
+                        concrete_maps_and_sets,
+                        transitive_closure,
+                    ))),
+                    (Some(mut ty), None) | (None, Some(mut ty)) => {
+                        ty.0.make_most_concrete(concrete_maps_and_sets, transitive_closure);
+                        Some(ty)
+                    }
+                    (None, None) => None,
+                };
+                args1.inputs = inputs;
+                args1.output = output;
+                TypeNode::Path(path1)
             }
             _ => panic!("Path::make_most_concrete_from_pair: incompatible types"),
         }
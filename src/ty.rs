@@ -1,6 +1,7 @@
 use crate::{
-    generics, Data, GenericParam, Generics, Ident, Lifetime, ParamMap, Path, Print, Struct,
-    SynParamMap, TupleStruct, TypeParam, TypeParamBound,
+    generics, Data, Expr, GenericParam, Generics, GenericsEnv, GlobalPush, Ident, Lifetime, Parent,
+    Path, Print, Struct, Substitution, TupleStruct, TypeParam, TypeParamBound, Value, ValueNode,
+    STATIC_LIFETIME, VALUES,
 };
 use proc_macro2::TokenStream;
 use quote::{quote, ToTokens};
@@ -27,11 +28,22 @@ pub(crate) enum TypeNode {
     DataStructure(Box<DataStructure>),
     Path(Path),
     TypeParam(TypeParam),
+    Array {
+        elem: Box<TypeNode>,
+        len: Expr,
+    },
+    // An associated type of a trait, like `<T as Iterator>::Item`
+    Projection {
+        self_ty: Box<TypeNode>,
+        trait_path: Path,
+        name: Ident,
+    },
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub(crate) struct DataStructure {
     pub name: Ident,
+    pub vis: syn::Visibility,
     pub generics: Generics,
     pub data: Data<Type>,
 }
@@ -47,6 +59,16 @@ impl Type {
         ))
     }
 
+    /// An array of this type, where the length is a const param from the
+    /// param map or a const expression like `4`
+    pub fn array(&self, len: &str, env: &GenericsEnv) -> Self {
+        let len = syn::parse_str(len).expect("Type::array: Not a const expression");
+        Type(TypeNode::Array {
+            elem: Box::new(self.0.clone()),
+            len: Expr::syn_to_expr(len, env),
+        })
+    }
+
     pub fn primitive_str() -> Self {
         Type(TypeNode::PrimitiveStr)
     }
@@ -59,8 +81,8 @@ impl Type {
         })
     }
 
-    pub fn reference_with_lifetime(&self, lifetime: &str, param_map: &SynParamMap) -> Self {
-        let lifetime = param_map.get_lifetime(&lifetime);
+    pub fn reference_with_lifetime(&self, lifetime: &str, env: &GenericsEnv) -> Self {
+        let lifetime = env.get_lifetime(lifetime);
 
         Type(TypeNode::Reference {
             is_mut: false,
@@ -77,8 +99,8 @@ impl Type {
         })
     }
 
-    pub fn reference_mut_with_lifetime(&self, lifetime: &str, param_map: &SynParamMap) -> Self {
-        let lifetime = param_map.get_lifetime(&lifetime);
+    pub fn reference_mut_with_lifetime(&self, lifetime: &str, env: &GenericsEnv) -> Self {
+        let lifetime = env.get_lifetime(lifetime);
 
         Type(TypeNode::Reference {
             is_mut: true,
@@ -108,6 +130,14 @@ impl Type {
                     inner: Box::new(field.element.0),
                 })
             }),
+            TypeNode::Path(path) => {
+                let parent = Parent::get_data_parent(path).unwrap_or_else(|| {
+                    panic!("Type::data: {} is not a data type", self.0.get_name())
+                });
+                let substitution = parent.generics.get_substitution(path.get_generic_args());
+                let data = parent.data.clone().expect("Type::data: Parent has no data");
+                data.map(|field| Type(field.element.0.substitute(&substitution)))
+            }
             _ => panic!("Type::data"),
         }
     }
@@ -127,17 +157,50 @@ impl Type {
         }
     }
 
-    pub fn get_trait_object(type_param_bounds: &[&str], param_map: &mut SynParamMap) -> Self {
+    /// Constructs a value of this struct from the values of its fields, in the
+    /// order in which they are declared
+    pub fn construct(&self, fields: &[Value]) -> Value {
+        let data = match &self.0 {
+            TypeNode::DataStructure(data) => match &data.data {
+                Data::Struct(data) => data.clone(),
+                Data::Enum(_) => panic!("Type::construct: Not a struct"),
+            },
+            _ => panic!("Type::construct: Not a data structure"),
+        };
+        if data.fields().count() != fields.len() {
+            panic!("Type::construct: Wrong number of fields");
+        }
+        let mut fields = fields.iter();
+        let node = ValueNode::DataStructure {
+            ty: self.clone(),
+            data: Data::Struct(data.map(|_| fields.next().unwrap().index)),
+        };
+        Value {
+            index: VALUES.index_push(node),
+        }
+    }
+
+    /// The associated type `name` of the trait at `trait_path` for this type,
+    /// like `<T as Iterator>::Item`
+    pub fn get_associated_type(&self, trait_path: Path, name: &str) -> Self {
+        Type(TypeNode::Projection {
+            self_ty: Box::new(self.0.clone()),
+            trait_path,
+            name: Ident::new(name),
+        })
+    }
+
+    pub fn get_trait_object(type_param_bounds: &[&str], env: &mut GenericsEnv) -> Self {
         Type(TypeNode::TraitObject(
             type_param_bounds
                 .iter()
-                .map(|bound| TypeParamBound::get_type_param_bound(bound, param_map))
+                .map(|bound| TypeParamBound::get_type_param_bound(bound, env))
                 .collect(),
         ))
     }
 
-    pub fn type_param_from_str(type_param: &str, param_map: &mut SynParamMap) -> Self {
-        if let Some(&param) = param_map.get(&type_param) {
+    pub fn type_param_from_str(type_param: &str, env: &mut GenericsEnv) -> Self {
+        if let Some(param) = env.get(type_param) {
             Type(TypeNode::TypeParam(
                 param
                     .type_param()
@@ -148,15 +211,11 @@ impl Type {
         }
     }
 
-    pub(crate) fn syn_to_type(ty: syn::Type, param_map: &mut SynParamMap) -> Self {
+    pub(crate) fn syn_to_type(ty: syn::Type, env: &mut GenericsEnv) -> Self {
         match ty {
-            syn::Type::Path(TypePath {
-                //FIXME: add qself to Path
-                qself: None,
-                path,
-            }) => {
+            syn::Type::Path(TypePath { qself: None, path }) => {
                 if let Some(ident) = path.get_ident() {
-                    if let Some(&param) = param_map.get(&ident.to_string()) {
+                    if let Some(param) = env.get(&ident.to_string()) {
                         return Type(TypeNode::TypeParam(
                             param
                                 .type_param()
@@ -164,14 +223,31 @@ impl Type {
                         ));
                     }
                 }
-                Type(TypeNode::Path(Path::syn_to_path(path, param_map)))
+                Type(TypeNode::Path(Path::syn_to_path(path, env)))
+            }
+
+            syn::Type::Path(TypePath {
+                qself: Some(qself),
+                mut path,
+            }) => {
+                let name = path
+                    .segments
+                    .pop()
+                    .expect("syn_to_type: Not an associated type")
+                    .into_value()
+                    .ident;
+                Type(TypeNode::Projection {
+                    self_ty: Box::new(Type::syn_to_type(*qself.ty, env).0),
+                    trait_path: Path::syn_to_path(path, env),
+                    name: Ident::from(name),
+                })
             }
 
             syn::Type::Reference(reference) => {
-                let inner = Box::new(Type::syn_to_type(*reference.elem, param_map).0);
+                let inner = Box::new(Type::syn_to_type(*reference.elem, env).0);
                 let lifetime = reference
                     .lifetime
-                    .map(|lifetime| param_map.get_lifetime(&lifetime.to_string()));
+                    .map(|lifetime| env.get_lifetime(&lifetime.to_string()));
 
                 Type(TypeNode::Reference {
                     is_mut: reference.mutability.is_some(),
@@ -181,7 +257,7 @@ impl Type {
             }
 
             syn::Type::TraitObject(type_trait_object) => Type(TypeNode::TraitObject(
-                generics::syn_to_type_param_bounds(type_trait_object.bounds, param_map).collect(),
+                generics::syn_to_type_param_bounds(type_trait_object.bounds, env).collect(),
             )),
 
             syn::Type::Tuple(type_tuple) => {
@@ -190,23 +266,29 @@ impl Type {
                 } else if type_tuple.elems.len() == 1 && !type_tuple.elems.trailing_punct() {
                     // It is not a tuple. The parentheses were just used to
                     // disambiguate the type.
-                    Self::syn_to_type(type_tuple.elems.into_iter().next().unwrap(), param_map)
+                    Self::syn_to_type(type_tuple.elems.into_iter().next().unwrap(), env)
                 } else {
                     Type(TypeNode::Tuple(
                         type_tuple
                             .elems
                             .into_iter()
-                            .map(|elem| Self::syn_to_type(elem, param_map).0)
+                            .map(|elem| Self::syn_to_type(elem, env).0)
                             .collect(),
                     ))
                 }
             }
+
+            syn::Type::Array(type_array) => Type(TypeNode::Array {
+                elem: Box::new(Type::syn_to_type(*type_array.elem, env).0),
+                len: Expr::syn_to_expr(type_array.len, env),
+            }),
+
             _ => unimplemented!("Type::syn_to_type"),
         }
     }
 
-    pub(crate) fn clone_with_fresh_generics(&self, param_map: &ParamMap) -> Self {
-        Type(self.0.clone_with_fresh_generics(param_map))
+    pub(crate) fn clone_with_fresh_generics(&self, env: &GenericsEnv) -> Self {
+        Type(self.0.clone_with_fresh_generics(env))
     }
 }
 
@@ -218,9 +300,14 @@ impl TypeNode {
                 let types = types.iter().map(Print::ref_cast);
                 quote!((#(#types),*)).to_string()
             }
+            TypeNode::Array { .. } | TypeNode::Projection { .. } => {
+                let mut tokens = TokenStream::new();
+                Print::ref_cast(self).to_tokens(&mut tokens);
+                tokens.to_string()
+            }
             TypeNode::PrimitiveStr => String::from("str"),
             TypeNode::DataStructure(data) => data.name.to_string(),
-            TypeNode::Reference { inner, .. } => (&**inner).get_name(),
+            TypeNode::Reference { inner, .. } => inner.get_name(),
             TypeNode::Path(path) => {
                 let mut tokens = TokenStream::new();
                 Print::ref_cast(path).to_tokens(&mut tokens);
@@ -236,7 +323,119 @@ impl TypeNode {
         }
     }
 
-    pub(crate) fn clone_with_fresh_generics(&self, param_map: &ParamMap) -> Self {
+    /// Path of the data structure or library data type underneath any
+    /// references, without generic arguments
+    pub(crate) fn data_path(&self) -> Path {
+        match self {
+            TypeNode::Reference { inner, .. } => inner.data_path(),
+            TypeNode::DataStructure(data) => Path::ident_to_path(data.name.clone()),
+            TypeNode::Path(path) => path.without_generic_args(),
+            _ => panic!("Type::data_path: Not a data type"),
+        }
+    }
+
+    /// The generic params that appear in the type, including the params of
+    /// data structures, in the order of first appearance
+    pub(crate) fn generic_params(&self, params: &mut Vec<GenericParam>) {
+        match self {
+            TypeNode::Tuple(types) => {
+                for ty in types {
+                    ty.generic_params(params);
+                }
+            }
+            TypeNode::Array { elem, len } => {
+                elem.generic_params(params);
+                if let Expr::Param(param) = len {
+                    push_param(params, GenericParam::Const(*param));
+                }
+            }
+            TypeNode::Reference {
+                lifetime, inner, ..
+            } => {
+                if let Some(lifetime) = lifetime {
+                    push_lifetime_param(params, *lifetime);
+                }
+                inner.generic_params(params);
+            }
+            TypeNode::Dereference(inner) => inner.generic_params(params),
+            TypeNode::TraitObject(bounds) => {
+                for bound in bounds {
+                    match bound {
+                        TypeParamBound::Trait(bound) => bound.path.generic_params(params),
+                        TypeParamBound::Lifetime(lifetime) => {
+                            push_lifetime_param(params, *lifetime)
+                        }
+                        TypeParamBound::Maybe(_) => {}
+                    }
+                }
+            }
+            TypeNode::DataStructure(data) => {
+                for &param in &data.generics.params {
+                    push_param(params, param);
+                }
+            }
+            TypeNode::Path(path) => path.generic_params(params),
+            TypeNode::TypeParam(type_param) => push_param(params, GenericParam::Type(*type_param)),
+            TypeNode::Projection {
+                self_ty,
+                trait_path,
+                ..
+            } => {
+                self_ty.generic_params(params);
+                trait_path.generic_params(params);
+            }
+            TypeNode::Infer | TypeNode::PrimitiveStr => {}
+        }
+    }
+
+    pub(crate) fn substitute(&self, substitution: &Substitution) -> Self {
+        use super::TypeNode::*;
+        match self {
+            Infer => Infer,
+            Tuple(types) => Tuple(types.iter().map(|ty| ty.substitute(substitution)).collect()),
+            Array { elem, len } => Array {
+                elem: Box::new(elem.substitute(substitution)),
+                len: len.substitute(substitution),
+            },
+            PrimitiveStr => PrimitiveStr,
+            Reference {
+                is_mut,
+                lifetime,
+                inner,
+            } => Reference {
+                is_mut: *is_mut,
+                lifetime: lifetime.map(|lifetime| lifetime.substitute(substitution)),
+                inner: Box::new(inner.substitute(substitution)),
+            },
+            Dereference(inner) => Dereference(Box::new(inner.substitute(substitution))),
+            TraitObject(bounds) => TraitObject(
+                bounds
+                    .iter()
+                    .map(|bound| bound.substitute(substitution))
+                    .collect(),
+            ),
+            DataStructure(data) => DataStructure(data.clone()),
+            Path(path) => match &substitution.self_ty {
+                Some(self_ty) if path.is_self() => self_ty.clone(),
+                _ => Path(path.substitute(substitution)),
+            },
+            TypeParam(type_param) => match substitution.types.get(type_param) {
+                Some(ty) => ty.clone(),
+                None => TypeParam(*type_param),
+            },
+            Projection {
+                self_ty,
+                trait_path,
+                name,
+            } => Projection {
+                self_ty: Box::new(self_ty.substitute(substitution)),
+                trait_path: trait_path.substitute(substitution),
+                name: name.clone(),
+            },
+        }
+    }
+
+    pub(crate) fn clone_with_fresh_generics(&self, env: &GenericsEnv) -> Self {
         use super::TypeNode::*;
         match self {
             Infer => Infer,
@@ -244,10 +443,15 @@ impl TypeNode {
             Tuple(types) => Tuple(
                 types
                     .iter()
-                    .map(|ty| ty.clone_with_fresh_generics(param_map))
+                    .map(|ty| ty.clone_with_fresh_generics(env))
                     .collect(),
             ),
 
+            Array { elem, len } => Array {
+                elem: Box::new(elem.clone_with_fresh_generics(env)),
+                len: len.clone_with_fresh_generics(env),
+            },
+
             PrimitiveStr => PrimitiveStr,
 
             Reference {
@@ -257,18 +461,18 @@ impl TypeNode {
             } => Reference {
                 is_mut: *is_mut,
 
-                lifetime: lifetime.map(|lifetime| lifetime.clone_with_fresh_generics(param_map)),
-                inner: Box::new(inner.clone_with_fresh_generics(param_map)),
+                lifetime: lifetime.map(|lifetime| lifetime.clone_with_fresh_generics(env)),
+                inner: Box::new(inner.clone_with_fresh_generics(env)),
             },
 
             Dereference(dereference) => {
-                Dereference(Box::new(dereference.clone_with_fresh_generics(param_map)))
+                Dereference(Box::new(dereference.clone_with_fresh_generics(env)))
             }
 
             TraitObject(bounds) => TraitObject(
                 bounds
                     .iter()
-                    .map(|bound| bound.clone_with_fresh_generics(param_map))
+                    .map(|bound| bound.clone_with_fresh_generics(env))
                     .collect(),
             ),
 
@@ -276,14 +480,36 @@ impl TypeNode {
                 unimplemented!("Type::clone_with_fresh_generics: DataStructure")
             }
 
-            Path(path) => Path(path.clone_with_fresh_generics(param_map)),
+            Path(path) => Path(path.clone_with_fresh_generics(env)),
 
             TypeParam(type_param) => TypeParam(
-                param_map
-                    .get(&GenericParam::Type(*type_param))
+                env.get_fresh(GenericParam::Type(*type_param))
                     .and_then(|param| param.type_param())
                     .unwrap(),
             ),
+
+            Projection {
+                self_ty,
+                trait_path,
+                name,
+            } => Projection {
+                self_ty: Box::new(self_ty.clone_with_fresh_generics(env)),
+                trait_path: trait_path.clone_with_fresh_generics(env),
+                name: name.clone(),
+            },
         }
     }
 }
+
+pub(crate) fn push_param(params: &mut Vec<GenericParam>, param: GenericParam) {
+    if !params.contains(&param) {
+        params.push(param);
+    }
+}
+
+/// The `'static` lifetime is not a param, so it is skipped
+pub(crate) fn push_lifetime_param(params: &mut Vec<GenericParam>, lifetime: Lifetime) {
+    if lifetime != STATIC_LIFETIME {
+        push_param(params, GenericParam::Lifetime(lifetime));
+    }
+}
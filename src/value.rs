@@ -1,6 +1,6 @@
 use crate::{
-    ty::DataStructure, Accessor, Data, GlobalBorrow, GlobalPush, Struct, TupleStruct, Type,
-    TypeNode, ValueNode, ValueRef, VALUES,
+    ty::DataStructure, Accessor, Data, GlobalPush, MacroInvoke, Path, Struct, TupleStruct, Type,
+    TypeNode, ValueNode, ValueRef, MACROS, VALUES,
 };
 
 #[derive(Debug, Clone, Copy)]
@@ -16,6 +16,23 @@ impl Value {
         }
     }
 
+    /// A `compile_error!` in place of a value that can not be generated, so
+    /// that the error is reported by the compiler instead of panicking
+    pub(crate) fn compile_error(message: &str) -> Self {
+        let message = Value {
+            index: VALUES.index_push(ValueNode::Str(message.to_owned())),
+        };
+        let invoke = MACROS.index_push(MacroInvoke {
+            macro_path: Path::root()
+                .get_simple_path("core")
+                .get_simple_path("compile_error"),
+            args: vec![message.index],
+        });
+        Value {
+            index: VALUES.index_push(ValueNode::MacroInvocation(invoke)),
+        }
+    }
+
     pub fn reference(&self) -> Self {
         let node = ValueNode::Reference {
             is_mut: false,
@@ -61,16 +78,25 @@ impl Value {
             DataStructure { data, .. } => data.map(|value_ref| Value {
                 index: value_ref.element,
             }),
-            Reference { is_mut, value } if !is_mut => {
-                Value { index: value }.data().map(|v| v.element.reference())
-            }
-
-            Reference { is_mut, value } if is_mut => Value { index: value }
-                .data()
-                .map(|v| v.element.reference_mut()),
+            Reference { is_mut, value } => match (Value { index: value }).data() {
+                Data::Struct(data) => Data::Struct(data.map(|v| {
+                    if is_mut {
+                        v.element.reference_mut()
+                    } else {
+                        v.element.reference()
+                    }
+                })),
+                // Match on the reference itself so that the pattern bindings
+                // bind by reference
+                Data::Enum(_) => self.data_from_type(),
+            },
+            _ => self.data_from_type(),
+        }
+    }
 
-            // FIXME generate match and propagate the binding
-            Binding { name, ty } => ty.data().map(|field| {
+    fn data_from_type(&self) -> Data<Self> {
+        match self.index.get_type().data() {
+            Data::Struct(data) => Data::Struct(data.map(|field| {
                 let node = ValueNode::Destructure {
                     parent: self.index,
                     accessor: field.accessor.clone(),
@@ -79,8 +105,17 @@ impl Value {
                 Value {
                     index: VALUES.index_push(node),
                 }
-            }),
-            _ => panic!("Value::data"),
+            })),
+            Data::Enum(data) => {
+                let mut data = data.map(|field| {
+                    let node = ValueNode::PatternBinding { ty: field.element };
+                    Value {
+                        index: VALUES.index_push(node),
+                    }
+                });
+                data.scrutinee = Some(self.index);
+                Data::Enum(data)
+            }
         }
     }
 
@@ -123,7 +158,7 @@ impl Value {
             ValueNode::Binding {
                 ty: Type(TypeNode::DataStructure(data)),
                 ..
-            } if is_tuple_struct(&*data) => {
+            } if is_tuple_struct(&data) => {
                 if let Data::Struct(Struct::Tuple(TupleStruct { fields, .. })) = data.data {
                     let field = &fields[index];
                     let node = ValueNode::Destructure {
@@ -153,11 +188,7 @@ impl Value {
 }
 
 fn is_tuple_struct(data: &DataStructure) -> bool {
-    if let Data::Struct(Struct::Tuple(_)) = data.data {
-        true
-    } else {
-        false
-    }
+    matches!(data.data, Data::Struct(Struct::Tuple(_)))
 }
 
 impl Value {
@@ -0,0 +1,210 @@
+use crate::{Data, GenericArgument, GenericParam, Parent, Path, Type, TypeNode};
+
+/// How the subtyping of a type follows from the subtyping of the argument of
+/// one of its generic params. There are no contravariant params, since
+/// function pointer types are not supported.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum Variance {
+    /// The param is not used
+    Bivariant,
+    /// `Shared<'a>` is a subtype of `Shared<'b>` if `'a: 'b`
+    Covariant,
+    /// `Cell<&'a T>` is only a subtype of `Cell<&'b T>` if `'a` equals `'b`
+    Invariant,
+}
+
+/// Data types that are being computed, with the variances assumed for them
+/// when they are used recursively
+type Assumed = Vec<(Path, Vec<Variance>)>;
+
+impl Variance {
+    /// The variance of a param that is used in both positions
+    fn join(self, other: Self) -> Self {
+        use Variance::*;
+        match (self, other) {
+            (Bivariant, variance) | (variance, Bivariant) => variance,
+            (Covariant, Covariant) => Covariant,
+            _ => Invariant,
+        }
+    }
+
+    /// The variance of a position inside of a position with this variance
+    fn compose(self, inner: Self) -> Self {
+        use Variance::*;
+        match (self, inner) {
+            (Bivariant, _) | (_, Bivariant) => Bivariant,
+            (Covariant, variance) => variance,
+            (Invariant, _) => Invariant,
+        }
+    }
+}
+
+impl Path {
+    /// Whether the path is a data type declared with its fields, that can be
+    /// compared with `other` argument by argument
+    pub(crate) fn has_variances(&self, other: &Path) -> bool {
+        self.without_generic_args() == other.without_generic_args()
+            && self.get_generic_args().len() == other.get_generic_args().len()
+            && Parent::get_data_parent(self).is_some_and(|parent| parent.data.is_some())
+    }
+
+    /// The variances of a data type in its generic params, in the order of
+    /// the generic arguments of the path. The params of types that are not
+    /// declared with their fields are invariant.
+    pub(crate) fn variances(&self) -> Vec<Variance> {
+        self.variances_assuming(&mut Vec::new())
+    }
+
+    fn variances_assuming(&self, assumed: &mut Assumed) -> Vec<Variance> {
+        let args = self.get_generic_args();
+        let parent = match Parent::get_data_parent(self) {
+            Some(parent) => parent,
+            None => return vec![Variance::Invariant; args.len()],
+        };
+        let params = &parent.generics.params;
+        let variances = data_variances(&parent, assumed);
+
+        // Lifetime arguments are given in the order of the lifetime params,
+        // and type and const arguments in the order of their params
+        let mut lifetimes = params
+            .iter()
+            .zip(&variances)
+            .filter(|(param, _)| param.lifetime().is_some())
+            .map(|(_, &variance)| variance);
+        let mut others = params
+            .iter()
+            .zip(&variances)
+            .filter(|(param, _)| param.lifetime().is_none())
+            .map(|(_, &variance)| variance);
+        args.iter()
+            .map(|arg| match arg {
+                GenericArgument::Lifetime(_) => lifetimes.next(),
+                _ => others.next(),
+            })
+            .map(|variance| variance.unwrap_or(Variance::Invariant))
+            .collect()
+    }
+}
+
+/// The variances of a library data type in each of its generic params. A
+/// recursive use of the type is assumed to have the variances found so far,
+/// starting from bivariant, until they no longer change.
+fn data_variances(parent: &Parent, assumed: &mut Assumed) -> Vec<Variance> {
+    let params = &parent.generics.params;
+    let data = match &parent.data {
+        Some(data) => data,
+        None => return vec![Variance::Invariant; params.len()],
+    };
+    let path = parent.path.without_generic_args();
+    if let Some((_, variances)) = assumed.iter().find(|(assumed, _)| *assumed == path) {
+        return variances.clone();
+    }
+
+    assumed.push((path, vec![Variance::Bivariant; params.len()]));
+    loop {
+        let variances: Vec<_> = params
+            .iter()
+            .map(|&param| data_variance(data, param, assumed))
+            .collect();
+        let (_, last) = assumed.last_mut().unwrap();
+        if *last == variances {
+            assumed.pop();
+            return variances;
+        }
+        *last = variances;
+    }
+}
+
+/// The variance of a data structure in a generic param, from its fields
+fn data_variance(data: &Data<Type>, param: GenericParam, assumed: &mut Assumed) -> Variance {
+    let fields: Vec<_> = match data {
+        Data::Struct(data) => data.fields().collect(),
+        Data::Enum(data) => data
+            .variants()
+            .flat_map(|variant| variant.fields())
+            .collect(),
+    };
+    fields.iter().fold(Variance::Bivariant, |variance, field| {
+        variance.join(field.element.0.variance(param, assumed))
+    })
+}
+
+impl TypeNode {
+    /// The variance of the type in a generic param
+    fn variance(&self, param: GenericParam, assumed: &mut Assumed) -> Variance {
+        // Const arguments are always invariant
+        if param.const_param().is_some() {
+            return self.variance_if_used(param);
+        }
+        match self {
+            TypeNode::TypeParam(type_param) => {
+                if GenericParam::Type(*type_param) == param {
+                    Variance::Covariant
+                } else {
+                    Variance::Bivariant
+                }
+            }
+            TypeNode::Reference {
+                is_mut,
+                lifetime,
+                inner,
+            } => {
+                let lifetime = match lifetime {
+                    Some(lifetime) if GenericParam::Lifetime(*lifetime) == param => {
+                        Variance::Covariant
+                    }
+                    _ => Variance::Bivariant,
+                };
+                let inner = inner.variance(param, assumed);
+                let inner = if *is_mut {
+                    Variance::Invariant.compose(inner)
+                } else {
+                    inner
+                };
+                lifetime.join(inner)
+            }
+            TypeNode::Tuple(types) => types.iter().fold(Variance::Bivariant, |variance, ty| {
+                variance.join(ty.variance(param, assumed))
+            }),
+            TypeNode::Array { elem, .. } => elem.variance(param, assumed),
+            TypeNode::Path(path) => {
+                let variances = path.variances_assuming(assumed);
+                path.get_generic_args().iter().zip(variances).fold(
+                    Variance::Bivariant,
+                    |variance, (arg, outer)| {
+                        let inner = match arg {
+                            GenericArgument::Type(ty) => ty.0.variance(param, assumed),
+                            GenericArgument::Lifetime(lifetime) => {
+                                if GenericParam::Lifetime(*lifetime) == param {
+                                    Variance::Covariant
+                                } else {
+                                    Variance::Bivariant
+                                }
+                            }
+                            GenericArgument::Binding(binding) => {
+                                Variance::Invariant.compose(binding.ty.0.variance(param, assumed))
+                            }
+                            GenericArgument::Const(_) | GenericArgument::Constraint(_) => {
+                                Variance::Bivariant
+                            }
+                        };
+                        variance.join(outer.compose(inner))
+                    },
+                )
+            }
+            TypeNode::DataStructure(data) => data_variance(&data.data, param, assumed),
+            node => node.variance_if_used(param),
+        }
+    }
+
+    /// Invariant if the type mentions the param at all
+    fn variance_if_used(&self, param: GenericParam) -> Variance {
+        let mut params = Vec::new();
+        self.generic_params(&mut params);
+        if params.contains(&param) {
+            Variance::Invariant
+        } else {
+            Variance::Bivariant
+        }
+    }
+}
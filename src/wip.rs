@@ -1,10 +1,13 @@
 use crate::{
-    Function, GlobalBorrow, GlobalPush, Ident, InvokeRef, MacroInvokeRef, Parent, Path,
-    RuntimeFunction, Type, TypeNode, Value, ValueNode, ValueRef, INVOKES, MACROS, VALUES,
+    Function, GlobalPush, Ident, InvokeRef, MacroInvokeRef, Parent, Path, RuntimeFunction,
+    Substitution, Type, TypeNode, Value, ValueNode, ValueRef, INVOKES, MACROS, VALUES,
 };
+use proc_macro2::TokenStream;
+use quote::quote;
 use std::cell::RefCell;
 use std::ops::Range;
 use std::rc::Rc;
+use syn::{AttrStyle, Attribute, Visibility};
 
 #[derive(Debug, Clone)]
 pub struct MakeImpl<'a> {
@@ -16,6 +19,8 @@ pub(crate) struct WipImpl {
     pub(crate) trait_ty: Option<Rc<Parent>>,
     pub(crate) ty: Type,
     pub(crate) functions: RefCell<Vec<WipFunction>>,
+    // The contents of the attributes on the impl, like `inline` in `#[inline]`
+    pub(crate) attrs: RefCell<Vec<TokenStream>>,
 }
 
 #[derive(Debug, Clone)]
@@ -32,6 +37,8 @@ pub(crate) struct WipFunction {
     pub(crate) invokes: WipRange<InvokeRef>,
     pub(crate) macros: WipRange<MacroInvokeRef>,
     pub(crate) ret: Option<ValueRef>,
+    pub(crate) attrs: RefCell<Vec<TokenStream>>,
+    pub(crate) vis: RefCell<Visibility>,
 }
 
 #[derive(Debug, Clone)]
@@ -72,17 +79,47 @@ impl<Idx> WipRange<Idx> {
 }
 
 impl<'a> MakeImpl<'a> {
-    pub fn make_function<F>(&self, f: F, run: fn(MakeFunction) -> Value)
+    /// Add an attribute to the impl, like `"allow(unused_qualifications)"`
+    /// for `#[allow(unused_qualifications)]`
+    pub fn add_attribute(&self, attr: &str) {
+        self.wip.attrs.borrow_mut().push(parse_attribute(attr));
+    }
+
+    pub fn make_function<F, Run>(&self, f: F, run: Run)
     where
         F: RuntimeFunction,
+        Run: FnOnce(MakeFunction) -> Value,
     {
+        self.wip
+            .make_function(f.SELF(), Some(self.wip.ty.clone()), run);
+    }
+}
+
+impl WipImpl {
+    pub(crate) fn new(trait_ty: Option<Rc<Parent>>, ty: Type) -> Self {
+        WipImpl {
+            trait_ty,
+            ty,
+            functions: RefCell::new(Vec::new()),
+            attrs: RefCell::new(Vec::new()),
+        }
+    }
+
+    pub(crate) fn make_function(
+        &self,
+        f: Rc<Function>,
+        self_ty: Option<Type>,
+        run: impl FnOnce(MakeFunction) -> Value,
+    ) {
         let mut wip = WipFunction {
-            self_ty: Some(self.wip.ty.clone()),
-            f: f.SELF(),
+            self_ty,
+            f,
             values: WipRange::new(ValueRef(VALUES.with_borrow(Vec::len))),
             invokes: WipRange::new(InvokeRef(INVOKES.with_borrow(Vec::len))),
             macros: WipRange::new(MacroInvokeRef(MACROS.with_borrow(Vec::len))),
             ret: None,
+            attrs: RefCell::new(Vec::new()),
+            vis: RefCell::new(Visibility::Inherited),
         };
         let ret = Some(run(MakeFunction { wip: &wip }).index);
         wip.values.end = Some(ValueRef(VALUES.with_borrow(Vec::len)));
@@ -90,11 +127,31 @@ impl<'a> MakeImpl<'a> {
         wip.macros.end = Some(MacroInvokeRef(MACROS.with_borrow(Vec::len)));
         wip.ret = ret;
 
-        self.wip.functions.borrow_mut().push(wip);
+        self.functions.borrow_mut().push(wip);
     }
 }
 
 impl<'a> MakeFunction<'a> {
+    /// Add an attribute to the function, like `"inline"` for `#[inline]`
+    pub fn add_attribute(&self, attr: &str) {
+        self.wip.attrs.borrow_mut().push(parse_attribute(attr));
+    }
+
+    /// Declare the function with the visibility and the outer attributes of
+    /// an item, like the function annotated with an attribute macro
+    pub(crate) fn declare_like(&self, vis: &Visibility, attrs: &[Attribute]) {
+        *self.wip.vis.borrow_mut() = vis.clone();
+        let attrs = attrs
+            .iter()
+            .filter(|attr| attr.style == AttrStyle::Outer)
+            .map(|attr| {
+                let path = &attr.path;
+                let tokens = &attr.tokens;
+                quote!(#path #tokens)
+            });
+        self.wip.attrs.borrow_mut().extend(attrs);
+    }
+
     pub fn unit(&self) -> Value {
         self.wip.unit()
     }
@@ -128,7 +185,7 @@ impl<'a> MakeFunction<'a> {
             },
             None => ValueNode::Binding {
                 name: Ident::new(format!("__arg{}", index)),
-                ty: wip.f.sig.inputs[index].clone(),
+                ty: wip.with_self_ty(&wip.f.sig.inputs[index]),
             },
         };
         Value {
@@ -137,11 +194,24 @@ impl<'a> MakeFunction<'a> {
     }
 }
 
+fn parse_attribute(attr: &str) -> TokenStream {
+    attr.parse()
+        .unwrap_or_else(|_| panic!("add_attribute: `{}` is not a valid attribute", attr))
+}
+
 impl WipFunction {
     pub(crate) fn node(&self, index: ValueRef) -> ValueNode {
         index.node()
     }
 
+    /// A type from the signature, with `Self` replaced by the type of the impl
+    pub(crate) fn with_self_ty(&self, ty: &Type) -> Type {
+        match &self.self_ty {
+            Some(self_ty) => Type(ty.0.substitute(&Substitution::self_ty(self_ty.0.clone()))),
+            None => ty.clone(),
+        }
+    }
+
     fn unit(&self) -> Value {
         let node = ValueNode::Tuple(Vec::new());
         Value {
@@ -158,15 +228,13 @@ impl WipFunction {
 }
 impl WipImpl {
     pub(crate) fn has_generics(&self) -> bool {
-        if let TypeNode::DataStructure(data) = &self.ty.0 {
-            !data.generics.params.is_empty()
-                || if let Some(parent) = &self.trait_ty {
-                    !parent.generics.params.is_empty()
-                } else {
-                    false
-                }
-        } else {
-            false
-        }
+        let mut params = Vec::new();
+        self.ty.0.generic_params(&mut params);
+        !params.is_empty()
+            || if let Some(parent) = &self.trait_ty {
+                !parent.generics.params.is_empty()
+            } else {
+                false
+            }
     }
 }
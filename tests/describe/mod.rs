@@ -0,0 +1,49 @@
+use reflect::*;
+
+reflect::library! {
+    use std {
+        mod string {
+            type String;
+        }
+    }
+
+    use describe {
+        trait Describe {
+            fn describe(&self);
+        }
+
+        type Describer;
+
+        impl Describer {
+            fn field<T: ::describe::Describe>(&T);
+            fn unsized_field<T: ?Sized + ::describe::Describe>(&T);
+            fn any<T>(&T);
+            fn same<T>(&T, &T);
+            fn string(&::std::string::String);
+            fn forever<T>(&'static T);
+        }
+    }
+}
+
+pub use self::RUNTIME::describe::Describer;
+
+/// Derive `Describe` for a struct, where `describe` invokes the functions of
+/// `Describer` with the fields of the struct
+pub fn derive(ex: Execution, describe: impl FnOnce(&[Value])) {
+    ex.make_trait_impl(RUNTIME::describe::Describe, ex.target_type(), |block| {
+        block.make_function(RUNTIME::describe::Describe::describe, |make_function| {
+            let receiver = make_function.arg(0);
+            let fields: Vec<_> = match receiver.data() {
+                Data::Struct(Struct::Struct(receiver)) => {
+                    receiver.fields().map(|field| field.get_value()).collect()
+                }
+                Data::Struct(Struct::Tuple(receiver)) => {
+                    receiver.fields().map(|field| field.get_value()).collect()
+                }
+                _ => unimplemented!(),
+            };
+            describe(&fields);
+            make_function.unit()
+        });
+    });
+}
@@ -0,0 +1,203 @@
+use quote::quote;
+use reflect::library::std::*;
+use reflect::*;
+
+struct DebugAttrs {
+    schema: AttrSchema,
+    rename: AttrKey<Option<String>>,
+    skip: AttrKey<bool>,
+    with: AttrKey<Option<Path>>,
+}
+
+fn schema() -> DebugAttrs {
+    let mut schema = AttrSchema::new("debug");
+    let rename = schema.add_string("rename");
+    let skip = schema.add_flag("skip");
+    let with = schema.add_path("with");
+    DebugAttrs {
+        schema,
+        rename,
+        skip,
+        with,
+    }
+}
+
+fn derive_debug(ex: Execution) {
+    let DebugAttrs {
+        schema,
+        rename,
+        skip,
+        ..
+    } = schema();
+    let container = match ex.target_type().data().parse_attrs(&schema) {
+        Ok(container) => container,
+        Err(error) => return ex.emit_error(error),
+    };
+    let mut fields = Vec::new();
+    if let Data::Struct(Struct::Struct(data)) = ex.target_type().data() {
+        for field in data.fields() {
+            match field.parse_attrs(&schema) {
+                Ok(attrs) => fields.push(attrs),
+                Err(error) => return ex.emit_error(error),
+            }
+        }
+    }
+
+    ex.make_trait_impl(fmt::Debug, ex.target_type(), |block| {
+        block.make_function(fmt::Debug::fmt, |f| {
+            let receiver = f.arg(0);
+            let formatter = f.arg(1);
+            let name = match container.get(&rename) {
+                Some(rename) => f.string(&rename),
+                None => receiver.get_type_name(),
+            };
+
+            match receiver.data() {
+                Data::Struct(Struct::Struct(receiver)) => {
+                    let builder = fmt::Formatter::debug_struct
+                        .INVOKE(formatter, name)
+                        .reference_mut();
+                    for (field, attrs) in receiver.fields().zip(&fields) {
+                        if attrs.get(&skip) {
+                            continue;
+                        }
+                        fmt::DebugStruct::field.INVOKE(
+                            builder,
+                            field.get_name(),
+                            field.get_value(),
+                        );
+                    }
+                    fmt::DebugStruct::finish.INVOKE(builder)
+                }
+                _ => unimplemented!(),
+            }
+        });
+    });
+}
+
+#[test]
+fn test_schema() {
+    let input = quote! {
+        #[debug(rename = "P")]
+        struct Point {
+            x: i32,
+            #[debug(skip)]
+            y: i32,
+        }
+    };
+
+    let expected = quote! {
+        #[automatically_derived]
+        impl ::std::fmt::Debug for Point {
+            fn fmt<'__a1, '__a2>(
+                &'__a1 self,
+                __arg0: &'__a2 mut ::std::fmt::Formatter
+            ) -> ::std::fmt::Result {
+                let __v0 = self;
+                let __v1 = __arg0;
+                let __v3 = &__v0.x;
+                let mut __v5 = ::std::fmt::Formatter::debug_struct(__v1, "P");
+                let __v6 = &mut __v5;
+                let _ = ::std::fmt::DebugStruct::field(__v6, "x", __v3);
+                let __v9 = ::std::fmt::DebugStruct::finish(__v6);
+                __v9
+            }
+        }
+    };
+
+    let output = derive(input, derive_debug);
+    assert_eq!(output.to_string(), expected.to_string());
+}
+
+#[test]
+fn test_path() {
+    let input = quote! {
+        struct Point {
+            #[debug(with = "::my::fmt_x")]
+            x: i32,
+        }
+    };
+
+    derive(input, |ex| {
+        if let Data::Struct(Struct::Struct(data)) = ex.target_type().data() {
+            let field = data.fields().next().unwrap();
+            let keys = schema();
+            let attrs = field.parse_attrs(&keys.schema).unwrap();
+            let path = attrs.get(&keys.with).unwrap();
+            let expected = Path::path_from_str("::my::fmt_x", &mut GenericsEnv::default());
+            assert_eq!(path, expected);
+            assert!(!attrs.get(&keys.skip));
+            assert_eq!(attrs.get(&keys.rename), None);
+        }
+    });
+}
+
+#[test]
+fn test_with() {
+    let input = quote! {
+        struct Point {
+            #[debug(with = "::my::fmt_x")]
+            x: i32,
+        }
+    };
+
+    let expected = quote! {
+        #[automatically_derived]
+        impl ::std::fmt::Debug for Point {
+            fn fmt<'__a1, '__a2>(
+                &'__a1 self,
+                __arg0: &'__a2 mut ::std::fmt::Formatter
+            ) -> ::std::fmt::Result {
+                let __v0 = self;
+                let __v1 = __arg0;
+                let __v2 = &__v0.x;
+                let __v3 = ::my::fmt_x(__v2, __v1);
+                __v3
+            }
+        }
+    };
+
+    let output = derive(input, |ex| {
+        let DebugAttrs { schema, with, .. } = schema();
+        ex.make_trait_impl(fmt::Debug, ex.target_type(), |block| {
+            block.make_function(fmt::Debug::fmt, |f| {
+                let receiver = f.arg(0);
+                let formatter = f.arg(1);
+
+                match receiver.data() {
+                    Data::Struct(Struct::Struct(receiver)) => {
+                        let field = receiver.fields().next().unwrap();
+                        let attrs = field.parse_attrs(&schema).unwrap();
+                        let sig = Signature::parse(
+                            "fn(&i32, &mut ::std::fmt::Formatter) -> ::std::fmt::Result",
+                        )
+                        .unwrap();
+                        let with = attrs.function(&with, sig).unwrap();
+                        with.invoke(&[field.get_value(), formatter])
+                    }
+                    _ => unimplemented!(),
+                }
+            });
+        });
+    });
+    assert_eq!(output.to_string(), expected.to_string());
+}
+
+#[test]
+fn test_errors() {
+    let input = quote! {
+        #[debug(rename = "P", rename = "Q", renam = "R", skip = "yes")]
+        struct Point {
+            x: i32,
+        }
+    };
+
+    let expected = quote! {
+        compile_error! { "duplicate debug attribute `rename`" }
+        compile_error! { "unknown debug attribute `renam`" }
+        compile_error! { "`skip` does not take a value" }
+    };
+
+    let output = derive(input, derive_debug);
+    assert_eq!(output.to_string(), expected.to_string());
+}
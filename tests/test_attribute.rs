@@ -0,0 +1,259 @@
+use quote::quote;
+use reflect::*;
+
+library! {
+    extern crate names {
+        trait Name {
+            fn name() -> &'static ::std::primitive::str;
+        }
+    }
+}
+
+#[test]
+fn test_wrap_function() {
+    let args = quote!(prefix = "show");
+    let item = quote! {
+        fn show<T: ::std::fmt::Debug>(value: T, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+            write!(f, "{:?}", value)
+        }
+    };
+
+    let output = attribute(args, item, |ex| {
+        assert_eq!(ex.args().to_string(), quote!(prefix = "show").to_string());
+        let original = ex.emit_original("__show_original");
+        ex.make_function(ex.function(), |f| original.invoke(&[f.arg(0), f.arg(1)]));
+    });
+
+    let expected = quote! {
+        fn __show_original<T: ::std::fmt::Debug>(
+            value: T,
+            f: &mut ::std::fmt::Formatter
+        ) -> ::std::fmt::Result {
+            write!(f, "{:?}", value)
+        }
+        fn show<'__a1, __T0>(
+            __arg0: __T0,
+            __arg1: &'__a1 mut ::std::fmt::Formatter
+        ) -> ::std::fmt::Result
+        where
+            __T0: ::std::fmt::Debug,
+        {
+            let __v0 = __arg0;
+            let __v1 = __arg1;
+            let __v2 = __show_original(__v0, __v1);
+            __v2
+        }
+    };
+    assert_eq!(output.to_string(), expected.to_string());
+}
+
+#[test]
+fn test_keep_visibility_and_attributes() {
+    let item = quote! {
+        /// Shows the value
+        #[inline]
+        pub fn show(value: ::std::primitive::u8) -> ::std::primitive::u8 {
+            value
+        }
+    };
+
+    let output = attribute(quote!(), item, |ex| {
+        let original = ex.emit_original("__show_original");
+        ex.make_function(ex.function(), |f| original.invoke(&[f.arg(0)]));
+    });
+
+    let expected = quote! {
+        /// Shows the value
+        #[inline]
+        pub fn __show_original(value: ::std::primitive::u8) -> ::std::primitive::u8 {
+            value
+        }
+        /// Shows the value
+        #[inline]
+        pub fn show(__arg0: ::std::primitive::u8) -> ::std::primitive::u8 {
+            let __v0 = __arg0;
+            let __v1 = __show_original(__v0);
+            __v1
+        }
+    };
+    assert_eq!(output.to_string(), expected.to_string());
+}
+
+#[test]
+fn test_impl_methods() {
+    let item = quote! {
+        impl Counter {
+            pub fn new() -> Self {
+                Counter(0)
+            }
+        }
+    };
+
+    let output = attribute(quote!(), item, |ex| {
+        ex.emit_item();
+        let new = ex.methods()[0].clone();
+        let sig = Signature::parse("fn() -> Counter").unwrap();
+        let counter = Function::get_function("counter", sig);
+        ex.make_function(counter, |_f| new.invoke(&[]));
+    });
+
+    let expected = quote! {
+        impl Counter {
+            pub fn new() -> Self {
+                Counter(0)
+            }
+        }
+        fn counter() -> Counter {
+            let __v0 = Counter::new();
+            __v0
+        }
+    };
+    assert_eq!(output.to_string(), expected.to_string());
+}
+
+#[test]
+fn test_unsupported_item() {
+    let item = quote! {
+        struct Counter(u8);
+    };
+
+    let output = attribute(quote!(), item, |_ex| unreachable!());
+
+    let expected = quote! {
+        compile_error! { "expected a `fn` or an `impl`" }
+    };
+    assert_eq!(output.to_string(), expected.to_string());
+}
+
+#[test]
+fn test_unsupported_signature() {
+    let output = attribute(
+        quote!(),
+        quote!(
+            fn f(data: &[u8]) {}
+        ),
+        |_ex| unreachable!(),
+    );
+    let expected = quote! {
+        compile_error! { "slice types are not supported in library!" }
+    };
+    assert_eq!(output.to_string(), expected.to_string());
+
+    let output = attribute(
+        quote!(),
+        quote!(
+            fn f(x: impl Clone) {}
+        ),
+        |_ex| unreachable!(),
+    );
+    let expected = quote! {
+        compile_error! { "`impl Trait` is not supported in library!, use a generic parameter instead" }
+    };
+    assert_eq!(output.to_string(), expected.to_string());
+}
+
+#[test]
+fn test_unsupported_method_signature() {
+    let item = quote! {
+        impl<'a> Reader<'a> {
+            fn read(&mut self, buf: &'a mut [u8]) {}
+        }
+    };
+
+    let output = attribute(quote!(), item, |_ex| unreachable!());
+
+    let expected = quote! {
+        compile_error! { "slice types are not supported in library!" }
+    };
+    assert_eq!(output.to_string(), expected.to_string());
+
+    let item = quote! {
+        impl Reader<[u8]> {}
+    };
+
+    let output = attribute(quote!(), item, |_ex| unreachable!());
+
+    assert_eq!(output.to_string(), expected.to_string());
+}
+
+#[test]
+fn test_function_like() {
+    let input = quote! {
+        ::std::string::String, (::std::string::String, bool)
+    };
+
+    let mut index = 0;
+    let output = function_like(input, |ex| {
+        let name = format!("type{}", index);
+        index += 1;
+        ex.make_trait_impl(RUNTIME::names::Name, ex.target_type(), |block| {
+            block.make_function(RUNTIME::names::Name::name, |f| f.string(&name));
+        });
+    });
+
+    let expected = quote! {
+        impl ::names::Name for ::std::string::String {
+            fn name() -> &'static ::std::primitive::str {
+                "type0"
+            }
+        }
+        impl ::names::Name for (::std::string::String, bool) {
+            fn name() -> &'static ::std::primitive::str {
+                "type1"
+            }
+        }
+    };
+    assert_eq!(output.to_string(), expected.to_string());
+}
+
+#[test]
+fn test_function_like_parse_error() {
+    let output = function_like(quote!(::std::string::String;), |_ex| unreachable!());
+
+    let expected = quote! {
+        compile_error! { "expected `,`" }
+    };
+    assert_eq!(output.to_string(), expected.to_string());
+}
+
+#[test]
+fn test_function_like_generics() {
+    let input = quote! {
+        <A> (A,), <'a, A, B> (&'a A, B)
+    };
+
+    let output = function_like(input, |ex| {
+        ex.make_trait_impl(RUNTIME::names::Name, ex.target_type(), |block| {
+            block.make_function(RUNTIME::names::Name::name, |f| f.string("tuple"));
+        });
+    });
+
+    let expected = quote! {
+        impl<__T0> ::names::Name for (__T0,) {
+            fn name() -> &'static ::std::primitive::str {
+                "tuple"
+            }
+        }
+        impl<'__a1, __T1, __T2> ::names::Name for (&'__a1 __T1, __T2) {
+            fn name() -> &'static ::std::primitive::str {
+                "tuple"
+            }
+        }
+    };
+    assert_eq!(output.to_string(), expected.to_string());
+}
+
+#[test]
+fn test_function_like_unsupported_type() {
+    let output = function_like(quote!(&[u8]), |_ex| unreachable!());
+    let expected = quote! {
+        compile_error! { "slice types are not supported in library!" }
+    };
+    assert_eq!(output.to_string(), expected.to_string());
+
+    let output = function_like(quote!(<A: Clone> (A,)), |_ex| unreachable!());
+    let expected = quote! {
+        compile_error! { "expected a generic parameter without bounds, the bounds of the impl are inferred" }
+    };
+    assert_eq!(output.to_string(), expected.to_string());
+}
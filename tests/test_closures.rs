@@ -0,0 +1,73 @@
+use quote::quote;
+use reflect::library::std::*;
+use reflect::*;
+
+// Options that a macro would parse from the attributes of the input
+struct Options {
+    rename: String,
+}
+
+fn derive_debug(ex: Execution, options: &Options) {
+    ex.make_trait_impl(fmt::Debug, ex.target_type(), |block| {
+        block.make_function(fmt::Debug::fmt, |f| {
+            let receiver = f.arg(0);
+            let formatter = f.arg(1);
+
+            match receiver.data() {
+                Data::Struct(Struct::Struct(receiver)) => {
+                    let name = f.string(&options.rename);
+                    let builder = fmt::Formatter::debug_struct
+                        .INVOKE(formatter, name)
+                        .reference_mut();
+                    for field in receiver.fields() {
+                        fmt::DebugStruct::field.INVOKE(
+                            builder,
+                            field.get_name(),
+                            field.get_value(),
+                        );
+                    }
+                    fmt::DebugStruct::finish.INVOKE(builder)
+                }
+                _ => unimplemented!(),
+            }
+        });
+    });
+}
+
+#[test]
+fn test_captured_options() {
+    let input = quote! {
+        struct Point {
+            x: i32,
+            y: i32,
+        }
+    };
+
+    let options = Options {
+        rename: "P".to_owned(),
+    };
+
+    let expected = quote! {
+        #[automatically_derived]
+        impl ::std::fmt::Debug for Point {
+            fn fmt<'__a1, '__a2>(
+                &'__a1 self,
+                __arg0: &'__a2 mut ::std::fmt::Formatter
+            ) -> ::std::fmt::Result {
+                let __v0 = self;
+                let __v1 = __arg0;
+                let __v2 = &__v0.x;
+                let __v3 = &__v0.y;
+                let mut __v5 = ::std::fmt::Formatter::debug_struct(__v1, "P");
+                let __v6 = &mut __v5;
+                let _ = ::std::fmt::DebugStruct::field(__v6, "x", __v2);
+                let _ = ::std::fmt::DebugStruct::field(__v6, "y", __v3);
+                let __v11 = ::std::fmt::DebugStruct::finish(__v6);
+                __v11
+            }
+        }
+    };
+
+    let output = derive(input, |ex| derive_debug(ex, &options));
+    assert_eq!(output.to_string(), expected.to_string());
+}
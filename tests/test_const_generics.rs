@@ -0,0 +1,113 @@
+use quote::quote;
+use reflect::*;
+
+library! {
+    use checksum {
+        trait Checksum {
+            fn checksum(&self) -> ::std::primitive::u32;
+        }
+
+        type Bytes;
+
+        impl Bytes {
+            fn sum<const N: ::std::primitive::usize>(&[::std::primitive::u8; N]) -> ::std::primitive::u32;
+            fn sum_all<T: ::checksum::Checksum, const N: ::std::primitive::usize>(&[T; N]) -> ::std::primitive::u32;
+        }
+    }
+}
+
+fn derive_checksum(ex: Execution, sum: fn(Value) -> Value) {
+    ex.make_trait_impl(RUNTIME::checksum::Checksum, ex.target_type(), |block| {
+        block.make_function(RUNTIME::checksum::Checksum::checksum, |make_function| {
+            let receiver = make_function.arg(0);
+            match receiver.data() {
+                Data::Struct(Struct::Struct(receiver)) => {
+                    let field = receiver.fields().next().unwrap();
+                    sum(field.get_value())
+                }
+                _ => unimplemented!(),
+            }
+        });
+    });
+}
+
+#[test]
+fn test_const_param() {
+    let input = quote! {
+        struct Buf<const N: usize> {
+            data: [u8; N],
+        }
+    };
+
+    let expected = quote! {
+        #[automatically_derived]
+        impl<const __C0: usize> ::checksum::Checksum for Buf<__C0> {
+            fn checksum<'__a1>(&'__a1 self) -> ::std::primitive::u32 {
+                let __v0 = self;
+                let __v1 = &__v0.data;
+                let __v2 = ::checksum::Bytes::sum(__v1);
+                __v2
+            }
+        }
+    };
+
+    let output = derive(input, |ex| {
+        derive_checksum(ex, |value| RUNTIME::checksum::Bytes::sum.INVOKE(value))
+    });
+    assert_eq!(output.to_string(), expected.to_string());
+}
+
+#[test]
+fn test_const_value() {
+    let input = quote! {
+        struct Header {
+            magic: [u8; 4],
+        }
+    };
+
+    let expected = quote! {
+        #[automatically_derived]
+        impl ::checksum::Checksum for Header {
+            fn checksum<'__a1>(&'__a1 self) -> ::std::primitive::u32 {
+                let __v0 = self;
+                let __v1 = &__v0.magic;
+                let __v2 = ::checksum::Bytes::sum(__v1);
+                __v2
+            }
+        }
+    };
+
+    let output = derive(input, |ex| {
+        derive_checksum(ex, |value| RUNTIME::checksum::Bytes::sum.INVOKE(value))
+    });
+    assert_eq!(output.to_string(), expected.to_string());
+}
+
+#[test]
+fn test_const_param_with_type_param() {
+    let input = quote! {
+        struct Table<T, const N: usize = 16> {
+            rows: [T; N],
+        }
+    };
+
+    let expected = quote! {
+        #[automatically_derived]
+        impl<__T0, const __C0: usize> ::checksum::Checksum for Table<__T0, __C0>
+        where
+            __T0: ::checksum::Checksum,
+        {
+            fn checksum<'__a1>(&'__a1 self) -> ::std::primitive::u32 {
+                let __v0 = self;
+                let __v1 = &__v0.rows;
+                let __v2 = ::checksum::Bytes::sum_all(__v1);
+                __v2
+            }
+        }
+    };
+
+    let output = derive(input, |ex| {
+        derive_checksum(ex, |value| RUNTIME::checksum::Bytes::sum_all.INVOKE(value))
+    });
+    assert_eq!(output.to_string(), expected.to_string());
+}
@@ -0,0 +1,93 @@
+use quote::quote;
+use reflect::*;
+
+library! {
+    use zero {
+        trait Zero {
+            fn zero();
+        }
+    }
+}
+
+fn derive_zero(ex: Execution) {
+    ex.make_trait_impl(RUNTIME::zero::Zero, ex.target_type(), |block| {
+        block.make_function(RUNTIME::zero::Zero::zero, |make_function| {
+            make_function.unit()
+        });
+    });
+}
+
+fn derive_zero_with_check(ex: Execution) {
+    let checks = ex.load_crate("checks");
+    ex.make_trait_impl(RUNTIME::zero::Zero, ex.target_type(), |block| {
+        block.make_function(RUNTIME::zero::Zero::zero, |make_function| {
+            checks.invoke_macro("check", &[]);
+            make_function.unit()
+        });
+    });
+}
+
+#[test]
+fn test_extern_crate() {
+    let input = quote! {
+        struct Unit;
+    };
+
+    let expected = quote! {
+        const _: () = {
+            extern crate checks;
+
+            #[automatically_derived]
+            impl ::zero::Zero for Unit {
+                fn zero() {
+                    let _ = checks::check!();
+                }
+            }
+        };
+    };
+
+    let output = derive(input, derive_zero_with_check);
+    assert_eq!(output.to_string(), expected.to_string());
+}
+
+#[test]
+fn test_crate_path() {
+    let input = quote! {
+        struct Unit;
+    };
+
+    let expected = quote! {
+        #[automatically_derived]
+        impl my_zero::Zero for Unit {
+            fn zero() {}
+        }
+    };
+
+    let output = derive(input, |ex| {
+        ex.set_crate_path("zero", "my_zero");
+        derive_zero(ex);
+    });
+    assert_eq!(output.to_string(), expected.to_string());
+}
+
+#[test]
+fn test_loaded_crate_path() {
+    let input = quote! {
+        struct Unit;
+    };
+
+    let expected = quote! {
+        #[automatically_derived]
+        impl ::zero::Zero for Unit {
+            fn zero() {
+                let _ = crate::reexports::checks::check!();
+            }
+        }
+    };
+
+    let output = derive(input, |ex| {
+        ex.set_crate_path("checks", "crate::reexports::checks");
+        derive_zero_with_check(ex);
+    });
+    assert_eq!(output.to_string(), expected.to_string());
+}
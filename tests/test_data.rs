@@ -0,0 +1,236 @@
+use quote::quote;
+use reflect::*;
+
+library! {
+    extern crate std {
+        mod option {
+            enum Option<T> {
+                Some(T),
+                None,
+            }
+        }
+    }
+
+    use span {
+        type Pos;
+
+        struct Span {
+            pub lo: Pos,
+            pub hi: Pos,
+        }
+
+        enum Token {
+            Ident(Span),
+            Punct { span: Span },
+            Eof,
+        }
+
+        impl Pos {
+            fn start() -> Pos;
+        }
+
+        trait Spanned {
+            fn span(&self) -> Span;
+            fn lo(&self) -> ::std::option::Option<Pos>;
+        }
+
+        trait TokenSpan {
+            fn token_span<'a>(&self, &'a Token) -> ::std::option::Option<&'a Span>;
+            fn lo_or_start(&self, ::std::option::Option<Pos>) -> Pos;
+        }
+
+        trait FirstSpan {
+            fn first_span(&self) -> ::std::option::Option<&Span>;
+        }
+    }
+}
+
+fn make_span() -> Value {
+    let lo = RUNTIME::span::Pos::start.INVOKE();
+    let hi = RUNTIME::span::Pos::start.INVOKE();
+    RUNTIME::span::Span.INVOKE(lo, hi)
+}
+
+fn derive_spanned(ex: Execution) {
+    ex.make_trait_impl(RUNTIME::span::Spanned, ex.target_type(), |block| {
+        block.make_function(RUNTIME::span::Spanned::span, |_make_function| make_span());
+
+        block.make_function(RUNTIME::span::Spanned::lo, |_make_function| {
+            let lo = match make_span().data() {
+                Data::Struct(Struct::Struct(span)) => span.fields().next().unwrap().get_value(),
+                _ => unreachable!(),
+            };
+            RUNTIME::std::option::Option::Some.INVOKE(lo)
+        });
+    });
+}
+
+#[test]
+fn test_construct() {
+    let input = quote! {
+        struct Input;
+    };
+
+    let expected = quote! {
+        #[automatically_derived]
+        impl ::span::Spanned for Input {
+            fn span<'__a1>(&'__a1 self) -> ::span::Span {
+                let __v0 = ::span::Pos::start();
+                let __v1 = ::span::Pos::start();
+                let __v2 = ::span::Span { lo: __v0, hi: __v1 };
+                __v2
+            }
+
+            fn lo<'__a2>(&'__a2 self) -> ::std::option::Option<::span::Pos> {
+                let __v3 = ::span::Pos::start();
+                let __v4 = ::span::Pos::start();
+                let __v5 = ::span::Span { lo: __v3, hi: __v4 };
+                let __v6 = __v5.lo;
+                let __v8 = ::std::option::Option::Some(__v6);
+                __v8
+            }
+        }
+    };
+
+    let output = reflect::derive(input, derive_spanned);
+    assert_eq!(output.to_string(), expected.to_string());
+}
+
+fn derive_token_span(ex: Execution) {
+    ex.make_trait_impl(RUNTIME::span::TokenSpan, ex.target_type(), |block| {
+        block.make_function(RUNTIME::span::TokenSpan::token_span, |make_function| {
+            match make_function.arg(1).data() {
+                Data::Enum(token) => token.match_variant(|variant| match variant {
+                    Variant::Unit(_variant) => RUNTIME::std::option::Option::None.INVOKE(),
+                    Variant::Tuple(variant) => {
+                        let span = variant.fields().next().unwrap().get_value();
+                        RUNTIME::std::option::Option::Some.INVOKE(span)
+                    }
+                    Variant::Struct(variant) => {
+                        let span = variant.fields().next().unwrap().get_value();
+                        RUNTIME::std::option::Option::Some.INVOKE(span)
+                    }
+                }),
+                Data::Struct(_) => unreachable!(),
+            }
+        });
+
+        block.make_function(RUNTIME::span::TokenSpan::lo_or_start, |make_function| {
+            match make_function.arg(1).data() {
+                Data::Enum(option) => option.match_variant(|variant| match variant {
+                    Variant::Tuple(variant) => variant.fields().next().unwrap().get_value(),
+                    _ => RUNTIME::span::Pos::start.INVOKE(),
+                }),
+                Data::Struct(_) => unreachable!(),
+            }
+        });
+    });
+}
+
+#[test]
+fn test_match() {
+    let input = quote! {
+        struct Input;
+    };
+
+    let expected = quote! {
+        #[automatically_derived]
+        impl ::span::TokenSpan for Input {
+            fn token_span<'__a2, '__a1>(
+                &'__a2 self,
+                __arg0: &'__a1 ::span::Token
+            ) -> ::std::option::Option<&'__a1 ::span::Span> {
+                let __v0 = __arg0;
+                let __v6 = match __v0 {
+                    ::span::Token::Ident(__v1) => {
+                        let __v3 = ::std::option::Option::Some(__v1);
+                        __v3
+                    }
+                    ::span::Token::Punct { span: __v2 } => {
+                        let __v4 = ::std::option::Option::Some(__v2);
+                        __v4
+                    }
+                    ::span::Token::Eof => {
+                        let __v5 = ::std::option::Option::None;
+                        __v5
+                    }
+                };
+                __v6
+            }
+
+            fn lo_or_start<'__a3>(
+                &'__a3 self,
+                __arg0: ::std::option::Option<::span::Pos>
+            ) -> ::span::Pos {
+                let __v7 = __arg0;
+                let __v10 = match __v7 {
+                    ::std::option::Option::Some(__v8) => {
+                        __v8
+                    }
+                    ::std::option::Option::None => {
+                        let __v9 = ::span::Pos::start();
+                        __v9
+                    }
+                };
+                __v10
+            }
+        }
+    };
+
+    let output = reflect::derive(input, derive_token_span);
+    assert_eq!(output.to_string(), expected.to_string());
+}
+
+fn derive_first_span(ex: Execution) {
+    ex.make_trait_impl(RUNTIME::span::FirstSpan, ex.target_type(), |block| {
+        block.make_function(RUNTIME::span::FirstSpan::first_span, |make_function| {
+            match make_function.arg(0).data() {
+                Data::Enum(receiver) => {
+                    receiver.match_variant(|variant| match variant.fields().next() {
+                        Some(field) => RUNTIME::std::option::Option::Some.INVOKE(field.get_value()),
+                        None => RUNTIME::std::option::Option::None.INVOKE(),
+                    })
+                }
+                Data::Struct(_) => unreachable!(),
+            }
+        });
+    });
+}
+
+#[test]
+fn test_match_derive_input() {
+    let input = quote! {
+        enum Input {
+            Ident(Span),
+            Group { open: Span, close: Span },
+            Eof,
+        }
+    };
+
+    let expected = quote! {
+        #[automatically_derived]
+        impl ::span::FirstSpan for Input {
+            fn first_span<'__a1>(&'__a1 self) -> ::std::option::Option<&'__a1 ::span::Span> {
+                let __v0 = self;
+                let __v7 = match __v0 {
+                    Input::Ident(__v1) => {
+                        let __v4 = ::std::option::Option::Some(__v1);
+                        __v4
+                    }
+                    Input::Group { open: __v2, close: __v3 } => {
+                        let __v5 = ::std::option::Option::Some(__v2);
+                        __v5
+                    }
+                    Input::Eof => {
+                        let __v6 = ::std::option::Option::None;
+                        __v6
+                    }
+                };
+                __v7
+            }
+        }
+    };
+
+    let output = reflect::derive(input, derive_first_span);
+    assert_eq!(output.to_string(), expected.to_string());
+}
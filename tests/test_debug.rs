@@ -14,6 +14,7 @@ fn test_debug() {
     };
 
     let expected = quote! {
+        #[automatically_derived]
         impl ::std::fmt::Debug for Point {
             fn fmt<'__a1 , '__a2>(&'__a1 self, __arg0: &'__a2 mut ::std::fmt::Formatter) -> ::std::fmt::Result {
                 /*
@@ -60,11 +61,12 @@ fn test_generic_debug() {
     };
 
     let expected = quote! {
+        #[automatically_derived]
         impl<'__a1, __T0, __T1> ::std::fmt::Debug for Generic<'__a1, __T0, __T1>
         where
+            &'__a1 __T1: ::std::fmt::Debug,
             __T0: ::std::fmt::Debug,
             __T1: ::std::clone::Clone,
-            &'__a1 __T1: ::std::fmt::Debug,
         {
             fn fmt<'__a2, '__a3>(
                 &'__a2 self,
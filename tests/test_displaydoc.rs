@@ -77,13 +77,11 @@ fn display_fmt(f: MakeFunction) -> Value {
 fn extract_doc_comment(attrs: &[Attribute]) -> Option<String> {
     for attr in attrs {
         if attr.path.is_ident("doc") {
-            if let Ok(meta) = attr.parse_meta() {
-                if let Meta::NameValue(MetaNameValue {
-                    lit: Lit::Str(lit), ..
-                }) = meta
-                {
-                    return Some(lit.value().trim().to_owned());
-                }
+            if let Ok(Meta::NameValue(MetaNameValue {
+                lit: Lit::Str(lit), ..
+            })) = attr.parse_meta()
+            {
+                return Some(lit.value().trim().to_owned());
             }
         }
     }
@@ -105,6 +103,7 @@ fn test_displaydoc() {
     };
 
     let expected = quote! {
+        #[automatically_derived]
         impl ::std::fmt::Display for Point {
             fn fmt<'__a1, '__a2>(&'__a1 self, __arg0: &'__a2 mut ::std::fmt::Formatter) -> ::std::fmt::Result {
                 let __v0 = self;
@@ -0,0 +1,161 @@
+use quote::quote;
+use reflect::*;
+
+library! {
+    use elision {
+        type Text;
+
+        impl Text {
+            fn prefix(::std::primitive::usize, &::std::primitive::str) -> &::std::primitive::str;
+            fn suffix(&self, &::std::primitive::str) -> &::std::primitive::str;
+            fn find(&self, &dyn ::std::ops::Fn(&::std::primitive::str) -> ::std::primitive::bool) -> &::std::primitive::str;
+            fn split(&::std::primitive::str, ::std::boxed::Box<dyn ::std::ops::Fn(&::std::primitive::str) -> ::std::primitive::bool>) -> &::std::primitive::str;
+        }
+
+        trait Shorten<'a, 'b> {
+            fn shorten(::std::primitive::usize, &'a ::std::primitive::str) -> &'b ::std::primitive::str;
+        }
+
+        trait Suffix<'a> {
+            fn suffix(&self, &'a ::std::primitive::str) -> &::std::primitive::str;
+        }
+
+        trait Find {
+            fn find(&self, &dyn ::std::ops::Fn(&::std::primitive::str) -> ::std::primitive::bool) -> &::std::primitive::str;
+        }
+    }
+}
+
+#[test]
+fn test_single_input_lifetime() {
+    let input = quote! {
+        struct Unit;
+    };
+
+    fn derive(ex: Execution) {
+        ex.make_trait_impl(RUNTIME::elision::Shorten, ex.target_type(), |block| {
+            block.make_function(RUNTIME::elision::Shorten::shorten, |make_function| {
+                let len = make_function.arg(0);
+                let text = make_function.arg(1);
+                RUNTIME::elision::Text::prefix.INVOKE(len, text)
+            });
+        });
+    }
+
+    let expected = quote! {
+        #[automatically_derived]
+        impl<'__a1, '__a2> ::elision::Shorten<'__a1, '__a2> for Unit
+        where
+            '__a1: '__a2,
+        {
+            fn shorten(
+                __arg0: ::std::primitive::usize,
+                __arg1: &'__a1 ::std::primitive::str
+            ) -> &'__a2 ::std::primitive::str {
+                let __v0 = __arg0;
+                let __v1 = __arg1;
+                let __v2 = ::elision::Text::prefix(__v0, __v1);
+                __v2
+            }
+        }
+    };
+
+    let output = reflect::derive(input, derive);
+    assert_eq!(output.to_string(), expected.to_string());
+}
+
+#[test]
+fn test_self_lifetime() {
+    let input = quote! {
+        struct Wrapper(::elision::Text);
+    };
+
+    fn derive(ex: Execution) {
+        ex.make_trait_impl(RUNTIME::elision::Suffix, ex.target_type(), |block| {
+            block.make_function(RUNTIME::elision::Suffix::suffix, |make_function| {
+                let receiver = make_function.arg(0);
+                let text = make_function.arg(1);
+                match receiver.data() {
+                    Data::Struct(Struct::Tuple(receiver)) => {
+                        let field = receiver.fields().next().unwrap().get_value();
+                        RUNTIME::elision::Text::suffix.INVOKE(field, text)
+                    }
+                    _ => unimplemented!(),
+                }
+            });
+        });
+    }
+
+    let expected = quote! {
+        #[automatically_derived]
+        impl<'__a1> ::elision::Suffix<'__a1> for Wrapper {
+            fn suffix<'__a2>(
+                &'__a2 self,
+                __arg0: &'__a1 ::std::primitive::str
+            ) -> &'__a2 ::std::primitive::str {
+                let __v0 = self;
+                let __v1 = __arg0;
+                let __v2 = &__v0.0;
+                let __v3 = ::elision::Text::suffix(__v2, __v1);
+                __v3
+            }
+        }
+    };
+
+    let output = reflect::derive(input, derive);
+    assert_eq!(output.to_string(), expected.to_string());
+}
+
+#[test]
+fn test_fn_sugar_lifetimes() {
+    let input = quote! {
+        struct Wrapper(::elision::Text);
+    };
+
+    fn derive(ex: Execution) {
+        ex.make_trait_impl(RUNTIME::elision::Find, ex.target_type(), |block| {
+            block.make_function(RUNTIME::elision::Find::find, |make_function| {
+                let receiver = make_function.arg(0);
+                let predicate = make_function.arg(1);
+                match receiver.data() {
+                    Data::Struct(Struct::Tuple(receiver)) => {
+                        let field = receiver.fields().next().unwrap().get_value();
+                        RUNTIME::elision::Text::find.INVOKE(field, predicate)
+                    }
+                    _ => unimplemented!(),
+                }
+            });
+        });
+    }
+
+    let expected = quote! {
+        #[automatically_derived]
+        impl ::elision::Find for Wrapper {
+            fn find<'__a1, '__a2>(
+                &'__a1 self,
+                __arg0: &'__a2 (dyn ::std::ops::Fn(&::std::primitive::str) -> ::std::primitive::bool)
+            ) -> &'__a1 ::std::primitive::str {
+                let __v0 = self;
+                let __v1 = __arg0;
+                let __v2 = &__v0.0;
+                let __v3 = ::elision::Text::find(__v2, __v1);
+                __v3
+            }
+        }
+    };
+
+    let output = reflect::derive(input, derive);
+    assert_eq!(output.to_string(), expected.to_string());
+}
+
+#[test]
+fn test_fn_sugar_is_not_a_lifetime_position() {
+    let sig = Signature::parse("fn(&str, ::std::boxed::Box<dyn Fn(&str)>) -> &str");
+    assert!(sig.is_ok());
+}
+
+#[test]
+fn test_missing_lifetime() {
+    let err = Signature::parse("fn(&str, &str) -> &str").unwrap_err();
+    assert_eq!(err.to_string(), "missing lifetime specifier");
+}
@@ -56,6 +56,7 @@ fn test_field_access() {
 
     // skip_me field should be skipped from the accessing
     let expected = quote! {
+        #[automatically_derived]
         impl ::base::Trait for Test {
             fn trivial<'__a1>(&'__a1 self) {
                 let __v0 = self;
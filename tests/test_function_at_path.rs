@@ -0,0 +1,125 @@
+use quote::quote;
+use reflect::library::std::*;
+use reflect::runtime::RuntimeType;
+use reflect::*;
+
+// The signature of functions named in `#[debug(with = "...")]`:
+// fn<T: Display>(&T, &mut Formatter) -> fmt::Result
+fn with_signature() -> Signature {
+    let mut sig = Signature::new();
+    sig.set_generic_params(&["T"]);
+    sig.set_generic_constraints(&["T: ::std::fmt::Display"]);
+    sig.add_input(|env: &mut GenericsEnv| Type::type_param_from_str("T", env).reference());
+    sig.add_input(fmt::Formatter.SELF().reference_mut());
+    sig.set_output(fmt::Result.SELF());
+    sig
+}
+
+fn derive_debug(ex: Execution) {
+    let mut schema = AttrSchema::new("debug");
+    let with = schema.add_path("with");
+
+    ex.make_trait_impl(fmt::Debug, ex.target_type(), |block| {
+        block.make_function(fmt::Debug::fmt, |f| {
+            let receiver = f.arg(0);
+            let formatter = f.arg(1);
+
+            match receiver.data() {
+                Data::Struct(Struct::Struct(receiver)) => {
+                    let field = receiver.fields().next().unwrap();
+                    let attrs = field.parse_attrs(&schema).unwrap();
+                    let with = attrs.function(&with, with_signature()).unwrap();
+                    with.invoke(&[field.get_value(), formatter])
+                }
+                _ => unimplemented!(),
+            }
+        });
+    });
+}
+
+#[test]
+fn test_function_at_path() {
+    let input = quote! {
+        struct Wrapper<T> {
+            #[debug(with = "my::fmt_inner")]
+            inner: T,
+        }
+    };
+
+    let expected = quote! {
+        #[automatically_derived]
+        impl<__T0> ::std::fmt::Debug for Wrapper<__T0>
+        where
+            __T0: ::std::fmt::Display,
+        {
+            fn fmt<'__a1, '__a2>(
+                &'__a1 self,
+                __arg0: &'__a2 mut ::std::fmt::Formatter
+            ) -> ::std::fmt::Result {
+                let __v0 = self;
+                let __v1 = __arg0;
+                let __v2 = &__v0.inner;
+                let __v3 = my::fmt_inner(__v2, __v1);
+                __v3
+            }
+        }
+    };
+
+    let output = derive(input, derive_debug);
+    assert_eq!(output.to_string(), expected.to_string());
+}
+
+#[test]
+fn test_function_at_path_str() {
+    let input = quote! {
+        struct Wrapper<T> {
+            inner: T,
+        }
+    };
+
+    let expected = quote! {
+        #[automatically_derived]
+        impl<__T0> ::std::fmt::Debug for Wrapper<__T0>
+        where
+            __T0: ::std::fmt::Display,
+        {
+            fn fmt<'__a1, '__a2>(
+                &'__a1 self,
+                __arg0: &'__a2 mut ::std::fmt::Formatter
+            ) -> ::std::fmt::Result {
+                let __v0 = self;
+                let __v1 = __arg0;
+                let __v2 = &__v0.inner;
+                let __v3 = my::fmt_inner(__v2, __v1);
+                __v3
+            }
+        }
+    };
+
+    let output = derive(input, |ex| {
+        ex.make_trait_impl(fmt::Debug, ex.target_type(), |block| {
+            block.make_function(fmt::Debug::fmt, |f| {
+                let receiver = f.arg(0);
+                let formatter = f.arg(1);
+
+                match receiver.data() {
+                    Data::Struct(Struct::Struct(receiver)) => {
+                        let field = receiver.fields().next().unwrap();
+                        let with =
+                            Function::get_function_at_path_str("my::fmt_inner", with_signature())
+                                .unwrap();
+                        with.invoke(&[field.get_value(), formatter])
+                    }
+                    _ => unimplemented!(),
+                }
+            });
+        });
+    });
+    assert_eq!(output.to_string(), expected.to_string());
+}
+
+#[test]
+fn test_function_at_path_str_error() {
+    let error = Function::get_function_at_path_str("not a path", with_signature()).unwrap_err();
+    assert_eq!(error.to_string(), "expected a path, found `not a path`");
+}
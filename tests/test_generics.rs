@@ -17,6 +17,16 @@ library! {
         trait Bound {}
 
         trait TraitArgs<'a, 'b: 'a, T, U: 'static> {}
+
+        trait Into<T> {}
+
+        trait Convert<T> {
+            fn convert<U: Into<T>>(self, U);
+        }
+
+        trait Shadow<T> {
+            fn shadow<T>(self, T);
+        }
     }
 }
 
@@ -37,6 +47,7 @@ fn test_generics1() {
     }
 
     let expected = quote! {
+        #[automatically_derived]
         impl<'__a1, __T0, __T1> ::generic::Generic<__T1> for Generics<'__a1, 'static, __T0>
         where
             __T0: ::generic::Bound + Send,
@@ -86,6 +97,7 @@ fn test_generics2() {
     }
 
     let expected = quote! {
+        #[automatically_derived]
         impl<__T0, __T1, __T4, __T7> ::generic::Generic<__T4>
             for GenericQuadruple<
                 __T0,
@@ -118,3 +130,88 @@ fn test_generics2() {
 
     assert_eq!(&output, &expected.to_string());
 }
+
+#[test]
+fn test_method_bound_on_parent_param() {
+    let input = quote! {
+        struct Unit;
+    };
+
+    fn derive(ex: Execution) {
+        ex.make_trait_impl(RUNTIME::generic::Convert, ex.target_type(), |block| {
+            block.make_function(RUNTIME::generic::Convert::convert, |make_function| {
+                make_function.unit()
+            });
+        });
+    }
+
+    let expected = quote! {
+        #[automatically_derived]
+        impl<__T0> ::generic::Convert<__T0> for Unit {
+            fn convert<__T1>(self, __arg0: __T1)
+            where
+                __T1: ::generic::Into<__T0>,
+            {}
+        }
+    };
+
+    let output = reflect::derive(input, derive);
+    assert_eq!(output.to_string(), expected.to_string());
+}
+
+#[test]
+fn test_shadowed_trait_param() {
+    let input = quote! {
+        struct Unit;
+    };
+
+    fn derive(ex: Execution) {
+        ex.make_trait_impl(RUNTIME::generic::Shadow, ex.target_type(), |block| {
+            block.make_function(RUNTIME::generic::Shadow::shadow, |make_function| {
+                make_function.unit()
+            });
+        });
+    }
+
+    let expected = quote! {
+        #[automatically_derived]
+        impl<__T0> ::generic::Shadow<__T0> for Unit {
+            fn shadow<__T1>(self, __arg0: __T1) {}
+        }
+    };
+
+    let output = reflect::derive(input, derive);
+    assert_eq!(output.to_string(), expected.to_string());
+}
+
+#[test]
+fn test_shadowed_param_parse() {
+    let input = quote! {
+        struct Unit;
+    };
+
+    fn derive(ex: Execution) {
+        ex.make_trait_impl(RUNTIME::generic::Shadow, ex.target_type(), |block| {
+            let parent = runtime::RuntimeParent::SELF(RUNTIME::generic::Shadow);
+            let mut sig = Signature::parse("fn<T>(self, T)").unwrap();
+            sig.add_parent_params(parent.get_generics_env());
+            sig.set_generic_constraints(&["T: ::generic::Bound"]);
+            let mut shadow = Function::get_function("shadow", sig);
+            shadow.set_parent(parent);
+            block.make_function(shadow, |make_function| make_function.unit());
+        });
+    }
+
+    let expected = quote! {
+        #[automatically_derived]
+        impl<__T0> ::generic::Shadow<__T0> for Unit {
+            fn shadow<__T1>(self, __arg0: __T1)
+            where
+                __T1: ::generic::Bound,
+            {}
+        }
+    };
+
+    let output = reflect::derive(input, derive);
+    assert_eq!(output.to_string(), expected.to_string());
+}
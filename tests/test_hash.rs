@@ -52,6 +52,7 @@ fn test_hash() {
     };
 
     let expected = quote! {
+        #[automatically_derived]
         impl ::std::hash::Hash for Point {
             fn hash<'__a1, '__a2, __T0>(&'__a1 self, __arg0: &'__a2 mut __T0)
             where
@@ -82,6 +83,7 @@ fn test_generic_hash() {
     };
 
     let expected = quote! {
+        #[automatically_derived]
         impl<__T0, __T1> ::std::hash::Hash for Generic<__T0, __T1>
         where
             __T0: ::std::hash::Hash,
@@ -0,0 +1,99 @@
+use quote::quote;
+use reflect::*;
+
+library! {
+    use std {
+        mod string {
+            type String;
+        }
+    }
+    use serde {
+        trait Deserialize<'de> {}
+
+        trait ToOwned {
+            fn to_owned(&self) -> ::std::string::String;
+        }
+
+        type Owned;
+
+        impl Owned {
+            fn from_str<T>(&T) -> ::std::string::String
+            where
+                for<'de> T: ::serde::Deserialize<'de>;
+            fn call<F: for<'a> ::std::ops::Fn(&'a ::std::primitive::str) -> R, R>(&F) -> R;
+        }
+    }
+}
+
+fn derive_to_owned(ex: Execution, to_owned: fn(Value) -> Value) {
+    ex.make_trait_impl(RUNTIME::serde::ToOwned, ex.target_type(), |block| {
+        block.make_function(RUNTIME::serde::ToOwned::to_owned, |make_function| {
+            let receiver = make_function.arg(0);
+            match receiver.data() {
+                Data::Struct(Struct::Struct(receiver)) => {
+                    let field = receiver.fields().next().unwrap();
+                    to_owned(field.get_value())
+                }
+                _ => unimplemented!(),
+            }
+        });
+    });
+}
+
+#[test]
+fn test_higher_ranked_predicate() {
+    let input = quote! {
+        struct Wrapper<T> {
+            value: T,
+        }
+    };
+
+    let expected = quote! {
+        #[automatically_derived]
+        impl<__T0> ::serde::ToOwned for Wrapper<__T0>
+        where
+            for<'__a5> __T0: ::serde::Deserialize<'__a5>,
+        {
+            fn to_owned<'__a1>(&'__a1 self) -> ::std::string::String {
+                let __v0 = self;
+                let __v1 = &__v0.value;
+                let __v2 = ::serde::Owned::from_str(__v1);
+                __v2
+            }
+        }
+    };
+
+    let output = derive(input, |ex| {
+        derive_to_owned(ex, |value| RUNTIME::serde::Owned::from_str.INVOKE(value))
+    });
+    assert_eq!(output.to_string(), expected.to_string());
+}
+
+#[test]
+fn test_higher_ranked_trait_bound() {
+    let input = quote! {
+        struct Callback<F> {
+            f: F,
+        }
+    };
+
+    let expected = quote! {
+        #[automatically_derived]
+        impl<__T0> ::serde::ToOwned for Callback<__T0>
+        where
+            __T0: for<'__a5> ::std::ops::Fn(&'__a5 ::std::primitive::str) -> ::std::string::String,
+        {
+            fn to_owned<'__a1>(&'__a1 self) -> ::std::string::String {
+                let __v0 = self;
+                let __v1 = &__v0.f;
+                let __v2 = ::serde::Owned::call(__v1);
+                __v2
+            }
+        }
+    };
+
+    let output = derive(input, |ex| {
+        derive_to_owned(ex, |value| RUNTIME::serde::Owned::call.INVOKE(value))
+    });
+    assert_eq!(output.to_string(), expected.to_string());
+}
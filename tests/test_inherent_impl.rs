@@ -0,0 +1,117 @@
+use quote::quote;
+use reflect::library::std::*;
+use reflect::runtime::RuntimeType;
+use reflect::*;
+
+fn derive_describe(ex: Execution) {
+    ex.make_impl(ex.target_type(), |block| {
+        let mut sig = Signature::new();
+        sig.set_self_by_reference();
+        sig.add_input(fmt::Formatter.SELF().reference_mut());
+        sig.set_output(fmt::Result.SELF());
+        block.make_function(Function::get_function("describe", sig), describe);
+    });
+}
+
+fn describe(f: MakeFunction) -> Value {
+    let receiver = f.arg(0);
+    let formatter = f.arg(1);
+    debug_fields(f, receiver, formatter)
+}
+
+fn debug_fields(f: MakeFunction, receiver: Value, formatter: Value) -> Value {
+    match receiver.data() {
+        Data::Struct(Struct::Struct(receiver)) => {
+            let builder = fmt::Formatter::debug_struct
+                .INVOKE(formatter, f.string("fields"))
+                .reference_mut();
+            for field in receiver.fields() {
+                fmt::DebugStruct::field.INVOKE(builder, field.get_name(), field.get_value());
+            }
+            fmt::DebugStruct::finish.INVOKE(builder)
+        }
+        _ => unimplemented!(),
+    }
+}
+
+#[test]
+fn test_inherent_impl() {
+    let input = quote! {
+        struct Point<T> {
+            x: T,
+            label: String,
+        }
+    };
+
+    let expected = quote! {
+        impl<__T0> Point<__T0>
+        where
+            __T0: ::std::fmt::Debug,
+        {
+            fn describe<'__a1, '__a2>(
+                &'__a1 self,
+                __arg0: &'__a2 mut ::std::fmt::Formatter
+            ) -> ::std::fmt::Result {
+                let __v0 = self;
+                let __v1 = __arg0;
+                let __v2 = &__v0.x;
+                let __v3 = &__v0.label;
+                let mut __v5 = ::std::fmt::Formatter::debug_struct(__v1, "fields");
+                let __v6 = &mut __v5;
+                let _ = ::std::fmt::DebugStruct::field(__v6, "x", __v2);
+                let _ = ::std::fmt::DebugStruct::field(__v6, "label", __v3);
+                let __v11 = ::std::fmt::DebugStruct::finish(__v6);
+                __v11
+            }
+        }
+    };
+
+    let output = derive(input, derive_describe);
+    assert_eq!(output.to_string(), expected.to_string());
+}
+
+fn derive_free_function(ex: Execution) {
+    let mut sig = Signature::new();
+    sig.add_input(ex.target_type().reference());
+    sig.add_input(fmt::Formatter.SELF().reference_mut());
+    sig.set_output(fmt::Result.SELF());
+    ex.make_free_function(Function::get_function("describe_point", sig), |f| {
+        let receiver = f.arg(0);
+        let formatter = f.arg(1);
+        debug_fields(f, receiver, formatter)
+    });
+}
+
+#[test]
+fn test_free_function() {
+    let input = quote! {
+        struct Point<T> {
+            x: T,
+            label: String,
+        }
+    };
+
+    let expected = quote! {
+        fn describe_point<__T0, '__a1, '__a2>(
+            __arg0: &'__a1 Point<__T0>,
+            __arg1: &'__a2 mut ::std::fmt::Formatter
+        ) -> ::std::fmt::Result
+        where
+            __T0: ::std::fmt::Debug,
+        {
+            let __v0 = __arg0;
+            let __v1 = __arg1;
+            let __v2 = &__v0.x;
+            let __v3 = &__v0.label;
+            let mut __v5 = ::std::fmt::Formatter::debug_struct(__v1, "fields");
+            let __v6 = &mut __v5;
+            let _ = ::std::fmt::DebugStruct::field(__v6, "x", __v2);
+            let _ = ::std::fmt::DebugStruct::field(__v6, "label", __v3);
+            let __v11 = ::std::fmt::DebugStruct::finish(__v6);
+            __v11
+        }
+    };
+
+    let output = derive(input, derive_free_function);
+    assert_eq!(output.to_string(), expected.to_string());
+}
@@ -0,0 +1,63 @@
+use quote::quote;
+use reflect::*;
+
+library! {
+    use zero {
+        trait Zero {
+            fn zero();
+        }
+    }
+}
+
+fn derive_zero(ex: Execution) {
+    ex.make_trait_impl(RUNTIME::zero::Zero, ex.target_type(), |block| {
+        block.add_attribute("allow(unused_qualifications)");
+        block.make_function(RUNTIME::zero::Zero::zero, |make_function| {
+            make_function.add_attribute("inline");
+            make_function.unit()
+        });
+    });
+}
+
+#[test]
+fn test_item_attributes() {
+    let input = quote! {
+        struct Unit;
+    };
+
+    let expected = quote! {
+        #[automatically_derived]
+        #[allow(unused_qualifications)]
+        impl ::zero::Zero for Unit {
+            #[inline]
+            fn zero() {}
+        }
+    };
+
+    let output = derive(input, derive_zero);
+    assert_eq!(output.to_string(), expected.to_string());
+}
+
+fn derive_free_function(ex: Execution) {
+    let mut sig = Signature::new();
+    sig.add_input(ex.target_type().reference());
+    ex.make_free_function(Function::get_function("ignore", sig), |f| {
+        f.add_attribute("doc(hidden)");
+        f.unit()
+    });
+}
+
+#[test]
+fn test_free_function_attributes() {
+    let input = quote! {
+        struct Unit;
+    };
+
+    let expected = quote! {
+        #[doc(hidden)]
+        fn ignore<'__a1>(__arg0: &'__a1 Unit) {}
+    };
+
+    let output = derive(input, derive_free_function);
+    assert_eq!(output.to_string(), expected.to_string());
+}
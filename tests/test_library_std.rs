@@ -0,0 +1,396 @@
+use quote::quote;
+use reflect::library::std::*;
+use reflect::*;
+
+fn derive_debug(ex: Execution) {
+    ex.make_trait_impl(fmt::Debug, ex.target_type(), |block| {
+        block.make_function(fmt::Debug::fmt, debug_fmt);
+    });
+}
+
+fn debug_fmt(f: MakeFunction) -> Value {
+    let receiver = f.arg(0);
+    let formatter = f.arg(1);
+    let type_name = receiver.get_type_name();
+
+    match receiver.data() {
+        Data::Struct(Struct::Tuple(receiver)) => {
+            let builder = fmt::Formatter::debug_tuple
+                .INVOKE(formatter, type_name)
+                .reference_mut();
+            for field in receiver.fields() {
+                fmt::DebugTuple::field.INVOKE(builder, field.get_value());
+            }
+            fmt::DebugTuple::finish.INVOKE(builder)
+        }
+        _ => unimplemented!(),
+    }
+}
+
+#[test]
+fn test_debug() {
+    let input = quote! {
+        struct Wrapper<T>(T, String);
+    };
+
+    let expected = quote! {
+        #[automatically_derived]
+        impl<__T0> ::std::fmt::Debug for Wrapper<__T0>
+        where
+            __T0: ::std::fmt::Debug,
+        {
+            fn fmt<'__a1, '__a2>(
+                &'__a1 self,
+                __arg0: &'__a2 mut ::std::fmt::Formatter
+            ) -> ::std::fmt::Result {
+                let __v0 = self;
+                let __v1 = __arg0;
+                let __v3 = &__v0.0;
+                let __v4 = &__v0.1;
+                let mut __v5 = ::std::fmt::Formatter::debug_tuple(__v1, "Wrapper");
+                let __v6 = &mut __v5;
+                let _ = ::std::fmt::DebugTuple::field(__v6, __v3);
+                let _ = ::std::fmt::DebugTuple::field(__v6, __v4);
+                let __v9 = ::std::fmt::DebugTuple::finish(__v6);
+                __v9
+            }
+        }
+    };
+
+    let output = derive(input, derive_debug);
+    assert_eq!(output.to_string(), expected.to_string());
+}
+
+fn derive_partial_eq(ex: Execution) {
+    ex.make_trait_impl(cmp::PartialEq, ex.target_type(), |block| {
+        block.make_function(cmp::PartialEq::eq, partial_eq_eq);
+    });
+}
+
+fn partial_eq_eq(f: MakeFunction) -> Value {
+    // The other value has the type `&Self`
+    match (f.arg(0).data(), f.arg(1).data()) {
+        (Data::Struct(Struct::Tuple(receiver)), Data::Struct(Struct::Tuple(other))) => {
+            let receiver = receiver.fields().next().unwrap().get_value();
+            let other = other.fields().next().unwrap().get_value();
+            cmp::PartialEq::eq.INVOKE(receiver, other)
+        }
+        _ => unimplemented!(),
+    }
+}
+
+#[test]
+fn test_self_input() {
+    let input = quote! {
+        struct Meters<T>(T);
+    };
+
+    let expected = quote! {
+        #[automatically_derived]
+        impl<__T0> ::std::cmp::PartialEq for Meters<__T0>
+        where
+            __T0: ::std::cmp::PartialEq,
+        {
+            fn eq<'__a1, '__a2>(&'__a1 self, __arg0: &'__a2 Self) -> ::std::primitive::bool {
+                let __v0 = self;
+                let __v1 = &__v0.0;
+                let __v2 = __arg0;
+                let __v3 = &__v2.0;
+                let __v4 = ::std::cmp::PartialEq::eq(__v1, __v3);
+                __v4
+            }
+        }
+    };
+
+    let output = derive(input, derive_partial_eq);
+    assert_eq!(output.to_string(), expected.to_string());
+}
+
+fn derive_default(ex: Execution) {
+    let ty = ex.target_type();
+    ex.make_trait_impl(default::Default, ex.target_type(), |block| {
+        block.make_function(default::Default::default, |_f| {
+            let fields: Vec<_> = match ty.data() {
+                Data::Struct(data) => data
+                    .fields()
+                    .map(|_| default::Default::default.INVOKE())
+                    .collect(),
+                Data::Enum(_) => unimplemented!(),
+            };
+            ty.construct(&fields)
+        });
+    });
+}
+
+#[test]
+fn test_self_output() {
+    let input = quote! {
+        struct Config<T>(T);
+    };
+
+    let expected = quote! {
+        #[automatically_derived]
+        impl<__T0> ::std::default::Default for Config<__T0>
+        where
+            __T0: ::std::default::Default,
+        {
+            fn default() -> Self {
+                let __v0 = ::std::default::Default::default();
+                let __v1 = Config(__v0);
+                __v1
+            }
+        }
+    };
+
+    let output = derive(input, derive_default);
+    assert_eq!(output.to_string(), expected.to_string());
+}
+
+#[test]
+fn test_named_fields_output() {
+    let input = quote! {
+        struct Config<T> {
+            name: T,
+            verbose: bool,
+        }
+    };
+
+    let expected = quote! {
+        #[automatically_derived]
+        impl<__T0> ::std::default::Default for Config<__T0>
+        where
+            __T0: ::std::default::Default,
+        {
+            fn default() -> Self {
+                let __v0 = ::std::default::Default::default();
+                let __v1 = ::std::default::Default::default();
+                let __v2 = Config {
+                    name: __v0,
+                    verbose: __v1
+                };
+                __v2
+            }
+        }
+    };
+
+    let output = derive(input, derive_default);
+    assert_eq!(output.to_string(), expected.to_string());
+}
+
+fn derive_clone(ex: Execution) {
+    let ty = ex.target_type();
+    ex.make_trait_impl(clone::Clone, ex.target_type(), |block| {
+        block.make_function(clone::Clone::clone, |f| {
+            let receiver = f.arg(0);
+            let fields: Vec<_> = match receiver.data() {
+                Data::Struct(data) => data
+                    .fields()
+                    .map(|field| clone::Clone::clone.INVOKE(field.get_value()))
+                    .collect(),
+                Data::Enum(_) => unimplemented!(),
+            };
+            ty.construct(&fields)
+        });
+    });
+}
+
+#[test]
+fn test_clone() {
+    let input = quote! {
+        struct Pair<A, B> {
+            left: A,
+            right: B,
+        }
+    };
+
+    let expected = quote! {
+        #[automatically_derived]
+        impl<__T0, __T1> ::std::clone::Clone for Pair<__T0, __T1>
+        where
+            __T0: ::std::clone::Clone,
+            __T1: ::std::clone::Clone,
+        {
+            fn clone<'__a1>(&'__a1 self) -> Self {
+                let __v0 = self;
+                let __v1 = &__v0.left;
+                let __v2 = &__v0.right;
+                let __v3 = ::std::clone::Clone::clone(__v1);
+                let __v4 = ::std::clone::Clone::clone(__v2);
+                let __v5 = Pair {
+                    left: __v3,
+                    right: __v4
+                };
+                __v5
+            }
+        }
+    };
+
+    let output = derive(input, derive_clone);
+    assert_eq!(output.to_string(), expected.to_string());
+}
+
+fn derive_hash(ex: Execution) {
+    ex.make_trait_impl(hash::Hash, ex.target_type(), |block| {
+        block.make_function(hash::Hash::hash, |f| {
+            let receiver = f.arg(0);
+            let state = f.arg(1);
+            match receiver.data() {
+                Data::Struct(data) => {
+                    for field in data.fields() {
+                        hash::Hash::hash.INVOKE(field.get_value(), state);
+                    }
+                }
+                Data::Enum(_) => unimplemented!(),
+            }
+            f.unit()
+        });
+    });
+}
+
+#[test]
+fn test_hash() {
+    let input = quote! {
+        struct Point<T>(T, T);
+    };
+
+    let expected = quote! {
+        #[automatically_derived]
+        impl<__T0> ::std::hash::Hash for Point<__T0>
+        where
+            __T0: ::std::hash::Hash,
+        {
+            fn hash<'__a1, '__a2, __T3>(&'__a1 self, __arg0: &'__a2 mut __T3)
+            where
+                __T3: ::std::hash::Hasher,
+            {
+                let __v0 = self;
+                let __v1 = __arg0;
+                let __v2 = &__v0.0;
+                let __v3 = &__v0.1;
+                let _ = ::std::hash::Hash::hash(__v2, __v1);
+                let _ = ::std::hash::Hash::hash(__v3, __v1);
+            }
+        }
+    };
+
+    let output = derive(input, derive_hash);
+    assert_eq!(output.to_string(), expected.to_string());
+}
+
+fn derive_display(ex: Execution) {
+    ex.make_trait_impl(fmt::Display, ex.target_type(), |block| {
+        block.make_function(fmt::Display::fmt, |f| {
+            let receiver = f.arg(0);
+            let formatter = f.arg(1);
+            match receiver.data() {
+                Data::Struct(Struct::Tuple(data)) => {
+                    let inner = data.fields().next().unwrap().get_value();
+                    let message = format.INVOKE(&[f.string("<{}>"), inner]);
+                    write.INVOKE(&[formatter, f.string("{}"), message])
+                }
+                _ => unimplemented!(),
+            }
+        });
+    });
+}
+
+#[test]
+fn test_macros() {
+    let input = quote! {
+        struct Wrapper(i32);
+    };
+
+    let expected = quote! {
+        #[automatically_derived]
+        impl ::std::fmt::Display for Wrapper {
+            fn fmt<'__a1, '__a2>(&'__a1 self, __arg0: &'__a2 mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+                let __v0 = self;
+                let __v1 = __arg0;
+                let __v2 = &__v0.0;
+                let __v4 = ::std::format!("<{}>", __v2);
+                let __v6 = ::std::write!(__v1, "{}", __v4);
+                __v6
+            }
+        }
+    };
+
+    let output = derive(input, derive_display);
+    assert_eq!(output.to_string(), expected.to_string());
+}
+
+library! {
+    use peek {
+        trait Peek {
+            fn peek(self, &mut ::std::fmt::Formatter) -> ::std::fmt::Result;
+        }
+    }
+}
+
+fn derive_peek(ex: Execution) {
+    ex.make_trait_impl(RUNTIME::peek::Peek, ex.target_type(), |block| {
+        block.make_function(RUNTIME::peek::Peek::peek, peek);
+    });
+}
+
+fn peek(f: MakeFunction) -> Value {
+    let receiver = f.arg(0);
+    let formatter = f.arg(1);
+
+    match receiver.data() {
+        Data::Struct(Struct::Tuple(receiver)) => {
+            let items = receiver.fields().next().unwrap().get_value();
+            let iter = iter::IntoIterator::into_iter.INVOKE(items);
+            let first = iter::Iterator::next.INVOKE(iter.reference_mut());
+            match first.data() {
+                Data::Enum(first) => first.match_variant(|variant| match variant.fields().next() {
+                    Some(item) => fmt::Debug::fmt.INVOKE(item.get_value().reference(), formatter),
+                    None => fmt::Formatter::write_str.INVOKE(formatter, f.string("None")),
+                }),
+                Data::Struct(_) => unreachable!(),
+            }
+        }
+        _ => unimplemented!(),
+    }
+}
+
+#[test]
+fn test_iter() {
+    let input = quote! {
+        struct Bag<T>(T);
+    };
+
+    let expected = quote! {
+        #[automatically_derived]
+        impl<__T0> ::peek::Peek for Bag<__T0>
+        where
+            < <__T0 as ::std::iter::IntoIterator>::IntoIter as ::std::iter::Iterator>::Item:
+                ::std::fmt::Debug,
+            <__T0 as ::std::iter::IntoIterator>::IntoIter: ::std::iter::Iterator,
+            __T0: ::std::iter::IntoIterator,
+        {
+            fn peek<'__a1>(self, __arg0: &'__a1 mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+                let __v0 = self;
+                let __v1 = __arg0;
+                let __v2 = __v0.0;
+                let mut __v3 = ::std::iter::IntoIterator::into_iter(__v2);
+                let __v4 = &mut __v3;
+                let __v5 = ::std::iter::Iterator::next(__v4);
+                let __v11 = match __v5 {
+                    ::std::option::Option::Some(__v6) => {
+                        let __v7 = &__v6;
+                        let __v8 = ::std::fmt::Debug::fmt(__v7, __v1);
+                        __v8
+                    }
+                    ::std::option::Option::None => {
+                        let __v10 = ::std::fmt::Formatter::write_str(__v1, "None");
+                        __v10
+                    }
+                };
+                __v11
+            }
+        }
+    };
+
+    let output = derive(input, derive_peek);
+    assert_eq!(output.to_string(), expected.to_string());
+}
@@ -0,0 +1,147 @@
+use quote::quote;
+use reflect::*;
+
+mod describe;
+
+use describe::Describer;
+
+library! {
+    use hash {
+        trait Hasher {}
+
+        trait Hash {
+            fn hash<H: ?Sized + ::hash::Hasher>(&self, &mut H);
+        }
+    }
+}
+
+#[test]
+fn test_maybe_sized_param() {
+    let input = quote! {
+        struct Wrapper<T: ?Sized>(Box<T>);
+    };
+
+    let expected = quote! {
+        #[automatically_derived]
+        impl<__T0> ::describe::Describe for Wrapper<__T0>
+        where
+            __T0: ?Sized,
+        {
+            fn describe<'__a1>(&'__a1 self) {
+                let __v0 = self;
+                let __v1 = &__v0.0;
+                let _ = ::describe::Describer::field(__v1);
+            }
+        }
+    };
+
+    let output = derive(input, |ex| {
+        describe::derive(ex, |fields| {
+            Describer::field.INVOKE(fields[0]);
+        })
+    });
+    assert_eq!(output.to_string(), expected.to_string());
+}
+
+#[test]
+fn test_sized_param_of_function() {
+    let input = quote! {
+        struct Wrapper<T: ?Sized>(T);
+    };
+
+    let expected = quote! {
+        #[automatically_derived]
+        impl<__T0> ::describe::Describe for Wrapper<__T0>
+        where
+            __T0: ::describe::Describe,
+        {
+            fn describe<'__a1>(&'__a1 self) {
+                let __v0 = self;
+                let __v1 = &__v0.0;
+                let _ = ::describe::Describer::field(__v1);
+            }
+        }
+    };
+
+    let output = derive(input, |ex| {
+        describe::derive(ex, |fields| {
+            Describer::field.INVOKE(fields[0]);
+        })
+    });
+    assert_eq!(output.to_string(), expected.to_string());
+}
+
+#[test]
+fn test_maybe_sized_param_of_function() {
+    let input = quote! {
+        struct Wrapper<T>(T)
+        where
+            T: ?Sized + Clone;
+    };
+
+    let expected = quote! {
+        #[automatically_derived]
+        impl<__T0> ::describe::Describe for Wrapper<__T0>
+        where
+            __T0: ::describe::Describe,
+            __T0: ?Sized + Clone,
+        {
+            fn describe<'__a1>(&'__a1 self) {
+                let __v0 = self;
+                let __v1 = &__v0.0;
+                let _ = ::describe::Describer::unsized_field(__v1);
+            }
+        }
+    };
+
+    let output = derive(input, |ex| {
+        describe::derive(ex, |fields| {
+            Describer::unsized_field.INVOKE(fields[0]);
+        })
+    });
+    assert_eq!(output.to_string(), expected.to_string());
+}
+
+#[test]
+fn test_maybe_sized_trait_function() {
+    let input = quote! {
+        struct Wrapper<T: ?Sized>(T);
+    };
+
+    let expected = quote! {
+        #[automatically_derived]
+        impl<__T0> ::hash::Hash for Wrapper<__T0>
+        where
+            __T0: ::hash::Hash,
+            __T0: ?Sized,
+        {
+            fn hash<'__a1, '__a2, __T1>(&'__a1 self, __arg0: &'__a2 mut __T1)
+            where
+                __T1: ?Sized + ::hash::Hasher,
+            {
+                let __v0 = self;
+                let __v1 = __arg0;
+                let __v2 = &__v0.0;
+                let _ = ::hash::Hash::hash(__v2, __v1);
+            }
+        }
+    };
+
+    let output = derive(input, |ex| {
+        ex.make_trait_impl(RUNTIME::hash::Hash, ex.target_type(), |block| {
+            block.make_function(RUNTIME::hash::Hash::hash, |make_function| {
+                let receiver = make_function.arg(0);
+                let state = make_function.arg(1);
+                match receiver.data() {
+                    Data::Struct(Struct::Tuple(receiver)) => {
+                        let field = receiver.fields().next().unwrap();
+                        RUNTIME::hash::Hash::hash.INVOKE(field.get_value(), state);
+                    }
+                    _ => unimplemented!(),
+                }
+                make_function.unit()
+            });
+        });
+    });
+    assert_eq!(output.to_string(), expected.to_string());
+}
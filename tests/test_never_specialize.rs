@@ -0,0 +1,72 @@
+use quote::quote;
+use reflect::*;
+
+mod describe;
+
+use describe::Describer;
+
+fn pair() -> proc_macro2::TokenStream {
+    quote! {
+        struct Pair<A, B> {
+            one: A,
+            two: B,
+        }
+    }
+}
+
+#[test]
+fn test_generic_impl() {
+    let expected = quote! {
+        #[automatically_derived]
+        impl<__T0, __T1> ::describe::Describe for Pair<__T0, __T1> {
+            fn describe<'__a1>(&'__a1 self) {
+                let __v0 = self;
+                let __v1 = &__v0.one;
+                let __v2 = &__v0.two;
+                let _ = ::describe::Describer::any(__v1);
+                let _ = ::describe::Describer::any(__v2);
+            }
+        }
+    };
+
+    let output = derive(pair(), |ex| {
+        ex.never_specialize();
+        describe::derive(ex, |fields| {
+            Describer::any.INVOKE(fields[0]);
+            Describer::any.INVOKE(fields[1]);
+        })
+    });
+    assert_eq!(output.to_string(), expected.to_string());
+}
+
+#[test]
+fn test_equal_params() {
+    let expected = quote! {
+        compile_error! { "the impl for `Pair` would only apply when `B` is `A`" }
+    };
+
+    let output = derive(pair(), |ex| {
+        ex.never_specialize();
+        describe::derive(ex, |fields| {
+            Describer::same.INVOKE(fields[0], fields[1]);
+        })
+    });
+    assert_eq!(output.to_string(), expected.to_string());
+}
+
+#[test]
+fn test_concrete_param() {
+    let expected = quote! {
+        compile_error! {
+            "the impl for `Pair` would only apply when `A` is `::std::string::String`"
+        }
+    };
+
+    let output = derive(pair(), |ex| {
+        ex.never_specialize();
+        describe::derive(ex, |fields| {
+            Describer::string.INVOKE(fields[0]);
+        })
+    });
+    assert_eq!(output.to_string(), expected.to_string());
+}
@@ -0,0 +1,137 @@
+use quote::quote;
+use reflect::*;
+
+mod describe;
+
+use describe::Describer;
+
+fn derive_describe(ex: Execution) {
+    ex.perfect_derive();
+    describe::derive(ex, |fields| {
+        for &field in fields {
+            Describer::field.INVOKE(field);
+        }
+    });
+}
+
+#[test]
+fn test_field_type_bound() {
+    let input = quote! {
+        struct Bag<T> {
+            value: T,
+            v: Vec<T>,
+        }
+    };
+
+    let expected = quote! {
+        #[automatically_derived]
+        impl<__T0> ::describe::Describe for Bag<__T0>
+        where
+            Vec<__T0>: ::describe::Describe,
+            __T0: ::describe::Describe,
+        {
+            fn describe<'__a1>(&'__a1 self) {
+                let __v0 = self;
+                let __v1 = &__v0.value;
+                let __v2 = &__v0.v;
+                let _ = ::describe::Describer::field(__v1);
+                let _ = ::describe::Describer::field(__v2);
+            }
+        }
+    };
+
+    let output = derive(input, derive_describe);
+    assert_eq!(output.to_string(), expected.to_string());
+}
+
+#[test]
+fn test_recursive_field_type() {
+    let input = quote! {
+        struct Tree<T> {
+            value: T,
+            children: Vec<Tree<T>>,
+        }
+    };
+
+    // A bound on `Vec<Tree<T>>` would require the impl itself, so only the
+    // bound on the param is kept
+    let expected = quote! {
+        #[automatically_derived]
+        impl<__T0> ::describe::Describe for Tree<__T0>
+        where
+            __T0: ::describe::Describe,
+        {
+            fn describe<'__a1>(&'__a1 self) {
+                let __v0 = self;
+                let __v1 = &__v0.value;
+                let __v2 = &__v0.children;
+                let _ = ::describe::Describer::field(__v1);
+                let _ = ::describe::Describer::field(__v2);
+            }
+        }
+    };
+
+    let output = derive(input, derive_describe);
+    assert_eq!(output.to_string(), expected.to_string());
+}
+
+#[test]
+fn test_private_field_type() {
+    let input = quote! {
+        pub struct Wrapper<T> {
+            pub value: T,
+            cache: Vec<Private<T>>,
+        }
+    };
+
+    let expected = quote! {
+        #[automatically_derived]
+        impl<__T0> ::describe::Describe for Wrapper<__T0>
+        where
+            __T0: ::describe::Describe,
+        {
+            fn describe<'__a1>(&'__a1 self) {
+                let __v0 = self;
+                let __v1 = &__v0.value;
+                let __v2 = &__v0.cache;
+                let _ = ::describe::Describer::field(__v1);
+                let _ = ::describe::Describer::field(__v2);
+            }
+        }
+    };
+
+    let output = derive(input, derive_describe);
+    assert_eq!(output.to_string(), expected.to_string());
+}
+
+#[test]
+fn test_pub_field_of_private_type() {
+    let input = quote! {
+        pub struct Wrapper<T> {
+            pub value: T,
+            pub inner: Private<T>,
+        }
+    };
+
+    // Only the visibility of the field is known, not that of its type, so the
+    // bound on the private type is kept
+    let expected = quote! {
+        #[automatically_derived]
+        impl<__T0> ::describe::Describe for Wrapper<__T0>
+        where
+            Private<__T0>: ::describe::Describe,
+            __T0: ::describe::Describe,
+        {
+            fn describe<'__a1>(&'__a1 self) {
+                let __v0 = self;
+                let __v1 = &__v0.value;
+                let __v2 = &__v0.inner;
+                let _ = ::describe::Describer::field(__v1);
+                let _ = ::describe::Describer::field(__v2);
+            }
+        }
+    };
+
+    let output = derive(input, derive_describe);
+    assert_eq!(output.to_string(), expected.to_string());
+}
@@ -0,0 +1,86 @@
+use quote::quote;
+use reflect::*;
+
+mod describe;
+
+use describe::Describer;
+
+fn pair() -> proc_macro2::TokenStream {
+    quote! {
+        struct Pair<'a, A, B> {
+            one: &'a A,
+            two: B,
+        }
+    }
+}
+
+#[test]
+fn test_report() {
+    let expected = "\
+impl<'__a1, __T0> ::describe::Describe for Pair<'__a1, __T0, &'__a1 __T0>
+  where clause predicates:
+    `&'__a1 __T0: ::describe::Describe`
+      from the bound `&'__a1 __T0: ::describe::Describe` of `::describe::Describer::field` through argument 0
+  type equalities:
+    `&'__a2 &'__a1 __T0` = `&'_ &'__a1 __T0`
+      from argument 0 of `::describe::Describer::field`
+      from argument 0 of `::describe::Describer::same`
+      from argument 1 of `::describe::Describer::same`
+";
+
+    let (_, report) = derive_with_report(pair(), |ex| {
+        describe::derive(ex, |fields| {
+            Describer::field.INVOKE(fields[1]);
+            Describer::same.INVOKE(fields[0], fields[1]);
+        })
+    });
+    assert_eq!(report.to_string(), expected);
+}
+
+#[test]
+fn test_report_static() {
+    let expected = "\
+impl<'__a1, __T0, __T1> ::describe::Describe for Pair<'__a1, __T0, __T1>
+  where clause predicates:
+    `&'__a1 __T0: 'static`
+      from a reference in the signature of `::describe::Describer::forever`
+  type equalities:
+    `&'__a2 &'__a1 __T0` = `&'static &'__a1 __T0`
+      from argument 0 of `::describe::Describer::forever`
+  lifetime outlives:
+    `'__a2: 'static`
+      from argument 0 of `::describe::Describer::forever`
+";
+
+    let (_, report) = derive_with_report(pair(), |ex| {
+        describe::derive(ex, |fields| {
+            Describer::forever.INVOKE(fields[0]);
+        })
+    });
+    assert_eq!(report.to_string(), expected);
+}
+
+#[test]
+fn test_report_output() {
+    let expected = quote! {
+        #[automatically_derived]
+        impl<'__a1, __T0, __T1> ::describe::Describe for Pair<'__a1, __T0, __T1>
+        where
+            __T1: ::describe::Describe,
+        {
+            fn describe<'__a2>(&'__a2 self) {
+                let __v0 = self;
+                let __v2 = &__v0.two;
+                let _ = ::describe::Describer::field(__v2);
+            }
+        }
+    };
+
+    fn derive_field(ex: Execution) {
+        describe::derive(ex, |fields| {
+            Describer::field.INVOKE(fields[1]);
+        })
+    }
+    let (output, _) = derive_with_report(pair(), derive_field);
+    assert_eq!(output.to_string(), expected.to_string());
+}
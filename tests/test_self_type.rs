@@ -0,0 +1,106 @@
+use quote::quote;
+use reflect::library::std::*;
+use reflect::*;
+
+library! {
+    extern crate labels {
+        type Label;
+
+        impl Label {
+            fn of<T: ::std::fmt::Debug>(T) -> Label;
+        }
+    }
+}
+
+fn debug_fmt(f: MakeFunction) -> Value {
+    let receiver = f.arg(0);
+    let formatter = f.arg(1);
+    let type_name = receiver.get_type_name();
+
+    match receiver.data() {
+        Data::Struct(Struct::Struct(receiver)) => {
+            let builder = fmt::Formatter::debug_struct
+                .INVOKE(formatter, type_name)
+                .reference_mut();
+            for field in receiver.fields() {
+                fmt::DebugStruct::field.INVOKE(builder, field.get_name(), field.get_value());
+            }
+            fmt::DebugStruct::finish.INVOKE(builder)
+        }
+        _ => unimplemented!(),
+    }
+}
+
+#[test]
+fn test_reference() {
+    let input = quote! {
+        struct Point<T> {
+            x: T,
+        }
+    };
+
+    fn derive_debug(ex: Execution) {
+        ex.make_trait_impl(fmt::Debug, ex.target_type().reference(), |block| {
+            block.make_function(fmt::Debug::fmt, debug_fmt);
+        });
+    }
+
+    let expected = quote! {
+        #[automatically_derived]
+        impl<'__a1, __T0> ::std::fmt::Debug for &'__a1 Point<__T0>
+        where
+            &'__a1 __T0: ::std::fmt::Debug,
+        {
+            fn fmt<'__a2, '__a3>(
+                &'__a2 self,
+                __arg0: &'__a3 mut ::std::fmt::Formatter
+            ) -> ::std::fmt::Result {
+                let __v0 = self;
+                let __v1 = __arg0;
+                let __v3 = & &__v0.x;
+                let mut __v4 = ::std::fmt::Formatter::debug_struct(__v1, "Point");
+                let __v5 = &mut __v4;
+                let _ = ::std::fmt::DebugStruct::field(__v5, "x", __v3);
+                let __v8 = ::std::fmt::DebugStruct::finish(__v5);
+                __v8
+            }
+        }
+    };
+
+    let output = derive(input, derive_debug);
+    assert_eq!(output.to_string(), expected.to_string());
+}
+
+#[test]
+fn test_path() {
+    let input = quote! {
+        struct Point<T> {
+            x: T,
+        }
+    };
+
+    fn derive_from(ex: Execution) {
+        ex.make_trait_impl(convert::From, RUNTIME::labels::Label, |block| {
+            block.make_function(convert::From::from, |f| {
+                RUNTIME::labels::Label::of.INVOKE(f.arg(0))
+            });
+        });
+    }
+
+    let expected = quote! {
+        #[automatically_derived]
+        impl<__T1> ::std::convert::From<__T1> for ::labels::Label
+        where
+            __T1: ::std::fmt::Debug,
+        {
+            fn from(__arg0: __T1) -> Self {
+                let __v0 = __arg0;
+                let __v1 = ::labels::Label::of(__v0);
+                __v1
+            }
+        }
+    };
+
+    let output = derive(input, derive_from);
+    assert_eq!(output.to_string(), expected.to_string());
+}
@@ -0,0 +1,149 @@
+use quote::quote;
+use reflect::*;
+
+#[test]
+fn test_parse_signature() {
+    let input = quote! {
+        struct Wrapper<T> {
+            inner: T,
+        }
+    };
+
+    let output = derive(input, |ex| {
+        ex.make_impl(ex.target_type(), |block| {
+            let sig =
+                Signature::parse("fn(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result")
+                    .unwrap();
+            block.make_function(Function::get_function("describe", sig), |f| {
+                let my = Path::path_from_str("my", &mut GenericsEnv::default());
+                let fmt_inner = Function::parse(
+                    &my,
+                    "fn fmt_inner<T>(&T, &mut ::std::fmt::Formatter) -> ::std::fmt::Result \
+                     where T: ::std::fmt::Display",
+                )
+                .unwrap();
+
+                let receiver = f.arg(0);
+                let formatter = f.arg(1);
+                match receiver.data() {
+                    Data::Struct(Struct::Struct(receiver)) => {
+                        let field = receiver.fields().next().unwrap();
+                        fmt_inner.invoke(&[field.get_value(), formatter])
+                    }
+                    _ => unimplemented!(),
+                }
+            });
+        });
+    });
+
+    let expected = quote! {
+        impl<__T0> Wrapper<__T0>
+        where
+            __T0: ::std::fmt::Display,
+        {
+            fn describe<'__a1, '__a2>(
+                &'__a1 self,
+                __arg0: &'__a2 mut ::std::fmt::Formatter
+            ) -> ::std::fmt::Result {
+                let __v0 = self;
+                let __v1 = __arg0;
+                let __v2 = &__v0.inner;
+                let __v3 = my::fmt_inner(__v2, __v1);
+                __v3
+            }
+        }
+    };
+    assert_eq!(output.to_string(), expected.to_string());
+}
+
+#[test]
+fn test_parse_fn_sugar() {
+    let input = quote! {
+        struct Wrapper<T> {
+            inner: T,
+        }
+    };
+
+    let output = derive(input, |ex| {
+        ex.make_impl(ex.target_type(), |block| {
+            let sig = Signature::parse("fn(&self) -> ::std::boxed::Box<dyn Fn(&str)>").unwrap();
+            block.make_function(Function::get_function("matcher", sig), |f| {
+                let my = Path::path_from_str("my", &mut GenericsEnv::default());
+                let matcher =
+                    Function::parse(&my, "fn matcher<T>(&T) -> ::std::boxed::Box<dyn Fn(&str)>")
+                        .unwrap();
+
+                let receiver = f.arg(0);
+                match receiver.data() {
+                    Data::Struct(Struct::Struct(receiver)) => {
+                        let field = receiver.fields().next().unwrap();
+                        matcher.invoke(&[field.get_value()])
+                    }
+                    _ => unimplemented!(),
+                }
+            });
+        });
+    });
+
+    let expected = quote! {
+        impl<__T0> Wrapper<__T0> {
+            fn matcher<'__a1>(&'__a1 self) -> ::std::boxed::Box<(dyn Fn(&str))> {
+                let __v0 = self;
+                let __v1 = &__v0.inner;
+                let __v2 = my::matcher(__v1);
+                __v2
+            }
+        }
+    };
+    assert_eq!(output.to_string(), expected.to_string());
+}
+
+#[test]
+fn test_parse_errors() {
+    let path = Path::path_from_str("my", &mut GenericsEnv::default());
+    let error = Function::parse(&path, "fn(&self)").unwrap_err();
+    assert_eq!(error.to_string(), "expected the name of the function");
+    assert!(Signature::parse("fn(&self,,)").is_err());
+    assert!(Signature::parse("fn(&self) ->").is_err());
+}
+
+#[test]
+fn test_unsupported_types() {
+    let error = Signature::parse("fn(&[u8])").unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "slice types are not supported in library!"
+    );
+    let error = Signature::parse("fn(*const u8)").unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "raw pointer types are not supported in library!",
+    );
+    let error = Signature::parse("fn(impl Fn())").unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "`impl Trait` is not supported in library!, use a generic parameter instead",
+    );
+    assert!(Signature::parse("fn(fn(u8) -> u8)").is_err());
+    assert!(Signature::parse("fn() -> !").is_err());
+    assert!(Signature::parse("fn(::std::vec::Vec<[u8]>)").is_err());
+    assert!(Signature::parse("fn<T>(&T) where T: ::std::convert::AsRef<[u8]>").is_err());
+
+    // Associated types are supported
+    assert!(Signature::parse(
+        "fn<T>(T) -> <T as ::std::iter::IntoIterator>::Item where T: ::std::iter::IntoIterator",
+    )
+    .is_ok());
+}
+
+#[test]
+fn test_undeclared_lifetimes() {
+    let error = Signature::parse("fn(&'a str) -> &'a str").unwrap_err();
+    assert_eq!(error.to_string(), "use of undeclared lifetime name `'a`");
+    assert!(Signature::parse("fn<T>(&T) where T: 'a").is_err());
+    assert!(Signature::parse("fn(&::std::boxed::Box<dyn ::std::fmt::Debug + 'a>)").is_err());
+
+    assert!(Signature::parse("fn<'a>(&'a str) -> &'a str").is_ok());
+    assert!(Signature::parse("fn(&'static str)").is_ok());
+    assert!(Signature::parse("fn<F>(F) where F: for<'a> ::std::ops::Fn(&'a u8)").is_ok());
+}
@@ -49,9 +49,12 @@ fn test_transitive_closure() {
     };
 
     let expected = quote! {
-        impl<__T0, __T1> ::subtypes::CallSubtypes<'static, 'static, __T0, __T1> for Trivial
+        #[automatically_derived]
+        impl<'__a1, '__a2, __T0, __T1> ::subtypes::CallSubtypes<'__a1, '__a2, __T0, __T1> for Trivial
+        where
+            __T1: 'static,
         {
-            fn call_subtypes(__arg0: &'static mut __T0, __arg1: &'static mut __T1) {
+            fn call_subtypes(__arg0: &'__a1 mut __T0, __arg1: &'__a2 mut __T1) {
                 let __v0 = __arg0;
                 let __v1 = __arg1;
                 let __v2 = ::subtypes::Subtypes::sub1(__v0, __v1);
@@ -86,6 +89,7 @@ fn keep_subtypes() {
     };
 
     let expected = quote! {
+        #[automatically_derived]
         impl<'__a1, '__a2, __T0> ::subtypes::HasSubtypes<'__a1, '__a2, __T0> for Trivial
         where
             '__a1: '__a2,
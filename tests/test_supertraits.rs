@@ -0,0 +1,92 @@
+use quote::quote;
+use reflect::*;
+
+library! {
+    use cmp {
+        trait PartialEq {
+            fn check_eq(&self);
+        }
+
+        trait Eq: PartialEq {}
+
+        trait PartialOrd: PartialEq {}
+
+        trait Ord: Eq + PartialOrd {
+            fn check_ord(&self);
+        }
+    }
+}
+
+fn derive_ord(ex: Execution) {
+    ex.make_trait_impl(RUNTIME::cmp::Ord, ex.target_type(), |block| {
+        block.make_function(
+            RUNTIME::cmp::Ord::check_ord,
+            |make_function| match make_function.arg(0).data() {
+                Data::Struct(Struct::Struct(receiver)) => {
+                    for field in receiver.fields() {
+                        RUNTIME::cmp::PartialEq::check_eq.INVOKE(field.get_value());
+                        RUNTIME::cmp::Ord::check_ord.INVOKE(field.get_value());
+                    }
+                    make_function.unit()
+                }
+                _ => unimplemented!(),
+            },
+        );
+    });
+}
+
+#[test]
+fn test_supertraits() {
+    let input = quote! {
+        struct Pair<T> {
+            first: T,
+            second: T,
+        }
+    };
+
+    let expected = quote! {
+        #[automatically_derived]
+        impl<__T0> ::cmp::Ord for Pair<__T0>
+        where
+            Pair<__T0>: ::cmp::Eq + ::cmp::PartialOrd,
+            __T0: ::cmp::Ord,
+        {
+            fn check_ord<'__a1>(&'__a1 self) {
+                let __v0 = self;
+                let __v1 = &__v0.first;
+                let __v2 = &__v0.second;
+                let _ = ::cmp::PartialEq::check_eq(__v1);
+                let _ = ::cmp::Ord::check_ord(__v1);
+                let _ = ::cmp::PartialEq::check_eq(__v2);
+                let _ = ::cmp::Ord::check_ord(__v2);
+            }
+        }
+    };
+
+    let output = reflect::derive(input, derive_ord);
+    assert_eq!(output.to_string(), expected.to_string());
+}
+
+#[test]
+fn test_concrete_supertraits() {
+    let input = quote! {
+        struct Point {
+            x: Coordinate,
+        }
+    };
+
+    let expected = quote! {
+        #[automatically_derived]
+        impl ::cmp::Ord for Point {
+            fn check_ord<'__a1>(&'__a1 self) {
+                let __v0 = self;
+                let __v1 = &__v0.x;
+                let _ = ::cmp::PartialEq::check_eq(__v1);
+                let _ = ::cmp::Ord::check_ord(__v1);
+            }
+        }
+    };
+
+    let output = reflect::derive(input, derive_ord);
+    assert_eq!(output.to_string(), expected.to_string());
+}
@@ -33,6 +33,7 @@ fn test_tuple() {
     };
 
     let expected = quote! {
+        #[automatically_derived]
         impl<__T0, __T1> ::tuple::Tuple<__T0, __T1> for TupleStruct<__T0, __T1> {
             fn swap(__arg0: (__T0, __T1)) -> (__T1, __T0) {
                 let __v0 = __arg0;
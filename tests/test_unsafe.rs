@@ -0,0 +1,106 @@
+use quote::quote;
+use reflect::*;
+
+library! {
+    use pod {
+        unsafe trait Pod {}
+
+        trait Reset {
+            unsafe fn reset(&mut self);
+        }
+
+        type Bytes;
+
+        impl Bytes {
+            unsafe fn zero(&mut ::std::primitive::u32);
+        }
+    }
+}
+
+fn derive_pod(ex: Execution) {
+    ex.allow_unsafe();
+    ex.make_trait_impl(RUNTIME::pod::Pod, ex.target_type(), |_block| {});
+}
+
+#[test]
+fn test_unsafe_impl() {
+    let input = quote! {
+        struct Point {
+            x: u32,
+            y: u32,
+        }
+    };
+
+    let expected = quote! {
+        #[automatically_derived]
+        unsafe impl ::pod::Pod for Point {}
+    };
+
+    let output = derive(input, derive_pod);
+    assert_eq!(output.to_string(), expected.to_string());
+}
+
+fn derive_reset(ex: Execution) {
+    ex.allow_unsafe();
+    ex.make_trait_impl(RUNTIME::pod::Reset, ex.target_type(), |block| {
+        block.make_function(RUNTIME::pod::Reset::reset, |make_function| {
+            let receiver = make_function.arg(0);
+            match receiver.data() {
+                Data::Struct(Struct::Struct(receiver)) => {
+                    for field in receiver.fields() {
+                        RUNTIME::pod::Bytes::zero.INVOKE(field.get_value());
+                    }
+                }
+                _ => unimplemented!(),
+            }
+            make_function.unit()
+        });
+    });
+}
+
+#[test]
+fn test_unsafe_fn() {
+    let input = quote! {
+        struct Point {
+            x: u32,
+            y: u32,
+        }
+    };
+
+    let expected = quote! {
+        #[automatically_derived]
+        impl ::pod::Reset for Point {
+            unsafe fn reset<'__a1>(&'__a1 mut self) {
+                let __v0 = self;
+                let __v1 = &mut __v0.x;
+                let __v2 = &mut __v0.y;
+                let _ = unsafe { ::pod::Bytes::zero(__v1) };
+                let _ = unsafe { ::pod::Bytes::zero(__v2) };
+            }
+        }
+    };
+
+    let output = derive(input, derive_reset);
+    assert_eq!(output.to_string(), expected.to_string());
+}
+
+#[test]
+fn test_unsafe_not_allowed() {
+    let input = quote! {
+        struct Point {
+            x: u32,
+            y: u32,
+        }
+    };
+
+    let expected = quote! {
+        compile_error! {
+            "the generated code needs `unsafe`, which is only emitted after Execution::allow_unsafe"
+        }
+    };
+
+    let output = derive(input, |ex| {
+        ex.make_trait_impl(RUNTIME::pod::Pod, ex.target_type(), |_block| {});
+    });
+    assert_eq!(output.to_string(), expected.to_string());
+}
@@ -0,0 +1,107 @@
+use quote::quote;
+use reflect::*;
+
+library! {
+    use variance {
+        type Cell<T>;
+
+        struct Shared<'a> {
+            pub value: &'a ::std::primitive::str,
+        }
+
+        struct Slot<'a> {
+            pub cell: Cell<&'a ::std::primitive::str>,
+        }
+
+        type Pick;
+
+        impl Pick {
+            fn shared<'x>(Shared<'x>, Shared<'x>) -> Shared<'x>;
+            fn slot<'x>(Slot<'x>, Slot<'x>) -> Slot<'x>;
+        }
+
+        trait Longest<'a, 'b> {
+            fn longest(Shared<'a>, Shared<'b>) -> Shared<'b>;
+        }
+
+        trait LongestSlot<'a, 'b> {
+            fn longest_slot(Slot<'a>, Slot<'b>) -> Slot<'b>;
+        }
+    }
+}
+
+/// `Shared` is covariant in its lifetime, so both arguments only need to
+/// outlive the lifetime of the result
+#[test]
+fn test_covariant() {
+    let input = quote! {
+        struct Trivial;
+    };
+
+    let expected = quote! {
+        #[automatically_derived]
+        impl<'__a1, '__a2> ::variance::Longest<'__a1, '__a2> for Trivial
+        where
+            '__a1: '__a2,
+        {
+            fn longest(
+                __arg0: ::variance::Shared<'__a1>,
+                __arg1: ::variance::Shared<'__a2>
+            ) -> ::variance::Shared<'__a2> {
+                let __v0 = __arg0;
+                let __v1 = __arg1;
+                let __v2 = ::variance::Pick::shared(__v0, __v1);
+                __v2
+            }
+        }
+    };
+
+    let output = derive(input, |ex| {
+        ex.make_trait_impl(RUNTIME::variance::Longest, ex.target_type(), |block| {
+            block.make_function(RUNTIME::variance::Longest::longest, |make_function| {
+                let a = make_function.arg(0);
+                let b = make_function.arg(1);
+                RUNTIME::variance::Pick::shared.INVOKE(a, b)
+            });
+        });
+    });
+    assert_eq!(output.to_string(), expected.to_string());
+}
+
+/// `Slot` contains a `Cell`, which is not declared with its fields, so it is
+/// invariant in its lifetime and both arguments must have the same lifetime
+#[test]
+fn test_invariant() {
+    let input = quote! {
+        struct Trivial;
+    };
+
+    let expected = quote! {
+        #[automatically_derived]
+        impl<'__a1> ::variance::LongestSlot<'__a1, '__a1> for Trivial {
+            fn longest_slot(
+                __arg0: ::variance::Slot<'__a1>,
+                __arg1: ::variance::Slot<'__a1>
+            ) -> ::variance::Slot<'__a1> {
+                let __v0 = __arg0;
+                let __v1 = __arg1;
+                let __v2 = ::variance::Pick::slot(__v0, __v1);
+                __v2
+            }
+        }
+    };
+
+    let output = derive(input, |ex| {
+        ex.make_trait_impl(RUNTIME::variance::LongestSlot, ex.target_type(), |block| {
+            block.make_function(
+                RUNTIME::variance::LongestSlot::longest_slot,
+                |make_function| {
+                    let a = make_function.arg(0);
+                    let b = make_function.arg(1);
+                    RUNTIME::variance::Pick::slot.INVOKE(a, b)
+                },
+            );
+        });
+    });
+    assert_eq!(output.to_string(), expected.to_string());
+}
@@ -26,6 +26,7 @@ fn test_zero_args() {
     };
 
     let expected = quote! {
+        #[automatically_derived]
         impl::zero::Zero for Zero {
             fn zero() {}
         }
@@ -0,0 +1,10 @@
+reflect::library! {
+    use lib {
+        trait IntoIterator {
+            type Item;
+            type IntoIter: Iterator;
+        }
+    }
+}
+
+fn main() {}
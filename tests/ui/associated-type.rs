@@ -0,0 +1,11 @@
+reflect::library! {
+    use lib {
+        type Thing;
+
+        impl Thing {
+            type Item;
+        }
+    }
+}
+
+fn main() {}
@@ -0,0 +1,8 @@
+use reflect::AttrSchema;
+
+fn main() {
+    let mut schema = AttrSchema::new("debug");
+    let skip = schema.add_flag("skip");
+    let attrs = schema.parse(&[]).unwrap();
+    let rename: Option<String> = attrs.get(&skip);
+}
@@ -0,0 +1,13 @@
+reflect::library! {
+    use lib {
+        type Thing;
+
+        impl Thing {
+            fn new() -> Thing {
+                Thing
+            }
+        }
+    }
+}
+
+fn main() {}
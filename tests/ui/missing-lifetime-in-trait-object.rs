@@ -0,0 +1,13 @@
+reflect::library! {
+    use lib {
+        type Thing;
+
+        trait Source<T> {}
+
+        impl Thing {
+            fn longest(&Thing, &Thing) -> ::std::boxed::Box<dyn Source<&Thing>>;
+        }
+    }
+}
+
+fn main() {}
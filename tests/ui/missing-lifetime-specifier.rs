@@ -0,0 +1,11 @@
+reflect::library! {
+    use lib {
+        type Thing;
+
+        impl Thing {
+            fn longest(&Thing, &Thing) -> &Thing;
+        }
+    }
+}
+
+fn main() {}
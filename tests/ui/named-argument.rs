@@ -0,0 +1,11 @@
+reflect::library! {
+    use lib {
+        type Thing;
+
+        trait Show {
+            fn show(&self, thing: &Thing);
+        }
+    }
+}
+
+fn main() {}
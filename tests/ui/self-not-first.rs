@@ -0,0 +1,11 @@
+reflect::library! {
+    use lib {
+        type Thing;
+
+        trait Compare {
+            fn compare(&Thing, &self);
+        }
+    }
+}
+
+fn main() {}
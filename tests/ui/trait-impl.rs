@@ -0,0 +1,10 @@
+reflect::library! {
+    use lib {
+        type Thing;
+        trait Show {}
+
+        impl Show for Thing {}
+    }
+}
+
+fn main() {}
@@ -0,0 +1,11 @@
+reflect::library! {
+    use lib {
+        type Thing;
+
+        trait Borrow {
+            fn borrow(&self) -> &'a Thing;
+        }
+    }
+}
+
+fn main() {}
@@ -0,0 +1,11 @@
+reflect::library! {
+    use lib {
+        trait Iterator {
+            type Item;
+
+            fn peek(&self) -> Option<&Self::Elem>;
+        }
+    }
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+reflect::library! {
+    use lib {
+        struct Callback {
+            pub f: fn(),
+        }
+    }
+}
+
+fn main() {}
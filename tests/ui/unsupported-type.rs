@@ -0,0 +1,9 @@
+reflect::library! {
+    use lib {
+        trait Bytes {
+            fn bytes(&self) -> &[u8];
+        }
+    }
+}
+
+fn main() {}